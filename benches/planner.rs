@@ -0,0 +1,80 @@
+use chrono::{Duration, TimeZone, Utc};
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use jarvis_lib::model::{
+    LoadProfile, LoadProfileSection, PlanningRequest, PlanningStrategy, SpotPrice,
+    SpotPricePlanner, SpotPricePlannerConfig,
+};
+
+// a week of quarter-hour prices
+fn quarter_hour_spot_prices() -> Vec<SpotPrice> {
+    let from = Utc.with_ymd_and_hms(2022, 4, 11, 0, 0, 0).unwrap();
+
+    (0..672)
+        .map(|i| {
+            SpotPrice::new(
+                from + Duration::minutes(15 * i),
+                from + Duration::minutes(15 * (i + 1)),
+                ((i * 37) % 97) as f64 / 1000.0,
+            )
+            .id(None)
+            .market_price_tax(0.021)
+            .sourcing_markup_price(0.017)
+            .energy_tax_price(0.081)
+            .build()
+            .unwrap()
+        })
+        .collect()
+}
+
+fn planning_request(planning_strategy: PlanningStrategy) -> PlanningRequest {
+    PlanningRequest {
+        spot_prices: quarter_hour_spot_prices(),
+        load_profile: LoadProfile {
+            sections: vec![
+                LoadProfileSection {
+                    duration_seconds: 7200,
+                    power_draw_watt: 2000.0,
+                    end_power_draw_watt: None,
+                    id: None,
+                },
+                LoadProfileSection {
+                    duration_seconds: 10800,
+                    power_draw_watt: 800.0,
+                    end_power_draw_watt: None,
+                    id: None,
+                },
+            ],
+        },
+        planning_strategy,
+        ..PlanningRequest::default()
+    }
+}
+
+fn get_best_spot_prices(c: &mut Criterion) {
+    let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig::default());
+
+    let request = planning_request(PlanningStrategy::LowestPrice);
+    c.bench_function("get_best_spot_prices 672 quarter hours", |b| {
+        b.iter(|| spot_price_planner.get_best_spot_prices(black_box(&request)))
+    });
+
+    let request = planning_request(PlanningStrategy::LowestPrice);
+    c.bench_function("get_best_spot_prices_windowed 672 quarter hours", |b| {
+        b.iter(|| {
+            spot_price_planner.get_best_spot_prices_windowed(
+                black_box(&request),
+                Duration::hours(24),
+                Duration::hours(12),
+            )
+        })
+    });
+
+    let request = planning_request(PlanningStrategy::LowestPriceNonConsecutive);
+    c.bench_function(
+        "get_best_spot_prices non-consecutive 672 quarter hours",
+        |b| b.iter(|| spot_price_planner.get_best_spot_prices(black_box(&request))),
+    );
+}
+
+criterion_group!(benches, get_best_spot_prices);
+criterion_main!(benches);
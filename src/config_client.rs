@@ -1,64 +1,1024 @@
 use crate::model::*;
 use serde::de::DeserializeOwned;
 use serde_yaml;
+use std::collections::HashMap;
 use std::env;
 use std::error::Error;
+use std::fmt;
 use std::fs;
-use tracing::{debug, info};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+use tracing::{debug, info, warn};
 
 pub trait SetDefaults {
     fn set_defaults(&mut self);
 }
 
+/// Top-level key holding the schema version of a config document; documents without it are version 1.
+pub const CONFIG_VERSION_KEY: &str = "configVersion";
+
+/// Upgrades raw config documents written for an older schema version of the implementing type.
+pub trait Migrate {
+    const CURRENT_VERSION: u32;
+
+    /// Migrates the document from `from_version` to `CURRENT_VERSION`.
+    fn migrate(
+        value: serde_yaml::Value,
+        from_version: u32,
+    ) -> Result<serde_yaml::Value, Box<dyn Error>>;
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io {
+        path: String,
+        source: std::io::Error,
+    },
+    Parse {
+        path: String,
+        line: Option<usize>,
+        column: Option<usize>,
+        message: String,
+    },
+    Http {
+        path: String,
+        message: String,
+    },
+    Validation {
+        path: String,
+        message: String,
+    },
+}
+
+impl ConfigError {
+    fn parse(path: &str, error: serde_yaml::Error) -> Self {
+        let location = error.location();
+
+        ConfigError::Parse {
+            path: path.to_string(),
+            line: location.as_ref().map(|l| l.line()),
+            column: location.as_ref().map(|l| l.column()),
+            message: error.to_string(),
+        }
+    }
+
+    fn validation(path: &str, error: Box<dyn Error>) -> Self {
+        ConfigError::Validation {
+            path: path.to_string(),
+            message: error.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::Io { path, source } => {
+                write!(f, "Failed reading config {}: {}", path, source)
+            }
+            ConfigError::Parse {
+                path,
+                line: Some(line),
+                column: Some(column),
+                message,
+            } => write!(
+                f,
+                "Failed parsing config {} at line {} column {}: {}",
+                path, line, column, message
+            ),
+            ConfigError::Parse { path, message, .. } => {
+                write!(f, "Failed parsing config {}: {}", path, message)
+            }
+            ConfigError::Http { path, message } => {
+                write!(f, "Failed fetching config {}: {}", path, message)
+            }
+            ConfigError::Validation { path, message } => {
+                write!(f, "Invalid config {}: {}", path, message)
+            }
+        }
+    }
+}
+
+impl Error for ConfigError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ConfigError::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
 pub struct ConfigClientConfig {
     config_path: String,
+    /// Planner config location; falls back to `config_path` when not set.
+    planner_config_path: Option<String>,
+    /// Secrets document merged over the config; only required to exist when explicitly configured.
+    secrets_path: Option<String>,
+    secrets_required: bool,
+    /// Rejects keys in the config that aren't used by the type it's deserialized into.
+    strict: bool,
+    http_timeout: Duration,
+    http_token: Option<String>,
+    http_cache_path: Option<String>,
 }
 
 impl ConfigClientConfig {
     pub fn new(config_path: String) -> Result<Self, Box<dyn Error>> {
         debug!("ConfigClientConfig::new(config_path: {})", config_path);
-        Ok(Self { config_path })
+        Ok(Self {
+            config_path,
+            planner_config_path: None,
+            secrets_path: None,
+            secrets_required: false,
+            strict: false,
+            http_timeout: Duration::from_secs(10),
+            http_token: None,
+            http_cache_path: None,
+        })
+    }
+
+    pub fn builder() -> ConfigClientConfigBuilder {
+        ConfigClientConfigBuilder::default()
     }
 
     pub fn from_env() -> Result<Self, Box<dyn Error>> {
-        let config_path =
-            env::var("CONFIG_PATH").unwrap_or_else(|_| "/configs/config.yaml".to_string());
+        let mut builder = Self::builder();
+
+        if let Ok(config_path) = env::var("CONFIG_PATH") {
+            builder = builder.config_path(config_path);
+        }
+        if let Ok(planner_config_path) = env::var("PLANNER_CONFIG_PATH") {
+            builder = builder.planner_config_path(planner_config_path);
+        }
+        if let Ok(secrets_path) = env::var("SECRETS_PATH") {
+            builder = builder.secrets_path(secrets_path);
+        }
+        if let Ok(strict) = env::var("CONFIG_STRICT") {
+            builder = builder.strict(strict.eq_ignore_ascii_case("true"));
+        }
+        if let Ok(http_timeout_seconds) = env::var("CONFIG_HTTP_TIMEOUT_SECONDS") {
+            builder = builder.http_timeout(Duration::from_secs(http_timeout_seconds.parse()?));
+        }
+        if let Ok(http_token) = env::var("CONFIG_HTTP_TOKEN") {
+            builder = builder.http_token(http_token);
+        }
+        if let Ok(http_cache_path) = env::var("CONFIG_HTTP_CACHE_PATH") {
+            builder = builder.http_cache_path(http_cache_path);
+        }
+
+        builder.build()
+    }
+
+    pub fn config_path(&self) -> &str {
+        &self.config_path
+    }
+
+    pub fn planner_config_path(&self) -> Option<&str> {
+        self.planner_config_path.as_deref()
+    }
+
+    pub fn secrets_path(&self) -> Option<&str> {
+        self.secrets_path.as_deref()
+    }
+
+    pub fn secrets_required(&self) -> bool {
+        self.secrets_required
+    }
+
+    pub fn strict(&self) -> bool {
+        self.strict
+    }
+
+    pub fn http_timeout(&self) -> Duration {
+        self.http_timeout
+    }
+
+    pub fn http_token(&self) -> Option<&str> {
+        self.http_token.as_deref()
+    }
+
+    pub fn http_cache_path(&self) -> Option<&str> {
+        self.http_cache_path.as_deref()
+    }
+}
+
+/// Builds a [`ConfigClientConfig`] with the same defaults as [`ConfigClientConfig::from_env`]
+/// when no environment variables are set.
+#[derive(Default)]
+pub struct ConfigClientConfigBuilder {
+    config_path: Option<String>,
+    planner_config_path: Option<String>,
+    secrets_path: Option<String>,
+    strict: bool,
+    http_timeout: Option<Duration>,
+    http_token: Option<String>,
+    http_cache_path: Option<String>,
+}
+
+impl ConfigClientConfigBuilder {
+    pub fn config_path(mut self, config_path: impl Into<String>) -> Self {
+        self.config_path = Some(config_path.into());
+        self
+    }
+
+    pub fn planner_config_path(mut self, planner_config_path: impl Into<String>) -> Self {
+        self.planner_config_path = Some(planner_config_path.into());
+        self
+    }
+
+    /// Sets an explicit secrets file, which then has to exist; without it `/secrets/secrets.yaml`
+    /// is merged only when present.
+    pub fn secrets_path(mut self, secrets_path: impl Into<String>) -> Self {
+        self.secrets_path = Some(secrets_path.into());
+        self
+    }
+
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    pub fn http_timeout(mut self, http_timeout: Duration) -> Self {
+        self.http_timeout = Some(http_timeout);
+        self
+    }
+
+    pub fn http_token(mut self, http_token: impl Into<String>) -> Self {
+        self.http_token = Some(http_token.into());
+        self
+    }
+
+    pub fn http_cache_path(mut self, http_cache_path: impl Into<String>) -> Self {
+        self.http_cache_path = Some(http_cache_path.into());
+        self
+    }
+
+    pub fn build(self) -> Result<ConfigClientConfig, Box<dyn Error>> {
+        let config_path = self
+            .config_path
+            .unwrap_or_else(|| "/configs/config.yaml".to_string());
+        let (secrets_path, secrets_required) = match self.secrets_path {
+            Some(secrets_path) => (secrets_path, true),
+            None => ("/secrets/secrets.yaml".to_string(), false),
+        };
+
+        Ok(ConfigClientConfig {
+            planner_config_path: self.planner_config_path,
+            secrets_path: Some(secrets_path),
+            secrets_required,
+            strict: self.strict,
+            http_timeout: self.http_timeout.unwrap_or_else(|| Duration::from_secs(10)),
+            http_token: self.http_token,
+            http_cache_path: self.http_cache_path,
+            ..ConfigClientConfig::new(config_path)?
+        })
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+enum ConfigFormat {
+    Yaml,
+    Json,
+}
+
+impl ConfigFormat {
+    fn from_path(path: &str) -> Self {
+        let path = path.split(['?', '#']).next().unwrap_or(path);
+        if path.to_lowercase().ends_with(".json") {
+            ConfigFormat::Json
+        } else {
+            ConfigFormat::Yaml
+        }
+    }
 
-        Self::new(config_path)
+    fn from_content_type(content_type: &str) -> Option<Self> {
+        let content_type = content_type.to_lowercase();
+        if content_type.contains("json") {
+            Some(ConfigFormat::Json)
+        } else if content_type.contains("yaml") {
+            Some(ConfigFormat::Yaml)
+        } else {
+            None
+        }
     }
 }
 
+fn is_url(path: &str) -> bool {
+    path.starts_with("http://") || path.starts_with("https://")
+}
+
+/// A parsed config document; `contents` holds the interpolated text as long as `value` is an
+/// untouched parse of it, so deserialization errors can be reported with their location.
+#[derive(Clone)]
+struct ConfigDocument {
+    path: String,
+    contents: Option<String>,
+    format: ConfigFormat,
+    value: serde_yaml::Value,
+}
+
+impl ConfigDocument {
+    fn parse(path: &str, contents: String, format: ConfigFormat) -> Result<Self, ConfigError> {
+        let value = deserialize_config(path, &contents, format)?;
+
+        Ok(Self {
+            path: path.to_string(),
+            contents: Some(contents),
+            format,
+            value,
+        })
+    }
+
+    fn deserialize<T>(self) -> Result<T, ConfigError>
+    where
+        T: DeserializeOwned,
+    {
+        let path = &self.path;
+        let format = self.format;
+        let contents = self.contents;
+
+        serde_yaml::from_value(self.value).map_err(|e| {
+            // deserializing from a value loses the location, so recover it from the text
+            match contents {
+                Some(contents) => match deserialize_config::<T>(path, &contents, format) {
+                    Err(located_error) => located_error,
+                    Ok(_) => ConfigError::parse(path, e),
+                },
+                None => ConfigError::parse(path, e),
+            }
+        })
+    }
+}
+
+struct CachedDocument {
+    modified: Option<SystemTime>,
+    len: u64,
+    /// The file's contents before interpolating environment variables, which can change without the file changing.
+    raw_contents: String,
+    document: ConfigDocument,
+}
+
 pub struct ConfigClient {
     config: ConfigClientConfig,
+    cache: Mutex<HashMap<String, CachedDocument>>,
+    /// Key paths the last strictly read config used, per path, so a planner config sharing the file accepts them.
+    config_keys: Mutex<HashMap<String, Vec<String>>>,
 }
 
 impl ConfigClient {
     pub fn new(config: ConfigClientConfig) -> Self {
-        Self { config }
+        Self {
+            config,
+            cache: Mutex::new(HashMap::new()),
+            config_keys: Mutex::new(HashMap::new()),
+        }
     }
 
     pub fn read_config_from_file<T>(&self) -> Result<T, Box<dyn Error>>
     where
         T: DeserializeOwned + SetDefaults,
     {
-        let config_file_contents = fs::read_to_string(&self.config.config_path)?;
-        let mut config: T = serde_yaml::from_str(&config_file_contents)?;
+        self.read_config_with(|_: &mut T| Ok(()))
+    }
+
+    /// Reads the config file and runs `f` after `set_defaults`, to normalize or validate the config
+    /// inline; errors from `f` are returned as [`ConfigError::Validation`] for the config path.
+    pub fn read_config_with<T, F>(&self, f: F) -> Result<T, Box<dyn Error>>
+    where
+        T: DeserializeOwned + SetDefaults,
+        F: FnOnce(&mut T) -> Result<(), Box<dyn Error>>,
+    {
+        let document = self.read_document(&self.config.config_path)?;
+        let path = document.path.clone();
 
-        config.set_defaults();
+        let mut config: T = self.config_from_document(document)?;
 
-        info!("Loaded config from {}", &self.config.config_path);
+        f(&mut config).map_err(|e| ConfigError::validation(&path, e))?;
 
         Ok(config)
     }
 
     pub fn read_planner_config_from_file(&self) -> Result<SpotPricePlannerConfig, Box<dyn Error>> {
-        let config_file_contents = fs::read_to_string(&self.config.config_path)?;
-        let config: SpotPricePlannerConfig = serde_yaml::from_str(&config_file_contents)?;
+        let document = self.read_document(self.planner_config_path())?;
+
+        self.planner_config_from_document(document)
+    }
+
+    /// Reads the config file like [`ConfigClient::read_config_from_file`], migrating it first when
+    /// its `configVersion` is older than the type's current version.
+    pub fn read_migrated_config_from_file<T>(&self) -> Result<T, Box<dyn Error>>
+    where
+        T: DeserializeOwned + SetDefaults + Migrate,
+    {
+        let document = self.read_document(&self.config.config_path)?;
+        let document = self.migrate_document::<T>(document)?;
+
+        self.config_from_document(document)
+    }
+
+    /// Reads the config file on top of an embedded defaults document, so the file only needs to
+    /// contain what differs. See [`merge_values`] for how both documents are combined.
+    pub fn read_config_with_defaults<T>(&self, defaults_yaml: &str) -> Result<T, Box<dyn Error>>
+    where
+        T: DeserializeOwned + SetDefaults,
+    {
+        let mut value: serde_yaml::Value = serde_yaml::from_str(defaults_yaml)
+            .map_err(|e| ConfigError::parse("embedded defaults", e))?;
+
+        let document = self.read_document(&self.config.config_path)?;
+        merge_values(&mut value, document.value);
+
+        self.config_from_document(ConfigDocument {
+            path: document.path,
+            contents: None,
+            format: document.format,
+            value,
+        })
+    }
+
+    /// Reads the config from a file or, when the config path is an http(s) url, fetches it.
+    pub async fn read_config<T>(&self) -> Result<T, Box<dyn Error>>
+    where
+        T: DeserializeOwned + SetDefaults,
+    {
+        if !is_url(&self.config.config_path) {
+            return self.read_config_from_file();
+        }
+
+        let document = self.fetch_document(&self.config.config_path).await?;
+
+        self.config_from_document(document)
+    }
+
+    /// Reads the planner config from a file or, when the config path is an http(s) url, fetches it.
+    pub async fn read_planner_config(&self) -> Result<SpotPricePlannerConfig, Box<dyn Error>> {
+        if !is_url(self.planner_config_path()) {
+            return self.read_planner_config_from_file();
+        }
+
+        let document = self.fetch_document(self.planner_config_path()).await?;
+
+        self.planner_config_from_document(document)
+    }
+
+    pub fn config_path(&self) -> &str {
+        &self.config.config_path
+    }
+
+    /// Returns the path the planner config is read from, which is the config path unless set separately.
+    pub fn planner_config_path(&self) -> &str {
+        self.config
+            .planner_config_path
+            .as_deref()
+            .unwrap_or(&self.config.config_path)
+    }
+
+    /// Drops all cached config documents so the next read parses the files again.
+    pub fn force_reload(&self) {
+        self.cache.lock().unwrap().clear();
+    }
+
+    fn config_from_document<T>(&self, document: ConfigDocument) -> Result<T, Box<dyn Error>>
+    where
+        T: DeserializeOwned + SetDefaults,
+    {
+        let mut config: T = match self.read_secrets_document()? {
+            Some((secrets_path, secrets_document)) => {
+                let path = document.path;
+                let mut value = document.value;
+                merge_values(&mut value, secrets_document.value);
+                let strict_value = self.config.strict.then(|| value.clone());
+
+                // serde messages can contain the offending value, so leave them out
+                let config = serde_yaml::from_value(value).map_err(|_| ConfigError::Parse {
+                    path: path.clone(),
+                    line: None,
+                    column: None,
+                    message: format!(
+                        "config merged with secrets from {} does not match the expected structure",
+                        secrets_path
+                    ),
+                })?;
+
+                if let Some(value) = strict_value {
+                    self.check_config_keys::<T>(&path, &value)?;
+                }
+
+                config
+            }
+            None => self.deserialize_document(document)?,
+        };
+
+        config.set_defaults();
+
+        Ok(config)
+    }
 
-        info!("Loaded planner config from {}", &self.config.config_path);
+    fn planner_config_from_document(
+        &self,
+        document: ConfigDocument,
+    ) -> Result<SpotPricePlannerConfig, Box<dyn Error>> {
+        let document = self.migrate_document::<SpotPricePlannerConfig>(document)?;
+        let path = document.path.clone();
+        let strict_value = self.config.strict.then(|| document.value.clone());
+
+        let config: SpotPricePlannerConfig = document.deserialize()?;
+
+        if let Some(value) = strict_value {
+            self.check_planner_config_keys(&path, &value)?;
+        }
+
+        config
+            .validate()
+            .map_err(|e| ConfigError::validation(&path, Box::new(e)))?;
+
+        Ok(config)
+    }
+
+    fn migrate_document<T>(
+        &self,
+        mut document: ConfigDocument,
+    ) -> Result<ConfigDocument, ConfigError>
+    where
+        T: Migrate,
+    {
+        let version = match &mut document.value {
+            serde_yaml::Value::Mapping(mapping) => match mapping.remove(CONFIG_VERSION_KEY) {
+                Some(version) => version.as_u64().ok_or_else(|| ConfigError::Validation {
+                    path: document.path.clone(),
+                    message: format!("{} must be a positive number", CONFIG_VERSION_KEY),
+                })? as u32,
+                None => 1,
+            },
+            _ => 1,
+        };
+
+        if version > T::CURRENT_VERSION {
+            return Err(ConfigError::Validation {
+                path: document.path.clone(),
+                message: format!(
+                    "{} {} is newer than the supported version {}",
+                    CONFIG_VERSION_KEY,
+                    version,
+                    T::CURRENT_VERSION
+                ),
+            });
+        }
+
+        if version < T::CURRENT_VERSION {
+            debug!(
+                "Migrating config {} from version {} to {}",
+                &document.path,
+                version,
+                T::CURRENT_VERSION
+            );
+
+            let migrated_value = T::migrate(document.value.clone(), version)
+                .map_err(|e| ConfigError::validation(&document.path, e))?;
+
+            // the text no longer matches a changed document, so error locations can't be recovered
+            if migrated_value != document.value {
+                document.value = migrated_value;
+                document.contents = None;
+            }
+        }
+
+        Ok(document)
+    }
+
+    fn deserialize_document<T>(&self, document: ConfigDocument) -> Result<T, ConfigError>
+    where
+        T: DeserializeOwned,
+    {
+        let path = document.path.clone();
+        let strict_value = self.config.strict.then(|| document.value.clone());
+
+        let config: T = document.deserialize()?;
+
+        if let Some(value) = strict_value {
+            self.check_config_keys::<T>(&path, &value)?;
+        }
 
         Ok(config)
     }
+
+    /// Whether the planner config is read from the config file as well, so both configs' keys belong in it.
+    fn shares_config_file(&self) -> bool {
+        self.config
+            .planner_config_path
+            .as_deref()
+            .is_none_or(|planner_config_path| planner_config_path == self.config.config_path)
+    }
+
+    /// Rejects keys in value that T doesn't use; in a file shared with the planner config, keys the planner config
+    /// uses are accepted as well.
+    fn check_config_keys<T>(&self, path: &str, value: &serde_yaml::Value) -> Result<(), ConfigError>
+    where
+        T: DeserializeOwned,
+    {
+        let mut unknown_keys = ignored_keys::<T>(value);
+
+        let mut keys = vec![];
+        collect_key_paths(value, "", &mut keys);
+        keys.retain(|key| {
+            !unknown_keys
+                .iter()
+                .any(|unknown_key| is_within(key, unknown_key))
+        });
+        self.config_keys
+            .lock()
+            .unwrap()
+            .insert(path.to_string(), keys);
+
+        if self.shares_config_file() {
+            let document = ConfigDocument {
+                path: path.to_string(),
+                contents: None,
+                format: ConfigFormat::Yaml,
+                value: value.clone(),
+            };
+            let planner_value = match self.migrate_document::<SpotPricePlannerConfig>(document) {
+                Ok(document) => document.value,
+                Err(_) => value.clone(),
+            };
+            // keys the planner migration renamed or removed aren't in planner_value, so they count as known too
+            let planner_unknown_keys = ignored_keys::<SpotPricePlannerConfig>(&planner_value);
+            unknown_keys.retain(|key| planner_unknown_keys.contains(key));
+        }
+
+        reject_unknown_keys(path, unknown_keys)
+    }
+
+    /// Rejects keys in value that the planner config doesn't use; in a file shared with the config, keys the config
+    /// used when it was read before are accepted as well.
+    fn check_planner_config_keys(
+        &self,
+        path: &str,
+        value: &serde_yaml::Value,
+    ) -> Result<(), ConfigError> {
+        let mut unknown_keys = ignored_keys::<SpotPricePlannerConfig>(value);
+
+        if self.shares_config_file() {
+            if let Some(config_keys) = self.config_keys.lock().unwrap().get(path) {
+                unknown_keys.retain(|key| !config_keys.contains(key));
+            }
+        }
+
+        reject_unknown_keys(path, unknown_keys)
+    }
+
+    fn read_secrets_document(&self) -> Result<Option<(&str, ConfigDocument)>, ConfigError> {
+        let secrets_path = match &self.config.secrets_path {
+            Some(secrets_path) => secrets_path,
+            None => return Ok(None),
+        };
+
+        if !self.config.secrets_required && !Path::new(secrets_path).exists() {
+            debug!("No secrets file present at {}", secrets_path);
+            return Ok(None);
+        }
+
+        Ok(Some((secrets_path, self.read_document(secrets_path)?)))
+    }
+
+    /// Returns the parsed config file, reusing the cached document while the file's modification
+    /// time and length and the values interpolated into it are unchanged.
+    fn read_document(&self, path: &str) -> Result<ConfigDocument, ConfigError> {
+        if is_url(path) {
+            return Err(ConfigError::Validation {
+                path: path.to_string(),
+                message: "config path is a url, use read_config or read_planner_config instead"
+                    .to_string(),
+            });
+        }
+
+        let io_error = |e| ConfigError::Io {
+            path: path.to_string(),
+            source: e,
+        };
+
+        let metadata = fs::metadata(path).map_err(io_error)?;
+        let modified = metadata.modified().ok();
+        let len = metadata.len();
+
+        let mut cache = self.cache.lock().unwrap();
+        let cached_raw_contents = cache
+            .get(path)
+            .filter(|cached| {
+                cached.modified.is_some() && cached.modified == modified && cached.len == len
+            })
+            .map(|cached| cached.raw_contents.clone());
+
+        let raw_contents = match cached_raw_contents {
+            Some(raw_contents) => raw_contents,
+            None => fs::read_to_string(path).map_err(io_error)?,
+        };
+        let contents =
+            interpolate_env_vars(&raw_contents).map_err(|e| ConfigError::validation(path, e))?;
+        if let Some(cached) = cache.get(path) {
+            if cached.raw_contents == raw_contents
+                && cached.document.contents.as_deref() == Some(contents.as_str())
+            {
+                debug!("Using cached config from {}", path);
+                return Ok(cached.document.clone());
+            }
+        }
+
+        let document = ConfigDocument::parse(path, contents, ConfigFormat::from_path(path))?;
+
+        info!("Loaded config from {}", path);
+
+        cache.insert(
+            path.to_string(),
+            CachedDocument {
+                modified,
+                len,
+                raw_contents,
+                document: document.clone(),
+            },
+        );
+
+        Ok(document)
+    }
+
+    async fn fetch_document(&self, url: &str) -> Result<ConfigDocument, ConfigError> {
+        let (contents, format) = match self.fetch_url(url).await {
+            Ok((contents, format)) => {
+                if let Some(http_cache_path) = &self.config.http_cache_path {
+                    if let Err(e) = fs::write(http_cache_path, &contents) {
+                        warn!(
+                            "Failed caching config from {} at {}: {}",
+                            url, http_cache_path, e
+                        );
+                    }
+                }
+
+                (contents, format)
+            }
+            Err(e) => match &self.config.http_cache_path {
+                Some(http_cache_path) if Path::new(http_cache_path).exists() => {
+                    warn!(
+                        "Failed fetching config from {}, falling back to cached copy at {}: {}",
+                        url, http_cache_path, e
+                    );
+
+                    let contents =
+                        fs::read_to_string(http_cache_path).map_err(|e| ConfigError::Io {
+                            path: http_cache_path.clone(),
+                            source: e,
+                        })?;
+
+                    (contents, ConfigFormat::from_path(url))
+                }
+                _ => return Err(e),
+            },
+        };
+
+        let contents =
+            interpolate_env_vars(&contents).map_err(|e| ConfigError::validation(url, e))?;
+        let document = ConfigDocument::parse(url, contents, format)?;
+
+        info!("Loaded config from {}", url);
+
+        Ok(document)
+    }
+
+    async fn fetch_url(&self, url: &str) -> Result<(String, ConfigFormat), ConfigError> {
+        let http_error = |e: reqwest::Error| ConfigError::Http {
+            path: url.to_string(),
+            message: e.to_string(),
+        };
+
+        let client = reqwest::Client::builder()
+            .timeout(self.config.http_timeout)
+            .build()
+            .map_err(http_error)?;
+
+        let mut request = client.get(url);
+        if let Some(http_token) = &self.config.http_token {
+            request = request.bearer_auth(http_token);
+        }
+
+        let response = request
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(http_error)?;
+
+        let format = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .and_then(ConfigFormat::from_content_type)
+            .unwrap_or_else(|| ConfigFormat::from_path(url));
+
+        let contents = response.text().await.map_err(http_error)?;
+
+        Ok((contents, format))
+    }
+}
+
+/// Deep-merges `overlay` into `base`: mappings are merged key by key recursively, while scalars,
+/// sequences and explicit nulls in `overlay` replace the value in `base` as a whole.
+pub fn merge_values(base: &mut serde_yaml::Value, overlay: serde_yaml::Value) {
+    match (base, overlay) {
+        (serde_yaml::Value::Mapping(base_mapping), serde_yaml::Value::Mapping(overlay_mapping)) => {
+            for (key, overlay_value) in overlay_mapping {
+                match base_mapping.get_mut(&key) {
+                    Some(base_value) => merge_values(base_value, overlay_value),
+                    None => {
+                        base_mapping.insert(key, overlay_value);
+                    }
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+/// Key paths in value that T ignores when deserializing it, like `loadProfile.sections[0].powerDrawWatts`.
+fn ignored_keys<T>(value: &serde_yaml::Value) -> Vec<String>
+where
+    T: DeserializeOwned,
+{
+    let mut ignored_keys = vec![];
+    // errors are reported when the config is deserialized itself
+    let _: Result<T, _> =
+        serde_ignored::deserialize(value.clone(), |path| ignored_keys.push(key_path(&path)));
+    ignored_keys
+}
+
+fn key_path(path: &serde_ignored::Path) -> String {
+    match path {
+        serde_ignored::Path::Root => String::new(),
+        serde_ignored::Path::Seq { parent, index } => format!("{}[{}]", key_path(parent), index),
+        serde_ignored::Path::Map { parent, key } => match key_path(parent) {
+            parent_path if parent_path.is_empty() => key.clone(),
+            parent_path => format!("{}.{}", parent_path, key),
+        },
+        serde_ignored::Path::Some { parent }
+        | serde_ignored::Path::NewtypeStruct { parent }
+        | serde_ignored::Path::NewtypeVariant { parent } => key_path(parent),
+    }
+}
+
+/// Key paths of all mappings in value, formatted like key_path.
+fn collect_key_paths(value: &serde_yaml::Value, path: &str, key_paths: &mut Vec<String>) {
+    match value {
+        serde_yaml::Value::Mapping(mapping) => {
+            for (key, value) in mapping {
+                let key_path = match key {
+                    serde_yaml::Value::String(k) if path.is_empty() => k.clone(),
+                    serde_yaml::Value::String(k) => format!("{}.{}", path, k),
+                    k => format!("{}.{:?}", path, k),
+                };
+                collect_key_paths(value, &key_path, key_paths);
+                key_paths.push(key_path);
+            }
+        }
+        serde_yaml::Value::Sequence(sequence) => {
+            for (index, value) in sequence.iter().enumerate() {
+                collect_key_paths(value, &format!("{}[{}]", path, index), key_paths);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Whether key_path is parent_path or lies below it.
+fn is_within(key_path: &str, parent_path: &str) -> bool {
+    key_path
+        .strip_prefix(parent_path)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('.') || rest.starts_with('['))
+}
+
+fn reject_unknown_keys(path: &str, mut unknown_keys: Vec<String>) -> Result<(), ConfigError> {
+    unknown_keys.retain(|key| key != CONFIG_VERSION_KEY);
+
+    if !unknown_keys.is_empty() {
+        return Err(ConfigError::Validation {
+            path: path.to_string(),
+            message: format!("unknown fields {}", unknown_keys.join(", ")),
+        });
+    }
+
+    Ok(())
+}
+
+fn deserialize_config<T>(path: &str, contents: &str, format: ConfigFormat) -> Result<T, ConfigError>
+where
+    T: DeserializeOwned,
+{
+    match format {
+        ConfigFormat::Yaml => {
+            serde_yaml::from_str(contents).map_err(|e| ConfigError::parse(path, e))
+        }
+        ConfigFormat::Json => serde_json::from_str(contents).map_err(|e| ConfigError::Parse {
+            path: path.to_string(),
+            line: Some(e.line()),
+            column: Some(e.column()),
+            message: e.to_string(),
+        }),
+    }
+}
+
+/// Replaces `${VAR}` and `${VAR:-default}` occurrences with the value of the environment variable,
+/// falling back to the default when the variable is unset or empty; `$${...}` yields a literal `${...}`. Comments
+/// are left as they are.
+pub fn interpolate_env_vars(contents: &str) -> Result<String, Box<dyn Error>> {
+    let mut interpolated = String::with_capacity(contents.len());
+    let mut unresolved: Vec<String> = vec![];
+
+    for line in contents.split_inclusive('\n') {
+        let (code, comment) = line.split_at(comment_start(line).unwrap_or(line.len()));
+        interpolate_line(code, &mut interpolated, &mut unresolved);
+        interpolated.push_str(comment);
+    }
+
+    if !unresolved.is_empty() {
+        return Err(Box::<dyn Error>::from(format!(
+            "Unresolved environment variables without default in config: {}",
+            unresolved.join(", ")
+        )));
+    }
+
+    Ok(interpolated)
+}
+
+/// Index of the `#` starting a yaml comment in line, skipping those within quoted scalars.
+fn comment_start(line: &str) -> Option<usize> {
+    let mut quote: Option<char> = None;
+    let mut previous: Option<char> = None;
+    let mut chars = line.char_indices().peekable();
+    while let Some((index, c)) = chars.next() {
+        match quote {
+            Some('"') if c == '\\' => {
+                chars.next();
+            }
+            Some('\'') if c == '\'' && chars.peek().map(|(_, next)| *next) == Some('\'') => {
+                chars.next();
+            }
+            Some(open) if c == open => quote = None,
+            Some(_) => {}
+            // quotes only start a scalar, not halfway a plain one like an apostrophe
+            None if (c == '"' || c == '\'')
+                && previous.is_none_or(|p| p.is_whitespace() || ":-[{,".contains(p)) =>
+            {
+                quote = Some(c)
+            }
+            None if c == '#' && previous.is_none_or(char::is_whitespace) => return Some(index),
+            None => {}
+        }
+        previous = Some(c);
+    }
+
+    None
+}
+
+fn interpolate_line(line: &str, interpolated: &mut String, unresolved: &mut Vec<String>) {
+    let mut rest = line;
+    while let Some(index) = rest.find('$') {
+        interpolated.push_str(&rest[..index]);
+        rest = &rest[index..];
+
+        if rest.starts_with("$${") {
+            // escaped, keep a literal ${
+            interpolated.push_str("${");
+            rest = &rest[3..];
+        } else if rest.starts_with("${") {
+            match rest.find('}') {
+                Some(end) => {
+                    let expression = &rest[2..end];
+                    let (name, default) = match expression.split_once(":-") {
+                        Some((name, default)) => (name, Some(default)),
+                        None => (expression, None),
+                    };
+
+                    match (env::var(name).ok().filter(|v| !v.is_empty()), default) {
+                        (Some(value), _) => interpolated.push_str(&value),
+                        (None, Some(default)) => interpolated.push_str(default),
+                        (None, None) => {
+                            if !unresolved.iter().any(|u| u == name) {
+                                unresolved.push(name.to_string());
+                            }
+                        }
+                    }
+
+                    rest = &rest[end + 1..];
+                }
+                None => {
+                    // no closing brace, leave the remainder untouched
+                    interpolated.push_str(rest);
+                    rest = "";
+                }
+            }
+        } else {
+            interpolated.push('$');
+            rest = &rest[1..];
+        }
+    }
+    interpolated.push_str(rest);
 }
 
 #[cfg(test)]
@@ -69,6 +1029,9 @@ mod tests {
     use chrono::Weekday;
     use pretty_assertions::assert_eq;
     use serde::{Deserialize, Serialize};
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::thread;
 
     #[derive(Serialize, Deserialize, Debug)]
     #[serde(rename_all = "camelCase")]
@@ -82,6 +1045,829 @@ mod tests {
         fn set_defaults(&mut self) {}
     }
 
+    // serves a single http response and returns the url plus a handle yielding the received request head
+    fn serve_once(
+        path: &str,
+        content_type: &str,
+        body: &str,
+    ) -> (String, thread::JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}{}", listener.local_addr().unwrap(), path);
+        let response = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            content_type,
+            body.len(),
+            body
+        );
+
+        let handle = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let request_head: Vec<String> = BufReader::new(stream.try_clone().unwrap())
+                .lines()
+                .map(|l| l.unwrap())
+                .take_while(|l| !l.is_empty())
+                .collect();
+            stream.write_all(response.as_bytes()).unwrap();
+            request_head
+        });
+
+        (url, handle)
+    }
+
+    fn unreachable_url(path: &str) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        format!("http://{}{}", listener.local_addr().unwrap(), path)
+    }
+
+    #[test]
+    fn read_config_fetches_yaml_from_url_with_bearer_token() {
+        let (url, handle) = serve_once(
+            "/config",
+            "application/yaml",
+            "location: My Home\nentityType: ENTITY_TYPE_DEVICE\nentityName: TP-Link HS110\n",
+        );
+
+        let config_client = ConfigClient::new(ConfigClientConfig {
+            http_token: Some("abc123".to_string()),
+            ..ConfigClientConfig::new(url).unwrap()
+        });
+
+        let config: Config = tokio_test::block_on(config_client.read_config()).unwrap();
+
+        assert_eq!(config.location, "My Home".to_string());
+        assert_eq!(config.entity_type, EntityType::Device);
+        assert_eq!(config.entity_name, "TP-Link HS110".to_string());
+
+        let request_head = handle.join().unwrap();
+        assert!(request_head
+            .iter()
+            .any(|h| h.to_lowercase() == "authorization: bearer abc123"));
+    }
+
+    #[test]
+    fn read_planner_config_fetches_json_from_url_based_on_content_type() {
+        let (url, handle) = serve_once(
+            "/planner",
+            "application/json; charset=utf-8",
+            r#"{"localTimeZone":"Europe/Amsterdam","loadProfile":{"sections":[{"durationSeconds":3600,"powerDrawWatt":1500}]}}"#,
+        );
+
+        let config_client = ConfigClient::new(ConfigClientConfig::new(url).unwrap());
+
+        let config = tokio_test::block_on(config_client.read_planner_config()).unwrap();
+
+        assert_eq!(config.local_time_zone, "Europe/Amsterdam".to_string());
+        assert_eq!(config.load_profile.sections.len(), 1);
+        assert_eq!(config.load_profile.sections[0].power_draw_watt, 1500.0);
+
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn read_config_falls_back_to_cached_copy_when_url_is_unreachable() {
+        let http_cache_path = env::temp_dir()
+            .join("jarvis-lib-read-config-falls-back-to-cached-copy.yaml")
+            .to_string_lossy()
+            .to_string();
+
+        // a successful fetch stores the cached copy
+        let (url, handle) = serve_once(
+            "/config.yaml",
+            "text/plain",
+            "location: My Home\nentityType: ENTITY_TYPE_DEVICE\nentityName: TP-Link HS110\n",
+        );
+        let config_client = ConfigClient::new(ConfigClientConfig {
+            http_cache_path: Some(http_cache_path.clone()),
+            ..ConfigClientConfig::new(url).unwrap()
+        });
+        let _: Config = tokio_test::block_on(config_client.read_config()).unwrap();
+        handle.join().unwrap();
+
+        // act
+        let config_client = ConfigClient::new(ConfigClientConfig {
+            http_cache_path: Some(http_cache_path.clone()),
+            ..ConfigClientConfig::new(unreachable_url("/config.yaml")).unwrap()
+        });
+        let config: Config = tokio_test::block_on(config_client.read_config()).unwrap();
+
+        assert_eq!(config.entity_name, "TP-Link HS110".to_string());
+
+        fs::remove_file(http_cache_path).unwrap();
+    }
+
+    #[test]
+    fn read_config_returns_http_error_when_url_is_unreachable_without_cached_copy() {
+        let config_client = ConfigClient::new(ConfigClientConfig {
+            http_timeout: Duration::from_secs(1),
+            ..ConfigClientConfig::new(unreachable_url("/config.yaml")).unwrap()
+        });
+
+        let error = tokio_test::block_on(config_client.read_config::<Config>()).unwrap_err();
+
+        assert!(matches!(
+            error.downcast_ref::<ConfigError>(),
+            Some(ConfigError::Http { .. })
+        ));
+    }
+
+    #[derive(Serialize, Deserialize, Debug)]
+    #[serde(rename_all = "camelCase")]
+    pub struct DeviceConfig {
+        pub location: String,
+        pub device: Device,
+        pub registers: Vec<u16>,
+    }
+
+    #[derive(Serialize, Deserialize, Debug)]
+    #[serde(rename_all = "camelCase")]
+    pub struct Device {
+        pub host: String,
+        pub port: u16,
+        pub unit_id: u8,
+    }
+
+    impl SetDefaults for DeviceConfig {
+        fn set_defaults(&mut self) {}
+    }
+
+    const DEVICE_CONFIG_DEFAULTS: &str = r#"
+location: My Home
+device:
+  host: 192.168.1.10
+  port: 502
+  unitId: 3
+registers: [30513, 30775]
+"#;
+
+    #[test]
+    fn read_config_with_defaults_keeps_defaults_not_overridden_by_file() {
+        let config_client = ConfigClient::new(
+            ConfigClientConfig::new("test-config-overrides.yaml".to_string()).unwrap(),
+        );
+
+        let config: DeviceConfig = config_client
+            .read_config_with_defaults(DEVICE_CONFIG_DEFAULTS)
+            .unwrap();
+
+        assert_eq!(config.location, "My Home".to_string());
+        assert_eq!(config.device.host, "192.168.1.10".to_string());
+        assert_eq!(config.device.port, 1502);
+        assert_eq!(config.device.unit_id, 3);
+        assert_eq!(config.registers, vec![30529]);
+    }
+
+    #[test]
+    fn merge_values_merges_mappings_and_replaces_scalars_and_sequences() {
+        let mut base: serde_yaml::Value =
+            serde_yaml::from_str("a: 1\nb:\n  c: 2\n  d: [1, 2]\ne: keep\n").unwrap();
+        let overlay: serde_yaml::Value =
+            serde_yaml::from_str("a: 10\nb:\n  d: [3]\nf: new\n").unwrap();
+
+        merge_values(&mut base, overlay);
+
+        assert_eq!(
+            base,
+            serde_yaml::from_str::<serde_yaml::Value>(
+                "a: 10\nb:\n  c: 2\n  d: [3]\ne: keep\nf: new\n"
+            )
+            .unwrap()
+        );
+    }
+
+    fn write_config_with_modified_time(path: &Path, entity_name: &str, modified: SystemTime) {
+        fs::write(
+            path,
+            format!(
+                "location: My Home\nentityType: ENTITY_TYPE_DEVICE\nentityName: {}\n",
+                entity_name
+            ),
+        )
+        .unwrap();
+        fs::File::options()
+            .write(true)
+            .open(path)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+    }
+
+    #[test]
+    fn read_config_from_file_reuses_cached_document_until_file_changes() {
+        let path = env::temp_dir().join("jarvis-lib-read-config-from-file-reuses-cache.yaml");
+        let modified = SystemTime::now() - Duration::from_secs(60);
+        write_config_with_modified_time(&path, "Plug A", modified);
+
+        let config_client =
+            ConfigClient::new(ConfigClientConfig::new(path.to_string_lossy().to_string()).unwrap());
+
+        let config: Config = config_client.read_config_from_file().unwrap();
+        assert_eq!(config.entity_name, "Plug A".to_string());
+
+        // same length and modification time, so the cached document is used
+        write_config_with_modified_time(&path, "Plug B", modified);
+        let config: Config = config_client.read_config_from_file().unwrap();
+        assert_eq!(config.entity_name, "Plug A".to_string());
+
+        // touching the file invalidates the cached document
+        write_config_with_modified_time(&path, "Plug B", modified + Duration::from_secs(10));
+        let config: Config = config_client.read_config_from_file().unwrap();
+        assert_eq!(config.entity_name, "Plug B".to_string());
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn read_config_from_file_reinterpolates_cached_document_when_env_var_changes() {
+        let path = env::temp_dir().join("jarvis-lib-read-config-from-file-reinterpolates.yaml");
+        let modified = SystemTime::now() - Duration::from_secs(60);
+        write_config_with_modified_time(&path, "${JARVIS_TEST_CACHED_ENTITY_NAME}", modified);
+        env::set_var("JARVIS_TEST_CACHED_ENTITY_NAME", "Plug A");
+
+        let config_client =
+            ConfigClient::new(ConfigClientConfig::new(path.to_string_lossy().to_string()).unwrap());
+
+        let config: Config = config_client.read_config_from_file().unwrap();
+        assert_eq!(config.entity_name, "Plug A".to_string());
+
+        env::set_var("JARVIS_TEST_CACHED_ENTITY_NAME", "Plug B");
+
+        // act
+        let config: Config = config_client.read_config_from_file().unwrap();
+
+        assert_eq!(config.entity_name, "Plug B".to_string());
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn force_reload_bypasses_cached_document() {
+        let path = env::temp_dir().join("jarvis-lib-force-reload-bypasses-cache.yaml");
+        let modified = SystemTime::now() - Duration::from_secs(60);
+        write_config_with_modified_time(&path, "Plug A", modified);
+
+        let config_client =
+            ConfigClient::new(ConfigClientConfig::new(path.to_string_lossy().to_string()).unwrap());
+
+        let config: Config = config_client.read_config_from_file().unwrap();
+        assert_eq!(config.entity_name, "Plug A".to_string());
+
+        write_config_with_modified_time(&path, "Plug C", modified);
+
+        // act
+        config_client.force_reload();
+
+        let config: Config = config_client.read_config_from_file().unwrap();
+        assert_eq!(config.entity_name, "Plug C".to_string());
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[derive(Serialize, Deserialize, Debug)]
+    #[serde(rename_all = "camelCase")]
+    pub struct CredentialsConfig {
+        pub location: String,
+        pub host: String,
+        pub username: String,
+        pub password: String,
+    }
+
+    impl SetDefaults for CredentialsConfig {
+        fn set_defaults(&mut self) {}
+    }
+
+    #[test]
+    fn read_config_from_file_merges_secrets_over_config() {
+        let config_client = ConfigClient::new(ConfigClientConfig {
+            secrets_path: Some("test-secrets.yaml".to_string()),
+            secrets_required: true,
+            ..ConfigClientConfig::new("test-config-credentials.yaml".to_string()).unwrap()
+        });
+
+        let config: CredentialsConfig = config_client.read_config_from_file().unwrap();
+
+        assert_eq!(config.location, "My Home".to_string());
+        assert_eq!(config.host, "192.168.1.10".to_string());
+        // secrets file wins over the placeholder in the config file
+        assert_eq!(config.username, "admin".to_string());
+        assert_eq!(config.password, "hunter2".to_string());
+    }
+
+    #[test]
+    fn read_config_from_file_ignores_missing_defaulted_secrets_file() {
+        let config_client = ConfigClient::new(ConfigClientConfig {
+            secrets_path: Some("test-secrets-does-not-exist.yaml".to_string()),
+            secrets_required: false,
+            ..ConfigClientConfig::new("test-config-credentials.yaml".to_string()).unwrap()
+        });
+
+        let config: CredentialsConfig = config_client.read_config_from_file().unwrap();
+
+        assert_eq!(config.username, "admin".to_string());
+        assert_eq!(config.password, "changeme".to_string());
+    }
+
+    #[test]
+    fn read_config_from_file_fails_for_missing_explicit_secrets_file() {
+        let config_client = ConfigClient::new(ConfigClientConfig {
+            secrets_path: Some("test-secrets-does-not-exist.yaml".to_string()),
+            secrets_required: true,
+            ..ConfigClientConfig::new("test-config-credentials.yaml".to_string()).unwrap()
+        });
+
+        let error = config_client
+            .read_config_from_file::<CredentialsConfig>()
+            .unwrap_err();
+
+        assert!(matches!(
+            error.downcast_ref::<ConfigError>(),
+            Some(ConfigError::Io { path, .. }) if path == "test-secrets-does-not-exist.yaml"
+        ));
+    }
+
+    #[test]
+    fn read_planner_config_from_file_accepts_unknown_fields_when_not_strict() {
+        let config_client = ConfigClient::new(
+            ConfigClientConfig::new("test-planner-config-typo.yaml".to_string()).unwrap(),
+        );
+
+        let config = config_client.read_planner_config_from_file().unwrap();
+
+        // the misspelled time zone silently falls back to the default
+        assert_eq!(config.local_time_zone, "UTC".to_string());
+    }
+
+    #[test]
+    fn read_planner_config_from_file_rejects_unknown_fields_when_strict() {
+        let config_client = ConfigClient::new(ConfigClientConfig {
+            strict: true,
+            ..ConfigClientConfig::new("test-planner-config-typo.yaml".to_string()).unwrap()
+        });
+
+        let error = config_client.read_planner_config_from_file().unwrap_err();
+
+        match error.downcast_ref::<ConfigError>() {
+            Some(ConfigError::Validation { message, .. }) => {
+                assert_eq!(
+                    message,
+                    "unknown fields localTimezone, loadProfile.sections[0].powerDrawWatts"
+                );
+            }
+            e => panic!("expected validation error, got {:?}", e),
+        }
+    }
+
+    #[derive(Deserialize, Debug)]
+    pub struct LocationConfig {
+        pub location: String,
+    }
+
+    impl SetDefaults for LocationConfig {
+        fn set_defaults(&mut self) {}
+    }
+
+    #[test]
+    fn read_config_from_file_rejects_unknown_fields_of_deserialize_only_config_when_strict() {
+        let config_client = ConfigClient::new(ConfigClientConfig {
+            strict: true,
+            planner_config_path: Some("test-planner-config-v2.yaml".to_string()),
+            ..ConfigClientConfig::new("test-config-credentials.yaml".to_string()).unwrap()
+        });
+
+        let error = config_client
+            .read_config_from_file::<LocationConfig>()
+            .unwrap_err();
+
+        match error.downcast_ref::<ConfigError>() {
+            Some(ConfigError::Validation { message, .. }) => {
+                assert_eq!(message, "unknown fields host, username, password");
+            }
+            e => panic!("expected validation error, got {:?}", e),
+        }
+    }
+
+    #[test]
+    fn read_config_from_file_accepts_planner_fields_in_shared_file_when_strict() {
+        let config_client = ConfigClient::new(ConfigClientConfig {
+            strict: true,
+            ..ConfigClientConfig::new("test-config.yaml".to_string()).unwrap()
+        });
+
+        let config: Config = config_client.read_config_from_file().unwrap();
+        let planner_config = config_client.read_planner_config_from_file().unwrap();
+
+        assert_eq!(config.entity_name, "TP-Link HS110".to_string());
+        assert_eq!(planner_config.load_profile.sections.len(), 2);
+    }
+
+    #[test]
+    fn read_config_from_file_accepts_known_fields_when_strict() {
+        let config_client = ConfigClient::new(ConfigClientConfig {
+            strict: true,
+            ..ConfigClientConfig::new("test-config-credentials.yaml".to_string()).unwrap()
+        });
+
+        let config: CredentialsConfig = config_client.read_config_from_file().unwrap();
+
+        assert_eq!(config.username, "admin".to_string());
+    }
+
+    #[test]
+    fn read_planner_config_from_file_migrates_unversioned_config() {
+        let config_client = ConfigClient::new(ConfigClientConfig {
+            strict: true,
+            ..ConfigClientConfig::new("test-planner-config-v1.yaml".to_string()).unwrap()
+        });
+
+        let config = config_client.read_planner_config_from_file().unwrap();
+
+        assert_eq!(config.plannable_local_time_slots.len(), 1);
+        assert_eq!(
+            config
+                .plannable_local_time_slots
+                .get(&Weekday::Sat)
+                .unwrap()
+                .len(),
+            1
+        );
+
+        let serialized = serde_yaml::to_value(&config).unwrap();
+        assert!(serialized.get("plannableTimeSlots").is_some());
+        assert!(serialized.get("plannableLocalTimeSlots").is_none());
+    }
+
+    #[test]
+    fn read_planner_config_from_file_reads_current_version_without_migration() {
+        let config_client = ConfigClient::new(ConfigClientConfig {
+            strict: true,
+            ..ConfigClientConfig::new("test-planner-config-v2.yaml".to_string()).unwrap()
+        });
+
+        let config = config_client.read_planner_config_from_file().unwrap();
+
+        assert_eq!(config.plannable_local_time_slots.len(), 1);
+        assert_eq!(
+            config
+                .plannable_local_time_slots
+                .get(&Weekday::Sun)
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn read_planner_config_from_file_expands_weekday_groups_when_strict() {
+        let config_client = ConfigClient::new(ConfigClientConfig {
+            strict: true,
+            ..ConfigClientConfig::new("test-planner-config-groups.yaml".to_string()).unwrap()
+        });
+
+        let config = config_client.read_planner_config_from_file().unwrap();
+
+        assert_eq!(config.plannable_local_time_slots.len(), 7);
+        assert_eq!(
+            config.plannable_local_time_slots[&Weekday::Wed][0].from,
+            NaiveTime::from_hms_opt(1, 0, 0).unwrap()
+        );
+        assert_eq!(
+            config.plannable_local_time_slots[&Weekday::Sat][0].till,
+            NaiveTime::from_hms_opt(0, 0, 0).unwrap()
+        );
+    }
+
+    #[test]
+    fn read_planner_config_from_file_rejects_newer_config_version() {
+        let path = env::temp_dir().join("jarvis-lib-read-planner-config-newer-version.yaml");
+        fs::write(
+            &path,
+            "configVersion: 99\nloadProfile:\n  sections:\n    - durationSeconds: 7200\n      powerDrawWatt: 2000\n",
+        )
+        .unwrap();
+
+        let config_client =
+            ConfigClient::new(ConfigClientConfig::new(path.to_string_lossy().to_string()).unwrap());
+
+        let error = config_client.read_planner_config_from_file().unwrap_err();
+
+        assert!(error.to_string().contains("configVersion 99"));
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn read_config_from_file_and_read_planner_config_from_file_use_separate_paths() {
+        let config_client = ConfigClient::new(ConfigClientConfig {
+            planner_config_path: Some("test-planner-config-v2.yaml".to_string()),
+            ..ConfigClientConfig::new("test-config-credentials.yaml".to_string()).unwrap()
+        });
+
+        let config: CredentialsConfig = config_client.read_config_from_file().unwrap();
+        let planner_config = config_client.read_planner_config_from_file().unwrap();
+
+        assert_eq!(config.host, "192.168.1.10".to_string());
+        assert_eq!(
+            planner_config
+                .plannable_local_time_slots
+                .get(&Weekday::Sun)
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn read_planner_config_from_file_falls_back_to_config_path() {
+        let config_client = ConfigClient::new(
+            ConfigClientConfig::new("test-planner-config-v2.yaml".to_string()).unwrap(),
+        );
+
+        let planner_config = config_client.read_planner_config_from_file().unwrap();
+
+        assert_eq!(planner_config.plannable_local_time_slots.len(), 1);
+    }
+
+    #[test]
+    fn read_config_with_runs_normalizing_closure_after_set_defaults() {
+        let config_client =
+            ConfigClient::new(ConfigClientConfig::new("test-config.yaml".to_string()).unwrap());
+
+        let config: Config = config_client
+            .read_config_with(|config: &mut Config| {
+                config.entity_name = config.entity_name.to_lowercase();
+                Ok(())
+            })
+            .unwrap();
+
+        assert_eq!(config.entity_name, "tp-link hs110".to_string());
+    }
+
+    #[test]
+    fn read_config_with_wraps_closure_error_with_config_path() {
+        let config_client =
+            ConfigClient::new(ConfigClientConfig::new("test-config.yaml".to_string()).unwrap());
+
+        let error = config_client
+            .read_config_with(|config: &mut Config| {
+                if config.entity_type == EntityType::Device {
+                    return Err(Box::<dyn Error>::from("devices are not supported"));
+                }
+                Ok(())
+            })
+            .unwrap_err();
+
+        match error.downcast_ref::<ConfigError>() {
+            Some(ConfigError::Validation { path, message }) => {
+                assert_eq!(path, "test-config.yaml");
+                assert_eq!(message, "devices are not supported");
+            }
+            e => panic!("expected validation error, got {:?}", e),
+        }
+        assert_eq!(
+            error.to_string(),
+            "Invalid config test-config.yaml: devices are not supported"
+        );
+    }
+
+    #[test]
+    fn builder_defaults_match_from_env_defaults() {
+        for name in [
+            "CONFIG_PATH",
+            "PLANNER_CONFIG_PATH",
+            "SECRETS_PATH",
+            "CONFIG_STRICT",
+            "CONFIG_HTTP_TIMEOUT_SECONDS",
+            "CONFIG_HTTP_TOKEN",
+            "CONFIG_HTTP_CACHE_PATH",
+        ] {
+            env::remove_var(name);
+        }
+
+        let built = ConfigClientConfig::builder().build().unwrap();
+        let from_env = ConfigClientConfig::from_env().unwrap();
+
+        assert_eq!(built.config_path(), "/configs/config.yaml");
+        assert_eq!(built.config_path(), from_env.config_path());
+        assert_eq!(built.planner_config_path(), from_env.planner_config_path());
+        assert_eq!(built.secrets_path(), Some("/secrets/secrets.yaml"));
+        assert_eq!(built.secrets_path(), from_env.secrets_path());
+        assert!(!built.secrets_required());
+        assert_eq!(built.secrets_required(), from_env.secrets_required());
+        assert_eq!(built.strict(), from_env.strict());
+        assert_eq!(built.http_timeout(), Duration::from_secs(10));
+        assert_eq!(built.http_timeout(), from_env.http_timeout());
+        assert_eq!(built.http_token(), from_env.http_token());
+        assert_eq!(built.http_cache_path(), from_env.http_cache_path());
+    }
+
+    #[test]
+    fn builder_sets_fields_and_client_exposes_paths() {
+        let config = ConfigClientConfig::builder()
+            .config_path("test-config-credentials.yaml")
+            .planner_config_path("test-planner-config-v2.yaml")
+            .secrets_path("test-secrets.yaml")
+            .strict(true)
+            .http_timeout(Duration::from_secs(3))
+            .build()
+            .unwrap();
+
+        assert_eq!(config.secrets_path(), Some("test-secrets.yaml"));
+        assert!(config.secrets_required());
+        assert!(config.strict());
+        assert_eq!(config.http_timeout(), Duration::from_secs(3));
+
+        let config_client = ConfigClient::new(config);
+
+        assert_eq!(config_client.config_path(), "test-config-credentials.yaml");
+        assert_eq!(
+            config_client.planner_config_path(),
+            "test-planner-config-v2.yaml"
+        );
+
+        let config: CredentialsConfig = config_client.read_config_from_file().unwrap();
+        assert_eq!(config.password, "hunter2".to_string());
+    }
+
+    #[test]
+    fn interpolate_env_vars_replaces_set_variables() {
+        env::set_var("JARVIS_TEST_INTERPOLATE_SET", "secret");
+
+        let interpolated =
+            interpolate_env_vars("password: ${JARVIS_TEST_INTERPOLATE_SET}\n").unwrap();
+
+        assert_eq!(interpolated, "password: secret\n");
+    }
+
+    #[test]
+    fn interpolate_env_vars_uses_default_for_unset_variables() {
+        env::remove_var("JARVIS_TEST_INTERPOLATE_UNSET");
+
+        let interpolated =
+            interpolate_env_vars("host: ${JARVIS_TEST_INTERPOLATE_UNSET:-192.168.1.10}").unwrap();
+
+        assert_eq!(interpolated, "host: 192.168.1.10");
+    }
+
+    #[test]
+    fn interpolate_env_vars_prefers_set_variable_over_default() {
+        env::set_var("JARVIS_TEST_INTERPOLATE_SET_WITH_DEFAULT", "10.0.0.1");
+
+        let interpolated =
+            interpolate_env_vars("host: ${JARVIS_TEST_INTERPOLATE_SET_WITH_DEFAULT:-192.168.1.10}")
+                .unwrap();
+
+        assert_eq!(interpolated, "host: 10.0.0.1");
+    }
+
+    #[test]
+    fn interpolate_env_vars_fails_listing_unresolved_variables() {
+        env::remove_var("JARVIS_TEST_INTERPOLATE_MISSING_A");
+        env::remove_var("JARVIS_TEST_INTERPOLATE_MISSING_B");
+
+        let result = interpolate_env_vars(
+            "user: ${JARVIS_TEST_INTERPOLATE_MISSING_A}\npassword: ${JARVIS_TEST_INTERPOLATE_MISSING_B}\n",
+        );
+
+        let message = result.unwrap_err().to_string();
+        assert!(message.contains("JARVIS_TEST_INTERPOLATE_MISSING_A"));
+        assert!(message.contains("JARVIS_TEST_INTERPOLATE_MISSING_B"));
+    }
+
+    #[test]
+    fn interpolate_env_vars_keeps_escaped_expressions_literal() {
+        env::set_var("JARVIS_TEST_INTERPOLATE_ESCAPED", "secret");
+
+        let interpolated =
+            interpolate_env_vars("literal: $${JARVIS_TEST_INTERPOLATE_ESCAPED} costs $5").unwrap();
+
+        assert_eq!(
+            interpolated,
+            "literal: ${JARVIS_TEST_INTERPOLATE_ESCAPED} costs $5"
+        );
+    }
+
+    #[test]
+    fn interpolate_env_vars_leaves_comments_untouched() {
+        env::set_var("JARVIS_TEST_INTERPOLATE_COMMENTED_SET", "secret");
+        env::remove_var("JARVIS_TEST_INTERPOLATE_COMMENTED_UNSET");
+
+        let interpolated = interpolate_env_vars(
+            "# password: ${JARVIS_TEST_INTERPOLATE_COMMENTED_UNSET}\npassword: ${JARVIS_TEST_INTERPOLATE_COMMENTED_SET} # was ${JARVIS_TEST_INTERPOLATE_COMMENTED_UNSET}\nname: \"plug #${JARVIS_TEST_INTERPOLATE_COMMENTED_SET}\"\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            interpolated,
+            "# password: ${JARVIS_TEST_INTERPOLATE_COMMENTED_UNSET}\npassword: secret # was ${JARVIS_TEST_INTERPOLATE_COMMENTED_UNSET}\nname: \"plug #secret\"\n"
+        );
+    }
+
+    #[test]
+    fn read_config_from_file_interpolates_env_vars() {
+        env::set_var("JARVIS_TEST_ENTITY_NAME", "TP-Link HS110");
+        env::remove_var("JARVIS_TEST_LOCATION");
+
+        let config_client =
+            ConfigClient::new(ConfigClientConfig::new("test-config-env.yaml".to_string()).unwrap());
+
+        let config: Config = config_client.read_config_from_file().unwrap();
+
+        assert_eq!(config.location, "My Home".to_string());
+        assert_eq!(config.entity_type, EntityType::Device);
+        assert_eq!(config.entity_name, "TP-Link HS110".to_string());
+    }
+
+    #[test]
+    fn read_planner_config_from_file_applies_defaults_for_minimal_config(
+    ) -> Result<(), Box<dyn Error>> {
+        let config_client = ConfigClient::new(ConfigClientConfig::new(
+            "test-planner-config-minimal.yaml".to_string(),
+        )?);
+
+        let config: SpotPricePlannerConfig = config_client.read_planner_config_from_file()?;
+
+        assert_eq!(config.local_time_zone, "UTC".to_string());
+        assert!(config.plannable_local_time_slots.is_empty());
+        assert_eq!(config.load_profile.sections.len(), 1);
+
+        let spot_price_response: SpotPriceResponse =
+            serde_json::from_str(&fs::read_to_string("spot_price_predictions.json")?)?;
+        let spot_prices = spot_price_response.data.market_prices_electricity;
+
+        let spot_price_planner = SpotPricePlanner::new(config);
+
+        let plannable_spot_prices =
+            spot_price_planner.get_plannable_spot_prices(&spot_prices, &None, &None)?;
+        assert_eq!(plannable_spot_prices.len(), spot_prices.len());
+
+        let response = spot_price_planner
+            .get_best_spot_prices(&PlanningRequest {
+                spot_prices,
+                load_profile: spot_price_planner.config.load_profile.clone(),
+                planning_strategy: PlanningStrategy::LowestPrice,
+                after: None,
+                before: None,
+                ..PlanningRequest::default()
+            })?
+            .into_planned()
+            .ok_or("not plannable")?;
+        assert_eq!(response.spot_prices.len(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn read_planner_config_from_file_returns_located_error_for_invalid_value() {
+        let config_client = ConfigClient::new(
+            ConfigClientConfig::new("test-config-broken.yaml".to_string()).unwrap(),
+        );
+
+        let error = config_client.read_planner_config_from_file().unwrap_err();
+
+        match error.downcast_ref::<ConfigError>() {
+            Some(ConfigError::Parse {
+                path, line, column, ..
+            }) => {
+                assert_eq!(path, "test-config-broken.yaml");
+                assert_eq!(*line, Some(4));
+                assert_eq!(*column, Some(24));
+            }
+            e => panic!("expected parse error, got {:?}", e),
+        }
+
+        let message = error.to_string();
+        assert!(message.contains("test-config-broken.yaml"));
+        assert!(message.contains("line 4 column 24"));
+    }
+
+    #[test]
+    fn read_config_from_file_returns_io_error_with_path_for_missing_file() {
+        let config_client = ConfigClient::new(
+            ConfigClientConfig::new("test-config-does-not-exist.yaml".to_string()).unwrap(),
+        );
+
+        let error = config_client.read_config_from_file::<Config>().unwrap_err();
+
+        assert!(matches!(
+            error.downcast_ref::<ConfigError>(),
+            Some(ConfigError::Io { .. })
+        ));
+        assert!(error
+            .to_string()
+            .contains("test-config-does-not-exist.yaml"));
+    }
+
+    #[test]
+    fn read_planner_config_from_file_interpolates_env_vars() {
+        env::remove_var("JARVIS_TEST_LOCAL_TIME_ZONE");
+
+        let config_client =
+            ConfigClient::new(ConfigClientConfig::new("test-config-env.yaml".to_string()).unwrap());
+
+        let config: SpotPricePlannerConfig = config_client.read_planner_config_from_file().unwrap();
+
+        assert_eq!(config.local_time_zone, "Europe/Amsterdam".to_string());
+    }
+
     #[test]
     fn read_config_from_file_returns_deserialized_test_file() {
         let config_client =
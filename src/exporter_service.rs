@@ -2,15 +2,30 @@ use std::error::Error;
 
 use crate::config_client::{ConfigClient, SetDefaults};
 use crate::measurement_client::MeasurementClient;
+use crate::model::{has_errors, parse_labels, DedupePolicy, Measurement, PlausibilityRanges};
 use crate::nats_client::NatsClient;
 use crate::state_client::StateClient;
 use serde::de::DeserializeOwned;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::env;
+use tracing::warn;
 
 pub struct ExporterServiceConfig<T: ?Sized> {
     config_client: ConfigClient,
     nats_client: NatsClient,
     state_client: StateClient,
     measurement_client: Box<dyn MeasurementClient<T>>,
+    /// Aborts a run with invalid measurements instead of dropping them.
+    strict_validation: bool,
+    /// Labels added to every published measurement, read from `MEASUREMENT_LABELS`.
+    labels: BTreeMap<String, String>,
+    /// Removes duplicate samples from measurements, keeping the last, if `MEASUREMENT_DEDUPE` is true.
+    dedupe: bool,
+    /// Removes implausible samples instead of only logging them, if `MEASUREMENT_DROP_IMPLAUSIBLE` is true.
+    drop_implausible: bool,
+    /// Replaces the location of published measurements, read from `MEASUREMENT_REDACT_LOCATION`.
+    redaction: MeasurementRedaction,
 }
 
 impl<T> ExporterServiceConfig<T> {
@@ -25,8 +40,97 @@ impl<T> ExporterServiceConfig<T> {
             nats_client,
             state_client,
             measurement_client,
+            strict_validation: false,
+            labels: match env::var("MEASUREMENT_LABELS") {
+                Ok(labels) => parse_labels(&labels)?,
+                Err(_) => BTreeMap::new(),
+            },
+            dedupe: env::var("MEASUREMENT_DEDUPE")
+                .map(|dedupe| dedupe.eq_ignore_ascii_case("true"))
+                .unwrap_or(false),
+            drop_implausible: env::var("MEASUREMENT_DROP_IMPLAUSIBLE")
+                .map(|drop_implausible| drop_implausible.eq_ignore_ascii_case("true"))
+                .unwrap_or(false),
+            redaction: MeasurementRedaction::parse(
+                &env::var("MEASUREMENT_REDACT_LOCATION").unwrap_or_default(),
+                env::var("MEASUREMENT_REDACT_SALT").ok(),
+            )?,
         })
     }
+
+    pub fn with_strict_validation(mut self, strict_validation: bool) -> Self {
+        self.strict_validation = strict_validation;
+        self
+    }
+
+    pub fn with_labels(mut self, labels: BTreeMap<String, String>) -> Self {
+        self.labels = labels;
+        self
+    }
+
+    pub fn with_dedupe(mut self, dedupe: bool) -> Self {
+        self.dedupe = dedupe;
+        self
+    }
+
+    pub fn with_drop_implausible(mut self, drop_implausible: bool) -> Self {
+        self.drop_implausible = drop_implausible;
+        self
+    }
+
+    pub fn with_redaction(mut self, redaction: MeasurementRedaction) -> Self {
+        self.redaction = redaction;
+        self
+    }
+}
+
+/// How the location, often a home address, is hidden from published measurements.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub enum MeasurementRedaction {
+    #[default]
+    None,
+    /// Replaces the location with a hex SHA-256 hash of salt and location, the same across runs.
+    Hash { salt: String },
+    /// Replaces the location with a pseudonym.
+    Fixed(String),
+}
+
+impl MeasurementRedaction {
+    /// Redaction for a `hash`, `fixed:<pseudonym>` or `none` value; hashing requires a salt.
+    pub fn parse(value: &str, salt: Option<String>) -> Result<Self, Box<dyn Error>> {
+        match value {
+            "" | "none" => Ok(MeasurementRedaction::None),
+            "hash" => match salt {
+                Some(salt) if !salt.is_empty() => Ok(MeasurementRedaction::Hash { salt }),
+                _ => Err(Box::<dyn Error>::from(
+                    "Hashing locations requires a salt in MEASUREMENT_REDACT_SALT",
+                )),
+            },
+            _ => match value.strip_prefix("fixed:") {
+                Some(pseudonym) if !pseudonym.is_empty() => {
+                    Ok(MeasurementRedaction::Fixed(pseudonym.to_string()))
+                }
+                _ => Err(Box::<dyn Error>::from(format!(
+                    "Invalid location redaction {}, expected hash, fixed:<value> or none",
+                    value
+                ))),
+            },
+        }
+    }
+
+    pub fn redact_location(&self, location: &str) -> String {
+        match self {
+            MeasurementRedaction::None => location.to_string(),
+            MeasurementRedaction::Hash { salt } => Sha256::new()
+                .chain_update(salt)
+                .chain_update(location)
+                .finalize()
+                .iter()
+                .map(|byte| format!("{:02x}", byte))
+                .collect(),
+            MeasurementRedaction::Fixed(pseudonym) => pseudonym.clone(),
+        }
+    }
 }
 
 pub struct ExporterService<T> {
@@ -42,16 +146,32 @@ impl<T> ExporterService<T> {
     where
         T: DeserializeOwned + SetDefaults,
     {
-        let config: T = self.config.config_client.read_config_from_file()?;
+        let config: T = self.config.config_client.read_config().await?;
 
-        let last_measurement = self.config.state_client.read_state()?;
+        let last_measurements = self.config.state_client.read_state()?;
+        let plausibility_ranges = self.config.measurement_client.plausibility_ranges(&config);
 
         let measurements = self
             .config
             .measurement_client
-            .get_measurements(config, last_measurement)?;
+            .get_measurements(config, last_measurements.clone())?;
+        let measurements = check_plausibility(
+            measurements,
+            last_measurements.as_deref().unwrap_or_default(),
+            &plausibility_ranges,
+            self.config.drop_implausible,
+        );
+        let measurements = if self.config.dedupe {
+            dedupe_measurements(measurements)
+        } else {
+            measurements
+        };
+        let measurements = validate_measurements(
+            inject_labels(measurements, &self.config.labels),
+            self.config.strict_validation,
+        )?;
 
-        for measurement in &measurements {
+        for measurement in &redact_measurements(&measurements, &self.config.redaction) {
             self.config.nats_client.publish(measurement)?;
         }
 
@@ -62,3 +182,309 @@ impl<T> ExporterService<T> {
         Ok(())
     }
 }
+
+/// Measurements with implausible samples logged and, with drop_implausible, removed.
+fn check_plausibility(
+    mut measurements: Vec<Measurement>,
+    last_measurements: &[Measurement],
+    ranges: &PlausibilityRanges,
+    drop_implausible: bool,
+) -> Vec<Measurement> {
+    for measurement in &mut measurements {
+        let violations =
+            measurement.check_plausibility(ranges, measurement.find_previous(last_measurements));
+        for violation in &violations {
+            warn!("Measurement {}: {}", measurement.id, violation);
+        }
+        if drop_implausible && !violations.is_empty() {
+            let mut i = 0;
+            measurement.samples.retain(|_| {
+                let keep = !violations
+                    .iter()
+                    .any(|violation| violation.sample_index == i);
+                i += 1;
+                keep
+            });
+        }
+    }
+    measurements
+}
+
+/// Measurements without duplicate samples, keeping the last of each.
+fn dedupe_measurements(mut measurements: Vec<Measurement>) -> Vec<Measurement> {
+    for measurement in &mut measurements {
+        let removed = measurement.dedupe_samples(DedupePolicy::KeepLast);
+        if removed > 0 {
+            warn!(
+                "Removed {} duplicate samples from measurement {}",
+                removed, measurement.id
+            );
+        }
+    }
+    measurements
+}
+
+/// Measurements with labels added, overriding labels with the same key they already have.
+fn inject_labels(
+    mut measurements: Vec<Measurement>,
+    labels: &BTreeMap<String, String>,
+) -> Vec<Measurement> {
+    if labels.is_empty() {
+        return measurements;
+    }
+    for measurement in &mut measurements {
+        measurement
+            .labels
+            .get_or_insert_with(BTreeMap::new)
+            .extend(labels.clone());
+    }
+    measurements
+}
+
+/// Copies of measurements with their location redacted for publishing; measurements themselves keep it for the state.
+fn redact_measurements(
+    measurements: &[Measurement],
+    redaction: &MeasurementRedaction,
+) -> Vec<Measurement> {
+    measurements
+        .iter()
+        .map(|measurement| Measurement {
+            location: redaction.redact_location(&measurement.location),
+            ..measurement.clone()
+        })
+        .collect()
+}
+
+/// Measurements without validation errors; with strict_validation any invalid measurement is an error instead.
+fn validate_measurements(
+    measurements: Vec<Measurement>,
+    strict_validation: bool,
+) -> Result<Vec<Measurement>, Box<dyn Error>> {
+    let mut valid_measurements = vec![];
+    for measurement in measurements {
+        let issues = measurement.validate();
+        for issue in &issues {
+            warn!("Measurement {}: {}", measurement.id, issue);
+        }
+        if !has_errors(&issues) {
+            valid_measurements.push(measurement);
+        } else if strict_validation {
+            return Err(Box::<dyn Error>::from(format!(
+                "Measurement {} is invalid",
+                measurement.id
+            )));
+        } else {
+            warn!("Dropping invalid measurement {}", measurement.id);
+        }
+    }
+
+    Ok(valid_measurements)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{Sample, SampleType};
+    use pretty_assertions::assert_eq;
+
+    fn measurement(id: &str, value: f64) -> Measurement {
+        Measurement::builder("jarvis-modbus-exporter", "My Home")
+            .id(id)
+            .sample(
+                Sample::builder(SampleType::Temperature, value)
+                    .entity_name("Living room")
+                    .sample_name("Thermostat")
+                    .build(),
+            )
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn validate_measurements_drops_invalid_measurements() {
+        let measurements = vec![measurement("valid", 21.5), measurement("invalid", f64::NAN)];
+
+        // act
+        let valid_measurements = validate_measurements(measurements, false).unwrap();
+
+        assert_eq!(
+            valid_measurements
+                .iter()
+                .map(|measurement| measurement.id.as_str())
+                .collect::<Vec<_>>(),
+            vec!["valid"]
+        );
+    }
+
+    #[test]
+    fn validate_measurements_aborts_on_invalid_measurement_when_strict() {
+        let measurements = vec![measurement("valid", 21.5), measurement("invalid", f64::NAN)];
+
+        // act
+        let result = validate_measurements(measurements, true);
+
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Measurement invalid is invalid"
+        );
+    }
+
+    #[test]
+    fn validate_measurements_keeps_measurements_with_warnings() {
+        let mut with_warning = measurement("with warning", 21.5);
+        with_warning.samples[0].sample_name = "".into();
+
+        // act
+        let valid_measurements = validate_measurements(vec![with_warning], true).unwrap();
+
+        assert_eq!(valid_measurements.len(), 1);
+    }
+
+    #[test]
+    fn inject_labels_adds_labels_to_every_measurement() {
+        let mut with_label = measurement("with label", 21.5);
+        with_label.labels = Some(BTreeMap::from([
+            ("site-id".to_string(), "cabin".to_string()),
+            ("room".to_string(), "attic".to_string()),
+        ]));
+        let labels = parse_labels("site-id=home,firmware=1.2").unwrap();
+
+        // act
+        let measurements = inject_labels(
+            vec![measurement("without label", 21.5), with_label],
+            &labels,
+        );
+
+        assert_eq!(measurements[0].labels, Some(labels));
+        assert_eq!(
+            measurements[1].labels,
+            Some(BTreeMap::from([
+                ("firmware".to_string(), "1.2".to_string()),
+                ("room".to_string(), "attic".to_string()),
+                ("site-id".to_string(), "home".to_string()),
+            ]))
+        );
+    }
+
+    #[test]
+    fn inject_labels_without_labels_leaves_measurements_unlabeled() {
+        // act
+        let measurements = inject_labels(vec![measurement("valid", 21.5)], &BTreeMap::new());
+
+        assert_eq!(measurements[0].labels, None);
+    }
+
+    #[test]
+    fn dedupe_measurements_keeps_last_duplicate_sample() {
+        let mut measurement = measurement("duplicate", 21.5);
+        let mut duplicate = measurement.samples[0].clone();
+        duplicate.value = 22.0;
+        measurement.samples.push(duplicate);
+
+        // act
+        let measurements = dedupe_measurements(vec![measurement]);
+
+        assert_eq!(measurements[0].samples.len(), 1);
+        assert_eq!(measurements[0].samples[0].value, 22.0);
+    }
+
+    #[test]
+    fn check_plausibility_drops_implausible_samples_when_dropping() {
+        let mut measurement = measurement("hot", 21.5);
+        let mut implausible = measurement.samples[0].clone();
+        implausible.value = 6500.0;
+        measurement.samples.push(implausible);
+
+        // act
+        let measurements =
+            check_plausibility(vec![measurement], &[], &PlausibilityRanges::default(), true);
+
+        assert_eq!(measurements[0].samples.len(), 1);
+        assert_eq!(measurements[0].samples[0].value, 21.5);
+    }
+
+    #[test]
+    fn check_plausibility_keeps_implausible_samples_when_flagging() {
+        // act
+        let measurements = check_plausibility(
+            vec![measurement("hot", 6500.0)],
+            &[],
+            &PlausibilityRanges::default(),
+            false,
+        );
+
+        assert_eq!(measurements[0].samples[0].value, 6500.0);
+    }
+
+    #[test]
+    fn redaction_parses_hash_fixed_and_none() {
+        assert_eq!(
+            MeasurementRedaction::parse("hash", Some("pepper".into())).unwrap(),
+            MeasurementRedaction::Hash {
+                salt: "pepper".into()
+            }
+        );
+        assert_eq!(
+            MeasurementRedaction::parse("fixed:Home 1", None).unwrap(),
+            MeasurementRedaction::Fixed("Home 1".into())
+        );
+        assert_eq!(
+            MeasurementRedaction::parse("none", None).unwrap(),
+            MeasurementRedaction::None
+        );
+        assert_eq!(
+            MeasurementRedaction::parse("", None).unwrap(),
+            MeasurementRedaction::None
+        );
+    }
+
+    #[test]
+    fn redaction_rejects_hash_without_salt_and_unknown_values() {
+        assert!(MeasurementRedaction::parse("hash", None).is_err());
+        assert!(MeasurementRedaction::parse("hash", Some("".into())).is_err());
+        assert!(MeasurementRedaction::parse("fixed:", None).is_err());
+        assert!(MeasurementRedaction::parse("blank", None).is_err());
+    }
+
+    #[test]
+    fn hash_redaction_is_stable_and_depends_on_salt() {
+        let redaction = MeasurementRedaction::Hash {
+            salt: "pepper".into(),
+        };
+
+        // act
+        let hash = redaction.redact_location("My Home");
+
+        assert_eq!(
+            hash,
+            "a8a8247a0118a108cbeadddd772afe04c53eb651ed61dc3996ada65d1e22c889"
+        );
+        assert_eq!(redaction.redact_location("My Home"), hash);
+        assert_ne!(
+            MeasurementRedaction::Hash {
+                salt: "salt".into()
+            }
+            .redact_location("My Home"),
+            hash
+        );
+    }
+
+    #[test]
+    fn redact_measurements_replaces_location_of_published_copies_only() {
+        let measurements = vec![measurement("at home", 21.5)];
+
+        // act
+        let redacted =
+            redact_measurements(&measurements, &MeasurementRedaction::Fixed("Home 1".into()));
+
+        assert_eq!(redacted[0].location, "Home 1");
+        assert_eq!(measurements[0].location, "My Home");
+        assert_eq!(
+            Measurement {
+                location: "My Home".into(),
+                ..redacted[0].clone()
+            },
+            measurements[0]
+        );
+    }
+}
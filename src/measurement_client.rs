@@ -1,4 +1,4 @@
-use crate::model::Measurement;
+use crate::model::{Measurement, PlausibilityRanges};
 use serde::de::DeserializeOwned;
 use std::error::Error;
 
@@ -10,4 +10,9 @@ pub trait MeasurementClient<T: ?Sized> {
     ) -> Result<Vec<Measurement>, Box<dyn Error>>
     where
         T: DeserializeOwned;
+
+    /// Ranges the exporter service checks measurements against; override to read them from config.
+    fn plausibility_ranges(&self, _config: &T) -> PlausibilityRanges {
+        PlausibilityRanges::default()
+    }
 }
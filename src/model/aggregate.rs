@@ -0,0 +1,237 @@
+use crate::model::{EntityType, Measurement, Sample};
+use tracing::warn;
+
+/// Entity and sample name of samples combining other samples.
+pub const AGGREGATE_NAME: &str = "aggregate";
+
+/// What samples are summed by; samples with different metric types or units are never summed together.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum AggregateKey {
+    /// Per sample type, as samples for the zone covering the whole location.
+    SampleType,
+    SampleTypeAndEntityType,
+}
+
+/// Sums of the values of samples per key, in order of first appearance.
+pub(crate) fn aggregate<'a>(
+    samples: impl IntoIterator<Item = &'a Sample>,
+    key: AggregateKey,
+) -> Vec<Sample> {
+    let mut aggregates: Vec<Sample> = vec![];
+    for sample in samples {
+        let entity_type = match key {
+            AggregateKey::SampleType => EntityType::Zone,
+            AggregateKey::SampleTypeAndEntityType => sample.entity_type.clone(),
+        };
+        match aggregates
+            .iter_mut()
+            .find(|aggregate| is_same_group(aggregate, &entity_type, sample))
+        {
+            Some(aggregate) => aggregate.value += sample.value,
+            None => aggregates.push(Sample {
+                entity_type,
+                entity_name: AGGREGATE_NAME.to_string(),
+                sample_name: AGGREGATE_NAME.to_string(),
+                ..sample.clone()
+            }),
+        }
+    }
+
+    aggregates
+}
+
+fn is_same_group(aggregate: &Sample, entity_type: &EntityType, sample: &Sample) -> bool {
+    &aggregate.entity_type == entity_type
+        && aggregate.sample_type == sample.sample_type
+        && aggregate.metric_type == sample.metric_type
+        && aggregate.unit == sample.unit
+}
+
+/// Sums of the values of the samples of measurements per key; measurements at another location than the first are
+/// left out.
+pub fn aggregate_measurements(measurements: &[Measurement], key: AggregateKey) -> Vec<Sample> {
+    let location = match measurements.first() {
+        Some(measurement) => &measurement.location,
+        None => return vec![],
+    };
+    let samples = measurements
+        .iter()
+        .filter(|measurement| {
+            let same_location = &measurement.location == location;
+            if !same_location {
+                warn!(
+                    "Leaving measurement {} at {} out of aggregate for {}",
+                    measurement.id, measurement.location, location
+                );
+            }
+            same_location
+        })
+        .flat_map(|measurement| &measurement.samples);
+
+    aggregate(samples, key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{MetricType, SampleType, SampleUnit};
+    use pretty_assertions::assert_eq;
+    use std::fs;
+
+    fn measurements() -> Vec<Measurement> {
+        serde_yaml::from_str(&fs::read_to_string("test-measurements-aggregate.yaml").unwrap())
+            .unwrap()
+    }
+
+    fn summary(samples: &[Sample]) -> Vec<(EntityType, SampleType, MetricType, f64)> {
+        samples
+            .iter()
+            .map(|sample| {
+                (
+                    sample.entity_type.clone(),
+                    sample.sample_type.clone(),
+                    sample.metric_type.clone(),
+                    sample.value,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn aggregate_measurements_sums_per_sample_type_keeping_counters_and_gauges_apart() {
+        // act
+        let samples = aggregate_measurements(&measurements(), AggregateKey::SampleType);
+
+        assert_eq!(
+            summary(&samples),
+            vec![
+                (
+                    EntityType::Zone,
+                    SampleType::ElectricityConsumption,
+                    MetricType::Counter,
+                    1500.0
+                ),
+                (
+                    EntityType::Zone,
+                    SampleType::ElectricityConsumption,
+                    MetricType::Gauge,
+                    400.0
+                ),
+                (
+                    EntityType::Zone,
+                    SampleType::ElectricityProduction,
+                    MetricType::Gauge,
+                    900.0
+                ),
+                (
+                    EntityType::Zone,
+                    SampleType::Temperature,
+                    MetricType::Gauge,
+                    21.5
+                ),
+            ]
+        );
+        assert!(samples
+            .iter()
+            .all(|sample| sample.entity_name == "aggregate" && sample.sample_name == "aggregate"));
+    }
+
+    #[test]
+    fn aggregate_measurements_sums_per_sample_type_and_entity_type() {
+        // act
+        let samples =
+            aggregate_measurements(&measurements(), AggregateKey::SampleTypeAndEntityType);
+
+        assert_eq!(
+            summary(&samples),
+            vec![
+                (
+                    EntityType::Device,
+                    SampleType::ElectricityConsumption,
+                    MetricType::Counter,
+                    1500.0
+                ),
+                (
+                    EntityType::Device,
+                    SampleType::ElectricityConsumption,
+                    MetricType::Gauge,
+                    150.0
+                ),
+                (
+                    EntityType::Phase,
+                    SampleType::ElectricityConsumption,
+                    MetricType::Gauge,
+                    250.0
+                ),
+                (
+                    EntityType::Phase,
+                    SampleType::ElectricityProduction,
+                    MetricType::Gauge,
+                    900.0
+                ),
+                (
+                    EntityType::Zone,
+                    SampleType::Temperature,
+                    MetricType::Gauge,
+                    21.5
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn aggregate_measurements_leaves_out_other_locations() {
+        let mut measurements = measurements();
+        measurements[2].location = "Holiday Home".into();
+
+        // act
+        let samples = aggregate_measurements(&measurements, AggregateKey::SampleType);
+
+        assert_eq!(samples[0].value, 1000.0);
+        assert_eq!(samples.len(), 3);
+    }
+
+    #[test]
+    fn aggregate_keeps_different_units_apart() {
+        let sample = |value, unit| {
+            Sample::builder(SampleType::ElectricityConsumption, value)
+                .unit(unit)
+                .build()
+        };
+        let samples = vec![
+            sample(1500.0, SampleUnit::Watt),
+            sample(2.0, SampleUnit::Kilowatt),
+            sample(500.0, SampleUnit::Watt),
+        ];
+
+        // act
+        let aggregates = aggregate(&samples, AggregateKey::SampleType);
+
+        assert_eq!(
+            aggregates
+                .iter()
+                .map(|sample| (sample.value, sample.unit))
+                .collect::<Vec<_>>(),
+            vec![
+                (2000.0, Some(SampleUnit::Watt)),
+                (2.0, Some(SampleUnit::Kilowatt))
+            ]
+        );
+    }
+
+    #[test]
+    fn aggregate_samples_sums_samples_of_one_measurement() {
+        let measurement = &measurements()[1];
+
+        // act
+        let samples = measurement.aggregate_samples(AggregateKey::SampleType);
+
+        assert_eq!(
+            samples
+                .iter()
+                .map(|sample| sample.value)
+                .collect::<Vec<_>>(),
+            vec![250.0, 900.0]
+        );
+    }
+}
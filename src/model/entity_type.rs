@@ -1,16 +1,158 @@
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::hash::{Hash, Hasher};
 
-#[derive(Copy, Clone, PartialEq, Eq, Serialize, Deserialize, Debug)]
-#[serde(rename_all = "camelCase")]
+/// Unknown names, like `ENTITY_TYPE_*` names added after this version, are kept as Other and serialized back as is.
+#[derive(Clone, Debug)]
 pub enum EntityType {
-    #[serde(rename = "")]
     Invalid,
-    #[serde(rename = "ENTITY_TYPE_TARIFF")]
     Tariff,
-    #[serde(rename = "ENTITY_TYPE_ZONE")]
     Zone,
-    #[serde(rename = "ENTITY_TYPE_DEVICE")]
     Device,
-    #[serde(rename = "ENTITY_TYPE_PHASE")]
     Phase,
+    Battery,
+    Vehicle,
+    Grid,
+    Other(String),
+}
+
+impl EntityType {
+    /// Name as serialized.
+    pub fn as_str(&self) -> &str {
+        match self {
+            EntityType::Invalid => "",
+            EntityType::Tariff => "ENTITY_TYPE_TARIFF",
+            EntityType::Zone => "ENTITY_TYPE_ZONE",
+            EntityType::Device => "ENTITY_TYPE_DEVICE",
+            EntityType::Phase => "ENTITY_TYPE_PHASE",
+            EntityType::Battery => "ENTITY_TYPE_BATTERY",
+            EntityType::Vehicle => "ENTITY_TYPE_VEHICLE",
+            EntityType::Grid => "ENTITY_TYPE_GRID",
+            EntityType::Other(name) => name,
+        }
+    }
+
+    /// Named variant for name, or Other if there is none.
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            "" => EntityType::Invalid,
+            "ENTITY_TYPE_TARIFF" => EntityType::Tariff,
+            "ENTITY_TYPE_ZONE" => EntityType::Zone,
+            "ENTITY_TYPE_DEVICE" => EntityType::Device,
+            "ENTITY_TYPE_PHASE" => EntityType::Phase,
+            "ENTITY_TYPE_BATTERY" => EntityType::Battery,
+            "ENTITY_TYPE_VEHICLE" => EntityType::Vehicle,
+            "ENTITY_TYPE_GRID" => EntityType::Grid,
+            _ => EntityType::Other(name.to_string()),
+        }
+    }
+}
+
+impl PartialEq for EntityType {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl Eq for EntityType {}
+
+impl Hash for EntityType {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_str().hash(state);
+    }
+}
+
+impl Serialize for EntityType {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for EntityType {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Ok(EntityType::from_name(&String::deserialize(deserializer)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::collections::HashSet;
+
+    #[test]
+    fn unknown_name_round_trips_as_other() {
+        // act
+        let entity_type = serde_json::from_str::<EntityType>(r#""ENTITY_TYPE_HEAT_PUMP""#).unwrap();
+
+        assert_eq!(
+            entity_type,
+            EntityType::Other("ENTITY_TYPE_HEAT_PUMP".to_string())
+        );
+        assert!(matches!(entity_type, EntityType::Other(_)));
+        assert_eq!(
+            serde_json::to_string(&entity_type).unwrap(),
+            r#""ENTITY_TYPE_HEAT_PUMP""#
+        );
+    }
+
+    #[test]
+    fn other_with_known_name_equals_named_variant() {
+        let other = EntityType::Other("ENTITY_TYPE_DEVICE".to_string());
+
+        // act
+        let unique = HashSet::from([other.clone(), EntityType::Device]);
+
+        assert_eq!(other, EntityType::Device);
+        assert_eq!(unique.len(), 1);
+    }
+
+    #[test]
+    fn known_names_deserialize_to_named_variants() {
+        for entity_type in [
+            EntityType::Invalid,
+            EntityType::Tariff,
+            EntityType::Zone,
+            EntityType::Device,
+            EntityType::Phase,
+            EntityType::Battery,
+            EntityType::Vehicle,
+            EntityType::Grid,
+        ] {
+            let json = serde_json::to_string(&entity_type).unwrap();
+
+            // act
+            let deserialized = serde_json::from_str::<EntityType>(&json).unwrap();
+
+            assert!(!matches!(deserialized, EntityType::Other(_)));
+            assert_eq!(deserialized, entity_type);
+        }
+    }
+
+    #[test]
+    fn battery_vehicle_and_grid_round_trip_through_json_and_yaml() {
+        for (entity_type, name) in [
+            (EntityType::Battery, "ENTITY_TYPE_BATTERY"),
+            (EntityType::Vehicle, "ENTITY_TYPE_VEHICLE"),
+            (EntityType::Grid, "ENTITY_TYPE_GRID"),
+        ] {
+            let json = serde_json::to_string(&entity_type).unwrap();
+            let yaml = serde_yaml::to_string(&entity_type).unwrap();
+
+            // act
+            let from_json = serde_json::from_str::<EntityType>(&json).unwrap();
+            let from_yaml = serde_yaml::from_str::<EntityType>(&yaml).unwrap();
+
+            assert_eq!(json, format!(r#""{}""#, name));
+            assert_eq!(yaml.trim(), name);
+            assert!(!matches!(from_json, EntityType::Other(_)));
+            assert_eq!(from_json, entity_type);
+            assert_eq!(from_yaml, entity_type);
+        }
+    }
 }
@@ -0,0 +1,201 @@
+use crate::model::{Measurement, MetricType, SampleKey};
+use chrono::{DateTime, Duration, Utc};
+
+/// How far before the first or after the last of two measurements interpolate_sample still extrapolates.
+pub const DEFAULT_INTERPOLATION_TOLERANCE_SECONDS: i64 = 300;
+
+/// Value of the sample identified by key at time at, estimated from the measurements before and after it; see
+/// interpolate_sample_with_tolerance.
+pub fn interpolate_sample(
+    before: &Measurement,
+    after: &Measurement,
+    key: &SampleKey,
+    at: DateTime<Utc>,
+) -> Option<f64> {
+    interpolate_sample_with_tolerance(
+        before,
+        after,
+        key,
+        at,
+        Duration::seconds(DEFAULT_INTERPOLATION_TOLERANCE_SECONDS),
+    )
+}
+
+/// Value of the sample identified by key at time at, linearly interpolated for gauges and carried forward from the last
+/// observation for counters. Times up to tolerance outside the measurements are extrapolated; further out, or when
+/// either measurement lacks the sample or has it with another metric type or unit, there's no value.
+pub fn interpolate_sample_with_tolerance(
+    before: &Measurement,
+    after: &Measurement,
+    key: &SampleKey,
+    at: DateTime<Utc>,
+    tolerance: Duration,
+) -> Option<f64> {
+    let (before, after) = if before.measured_at_time <= after.measured_at_time {
+        (before, after)
+    } else {
+        (after, before)
+    };
+    if at < before.measured_at_time - tolerance || at > after.measured_at_time + tolerance {
+        return None;
+    }
+
+    let before_sample = before.samples.iter().find(|sample| key.matches(sample))?;
+    let after_sample = after.samples.iter().find(|sample| key.matches(sample))?;
+    if before_sample.metric_type != after_sample.metric_type
+        || before_sample.unit != after_sample.unit
+    {
+        return None;
+    }
+
+    match before_sample.metric_type {
+        MetricType::Counter if at < after.measured_at_time => Some(before_sample.value),
+        MetricType::Counter => Some(after_sample.value),
+        MetricType::Gauge => {
+            let span = (after.measured_at_time - before.measured_at_time).num_milliseconds();
+            if span == 0 {
+                return Some(after_sample.value);
+            }
+            let fraction = (at - before.measured_at_time).num_milliseconds() as f64 / span as f64;
+
+            Some(before_sample.value + (after_sample.value - before_sample.value) * fraction)
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{EntityType, Sample, SampleType};
+    use chrono::TimeZone;
+    use pretty_assertions::assert_eq;
+
+    fn at(minute: u32, second: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2023, 7, 2, 13, minute, second)
+            .unwrap()
+    }
+
+    fn measurement(measured_at_time: DateTime<Utc>, samples: Vec<Sample>) -> Measurement {
+        Measurement::builder("jarvis-modbus-exporter", "My Home")
+            .measured_at(measured_at_time)
+            .samples(samples)
+            .build()
+            .unwrap()
+    }
+
+    fn temperature(value: f64) -> Sample {
+        Sample::builder(SampleType::Temperature, value)
+            .entity_type(EntityType::Zone)
+            .entity_name("Living room")
+            .sample_name("Thermostat")
+            .build()
+    }
+
+    fn consumption(value: f64) -> Sample {
+        Sample::builder(SampleType::ElectricityConsumption, value)
+            .entity_name("Sunny Boy")
+            .sample_name("Total")
+            .metric_type(MetricType::Counter)
+            .build()
+    }
+
+    fn temperatures() -> (Measurement, Measurement) {
+        (
+            measurement(at(57, 0), vec![temperature(20.0)]),
+            measurement(at(59, 0), vec![temperature(21.0)]),
+        )
+    }
+
+    #[test]
+    fn interpolate_sample_interpolates_gauge_linearly() {
+        let (before, after) = temperatures();
+
+        // act
+        let value = interpolate_sample(&before, &after, &temperature(0.0).key(), at(58, 30));
+
+        assert_eq!(value, Some(20.75));
+    }
+
+    #[test]
+    fn interpolate_sample_returns_values_at_endpoints() {
+        let (before, after) = temperatures();
+        let key = temperature(0.0).key();
+
+        // act
+        let values = (
+            interpolate_sample(&before, &after, &key, at(57, 0)),
+            interpolate_sample(&before, &after, &key, at(59, 0)),
+        );
+
+        assert_eq!(values, (Some(20.0), Some(21.0)));
+    }
+
+    #[test]
+    fn interpolate_sample_accepts_measurements_in_either_order() {
+        let (before, after) = temperatures();
+
+        // act
+        let value = interpolate_sample(&after, &before, &temperature(0.0).key(), at(58, 30));
+
+        assert_eq!(value, Some(20.75));
+    }
+
+    #[test]
+    fn interpolate_sample_carries_counter_forward() {
+        let before = measurement(at(57, 0), vec![consumption(1000.0)]);
+        let after = measurement(at(59, 0), vec![consumption(2000.0)]);
+        let key = consumption(0.0).key();
+
+        // act
+        let values = (
+            interpolate_sample(&before, &after, &key, at(58, 59)),
+            interpolate_sample(&before, &after, &key, at(59, 30)),
+        );
+
+        assert_eq!(values, (Some(1000.0), Some(2000.0)));
+    }
+
+    #[test]
+    fn interpolate_sample_extrapolates_within_tolerance() {
+        let (before, after) = temperatures();
+
+        // act
+        let value = interpolate_sample_with_tolerance(
+            &before,
+            &after,
+            &temperature(0.0).key(),
+            at(59, 30),
+            Duration::seconds(30),
+        );
+
+        assert_eq!(value, Some(21.25));
+    }
+
+    #[test]
+    fn interpolate_sample_returns_none_outside_tolerance() {
+        let (before, after) = temperatures();
+
+        // act
+        let value = interpolate_sample_with_tolerance(
+            &before,
+            &after,
+            &temperature(0.0).key(),
+            at(59, 31),
+            Duration::seconds(30),
+        );
+
+        assert_eq!(value, None);
+    }
+
+    #[test]
+    fn interpolate_sample_returns_none_for_sample_missing_from_either_measurement() {
+        let (before, _) = temperatures();
+        let after = measurement(at(59, 0), vec![consumption(2000.0)]);
+
+        // act
+        let value = interpolate_sample(&before, &after, &temperature(0.0).key(), at(58, 0));
+
+        assert_eq!(value, None);
+    }
+}
@@ -0,0 +1,186 @@
+use crate::model::{Measurement, Sample};
+
+/// InfluxDB line protocol lines of the samples of measurements, one line per sample; samples with a non-finite value or
+/// measured outside the range of nanosecond timestamps are left out, as the protocol can't express them.
+pub fn measurements_to_line_protocol(measurements: &[Measurement]) -> String {
+    measurements
+        .iter()
+        .flat_map(|measurement| {
+            measurement
+                .samples
+                .iter()
+                .filter_map(move |sample| line(measurement, sample))
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn line(measurement: &Measurement, sample: &Sample) -> Option<String> {
+    if !sample.value.is_finite() {
+        return None;
+    }
+    let timestamp = measurement.measured_at_time.timestamp_nanos_opt()?;
+    let sample_type = sample.sample_type.as_str();
+    let name = sample_type
+        .strip_prefix("SAMPLE_TYPE_")
+        .unwrap_or(sample_type)
+        .to_lowercase();
+    if name.is_empty() {
+        return None;
+    }
+
+    // empty tag values aren't allowed, so those tags are left out
+    let tags: String = [
+        ("source", measurement.source.as_str()),
+        ("location", measurement.location.as_str()),
+        ("entity_type", sample.entity_type.as_str()),
+        ("entity_name", sample.entity_name.as_str()),
+        ("sample_name", sample.sample_name.as_str()),
+    ]
+    .iter()
+    .filter(|(_, value)| !value.is_empty())
+    .map(|(key, value)| format!(",{}={}", key, escape(value, &[',', '=', ' '])))
+    .collect();
+
+    Some(format!(
+        "{}{} value={} {}",
+        escape(&name, &[',', ' ']),
+        tags,
+        sample.value,
+        timestamp
+    ))
+}
+
+fn escape(value: &str, special_characters: &[char]) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if c == '\\' || special_characters.contains(&c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{EntityType, MetricType, SampleType};
+    use chrono::{DateTime, TimeZone, Utc};
+    use pretty_assertions::assert_eq;
+
+    fn measured_at_time() -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339("2021-05-01T05:45:03.043614293Z")
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    #[test]
+    fn to_line_protocol_writes_line_per_sample() {
+        let measurement = Measurement::builder("jarvis-tp-link-hs-110-exporter", "My Home")
+            .measured_at(measured_at_time())
+            .sample(
+                Sample::builder(SampleType::ElectricityConsumption, 9695872800.0)
+                    .entity_name("TP-Link HS110")
+                    .sample_name("Oven")
+                    .metric_type(MetricType::Counter)
+                    .build(),
+            )
+            .sample(
+                Sample::builder(SampleType::Temperature, 21.5)
+                    .entity_type(EntityType::Zone)
+                    .entity_name("Living room")
+                    .sample_name("Thermostat")
+                    .build(),
+            )
+            .build()
+            .unwrap();
+
+        // act
+        let lines = measurement.to_line_protocol();
+
+        assert_eq!(
+            lines,
+            r#"electricity_consumption,source=jarvis-tp-link-hs-110-exporter,location=My\ Home,entity_type=ENTITY_TYPE_DEVICE,entity_name=TP-Link\ HS110,sample_name=Oven value=9695872800 1619847903043614293
+temperature,source=jarvis-tp-link-hs-110-exporter,location=My\ Home,entity_type=ENTITY_TYPE_ZONE,entity_name=Living\ room,sample_name=Thermostat value=21.5 1619847903043614293"#
+        );
+    }
+
+    #[test]
+    fn to_line_protocol_escapes_tag_values() {
+        let measurement = Measurement::builder("jarvis-modbus-exporter", "My \"Home\"")
+            .measured_at(measured_at_time())
+            .sample(
+                Sample::builder(SampleType::Temperature, -3.25)
+                    .entity_name("a,b=c d")
+                    .sample_name("C:\\zone")
+                    .build(),
+            )
+            .build()
+            .unwrap();
+
+        // act
+        let lines = measurement.to_line_protocol();
+
+        assert_eq!(
+            lines,
+            r#"temperature,source=jarvis-modbus-exporter,location=My\ "Home",entity_type=ENTITY_TYPE_DEVICE,entity_name=a\,b\=c\ d,sample_name=C:\\zone value=-3.25 1619847903043614293"#
+        );
+    }
+
+    #[test]
+    fn to_line_protocol_writes_timestamp_in_nanoseconds() {
+        let measurement = Measurement::builder("jarvis-modbus-exporter", "Home")
+            .measured_at(Utc.timestamp_opt(1, 5).unwrap())
+            .sample(Sample::builder(SampleType::Humidity, 45.0).build())
+            .build()
+            .unwrap();
+
+        // act
+        let lines = measurement.to_line_protocol();
+
+        assert_eq!(
+            lines,
+            "humidity,source=jarvis-modbus-exporter,location=Home,entity_type=ENTITY_TYPE_DEVICE value=45 1000000005"
+        );
+    }
+
+    #[test]
+    fn to_line_protocol_leaves_out_unrepresentable_samples() {
+        let measurement = Measurement::builder("jarvis-modbus-exporter", "Home")
+            .measured_at(measured_at_time())
+            .sample(Sample::builder(SampleType::Humidity, f64::NAN).build())
+            .sample(Sample::builder(SampleType::Invalid, 1.0).build())
+            .build()
+            .unwrap();
+        let far_future = Measurement {
+            measured_at_time: Utc.with_ymd_and_hms(2300, 1, 1, 0, 0, 0).unwrap(),
+            samples: vec![Sample::builder(SampleType::Humidity, 45.0).build()],
+            ..measurement.clone()
+        };
+
+        // act
+        let lines = measurements_to_line_protocol(&[measurement, far_future]);
+
+        assert_eq!(lines, "");
+    }
+
+    #[test]
+    fn measurements_to_line_protocol_joins_lines_with_newlines() {
+        let measurement = |value| {
+            Measurement::builder("jarvis-modbus-exporter", "Home")
+                .measured_at(Utc.timestamp_opt(1, 0).unwrap())
+                .sample(Sample::builder(SampleType::Humidity, value).build())
+                .build()
+                .unwrap()
+        };
+
+        // act
+        let lines = measurements_to_line_protocol(&[measurement(45.0), measurement(50.0)]);
+
+        assert_eq!(
+            lines,
+            "humidity,source=jarvis-modbus-exporter,location=Home,entity_type=ENTITY_TYPE_DEVICE value=45 1000000000\nhumidity,source=jarvis-modbus-exporter,location=Home,entity_type=ENTITY_TYPE_DEVICE value=50 1000000000"
+        );
+    }
+}
@@ -1,13 +1,1635 @@
-use crate::model::Sample;
-use chrono::{DateTime, Utc};
+use crate::model::aggregate::aggregate;
+use crate::model::line_protocol::measurements_to_line_protocol;
+use crate::model::prometheus::measurements_to_prometheus;
+use crate::model::{
+    AggregateKey, EntityType, MetricType, Sample, SampleKey, SampleType, SampleUnit,
+    ValidationIssue, CORRECTED_ENERGY_NAMES_SCHEMA_VERSION, DEFAULT_MAX_FUTURE_SKEW_SECONDS,
+};
+use chrono::{DateTime, Duration, SecondsFormat, Utc};
+use rand::Rng;
 use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashSet};
+use std::error::Error;
+use std::fmt;
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+/// Serialized with PascalCase field names; camelCase names, as written by older exporters, are accepted as well.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 #[serde(rename_all = "PascalCase")]
 pub struct Measurement {
+    #[serde(alias = "id")]
     pub id: String,
+    #[serde(alias = "source")]
     pub source: String,
+    #[serde(alias = "location")]
     pub location: String,
+    #[serde(alias = "samples")]
     pub samples: Vec<Sample>,
+    #[serde(alias = "measuredAtTime")]
     pub measured_at_time: DateTime<Utc>,
+    /// Version of the schema the measurement follows; payloads without one follow version 1.
+    #[serde(default = "default_schema_version", alias = "schemaVersion")]
+    pub schema_version: u32,
+    /// Deployment metadata like site id or firmware version, keyed by lowercase alphanumerics, dashes and underscores.
+    #[serde(default, skip_serializing_if = "Option::is_none", alias = "labels")]
+    pub labels: Option<BTreeMap<String, String>>,
+}
+
+fn default_schema_version() -> u32 {
+    1
+}
+
+impl Measurement {
+    /// Schema version of measurements made by this version, bumped whenever fields are added or their meaning changes:
+    /// 2 added units and labels and corrected the names of energy and gas consumption sample types.
+    pub const CURRENT_SCHEMA_VERSION: u32 = 2;
+
+    /// Builder for a measurement from source at location, with a new id and measured now unless set.
+    pub fn builder(source: &str, location: &str) -> MeasurementBuilder {
+        MeasurementBuilder {
+            id: None,
+            source: source.to_string(),
+            location: location.to_string(),
+            samples: vec![],
+            measured_at_time: None,
+            labels: None,
+        }
+    }
+
+    /// Brings a measurement following an older schema version up to CURRENT_SCHEMA_VERSION.
+    pub fn migrate_to_current(&mut self) {
+        if self.schema_version < CORRECTED_ENERGY_NAMES_SCHEMA_VERSION {
+            for sample in &mut self.samples {
+                sample.sample_type =
+                    SampleType::from_wire_compat(sample.sample_type.as_str(), self.schema_version);
+            }
+        }
+        self.schema_version = Measurement::CURRENT_SCHEMA_VERSION;
+    }
+
+    /// Everything wrong with the measurement and its samples, allowing measured_at_time to lie up to
+    /// DEFAULT_MAX_FUTURE_SKEW_SECONDS in the future.
+    pub fn validate(&self) -> Vec<ValidationIssue> {
+        self.validate_with_max_future_skew(
+            Utc::now(),
+            Duration::seconds(DEFAULT_MAX_FUTURE_SKEW_SECONDS),
+        )
+    }
+
+    /// Everything wrong with the measurement and its samples, allowing measured_at_time to lie up to max_future_skew
+    /// after now.
+    pub fn validate_with_max_future_skew(
+        &self,
+        now: DateTime<Utc>,
+        max_future_skew: Duration,
+    ) -> Vec<ValidationIssue> {
+        let mut issues = vec![];
+        for (field, value) in [
+            ("id", &self.id),
+            ("source", &self.source),
+            ("location", &self.location),
+        ] {
+            if value.is_empty() {
+                issues.push(ValidationIssue::error(field, "is empty"));
+            }
+        }
+        if self.samples.is_empty() {
+            issues.push(ValidationIssue::error("samples", "is empty"));
+        }
+        if self.measured_at_time > now + max_future_skew {
+            issues.push(ValidationIssue::error(
+                "measuredAtTime",
+                &format!(
+                    "lies more than {} seconds in the future",
+                    max_future_skew.num_seconds()
+                ),
+            ));
+        }
+        for key in self.labels.iter().flat_map(|labels| labels.keys()) {
+            if !is_valid_label_key(key) {
+                issues.push(ValidationIssue::error(
+                    &format!("labels.{}", key),
+                    "is not made of lowercase alphanumerics, dashes and underscores",
+                ));
+            }
+        }
+        for (i, sample) in self.samples.iter().enumerate() {
+            issues.extend(sample.validate().into_iter().map(|issue| ValidationIssue {
+                field: format!("samples[{}].{}", i, issue.field),
+                ..issue
+            }));
+        }
+        issues
+    }
+
+    /// Change of each counter sample since the sample with the same entity and sample type and names in previous;
+    /// gauges and counters missing from previous are skipped.
+    pub fn deltas_since(&self, previous: &Measurement) -> Vec<SampleDelta> {
+        self.deltas_since_with_max_value(previous, None)
+    }
+
+    /// Like deltas_since, for counters wrapping to zero at max_value, like 2^16 for 16-bit registers.
+    pub fn deltas_since_with_max_value(
+        &self,
+        previous: &Measurement,
+        max_value: Option<f64>,
+    ) -> Vec<SampleDelta> {
+        let elapsed_seconds =
+            (self.measured_at_time - previous.measured_at_time).num_milliseconds() as f64 / 1000.0;
+
+        self.samples
+            .iter()
+            .filter(|sample| sample.metric_type == MetricType::Counter)
+            .filter_map(|sample| {
+                let previous_sample = previous.samples.iter().find(|previous_sample| {
+                    previous_sample.metric_type == MetricType::Counter
+                        && previous_sample.entity_type == sample.entity_type
+                        && previous_sample.entity_name == sample.entity_name
+                        && previous_sample.sample_type == sample.sample_type
+                        && previous_sample.sample_name == sample.sample_name
+                })?;
+                let (delta, counter_reset) =
+                    match counter_delta(previous_sample.value, sample.value, max_value) {
+                        CounterDelta::Delta(delta) | CounterDelta::Rollover(delta) => {
+                            (delta, false)
+                        }
+                        CounterDelta::Reset => (sample.value, true),
+                    };
+
+                Some(SampleDelta {
+                    entity_type: sample.entity_type.clone(),
+                    entity_name: sample.entity_name.clone(),
+                    sample_type: sample.sample_type.clone(),
+                    sample_name: sample.sample_name.clone(),
+                    delta,
+                    elapsed_seconds,
+                    counter_reset,
+                })
+            })
+            .collect()
+    }
+
+    /// Rate of change of each counter sample since previous: power in watts for energy counters, the counter's unit
+    /// per hour for water and gas and per second for other counters. Resets and non-positive elapsed times are skipped.
+    pub fn rates_since(&self, previous: &Measurement) -> Vec<RateSample> {
+        self.deltas_since(previous)
+            .into_iter()
+            .filter(|delta| !delta.counter_reset && delta.elapsed_seconds > 0.0)
+            .filter_map(|delta| {
+                let key = SampleKey {
+                    entity_type: delta.entity_type,
+                    entity_name: delta.entity_name,
+                    sample_type: delta.sample_type,
+                    sample_name: delta.sample_name,
+                };
+                let sample = self.samples.iter().find(|sample| key.matches(sample))?;
+                let energy_unit = sample
+                    .unit
+                    .or_else(|| SampleUnit::default_for(&sample.sample_type, &sample.metric_type))
+                    .filter(SampleUnit::is_energy);
+
+                let (rate, unit) = match energy_unit {
+                    Some(energy_unit) => (
+                        energy_unit.convert(delta.delta, SampleUnit::Joule).ok()?
+                            / delta.elapsed_seconds,
+                        RateUnit::Watt,
+                    ),
+                    None if key.sample_type == SampleType::WaterConsumption
+                        || key.sample_type == SampleType::GasConsumption =>
+                    {
+                        (
+                            delta.delta * 3600.0 / delta.elapsed_seconds,
+                            RateUnit::PerHour,
+                        )
+                    }
+                    None => (delta.delta / delta.elapsed_seconds, RateUnit::PerSecond),
+                };
+
+                Some(RateSample { key, rate, unit })
+            })
+            .collect()
+    }
+
+    /// Sums of the values of the samples per key, as samples named aggregate.
+    pub fn aggregate_samples(&self, group_by: AggregateKey) -> Vec<Sample> {
+        aggregate(&self.samples, group_by)
+    }
+
+    /// Prometheus exposition of the samples, as metrics named after namespace, sample type and unit.
+    pub fn to_prometheus(&self, namespace: &str) -> String {
+        measurements_to_prometheus(std::slice::from_ref(self), namespace)
+    }
+
+    /// InfluxDB line protocol lines of the samples, one line per sample.
+    pub fn to_line_protocol(&self) -> String {
+        measurements_to_line_protocol(std::slice::from_ref(self))
+    }
+
+    /// Row per sample with the columns of the jarvis-bigquery-sender table, plus an insert_id of the measurement id and
+    /// sample index for deduplicating retried inserts.
+    pub fn to_bigquery_rows(&self) -> Vec<serde_json::Value> {
+        let measured_at_time = self
+            .measured_at_time
+            .to_rfc3339_opts(SecondsFormat::AutoSi, true);
+
+        self.samples
+            .iter()
+            .enumerate()
+            .map(|(i, sample)| {
+                serde_json::json!({
+                    "ID": self.id,
+                    "Source": self.source,
+                    "Location": self.location,
+                    "EntityType": sample.entity_type,
+                    "EntityName": sample.entity_name,
+                    "SampleType": sample.sample_type,
+                    "SampleName": sample.sample_name,
+                    "MetricType": sample.metric_type,
+                    "Value": sample.value,
+                    "MeasuredAtTime": measured_at_time,
+                    "insert_id": format!("{}-{}", self.id, i),
+                })
+            })
+            .collect()
+    }
+
+    /// Whether other equals the measurement, with sample values differing at most epsilon.
+    pub fn approx_eq(&self, other: &Measurement, epsilon: f64) -> bool {
+        self.id == other.id
+            && self.source == other.source
+            && self.location == other.location
+            && self.measured_at_time == other.measured_at_time
+            && self.schema_version == other.schema_version
+            && self.labels == other.labels
+            && self.samples.len() == other.samples.len()
+            && self
+                .samples
+                .iter()
+                .zip(&other.samples)
+                .all(|(sample, other)| {
+                    (sample.value - other.value).abs() <= epsilon
+                        && Sample {
+                            value: other.value,
+                            ..sample.clone()
+                        } == *other
+                })
+    }
+
+    /// Removes samples with the same entity and sample type and names as another sample, keeping the one picked by
+    /// policy at the position of the first; returns the number of removed samples.
+    pub fn dedupe_samples(&mut self, policy: DedupePolicy) -> usize {
+        let mut keys = HashSet::new();
+        if self.samples.iter().all(|sample| keys.insert(sample.key())) {
+            return 0;
+        }
+
+        let mut deduped: Vec<Sample> = vec![];
+        for sample in &self.samples {
+            let key = sample.key();
+            match deduped.iter_mut().find(|kept| key.matches(kept)) {
+                Some(kept) => {
+                    let replace = match policy {
+                        DedupePolicy::KeepFirst => false,
+                        DedupePolicy::KeepLast => true,
+                        DedupePolicy::KeepMax => sample.value > kept.value,
+                    };
+                    if replace {
+                        *kept = sample.clone();
+                    }
+                }
+                None => deduped.push(sample.clone()),
+            }
+        }
+        let removed = self.samples.len() - deduped.len();
+        self.samples = deduped;
+        removed
+    }
+
+    /// First sample of sample_type for the entity named entity_name.
+    pub fn find_sample(&self, entity_name: &str, sample_type: SampleType) -> Option<&Sample> {
+        self.find_sample_matching(entity_name, sample_type, NameMatching::Exact)
+    }
+
+    pub fn find_sample_matching(
+        &self,
+        entity_name: &str,
+        sample_type: SampleType,
+        name_matching: NameMatching,
+    ) -> Option<&Sample> {
+        self.samples.iter().find(|sample| {
+            sample.sample_type == sample_type
+                && name_matching.matches(&sample.entity_name, entity_name)
+        })
+    }
+
+    pub fn find_samples_of_type(&self, sample_type: SampleType) -> Vec<&Sample> {
+        self.samples
+            .iter()
+            .filter(|sample| sample.sample_type == sample_type)
+            .collect()
+    }
+
+    /// Value of the first sample of sample_type for the entity named entity_name.
+    pub fn value_of(&self, entity_name: &str, sample_type: SampleType) -> Option<f64> {
+        self.value_of_matching(entity_name, sample_type, NameMatching::Exact)
+    }
+
+    pub fn value_of_matching(
+        &self,
+        entity_name: &str,
+        sample_type: SampleType,
+        name_matching: NameMatching,
+    ) -> Option<f64> {
+        self.find_sample_matching(entity_name, sample_type, name_matching)
+            .map(|sample| sample.value)
+    }
+
+    /// Orders by measured_at_time.
+    pub fn cmp_by_time(&self, other: &Measurement) -> Ordering {
+        self.measured_at_time.cmp(&other.measured_at_time)
+    }
+
+    /// Most recent measurement in state from the same source, measured before this one.
+    pub fn find_previous<'a>(&self, state: &'a [Measurement]) -> Option<&'a Measurement> {
+        state
+            .iter()
+            .filter(|measurement| {
+                measurement.source == self.source
+                    && measurement.measured_at_time < self.measured_at_time
+            })
+            .max_by_key(|measurement| measurement.measured_at_time)
+    }
+}
+
+/// How entity names are compared when looking up samples.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub enum NameMatching {
+    #[default]
+    Exact,
+    IgnoreCase,
+}
+
+impl NameMatching {
+    pub fn matches(self, name: &str, other: &str) -> bool {
+        match self {
+            NameMatching::Exact => name == other,
+            NameMatching::IgnoreCase => name.to_lowercase() == other.to_lowercase(),
+        }
+    }
+}
+
+/// Sample of sample_type for the entity named entity_name from the most recent measurement having one.
+pub fn find_latest_sample<'a>(
+    measurements: &'a [Measurement],
+    entity_name: &str,
+    sample_type: SampleType,
+    name_matching: NameMatching,
+) -> Option<&'a Sample> {
+    measurements
+        .iter()
+        .filter_map(|measurement| {
+            measurement
+                .find_sample_matching(entity_name, sample_type.clone(), name_matching)
+                .map(|sample| (measurement.measured_at_time, sample))
+        })
+        .max_by_key(|(measured_at_time, _)| *measured_at_time)
+        .map(|(_, sample)| sample)
+}
+
+/// Value of find_latest_sample.
+pub fn latest_value_of(
+    measurements: &[Measurement],
+    entity_name: &str,
+    sample_type: SampleType,
+    name_matching: NameMatching,
+) -> Option<f64> {
+    find_latest_sample(measurements, entity_name, sample_type, name_matching)
+        .map(|sample| sample.value)
+}
+
+/// Which of the samples with the same entity and sample type and names dedupe_samples keeps.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum DedupePolicy {
+    KeepFirst,
+    KeepLast,
+    /// The sample with the highest value, or the first of those.
+    KeepMax,
+}
+
+/// Sorts measurements by measured_at_time, keeping measurements measured at the same time in their order.
+pub fn sort_measurements_by_time(measurements: &mut [Measurement]) {
+    measurements.sort_by(Measurement::cmp_by_time);
+}
+
+/// Change of a counter between two readings.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum CounterDelta {
+    Delta(f64),
+    /// Change of a counter that went past its maximum value and started again from zero.
+    Rollover(f64),
+    /// The counter went down without rolling over, as it does after a device restarts or updates its firmware.
+    Reset,
+}
+
+/// Change of a counter from previous to current, for counters wrapping to zero at max_value if it's set.
+pub fn counter_delta(previous: f64, current: f64, max_value: Option<f64>) -> CounterDelta {
+    if current >= previous {
+        return CounterDelta::Delta(current - previous);
+    }
+    match max_value {
+        Some(max_value) if previous <= max_value => {
+            CounterDelta::Rollover(max_value - previous + current)
+        }
+        _ => CounterDelta::Reset,
+    }
+}
+
+/// Change of a counter sample between two measurements.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SampleDelta {
+    pub entity_type: EntityType,
+    pub entity_name: String,
+    pub sample_type: SampleType,
+    pub sample_name: String,
+    /// Difference between the values, or the current value if the counter was reset.
+    pub delta: f64,
+    pub elapsed_seconds: f64,
+    /// Whether the counter went down, as it does after a device restarts.
+    pub counter_reset: bool,
+}
+
+/// Rate of change of a counter sample between two measurements.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RateSample {
+    pub key: SampleKey,
+    pub rate: f64,
+    pub unit: RateUnit,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum RateUnit {
+    Watt,
+    /// Unit of the counter per hour, like cubic meters of gas per hour.
+    PerHour,
+    /// Unit of the counter per second.
+    PerSecond,
+}
+
+impl RateSample {
+    /// Gauge sample of the same entity and sample type and names holding the rate, in watts for energy counters.
+    pub fn to_sample(&self) -> Sample {
+        Sample {
+            entity_type: self.key.entity_type.clone(),
+            entity_name: self.key.entity_name.clone(),
+            sample_type: self.key.sample_type.clone(),
+            sample_name: self.key.sample_name.clone(),
+            metric_type: MetricType::Gauge,
+            value: self.rate,
+            unit: match self.unit {
+                RateUnit::Watt => Some(SampleUnit::Watt),
+                RateUnit::PerHour | RateUnit::PerSecond => None,
+            },
+        }
+    }
+}
+
+pub struct MeasurementBuilder {
+    id: Option<String>,
+    source: String,
+    location: String,
+    samples: Vec<Sample>,
+    measured_at_time: Option<DateTime<Utc>>,
+    labels: Option<BTreeMap<String, String>>,
+}
+
+impl MeasurementBuilder {
+    pub fn id(mut self, id: impl Into<String>) -> Self {
+        self.id = Some(id.into());
+        self
+    }
+
+    pub fn sample(mut self, sample: Sample) -> Self {
+        self.samples.push(sample);
+        self
+    }
+
+    pub fn samples(mut self, samples: Vec<Sample>) -> Self {
+        self.samples.extend(samples);
+        self
+    }
+
+    pub fn measured_at(mut self, measured_at_time: DateTime<Utc>) -> Self {
+        self.measured_at_time = Some(measured_at_time);
+        self
+    }
+
+    pub fn label(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.labels
+            .get_or_insert_with(BTreeMap::new)
+            .insert(key.into(), value.into());
+        self
+    }
+
+    /// The measurement, if it has a source, a location and at least one sample.
+    pub fn build(self) -> Result<Measurement, ValidationError> {
+        if self.source.is_empty() {
+            return Err(ValidationError::EmptySource);
+        }
+        if self.location.is_empty() {
+            return Err(ValidationError::EmptyLocation);
+        }
+        if self.samples.is_empty() {
+            return Err(ValidationError::NoSamples);
+        }
+
+        Ok(Measurement {
+            id: self.id.unwrap_or_else(new_uuid),
+            source: self.source,
+            location: self.location,
+            samples: self.samples,
+            measured_at_time: self.measured_at_time.unwrap_or_else(Utc::now),
+            schema_version: Measurement::CURRENT_SCHEMA_VERSION,
+            labels: self.labels,
+        })
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ValidationError {
+    EmptySource,
+    EmptyLocation,
+    NoSamples,
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValidationError::EmptySource => write!(f, "Measurement has no source"),
+            ValidationError::EmptyLocation => write!(f, "Measurement has no location"),
+            ValidationError::NoSamples => write!(f, "Measurement has no samples"),
+        }
+    }
+}
+
+impl Error for ValidationError {}
+
+/// Whether key is a non-empty string of lowercase alphanumerics, dashes and underscores.
+pub fn is_valid_label_key(key: &str) -> bool {
+    !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_')
+}
+
+/// Labels from a comma-separated list of key=value pairs, like `site=home,firmware=1.2`.
+pub fn parse_labels(labels: &str) -> Result<BTreeMap<String, String>, Box<dyn Error>> {
+    labels
+        .split(',')
+        .map(str::trim)
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair
+                .split_once('=')
+                .ok_or_else(|| format!("Label {} is not a key=value pair", pair))?;
+            let key = key.trim();
+            if !is_valid_label_key(key) {
+                return Err(format!(
+                    "Label key {} is not made of lowercase alphanumerics, dashes and underscores",
+                    key
+                )
+                .into());
+            }
+            Ok((key.to_string(), value.trim().to_string()))
+        })
+        .collect()
+}
+
+/// Random version 4 UUID, like the ids of measurements.
+pub(crate) fn new_uuid() -> String {
+    let mut bytes: [u8; 16] = rand::thread_rng().gen();
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex: String = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[0..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..32]
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{SampleUnit, Severity};
+    use chrono::{Duration, TimeZone};
+    use pretty_assertions::assert_eq;
+
+    fn sample() -> Sample {
+        Sample::builder(SampleType::Temperature, 21.5)
+            .entity_type(EntityType::Zone)
+            .entity_name("Living room")
+            .sample_name("Thermostat")
+            .build()
+    }
+
+    #[test]
+    fn builder_defaults_id_and_measured_at_time() {
+        let before = Utc::now();
+
+        // act
+        let measurement = Measurement::builder("jarvis-modbus-exporter", "My Home")
+            .sample(sample())
+            .build()
+            .unwrap();
+
+        assert_eq!(measurement.id.len(), 36);
+        assert_eq!(&measurement.id[14..15], "4");
+        assert_eq!(measurement.source, "jarvis-modbus-exporter");
+        assert_eq!(measurement.location, "My Home");
+        assert_eq!(measurement.samples.len(), 1);
+        assert!(measurement.measured_at_time >= before);
+        assert!(measurement.measured_at_time <= Utc::now());
+    }
+
+    #[test]
+    fn builder_generates_new_id_for_each_measurement() {
+        let first = Measurement::builder("jarvis-modbus-exporter", "My Home")
+            .sample(sample())
+            .build()
+            .unwrap();
+
+        // act
+        let second = Measurement::builder("jarvis-modbus-exporter", "My Home")
+            .sample(sample())
+            .build()
+            .unwrap();
+
+        assert!(first.id != second.id);
+    }
+
+    #[test]
+    fn builder_uses_overrides() {
+        let measured_at_time = Utc.with_ymd_and_hms(2021, 5, 1, 5, 45, 3).unwrap();
+
+        // act
+        let measurement = Measurement::builder("jarvis-modbus-exporter", "My Home")
+            .id("cc6e17bb-fd60-4dde-acc3-0cda7d752acc")
+            .measured_at(measured_at_time)
+            .sample(sample())
+            .samples(vec![sample(), sample()])
+            .build()
+            .unwrap();
+
+        assert_eq!(measurement.id, "cc6e17bb-fd60-4dde-acc3-0cda7d752acc");
+        assert_eq!(measurement.measured_at_time, measured_at_time);
+        assert_eq!(measurement.samples.len(), 3);
+    }
+
+    #[test]
+    fn builder_rejects_empty_source() {
+        // act
+        let result = Measurement::builder("", "My Home").sample(sample()).build();
+
+        assert_eq!(result.unwrap_err(), ValidationError::EmptySource);
+    }
+
+    #[test]
+    fn builder_rejects_empty_location() {
+        // act
+        let result = Measurement::builder("jarvis-modbus-exporter", "")
+            .sample(sample())
+            .build();
+
+        assert_eq!(result.unwrap_err(), ValidationError::EmptyLocation);
+    }
+
+    #[test]
+    fn builder_rejects_measurement_without_samples() {
+        // act
+        let result = Measurement::builder("jarvis-modbus-exporter", "My Home").build();
+
+        assert_eq!(result.unwrap_err(), ValidationError::NoSamples);
+    }
+
+    #[test]
+    fn sample_builder_defaults_to_gauge_without_unit() {
+        // act
+        let sample = Sample::builder(SampleType::ElectricityConsumption, 1500.0).build();
+
+        assert_eq!(sample.entity_type, EntityType::Device);
+        assert_eq!(sample.entity_name, "");
+        assert_eq!(sample.sample_name, "");
+        assert_eq!(sample.metric_type, MetricType::Gauge);
+        assert_eq!(sample.value, 1500.0);
+        assert_eq!(sample.unit, None);
+    }
+
+    #[test]
+    fn sample_builder_uses_overrides() {
+        // act
+        let sample = Sample::builder(SampleType::ElectricityConsumption, 9695872800.0)
+            .entity_name("TP-Link HS110")
+            .sample_name("Oven")
+            .metric_type(MetricType::Counter)
+            .unit(SampleUnit::Joule)
+            .build();
+
+        assert_eq!(sample.entity_name, "TP-Link HS110");
+        assert_eq!(sample.sample_name, "Oven");
+        assert_eq!(sample.metric_type, MetricType::Counter);
+        assert_eq!(sample.unit, Some(SampleUnit::Joule));
+    }
+
+    fn counter(sample_name: &str, value: f64) -> Sample {
+        Sample::builder(SampleType::ElectricityConsumption, value)
+            .entity_name("Sunny Boy")
+            .sample_name(sample_name)
+            .metric_type(MetricType::Counter)
+            .build()
+    }
+
+    fn measurement(measured_at_time: DateTime<Utc>, samples: Vec<Sample>) -> Measurement {
+        Measurement::builder("jarvis-modbus-exporter", "My Home")
+            .measured_at(measured_at_time)
+            .samples(samples)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn deltas_since_subtracts_previous_counter_value() {
+        let previous_at = Utc.with_ymd_and_hms(2021, 5, 1, 5, 45, 0).unwrap();
+        let previous = measurement(previous_at, vec![counter("Grid", 1000.0)]);
+        let current = measurement(
+            previous_at + Duration::seconds(90),
+            vec![counter("Grid", 1250.0)],
+        );
+
+        // act
+        let deltas = current.deltas_since(&previous);
+
+        assert_eq!(
+            deltas,
+            vec![SampleDelta {
+                entity_type: EntityType::Device,
+                entity_name: "Sunny Boy".into(),
+                sample_type: SampleType::ElectricityConsumption,
+                sample_name: "Grid".into(),
+                delta: 250.0,
+                elapsed_seconds: 90.0,
+                counter_reset: false,
+            }]
+        );
+    }
+
+    #[test]
+    fn deltas_since_detects_counter_reset() {
+        let previous_at = Utc.with_ymd_and_hms(2021, 5, 1, 5, 45, 0).unwrap();
+        let previous = measurement(previous_at, vec![counter("Grid", 1000.0)]);
+        let current = measurement(
+            previous_at + Duration::seconds(60),
+            vec![counter("Grid", 30.0)],
+        );
+
+        // act
+        let deltas = current.deltas_since(&previous);
+
+        assert_eq!(deltas.len(), 1);
+        assert!(deltas[0].counter_reset);
+        assert_eq!(deltas[0].delta, 30.0);
+    }
+
+    #[test]
+    fn deltas_since_skips_sample_missing_from_previous() {
+        let previous_at = Utc.with_ymd_and_hms(2021, 5, 1, 5, 45, 0).unwrap();
+        let previous = measurement(previous_at, vec![counter("Grid", 1000.0)]);
+        let current = measurement(
+            previous_at + Duration::seconds(60),
+            vec![counter("Grid", 1100.0), counter("Solar", 500.0)],
+        );
+
+        // act
+        let deltas = current.deltas_since(&previous);
+
+        assert_eq!(
+            deltas
+                .iter()
+                .map(|delta| delta.sample_name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["Grid"]
+        );
+    }
+
+    #[test]
+    fn deltas_since_skips_gauges() {
+        let previous_at = Utc.with_ymd_and_hms(2021, 5, 1, 5, 45, 0).unwrap();
+        let gauge = |value| {
+            Sample::builder(SampleType::ElectricityConsumption, value)
+                .entity_name("Sunny Boy")
+                .sample_name("Power")
+                .build()
+        };
+        let previous = measurement(previous_at, vec![gauge(1500.0)]);
+        let current = measurement(previous_at + Duration::seconds(60), vec![gauge(1700.0)]);
+
+        // act
+        let deltas = current.deltas_since(&previous);
+
+        assert_eq!(deltas, vec![]);
+    }
+
+    #[test]
+    fn find_previous_returns_most_recent_earlier_measurement_from_same_source() {
+        let at = Utc.with_ymd_and_hms(2021, 5, 1, 5, 45, 0).unwrap();
+        let other_source = Measurement::builder("jarvis-tp-link-hs-110-exporter", "My Home")
+            .measured_at(at - Duration::minutes(1))
+            .sample(counter("Grid", 1.0))
+            .build()
+            .unwrap();
+        let state = vec![
+            measurement(at - Duration::minutes(10), vec![counter("Grid", 1.0)]),
+            other_source,
+            measurement(at - Duration::minutes(5), vec![counter("Grid", 2.0)]),
+            measurement(at + Duration::minutes(5), vec![counter("Grid", 3.0)]),
+        ];
+        let current = measurement(at, vec![counter("Grid", 4.0)]);
+
+        // act
+        let previous = current.find_previous(&state);
+
+        assert_eq!(
+            previous.map(|m| m.measured_at_time),
+            Some(at - Duration::minutes(5))
+        );
+    }
+
+    #[test]
+    fn validate_accepts_complete_measurement() {
+        let at = Utc.with_ymd_and_hms(2021, 5, 1, 5, 45, 0).unwrap();
+
+        // act
+        let issues = measurement(at, vec![counter("Grid", 1.0)])
+            .validate_with_max_future_skew(at, Duration::minutes(5));
+
+        assert_eq!(issues, vec![]);
+    }
+
+    #[test]
+    fn validate_rejects_empty_id_source_location_and_samples() {
+        let at = Utc.with_ymd_and_hms(2021, 5, 1, 5, 45, 0).unwrap();
+        let measurement = Measurement {
+            id: "".into(),
+            source: "".into(),
+            location: "".into(),
+            samples: vec![],
+            measured_at_time: at,
+            schema_version: Measurement::CURRENT_SCHEMA_VERSION,
+            labels: None,
+        };
+
+        // act
+        let issues = measurement.validate_with_max_future_skew(at, Duration::minutes(5));
+
+        assert_eq!(
+            issues,
+            vec![
+                ValidationIssue::error("id", "is empty"),
+                ValidationIssue::error("source", "is empty"),
+                ValidationIssue::error("location", "is empty"),
+                ValidationIssue::error("samples", "is empty"),
+            ]
+        );
+    }
+
+    #[test]
+    fn validate_rejects_measured_at_time_beyond_max_future_skew() {
+        let now = Utc.with_ymd_and_hms(2021, 5, 1, 5, 45, 0).unwrap();
+        let within_skew = measurement(now + Duration::minutes(5), vec![counter("Grid", 1.0)]);
+        let beyond_skew = measurement(now + Duration::minutes(6), vec![counter("Grid", 1.0)]);
+
+        // act
+        let issues_within_skew =
+            within_skew.validate_with_max_future_skew(now, Duration::minutes(5));
+        let issues_beyond_skew =
+            beyond_skew.validate_with_max_future_skew(now, Duration::minutes(5));
+
+        assert_eq!(issues_within_skew, vec![]);
+        assert_eq!(
+            issues_beyond_skew,
+            vec![ValidationIssue::error(
+                "measuredAtTime",
+                "lies more than 300 seconds in the future"
+            )]
+        );
+    }
+
+    #[test]
+    fn validate_includes_sample_issues_with_their_index() {
+        let at = Utc.with_ymd_and_hms(2021, 5, 1, 5, 45, 0).unwrap();
+        let measurement = measurement(at, vec![counter("Grid", 1.0), counter("", f64::NAN)]);
+
+        // act
+        let issues = measurement.validate_with_max_future_skew(at, Duration::minutes(5));
+
+        assert_eq!(
+            issues,
+            vec![
+                ValidationIssue::warning("samples[1].sampleName", "is empty"),
+                ValidationIssue::error("samples[1].value", "is not finite"),
+            ]
+        );
+        assert_eq!(issues[1].severity, Severity::Error);
+    }
+
+    #[test]
+    fn labels_round_trip_through_json_and_yaml() {
+        let at = Utc.with_ymd_and_hms(2021, 5, 1, 5, 45, 0).unwrap();
+        let measurement = Measurement::builder("jarvis-modbus-exporter", "My Home")
+            .measured_at(at)
+            .sample(counter("Grid", 1.0))
+            .label("site-id", "home")
+            .label("firmware_version", "1.2.3")
+            .build()
+            .unwrap();
+
+        // act
+        let json = serde_json::to_string(&measurement).unwrap();
+        let from_json = serde_json::from_str::<Measurement>(&json).unwrap();
+        let from_yaml =
+            serde_yaml::from_str::<Measurement>(&serde_yaml::to_string(&measurement).unwrap())
+                .unwrap();
+
+        assert!(json.contains(r#""Labels":{"firmware_version":"1.2.3","site-id":"home"}"#));
+        assert_eq!(from_json.labels, measurement.labels);
+        assert_eq!(from_yaml.labels, measurement.labels);
+    }
+
+    #[test]
+    fn labels_are_left_out_when_absent() {
+        let at = Utc.with_ymd_and_hms(2021, 5, 1, 5, 45, 0).unwrap();
+
+        // act
+        let json = serde_json::to_string(&measurement(at, vec![counter("Grid", 1.0)])).unwrap();
+
+        assert!(!json.contains("Labels"));
+        assert_eq!(
+            serde_json::from_str::<Measurement>(&json).unwrap().labels,
+            None
+        );
+    }
+
+    #[test]
+    fn parse_labels_reads_key_value_pairs() {
+        // act
+        let labels = parse_labels("site-id=home, firmware_version=1.2.3,").unwrap();
+
+        assert_eq!(
+            labels,
+            BTreeMap::from([
+                ("firmware_version".to_string(), "1.2.3".to_string()),
+                ("site-id".to_string(), "home".to_string()),
+            ])
+        );
+        assert_eq!(parse_labels("").unwrap(), BTreeMap::new());
+    }
+
+    #[test]
+    fn parse_labels_rejects_invalid_keys_and_pairs() {
+        assert!(parse_labels("Site=home").is_err());
+        assert!(parse_labels("site id=home").is_err());
+        assert!(parse_labels("=home").is_err());
+        assert!(parse_labels("site").is_err());
+    }
+
+    #[test]
+    fn validate_rejects_invalid_label_keys() {
+        let at = Utc.with_ymd_and_hms(2021, 5, 1, 5, 45, 0).unwrap();
+        let measurement = Measurement::builder("jarvis-modbus-exporter", "My Home")
+            .measured_at(at)
+            .sample(counter("Grid", 1.0))
+            .label("site-id", "home")
+            .label("Experiment", "on")
+            .build()
+            .unwrap();
+
+        // act
+        let issues = measurement.validate_with_max_future_skew(at, Duration::minutes(5));
+
+        assert_eq!(
+            issues,
+            vec![ValidationIssue::error(
+                "labels.Experiment",
+                "is not made of lowercase alphanumerics, dashes and underscores"
+            )]
+        );
+    }
+
+    #[test]
+    fn schema_version_defaults_to_1_for_payloads_without_one() {
+        // act
+        let measurements = serde_yaml::from_str::<Vec<Measurement>>(
+            &std::fs::read_to_string("test-measurement.yaml").unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!(measurements[0].schema_version, 1);
+    }
+
+    #[test]
+    fn schema_version_round_trips() {
+        let at = Utc.with_ymd_and_hms(2021, 5, 1, 5, 45, 0).unwrap();
+        let measurement = Measurement {
+            schema_version: 7,
+            ..measurement(at, vec![counter("Grid", 1.0)])
+        };
+
+        // act
+        let json = serde_json::to_string(&measurement).unwrap();
+
+        assert!(json.contains(r#""SchemaVersion":7"#));
+        assert_eq!(
+            serde_json::from_str::<Measurement>(&json)
+                .unwrap()
+                .schema_version,
+            7
+        );
+    }
+
+    #[test]
+    fn builder_sets_current_schema_version() {
+        let at = Utc.with_ymd_and_hms(2021, 5, 1, 5, 45, 0).unwrap();
+
+        // act
+        let measurement = measurement(at, vec![counter("Grid", 1.0)]);
+
+        assert_eq!(
+            measurement.schema_version,
+            Measurement::CURRENT_SCHEMA_VERSION
+        );
+    }
+
+    #[test]
+    fn migrate_to_current_swaps_energy_names_of_version_1() {
+        let at = Utc.with_ymd_and_hms(2021, 5, 1, 5, 45, 0).unwrap();
+        let sample = |sample_type| Sample::builder(sample_type, 1.0).build();
+        let mut measurement = Measurement {
+            schema_version: 1,
+            ..measurement(
+                at,
+                vec![
+                    sample(SampleType::Energy),
+                    sample(SampleType::GasConsumption),
+                    sample(SampleType::Temperature),
+                ],
+            )
+        };
+
+        // act
+        measurement.migrate_to_current();
+
+        assert_eq!(
+            measurement.schema_version,
+            Measurement::CURRENT_SCHEMA_VERSION
+        );
+        assert_eq!(
+            measurement
+                .samples
+                .iter()
+                .map(|sample| sample.sample_type.clone())
+                .collect::<Vec<_>>(),
+            vec![
+                SampleType::GasConsumption,
+                SampleType::Energy,
+                SampleType::Temperature
+            ]
+        );
+    }
+
+    #[test]
+    fn migrate_to_current_leaves_current_measurement_unchanged() {
+        let at = Utc.with_ymd_and_hms(2021, 5, 1, 5, 45, 0).unwrap();
+        let mut measurement =
+            measurement(at, vec![Sample::builder(SampleType::Energy, 1.0).build()]);
+
+        // act
+        measurement.migrate_to_current();
+
+        assert_eq!(measurement.samples[0].sample_type, SampleType::Energy);
+    }
+
+    #[test]
+    fn to_bigquery_rows_returns_row_per_sample() {
+        let measurement = serde_json::from_str::<Measurement>(
+            &std::fs::read_to_string("test-measurement.json").unwrap(),
+        )
+        .unwrap();
+
+        // act
+        let rows = measurement.to_bigquery_rows();
+
+        assert_eq!(
+            rows,
+            vec![
+                serde_json::json!({
+                    "ID": "cc6e17bb-fd60-4dde-acc3-0cda7d752acc",
+                    "Source": "jarvis-tp-link-hs-110-exporter",
+                    "Location": "My Home",
+                    "EntityType": "ENTITY_TYPE_DEVICE",
+                    "EntityName": "TP-Link HS110",
+                    "SampleType": "SAMPLE_TYPE_ELECTRICITY_CONSUMPTION",
+                    "SampleName": "Oven",
+                    "MetricType": "METRIC_TYPE_COUNTER",
+                    "Value": 9695872800.0,
+                    "MeasuredAtTime": "2021-05-01T05:45:03.043614293Z",
+                    "insert_id": "cc6e17bb-fd60-4dde-acc3-0cda7d752acc-0"
+                }),
+                serde_json::json!({
+                    "ID": "cc6e17bb-fd60-4dde-acc3-0cda7d752acc",
+                    "Source": "jarvis-tp-link-hs-110-exporter",
+                    "Location": "My Home",
+                    "EntityType": "ENTITY_TYPE_ZONE",
+                    "EntityName": "Living room",
+                    "SampleType": "SAMPLE_TYPE_TEMPERATURE",
+                    "SampleName": "Thermostat",
+                    "MetricType": "METRIC_TYPE_GAUGE",
+                    "Value": 21.5,
+                    "MeasuredAtTime": "2021-05-01T05:45:03.043614293Z",
+                    "insert_id": "cc6e17bb-fd60-4dde-acc3-0cda7d752acc-1"
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn to_bigquery_rows_writes_unknown_types_as_received() {
+        let at = Utc.with_ymd_and_hms(2021, 5, 1, 5, 45, 0).unwrap();
+        let measurement = measurement(
+            at,
+            vec![
+                Sample::builder(SampleType::Other("SAMPLE_TYPE_SOIL_MOISTURE".into()), 0.3).build(),
+            ],
+        );
+
+        // act
+        let rows = measurement.to_bigquery_rows();
+
+        assert_eq!(rows[0]["SampleType"], "SAMPLE_TYPE_SOIL_MOISTURE");
+        assert_eq!(rows[0]["MeasuredAtTime"], "2021-05-01T05:45:00Z");
+    }
+    #[test]
+    fn sort_measurements_by_time_keeps_order_of_equal_timestamps() {
+        let at = Utc.with_ymd_and_hms(2021, 5, 1, 5, 45, 0).unwrap();
+        let named = |id: &str, measured_at_time| Measurement {
+            id: id.to_string(),
+            ..measurement(measured_at_time, vec![counter("Grid", 1.0)])
+        };
+        let mut measurements = vec![
+            named("late", at + Duration::minutes(1)),
+            named("first", at),
+            named("early", at - Duration::minutes(1)),
+            named("second", at),
+            named("third", at),
+        ];
+
+        // act
+        sort_measurements_by_time(&mut measurements);
+
+        assert_eq!(
+            measurements
+                .iter()
+                .map(|measurement| measurement.id.as_str())
+                .collect::<Vec<_>>(),
+            vec!["early", "first", "second", "third", "late"]
+        );
+    }
+
+    #[test]
+    fn cmp_by_time_compares_measured_at_time() {
+        let at = Utc.with_ymd_and_hms(2021, 5, 1, 5, 45, 0).unwrap();
+        let earlier = measurement(at, vec![counter("Grid", 1.0)]);
+        let later = measurement(at + Duration::seconds(1), vec![counter("Grid", 1.0)]);
+
+        assert_eq!(earlier.cmp_by_time(&later), Ordering::Less);
+        assert_eq!(later.cmp_by_time(&earlier), Ordering::Greater);
+        assert_eq!(earlier.cmp_by_time(&earlier.clone()), Ordering::Equal);
+    }
+
+    #[test]
+    fn approx_eq_allows_values_to_differ_up_to_epsilon() {
+        let at = Utc.with_ymd_and_hms(2021, 5, 1, 5, 45, 0).unwrap();
+        let measurement = measurement(at, vec![counter("Grid", 1.0)]);
+        let with_value = |value| {
+            let mut other = measurement.clone();
+            other.samples[0].value = value;
+            other
+        };
+
+        assert!(measurement.approx_eq(&with_value(1.25), 0.25));
+        assert!(measurement.approx_eq(&with_value(0.75), 0.25));
+        assert!(!measurement.approx_eq(&with_value(1.2500001), 0.25));
+        assert!(measurement != with_value(1.25));
+        assert_eq!(measurement, with_value(1.0));
+    }
+
+    #[test]
+    fn approx_eq_compares_other_fields_exactly() {
+        let at = Utc.with_ymd_and_hms(2021, 5, 1, 5, 45, 0).unwrap();
+        let measurement = measurement(at, vec![counter("Grid", 1.0)]);
+        let mut renamed = measurement.clone();
+        renamed.samples[0].sample_name = "Solar".into();
+        let mut relocated = measurement.clone();
+        relocated.location = "Holiday Home".into();
+        let mut with_extra_sample = measurement.clone();
+        with_extra_sample.samples.push(counter("Grid", 1.0));
+
+        assert!(!measurement.approx_eq(&renamed, 0.25));
+        assert!(!measurement.approx_eq(&relocated, 0.25));
+        assert!(!measurement.approx_eq(&with_extra_sample, 0.25));
+    }
+
+    fn modbus_measurement(samples: Vec<Sample>) -> Measurement {
+        measurement(Utc.with_ymd_and_hms(2021, 5, 1, 5, 45, 0).unwrap(), samples)
+    }
+
+    fn values(measurement: &Measurement) -> Vec<(&str, f64)> {
+        measurement
+            .samples
+            .iter()
+            .map(|sample| (sample.sample_name.as_str(), sample.value))
+            .collect()
+    }
+
+    #[test]
+    fn dedupe_samples_keeps_first() {
+        let mut measurement = modbus_measurement(vec![
+            counter("Grid", 3.0),
+            counter("Solar", 1.0),
+            counter("Grid", 5.0),
+            counter("Grid", 4.0),
+        ]);
+
+        // act
+        let removed = measurement.dedupe_samples(DedupePolicy::KeepFirst);
+
+        assert_eq!(removed, 2);
+        assert_eq!(values(&measurement), vec![("Grid", 3.0), ("Solar", 1.0)]);
+    }
+
+    #[test]
+    fn dedupe_samples_keeps_last() {
+        let mut measurement = modbus_measurement(vec![
+            counter("Grid", 3.0),
+            counter("Solar", 1.0),
+            counter("Grid", 5.0),
+            counter("Grid", 4.0),
+        ]);
+
+        // act
+        let removed = measurement.dedupe_samples(DedupePolicy::KeepLast);
+
+        assert_eq!(removed, 2);
+        assert_eq!(values(&measurement), vec![("Grid", 4.0), ("Solar", 1.0)]);
+    }
+
+    #[test]
+    fn dedupe_samples_keeps_max() {
+        let mut measurement = modbus_measurement(vec![
+            counter("Grid", 3.0),
+            counter("Solar", 1.0),
+            counter("Grid", 5.0),
+            counter("Grid", 4.0),
+        ]);
+
+        // act
+        let removed = measurement.dedupe_samples(DedupePolicy::KeepMax);
+
+        assert_eq!(removed, 2);
+        assert_eq!(values(&measurement), vec![("Grid", 5.0), ("Solar", 1.0)]);
+    }
+
+    #[test]
+    fn dedupe_samples_without_duplicates_removes_nothing() {
+        let gauge = Sample::builder(SampleType::ElectricityConsumption, 2.0)
+            .entity_name("Sunny Boy")
+            .sample_name("Power")
+            .build();
+        let mut measurement =
+            modbus_measurement(vec![counter("Grid", 3.0), gauge, counter("Solar", 1.0)]);
+        let original = measurement.clone();
+
+        // act
+        let removed = measurement.dedupe_samples(DedupePolicy::KeepLast);
+
+        assert_eq!(removed, 0);
+        assert_eq!(measurement, original);
+    }
+
+    #[test]
+    fn counter_delta_returns_increase() {
+        assert_eq!(
+            counter_delta(1000.0, 1250.0, None),
+            CounterDelta::Delta(250.0)
+        );
+        assert_eq!(
+            counter_delta(1000.0, 1000.0, Some(65536.0)),
+            CounterDelta::Delta(0.0)
+        );
+    }
+
+    #[test]
+    fn counter_delta_corrects_16_bit_rollover() {
+        assert_eq!(
+            counter_delta(65530.0, 4.0, Some(65536.0)),
+            CounterDelta::Rollover(10.0)
+        );
+    }
+
+    #[test]
+    fn counter_delta_corrects_32_bit_rollover() {
+        assert_eq!(
+            counter_delta(4294967290.0, 100.0, Some(4294967296.0)),
+            CounterDelta::Rollover(106.0)
+        );
+    }
+
+    #[test]
+    fn counter_delta_reports_reset_without_max_value() {
+        assert_eq!(counter_delta(1000.0, 30.0, None), CounterDelta::Reset);
+    }
+
+    #[test]
+    fn counter_delta_reports_reset_above_max_value() {
+        assert_eq!(
+            counter_delta(70000.0, 30.0, Some(65536.0)),
+            CounterDelta::Reset
+        );
+    }
+
+    #[test]
+    fn deltas_since_with_max_value_corrects_rollover() {
+        let previous_at = Utc.with_ymd_and_hms(2021, 5, 1, 5, 45, 0).unwrap();
+        let previous = measurement(previous_at, vec![counter("Grid", 65530.0)]);
+        let current = measurement(
+            previous_at + Duration::seconds(60),
+            vec![counter("Grid", 4.0)],
+        );
+
+        // act
+        let deltas = current.deltas_since_with_max_value(&previous, Some(65536.0));
+
+        assert_eq!(deltas[0].delta, 10.0);
+        assert!(!deltas[0].counter_reset);
+    }
+
+    #[test]
+    fn camel_case_field_names_deserialize_to_same_measurement() {
+        let pascal_case = std::fs::read_to_string("test-measurement.json").unwrap();
+        let camel_case = std::fs::read_to_string("test-measurement-camel-case.json").unwrap();
+
+        // act
+        let measurement = serde_json::from_str::<Measurement>(&camel_case).unwrap();
+
+        assert_eq!(
+            measurement,
+            serde_json::from_str::<Measurement>(&pascal_case).unwrap()
+        );
+        assert!(serde_json::to_string(&measurement)
+            .unwrap()
+            .contains(r#""MeasuredAtTime":"#));
+    }
+
+    fn boiler_measurement(measured_at_time: DateTime<Utc>, temperature: f64) -> Measurement {
+        measurement(
+            measured_at_time,
+            vec![
+                Sample::builder(SampleType::Temperature, temperature)
+                    .entity_name("Boiler")
+                    .sample_name("Top")
+                    .build(),
+                Sample::builder(SampleType::Temperature, temperature - 10.0)
+                    .entity_name("Boiler")
+                    .sample_name("Bottom")
+                    .build(),
+                Sample::builder(SampleType::Temperature, 21.5)
+                    .entity_type(EntityType::Zone)
+                    .entity_name("Living room")
+                    .build(),
+                counter("Total", 1000.0),
+            ],
+        )
+    }
+
+    #[test]
+    fn find_sample_returns_first_matching_sample() {
+        let measurement = boiler_measurement(Utc::now(), 60.0);
+
+        // act
+        let sample = measurement.find_sample("Boiler", SampleType::Temperature);
+
+        assert_eq!(sample, Some(&measurement.samples[0]));
+        assert_eq!(
+            measurement.value_of("Living room", SampleType::Temperature),
+            Some(21.5)
+        );
+    }
+
+    #[test]
+    fn find_sample_returns_none_for_absent_sample() {
+        let measurement = boiler_measurement(Utc::now(), 60.0);
+
+        // act
+        let samples = (
+            measurement.find_sample("Boiler", SampleType::Humidity),
+            measurement.find_sample("Garage", SampleType::Temperature),
+        );
+
+        assert_eq!(samples, (None, None));
+        assert_eq!(
+            measurement.value_of("Garage", SampleType::Temperature),
+            None
+        );
+    }
+
+    #[test]
+    fn find_samples_of_type_returns_all_matching_samples() {
+        let measurement = boiler_measurement(Utc::now(), 60.0);
+
+        // act
+        let samples = measurement.find_samples_of_type(SampleType::Temperature);
+
+        assert_eq!(
+            samples,
+            vec![
+                &measurement.samples[0],
+                &measurement.samples[1],
+                &measurement.samples[2]
+            ]
+        );
+    }
+
+    #[test]
+    fn find_sample_matching_ignores_case_only_when_asked() {
+        let measurement = boiler_measurement(Utc::now(), 60.0);
+
+        // act
+        let values = (
+            measurement.value_of_matching("boiler", SampleType::Temperature, NameMatching::Exact),
+            measurement.value_of_matching(
+                "boiler",
+                SampleType::Temperature,
+                NameMatching::IgnoreCase,
+            ),
+        );
+
+        assert_eq!(values, (None, Some(60.0)));
+    }
+
+    #[test]
+    fn find_latest_sample_picks_most_recent_measurement_containing_sample() {
+        let measured_at_time = Utc::now();
+        let measurements = vec![
+            boiler_measurement(measured_at_time - Duration::minutes(2), 55.0),
+            boiler_measurement(measured_at_time - Duration::minutes(1), 60.0),
+            measurement(measured_at_time, vec![counter("Total", 1100.0)]),
+            boiler_measurement(measured_at_time - Duration::minutes(3), 50.0),
+        ];
+
+        // act
+        let value = latest_value_of(
+            &measurements,
+            "BOILER",
+            SampleType::Temperature,
+            NameMatching::IgnoreCase,
+        );
+
+        assert_eq!(value, Some(60.0));
+        assert_eq!(
+            find_latest_sample(
+                &measurements,
+                "Garage",
+                SampleType::Temperature,
+                NameMatching::Exact
+            ),
+            None
+        );
+    }
+
+    fn water(value: f64) -> Sample {
+        Sample::builder(SampleType::WaterConsumption, value)
+            .entity_name("Water meter")
+            .sample_name("Total")
+            .metric_type(MetricType::Counter)
+            .build()
+    }
+
+    #[test]
+    fn rates_since_derives_watts_for_energy_counters() {
+        let previous_at = Utc.with_ymd_and_hms(2021, 5, 1, 5, 45, 0).unwrap();
+        let previous = measurement(
+            previous_at,
+            vec![
+                counter("Grid", 1000.0),
+                Sample {
+                    unit: Some(SampleUnit::KilowattHour),
+                    ..counter("Oven", 1.25)
+                },
+            ],
+        );
+        let current = measurement(
+            previous_at + Duration::seconds(60),
+            vec![
+                counter("Grid", 4600.0),
+                Sample {
+                    unit: Some(SampleUnit::KilowattHour),
+                    ..counter("Oven", 1.275)
+                },
+            ],
+        );
+
+        // act
+        let rates = current.rates_since(&previous);
+
+        assert_eq!(rates.len(), 2);
+        // 3600 J in 60 s
+        assert_eq!(rates[0].rate, 60.0);
+        assert_eq!(rates[0].unit, RateUnit::Watt);
+        // 0.025 kWh = 90000 J in 60 s
+        assert!((rates[1].rate - 1500.0).abs() < 1e-6);
+        assert_eq!(rates[1].unit, RateUnit::Watt);
+    }
+
+    #[test]
+    fn rates_since_derives_hourly_flow_for_water_counters() {
+        let previous_at = Utc.with_ymd_and_hms(2021, 5, 1, 5, 45, 0).unwrap();
+        let previous = measurement(previous_at, vec![water(120.0)]);
+        let current = measurement(previous_at + Duration::minutes(10), vec![water(125.0)]);
+
+        // act
+        let rates = current.rates_since(&previous);
+
+        assert_eq!(
+            rates,
+            vec![RateSample {
+                key: water(0.0).key(),
+                rate: 30.0,
+                unit: RateUnit::PerHour,
+            }]
+        );
+    }
+
+    #[test]
+    fn rates_since_skips_zero_elapsed_time() {
+        let measured_at_time = Utc.with_ymd_and_hms(2021, 5, 1, 5, 45, 0).unwrap();
+        let previous = measurement(measured_at_time, vec![counter("Grid", 1000.0)]);
+        let current = measurement(measured_at_time, vec![counter("Grid", 1100.0)]);
+
+        // act
+        let rates = current.rates_since(&previous);
+
+        assert_eq!(rates, vec![]);
+    }
+
+    #[test]
+    fn rates_since_skips_counter_reset() {
+        let previous_at = Utc.with_ymd_and_hms(2021, 5, 1, 5, 45, 0).unwrap();
+        let previous = measurement(
+            previous_at,
+            vec![counter("Grid", 1000.0), counter("Solar", 500.0)],
+        );
+        let current = measurement(
+            previous_at + Duration::seconds(60),
+            vec![counter("Grid", 30.0), counter("Solar", 560.0)],
+        );
+
+        // act
+        let rates = current.rates_since(&previous);
+
+        assert_eq!(rates.len(), 1);
+        assert_eq!(rates[0].key, counter("Solar", 0.0).key());
+        assert_eq!(rates[0].rate, 1.0);
+    }
+
+    #[test]
+    fn rate_sample_to_sample_returns_gauge_in_watts() {
+        let rate_sample = RateSample {
+            key: counter("Grid", 0.0).key(),
+            rate: 60.0,
+            unit: RateUnit::Watt,
+        };
+
+        // act
+        let sample = rate_sample.to_sample();
+
+        assert_eq!(
+            sample,
+            Sample {
+                metric_type: MetricType::Gauge,
+                unit: Some(SampleUnit::Watt),
+                ..counter("Grid", 60.0)
+            }
+        );
+    }
 }
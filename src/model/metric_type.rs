@@ -1,11 +1,112 @@
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::hash::{Hash, Hasher};
 
-#[derive(Copy, Clone, PartialEq, Eq, Serialize, Deserialize, Debug)]
+/// Unknown names, like `METRIC_TYPE_*` names added after this version, are kept as Other and serialized back as is.
+#[derive(Clone, Debug)]
 pub enum MetricType {
-    #[serde(rename = "")]
     Invalid,
-    #[serde(rename = "METRIC_TYPE_COUNTER")]
     Counter,
-    #[serde(rename = "METRIC_TYPE_GAUGE")]
     Gauge,
+    Other(String),
+}
+
+impl MetricType {
+    /// Name as serialized.
+    pub fn as_str(&self) -> &str {
+        match self {
+            MetricType::Invalid => "",
+            MetricType::Counter => "METRIC_TYPE_COUNTER",
+            MetricType::Gauge => "METRIC_TYPE_GAUGE",
+            MetricType::Other(name) => name,
+        }
+    }
+
+    /// Named variant for name, or Other if there is none.
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            "" => MetricType::Invalid,
+            "METRIC_TYPE_COUNTER" => MetricType::Counter,
+            "METRIC_TYPE_GAUGE" => MetricType::Gauge,
+            _ => MetricType::Other(name.to_string()),
+        }
+    }
+}
+
+impl PartialEq for MetricType {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl Eq for MetricType {}
+
+impl Hash for MetricType {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_str().hash(state);
+    }
+}
+
+impl Serialize for MetricType {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for MetricType {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Ok(MetricType::from_name(&String::deserialize(deserializer)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::collections::HashSet;
+
+    #[test]
+    fn unknown_name_round_trips_as_other() {
+        // act
+        let metric_type = serde_json::from_str::<MetricType>(r#""METRIC_TYPE_HISTOGRAM""#).unwrap();
+
+        assert_eq!(
+            metric_type,
+            MetricType::Other("METRIC_TYPE_HISTOGRAM".to_string())
+        );
+        assert!(matches!(metric_type, MetricType::Other(_)));
+        assert_eq!(
+            serde_json::to_string(&metric_type).unwrap(),
+            r#""METRIC_TYPE_HISTOGRAM""#
+        );
+    }
+
+    #[test]
+    fn other_with_known_name_equals_named_variant() {
+        let other = MetricType::Other("METRIC_TYPE_GAUGE".to_string());
+
+        // act
+        let unique = HashSet::from([other.clone(), MetricType::Gauge]);
+
+        assert_eq!(other, MetricType::Gauge);
+        assert_eq!(unique.len(), 1);
+    }
+
+    #[test]
+    fn known_names_deserialize_to_named_variants() {
+        for metric_type in [MetricType::Invalid, MetricType::Counter, MetricType::Gauge] {
+            let json = serde_json::to_string(&metric_type).unwrap();
+
+            // act
+            let deserialized = serde_json::from_str::<MetricType>(&json).unwrap();
+
+            assert!(!matches!(deserialized, MetricType::Other(_)));
+            assert_eq!(deserialized, metric_type);
+        }
+    }
 }
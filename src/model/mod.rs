@@ -1,20 +1,45 @@
+mod aggregate;
 mod entity_type;
+mod interpolation;
+mod line_protocol;
 mod measurement;
 mod metric_type;
+mod plausibility;
+mod prometheus;
 mod sample;
 mod sample_type;
+mod sample_unit;
 mod spot_price;
 mod spot_price_planner;
 mod spot_prices_state;
+mod validation;
 
+pub use crate::model::aggregate::{aggregate_measurements, AggregateKey, AGGREGATE_NAME};
 pub use crate::model::entity_type::EntityType;
-pub use crate::model::measurement::Measurement;
+pub use crate::model::interpolation::{
+    interpolate_sample, interpolate_sample_with_tolerance, DEFAULT_INTERPOLATION_TOLERANCE_SECONDS,
+};
+pub use crate::model::line_protocol::measurements_to_line_protocol;
+pub use crate::model::measurement::{
+    counter_delta, find_latest_sample, is_valid_label_key, latest_value_of, parse_labels,
+    sort_measurements_by_time, CounterDelta, DedupePolicy, Measurement, MeasurementBuilder,
+    NameMatching, RateSample, RateUnit, SampleDelta, ValidationError,
+};
 pub use crate::model::metric_type::MetricType;
-pub use crate::model::sample::Sample;
-pub use crate::model::sample_type::SampleType;
+pub use crate::model::plausibility::{
+    PlausibilityRanges, PlausibilityViolation, PlausibilityViolationKind, PlausibleRange,
+};
+pub use crate::model::prometheus::measurements_to_prometheus;
+pub use crate::model::sample::{Sample, SampleBuilder, SampleKey};
+pub use crate::model::sample_type::{SampleType, CORRECTED_ENERGY_NAMES_SCHEMA_VERSION};
+pub use crate::model::sample_unit::{
+    joules_to_kwh, kwh_to_joules, wh_to_joules, SampleUnit, UnitConversionError,
+    JOULES_PER_KILOWATT_HOUR, JOULES_PER_WATT_HOUR,
+};
 pub use crate::model::spot_price::*;
 pub use crate::model::spot_price_planner::*;
 pub use crate::model::spot_prices_state::*;
+pub use crate::model::validation::*;
 
 #[cfg(test)]
 mod tests {
@@ -37,11 +62,14 @@ mod tests {
                     sample_type: SampleType::ElectricityConsumption,
                     sample_name: "Oven".into(),
                     metric_type: MetricType::Counter,
-                    value: 9695872800.0
+                    value: 9695872800.0,
+                    unit: None,
                 }],
                 measured_at_time: DateTime::parse_from_rfc3339("2021-05-01T05:45:03.043614293Z")
                     .unwrap()
                     .with_timezone(&Utc),
+                schema_version: Measurement::CURRENT_SCHEMA_VERSION,
+                labels: None,
             })
             .unwrap(),
             r#"{
@@ -58,7 +86,8 @@ mod tests {
       "Value": 9695872800.0
     }
   ],
-  "MeasuredAtTime": "2021-05-01T05:45:03.043614293Z"
+  "MeasuredAtTime": "2021-05-01T05:45:03.043614293Z",
+  "SchemaVersion": 2
 }"#
         );
     }
@@ -90,23 +119,23 @@ mod tests {
         assert_eq!(measurement.location, "My Home");
         assert_eq!(measurement.samples.len(), 1);
         assert_eq!(
-            measurement.samples.get(0).unwrap().entity_type,
+            measurement.samples.first().unwrap().entity_type,
             EntityType::Device
         );
         assert_eq!(
-            measurement.samples.get(0).unwrap().entity_name,
+            measurement.samples.first().unwrap().entity_name,
             "TP-Link HS110"
         );
         assert_eq!(
-            measurement.samples.get(0).unwrap().sample_type,
+            measurement.samples.first().unwrap().sample_type,
             SampleType::ElectricityConsumption
         );
-        assert_eq!(measurement.samples.get(0).unwrap().sample_name, "Oven");
+        assert_eq!(measurement.samples.first().unwrap().sample_name, "Oven");
         assert_eq!(
-            measurement.samples.get(0).unwrap().metric_type,
+            measurement.samples.first().unwrap().metric_type,
             MetricType::Counter
         );
-        assert_eq!(measurement.samples.get(0).unwrap().value, 9695872800.0);
+        assert_eq!(measurement.samples.first().unwrap().value, 9695872800.0);
         assert_eq!(
             measurement.measured_at_time,
             DateTime::parse_from_rfc3339("2021-05-01T05:45:03.043614293Z")
@@ -128,11 +157,14 @@ mod tests {
                     sample_type: SampleType::ElectricityConsumption,
                     sample_name: "Oven".into(),
                     metric_type: MetricType::Counter,
-                    value: 9695872800.0
+                    value: 9695872800.0,
+                    unit: None,
                 }],
                 measured_at_time: DateTime::parse_from_rfc3339("2021-05-01T05:45:03.043614293Z")
                     .unwrap()
                     .with_timezone(&Utc),
+                schema_version: Measurement::CURRENT_SCHEMA_VERSION,
+                labels: None,
             })
             .unwrap(),
             r#"Id: cc6e17bb-fd60-4dde-acc3-0cda7d752acc
@@ -146,6 +178,7 @@ Samples:
   MetricType: METRIC_TYPE_COUNTER
   Value: 9695872800.0
 MeasuredAtTime: 2021-05-01T05:45:03.043614293Z
+SchemaVersion: 2
 "#
         );
     }
@@ -174,23 +207,23 @@ MeasuredAtTime: "2021-05-01T05:45:03.043614293Z"
         assert_eq!(measurement.location, "My Home");
         assert_eq!(measurement.samples.len(), 1);
         assert_eq!(
-            measurement.samples.get(0).unwrap().entity_type,
+            measurement.samples.first().unwrap().entity_type,
             EntityType::Device
         );
         assert_eq!(
-            measurement.samples.get(0).unwrap().entity_name,
+            measurement.samples.first().unwrap().entity_name,
             "TP-Link HS110"
         );
         assert_eq!(
-            measurement.samples.get(0).unwrap().sample_type,
+            measurement.samples.first().unwrap().sample_type,
             SampleType::ElectricityConsumption
         );
-        assert_eq!(measurement.samples.get(0).unwrap().sample_name, "Oven");
+        assert_eq!(measurement.samples.first().unwrap().sample_name, "Oven");
         assert_eq!(
-            measurement.samples.get(0).unwrap().metric_type,
+            measurement.samples.first().unwrap().metric_type,
             MetricType::Counter
         );
-        assert_eq!(measurement.samples.get(0).unwrap().value, 9695872800.0);
+        assert_eq!(measurement.samples.first().unwrap().value, 9695872800.0);
         assert_eq!(
             measurement.measured_at_time,
             DateTime::parse_from_rfc3339("2021-05-01T05:45:03.043614293Z")
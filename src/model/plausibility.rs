@@ -0,0 +1,338 @@
+use crate::model::{Measurement, MetricType, Sample, SampleType};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+
+/// Lowest and highest plausible value of a gauge.
+#[derive(Copy, Clone, PartialEq, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PlausibleRange {
+    pub min: f64,
+    pub max: f64,
+}
+
+impl PlausibleRange {
+    pub fn new(min: f64, max: f64) -> Self {
+        PlausibleRange { min, max }
+    }
+
+    pub fn contains(&self, value: f64) -> bool {
+        value >= self.min && value <= self.max
+    }
+}
+
+/// Plausible values of gauges per sample type, in the units they're published in, and whether counters may go down.
+#[derive(Clone, PartialEq, Serialize, Deserialize, Debug)]
+#[serde(default, rename_all = "camelCase")]
+pub struct PlausibilityRanges {
+    pub ranges: HashMap<SampleType, PlausibleRange>,
+    /// Reports counters lower than in the previous measurement.
+    pub check_counter_regression: bool,
+}
+
+impl Default for PlausibilityRanges {
+    /// Temperatures from -60 till 150 °C, humidity and state of charge from 0 till 100 %, voltages from 0 till 450 V
+    /// and power from 0 till 50 kW, with non-decreasing counters.
+    fn default() -> Self {
+        PlausibilityRanges {
+            ranges: HashMap::from([
+                (SampleType::Temperature, PlausibleRange::new(-60.0, 150.0)),
+                (SampleType::Humidity, PlausibleRange::new(0.0, 100.0)),
+                (
+                    SampleType::BatteryStateOfCharge,
+                    PlausibleRange::new(0.0, 100.0),
+                ),
+                (
+                    SampleType::ElectricityVoltage,
+                    PlausibleRange::new(0.0, 450.0),
+                ),
+                (
+                    SampleType::ElectricityConsumption,
+                    PlausibleRange::new(0.0, 50_000.0),
+                ),
+                (
+                    SampleType::ElectricityProduction,
+                    PlausibleRange::new(0.0, 50_000.0),
+                ),
+            ]),
+            check_counter_regression: true,
+        }
+    }
+}
+
+impl PlausibilityRanges {
+    /// Replaces the range of sample_type, like an exporter config overriding a default.
+    pub fn with_range(mut self, sample_type: SampleType, range: PlausibleRange) -> Self {
+        self.ranges.insert(sample_type, range);
+        self
+    }
+
+    /// Replaces the ranges of the sample types in overrides.
+    pub fn with_overrides(mut self, overrides: &HashMap<SampleType, PlausibleRange>) -> Self {
+        self.ranges
+            .extend(overrides.iter().map(|(k, v)| (k.clone(), *v)));
+        self
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum PlausibilityViolationKind {
+    /// A gauge outside the plausible range of its sample type.
+    OutOfRange { range: PlausibleRange },
+    /// A counter lower than the same counter in the previous measurement.
+    CounterRegression { previous_value: f64 },
+}
+
+/// Implausible sample of a measurement.
+#[derive(Clone, PartialEq, Debug)]
+pub struct PlausibilityViolation {
+    pub sample_index: usize,
+    pub entity_name: String,
+    pub sample_type: SampleType,
+    pub sample_name: String,
+    pub value: f64,
+    pub kind: PlausibilityViolationKind,
+}
+
+impl fmt::Display for PlausibilityViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Sample {} of {} with type {} has value {}",
+            self.sample_name,
+            self.entity_name,
+            self.sample_type.as_str(),
+            self.value
+        )?;
+        match &self.kind {
+            PlausibilityViolationKind::OutOfRange { range } => {
+                write!(f, " outside {} till {}", range.min, range.max)
+            }
+            PlausibilityViolationKind::CounterRegression { previous_value } => {
+                write!(f, " below the previous value {}", previous_value)
+            }
+        }
+    }
+}
+
+impl Measurement {
+    /// Gauges outside ranges and, if ranges checks them, counters lower than the same counter in previous.
+    pub fn check_plausibility(
+        &self,
+        ranges: &PlausibilityRanges,
+        previous: Option<&Measurement>,
+    ) -> Vec<PlausibilityViolation> {
+        self.samples
+            .iter()
+            .enumerate()
+            .filter_map(|(sample_index, sample)| {
+                let kind = match sample.metric_type {
+                    MetricType::Counter if ranges.check_counter_regression => previous
+                        .and_then(|previous| previous_counter(previous, sample))
+                        .filter(|previous_value| sample.value < *previous_value)
+                        .map(
+                            |previous_value| PlausibilityViolationKind::CounterRegression {
+                                previous_value,
+                            },
+                        ),
+                    MetricType::Gauge => ranges
+                        .ranges
+                        .get(&sample.sample_type)
+                        .filter(|range| !range.contains(sample.value))
+                        .map(|range| PlausibilityViolationKind::OutOfRange { range: *range }),
+                    _ => None,
+                }?;
+
+                Some(PlausibilityViolation {
+                    sample_index,
+                    entity_name: sample.entity_name.clone(),
+                    sample_type: sample.sample_type.clone(),
+                    sample_name: sample.sample_name.clone(),
+                    value: sample.value,
+                    kind,
+                })
+            })
+            .collect()
+    }
+}
+
+fn previous_counter(previous: &Measurement, sample: &Sample) -> Option<f64> {
+    previous
+        .samples
+        .iter()
+        .find(|previous_sample| {
+            previous_sample.metric_type == MetricType::Counter
+                && previous_sample.entity_type == sample.entity_type
+                && previous_sample.entity_name == sample.entity_name
+                && previous_sample.sample_type == sample.sample_type
+                && previous_sample.sample_name == sample.sample_name
+        })
+        .map(|previous_sample| previous_sample.value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn gauge(sample_type: SampleType, value: f64) -> Sample {
+        Sample::builder(sample_type, value)
+            .entity_name("Living room")
+            .sample_name("Sensor")
+            .build()
+    }
+
+    fn counter(value: f64) -> Sample {
+        Sample::builder(SampleType::ElectricityConsumption, value)
+            .entity_name("Meter")
+            .sample_name("Grid")
+            .metric_type(MetricType::Counter)
+            .build()
+    }
+
+    fn measurement(samples: Vec<Sample>) -> Measurement {
+        Measurement::builder("jarvis-modbus-exporter", "My Home")
+            .samples(samples)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn check_plausibility_accepts_samples_in_range() {
+        let measurement = measurement(vec![
+            gauge(SampleType::Temperature, -60.0),
+            gauge(SampleType::Temperature, 150.0),
+            gauge(SampleType::Humidity, 45.0),
+            gauge(SampleType::ElectricityVoltage, 231.2),
+            gauge(SampleType::ElectricityConsumption, 1500.0),
+            gauge(SampleType::Pressure, 100_000.0),
+        ]);
+
+        // act
+        let violations = measurement.check_plausibility(&PlausibilityRanges::default(), None);
+
+        assert_eq!(violations, vec![]);
+    }
+
+    #[test]
+    fn check_plausibility_reports_samples_out_of_range() {
+        let measurement = measurement(vec![
+            gauge(SampleType::Temperature, 6500.0),
+            gauge(SampleType::Humidity, 45.0),
+            gauge(SampleType::BatteryStateOfCharge, -1.0),
+        ]);
+
+        // act
+        let violations = measurement.check_plausibility(&PlausibilityRanges::default(), None);
+
+        assert_eq!(
+            violations,
+            vec![
+                PlausibilityViolation {
+                    sample_index: 0,
+                    entity_name: "Living room".into(),
+                    sample_type: SampleType::Temperature,
+                    sample_name: "Sensor".into(),
+                    value: 6500.0,
+                    kind: PlausibilityViolationKind::OutOfRange {
+                        range: PlausibleRange::new(-60.0, 150.0)
+                    },
+                },
+                PlausibilityViolation {
+                    sample_index: 2,
+                    entity_name: "Living room".into(),
+                    sample_type: SampleType::BatteryStateOfCharge,
+                    sample_name: "Sensor".into(),
+                    value: -1.0,
+                    kind: PlausibilityViolationKind::OutOfRange {
+                        range: PlausibleRange::new(0.0, 100.0)
+                    },
+                },
+            ]
+        );
+        assert_eq!(
+            violations[0].to_string(),
+            "Sample Sensor of Living room with type SAMPLE_TYPE_TEMPERATURE has value 6500 outside -60 till 150"
+        );
+    }
+
+    #[test]
+    fn check_plausibility_reports_counter_regression() {
+        let previous = measurement(vec![counter(1000.0)]);
+
+        // act
+        let regressed = measurement(vec![counter(999.0)])
+            .check_plausibility(&PlausibilityRanges::default(), Some(&previous));
+        let increased = measurement(vec![counter(1001.0)])
+            .check_plausibility(&PlausibilityRanges::default(), Some(&previous));
+
+        assert_eq!(
+            regressed[0].kind,
+            PlausibilityViolationKind::CounterRegression {
+                previous_value: 1000.0
+            }
+        );
+        assert_eq!(increased, vec![]);
+    }
+
+    #[test]
+    fn check_plausibility_skips_counter_regression_when_disabled() {
+        let previous = measurement(vec![counter(1000.0)]);
+        let ranges = PlausibilityRanges {
+            check_counter_regression: false,
+            ..PlausibilityRanges::default()
+        };
+
+        // act
+        let violations =
+            measurement(vec![counter(999.0)]).check_plausibility(&ranges, Some(&previous));
+
+        assert_eq!(violations, vec![]);
+    }
+
+    #[test]
+    fn check_plausibility_uses_overridden_ranges() {
+        let measurement = measurement(vec![
+            gauge(SampleType::Temperature, 300.0),
+            gauge(SampleType::Pressure, 3.0),
+        ]);
+        let ranges = PlausibilityRanges::default()
+            .with_range(SampleType::Temperature, PlausibleRange::new(0.0, 500.0))
+            .with_overrides(&HashMap::from([(
+                SampleType::Pressure,
+                PlausibleRange::new(0.5, 2.5),
+            )]));
+
+        // act
+        let violations = measurement.check_plausibility(&ranges, None);
+
+        assert_eq!(
+            violations
+                .iter()
+                .map(|violation| violation.sample_index)
+                .collect::<Vec<_>>(),
+            vec![1]
+        );
+    }
+
+    #[test]
+    fn plausibility_ranges_deserialize_from_config() {
+        // act
+        let ranges = serde_yaml::from_str::<PlausibilityRanges>(
+            r#"
+ranges:
+  SAMPLE_TYPE_TEMPERATURE:
+    min: 0
+    max: 500
+checkCounterRegression: false
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            ranges.ranges.get(&SampleType::Temperature),
+            Some(&PlausibleRange::new(0.0, 500.0))
+        );
+        assert!(!ranges.check_counter_regression);
+    }
+}
@@ -0,0 +1,265 @@
+use crate::model::{Measurement, MetricType, Sample, SampleUnit};
+
+/// Prometheus exposition of the samples of measurements, as metrics named after namespace, sample type and unit, with
+/// the headers of each metric once.
+pub fn measurements_to_prometheus(measurements: &[Measurement], namespace: &str) -> String {
+    let mut metrics: Vec<(String, &str, Vec<String>)> = vec![];
+    for measurement in measurements {
+        for sample in &measurement.samples {
+            let name = match metric_name(namespace, sample) {
+                Some(name) => name,
+                None => continue,
+            };
+            let line = format!(
+                "{}{{source=\"{}\",location=\"{}\",entity=\"{}\",sample=\"{}\"}} {} {}",
+                name,
+                escape_label_value(&measurement.source),
+                escape_label_value(&measurement.location),
+                escape_label_value(&sample.entity_name),
+                escape_label_value(&sample.sample_name),
+                format_value(sample.value),
+                measurement.measured_at_time.timestamp_millis()
+            );
+            match metrics.iter_mut().find(|(n, _, _)| *n == name) {
+                Some((_, _, lines)) => lines.push(line),
+                None => metrics.push((name, sample.sample_type.as_str(), vec![line])),
+            }
+        }
+    }
+
+    metrics
+        .iter()
+        .map(|(name, sample_type, lines)| {
+            let metric_type = if name.ends_with("_total") {
+                "counter"
+            } else {
+                "gauge"
+            };
+            format!(
+                "# HELP {} Jarvis samples of type {}.\n# TYPE {} {}\n{}\n",
+                name,
+                sample_type,
+                name,
+                metric_type,
+                lines.join("\n")
+            )
+        })
+        .collect()
+}
+
+/// Name like `jarvis_electricity_consumption_joules_total`, or None for samples without a sample type.
+fn metric_name(namespace: &str, sample: &Sample) -> Option<String> {
+    let sample_type = sample.sample_type.as_str();
+    let sample_type = sample_type
+        .strip_prefix("SAMPLE_TYPE_")
+        .unwrap_or(sample_type);
+    if sample_type.is_empty() {
+        return None;
+    }
+    let is_counter = sample.metric_type == MetricType::Counter;
+    // energy is only the canonical unit of counters, gauges of the same sample types measure power
+    let unit = sample.unit.or_else(|| {
+        SampleUnit::canonical_for(&sample.sample_type).filter(|unit| {
+            is_counter
+                || !matches!(
+                    unit,
+                    SampleUnit::Joule | SampleUnit::WattHour | SampleUnit::KilowattHour
+                )
+        })
+    });
+
+    let mut parts = vec![];
+    if !namespace.is_empty() {
+        parts.push(namespace.to_string());
+    }
+    parts.push(sample_type.to_lowercase());
+    if let Some(unit) = unit {
+        parts.push(unit_suffix(unit).to_string());
+    }
+    if is_counter {
+        parts.push("total".to_string());
+    }
+
+    Some(
+        parts
+            .join("_")
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '_' || c == ':' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect(),
+    )
+}
+
+fn unit_suffix(unit: SampleUnit) -> &'static str {
+    match unit {
+        SampleUnit::Joule => "joules",
+        SampleUnit::WattHour => "watt_hours",
+        SampleUnit::KilowattHour => "kilowatt_hours",
+        SampleUnit::Watt => "watts",
+        SampleUnit::Kilowatt => "kilowatts",
+        SampleUnit::Celsius => "celsius",
+        SampleUnit::Kelvin => "kelvin",
+        SampleUnit::Fahrenheit => "fahrenheit",
+    }
+}
+
+fn escape_label_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn format_value(value: f64) -> String {
+    if value.is_nan() {
+        "NaN".to_string()
+    } else if value.is_infinite() {
+        if value > 0.0 { "+Inf" } else { "-Inf" }.to_string()
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{EntityType, SampleType};
+    use chrono::{DateTime, Duration, Utc};
+    use pretty_assertions::assert_eq;
+
+    fn measured_at_time() -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339("2021-05-01T05:45:03.043614293Z")
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    fn oven_counter(value: f64) -> Sample {
+        Sample::builder(SampleType::ElectricityConsumption, value)
+            .entity_name("TP-Link HS110")
+            .sample_name("Oven")
+            .metric_type(MetricType::Counter)
+            .build()
+    }
+
+    #[test]
+    fn to_prometheus_writes_counter_with_total_suffix() {
+        let measurement = Measurement::builder("jarvis-tp-link-hs-110-exporter", "My Home")
+            .measured_at(measured_at_time())
+            .sample(oven_counter(9695872800.0))
+            .build()
+            .unwrap();
+
+        // act
+        let exposition = measurement.to_prometheus("jarvis");
+
+        assert_eq!(
+            exposition,
+            r#"# HELP jarvis_electricity_consumption_joules_total Jarvis samples of type SAMPLE_TYPE_ELECTRICITY_CONSUMPTION.
+# TYPE jarvis_electricity_consumption_joules_total counter
+jarvis_electricity_consumption_joules_total{source="jarvis-tp-link-hs-110-exporter",location="My Home",entity="TP-Link HS110",sample="Oven"} 9695872800 1619847903043
+"#
+        );
+    }
+
+    #[test]
+    fn to_prometheus_writes_gauges() {
+        let measurement = Measurement::builder("jarvis-modbus-exporter", "My Home")
+            .measured_at(measured_at_time())
+            .sample(
+                Sample::builder(SampleType::ElectricityConsumption, 1500.5)
+                    .entity_name("Sunny Boy")
+                    .sample_name("L1")
+                    .build(),
+            )
+            .sample(
+                Sample::builder(SampleType::Temperature, 21.5)
+                    .entity_type(EntityType::Zone)
+                    .entity_name("Living room")
+                    .sample_name("Thermostat")
+                    .build(),
+            )
+            .sample(
+                Sample::builder(SampleType::Humidity, 45.0)
+                    .entity_type(EntityType::Zone)
+                    .entity_name("Living room")
+                    .sample_name("Thermostat")
+                    .build(),
+            )
+            .build()
+            .unwrap();
+
+        // act
+        let exposition = measurement.to_prometheus("jarvis");
+
+        assert_eq!(
+            exposition,
+            r#"# HELP jarvis_electricity_consumption Jarvis samples of type SAMPLE_TYPE_ELECTRICITY_CONSUMPTION.
+# TYPE jarvis_electricity_consumption gauge
+jarvis_electricity_consumption{source="jarvis-modbus-exporter",location="My Home",entity="Sunny Boy",sample="L1"} 1500.5 1619847903043
+# HELP jarvis_temperature_celsius Jarvis samples of type SAMPLE_TYPE_TEMPERATURE.
+# TYPE jarvis_temperature_celsius gauge
+jarvis_temperature_celsius{source="jarvis-modbus-exporter",location="My Home",entity="Living room",sample="Thermostat"} 21.5 1619847903043
+# HELP jarvis_humidity Jarvis samples of type SAMPLE_TYPE_HUMIDITY.
+# TYPE jarvis_humidity gauge
+jarvis_humidity{source="jarvis-modbus-exporter",location="My Home",entity="Living room",sample="Thermostat"} 45 1619847903043
+"#
+        );
+    }
+
+    #[test]
+    fn to_prometheus_escapes_label_values() {
+        let measurement = Measurement::builder("jarvis-modbus-exporter", "My \"Home\"")
+            .measured_at(measured_at_time())
+            .sample(
+                Sample::builder(SampleType::Temperature, 21.5)
+                    .entity_name("C:\\zone")
+                    .sample_name("first\nsecond")
+                    .unit(SampleUnit::Kelvin)
+                    .build(),
+            )
+            .build()
+            .unwrap();
+
+        // act
+        let exposition = measurement.to_prometheus("");
+
+        assert_eq!(
+            exposition,
+            r#"# HELP temperature_kelvin Jarvis samples of type SAMPLE_TYPE_TEMPERATURE.
+# TYPE temperature_kelvin gauge
+temperature_kelvin{source="jarvis-modbus-exporter",location="My \"Home\"",entity="C:\\zone",sample="first\nsecond"} 21.5 1619847903043
+"#
+        );
+    }
+
+    #[test]
+    fn measurements_to_prometheus_writes_headers_once_per_metric() {
+        let first = Measurement::builder("jarvis-tp-link-hs-110-exporter", "My Home")
+            .measured_at(measured_at_time())
+            .sample(oven_counter(1000.0))
+            .build()
+            .unwrap();
+        let second = Measurement::builder("jarvis-tp-link-hs-110-exporter", "Holiday Home")
+            .measured_at(measured_at_time() + Duration::seconds(1))
+            .sample(oven_counter(2000.0))
+            .build()
+            .unwrap();
+
+        // act
+        let exposition = measurements_to_prometheus(&[first, second], "jarvis");
+
+        assert_eq!(
+            exposition,
+            r#"# HELP jarvis_electricity_consumption_joules_total Jarvis samples of type SAMPLE_TYPE_ELECTRICITY_CONSUMPTION.
+# TYPE jarvis_electricity_consumption_joules_total counter
+jarvis_electricity_consumption_joules_total{source="jarvis-tp-link-hs-110-exporter",location="My Home",entity="TP-Link HS110",sample="Oven"} 1000 1619847903043
+jarvis_electricity_consumption_joules_total{source="jarvis-tp-link-hs-110-exporter",location="Holiday Home",entity="TP-Link HS110",sample="Oven"} 2000 1619847904043
+"#
+        );
+    }
+}
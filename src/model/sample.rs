@@ -1,13 +1,402 @@
-use crate::model::{EntityType, MetricType, SampleType};
+use crate::model::{
+    EntityType, MetricType, SampleType, SampleUnit, UnitConversionError, ValidationIssue,
+};
 use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Serialize, Deserialize, Debug)]
+#[derive(Clone, PartialEq, Serialize, Deserialize, Debug)]
 #[serde(rename_all = "PascalCase")]
 pub struct Sample {
+    #[serde(alias = "entityType")]
     pub entity_type: EntityType,
+    #[serde(alias = "entityName")]
     pub entity_name: String,
+    #[serde(alias = "sampleType")]
     pub sample_type: SampleType,
+    #[serde(alias = "sampleName")]
     pub sample_name: String,
+    #[serde(alias = "metricType")]
     pub metric_type: MetricType,
+    #[serde(alias = "value")]
     pub value: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none", alias = "unit")]
+    pub unit: Option<SampleUnit>,
+}
+
+impl Sample {
+    /// Builder for a gauge sample of sample_type with value, for a device unless set.
+    pub fn builder(sample_type: SampleType, value: f64) -> SampleBuilder {
+        SampleBuilder {
+            sample: Sample {
+                entity_type: EntityType::Device,
+                entity_name: String::new(),
+                sample_type,
+                sample_name: String::new(),
+                metric_type: MetricType::Gauge,
+                value,
+                unit: None,
+            },
+        }
+    }
+
+    /// Everything wrong with the sample; empty names are warnings, invalid types and non-finite values errors.
+    pub fn validate(&self) -> Vec<ValidationIssue> {
+        let mut issues = vec![];
+        if self.entity_type == EntityType::Invalid {
+            issues.push(ValidationIssue::error("entityType", "is invalid"));
+        }
+        if self.entity_name.is_empty() {
+            issues.push(ValidationIssue::warning("entityName", "is empty"));
+        }
+        if self.sample_type == SampleType::Invalid {
+            issues.push(ValidationIssue::error("sampleType", "is invalid"));
+        }
+        if self.sample_name.is_empty() {
+            issues.push(ValidationIssue::warning("sampleName", "is empty"));
+        }
+        if self.metric_type == MetricType::Invalid {
+            issues.push(ValidationIssue::error("metricType", "is invalid"));
+        }
+        if !self.value.is_finite() {
+            issues.push(ValidationIssue::error("value", "is not finite"));
+        }
+        issues
+    }
+
+    /// Entity and sample type and names identifying the sample within a measurement.
+    pub fn key(&self) -> SampleKey {
+        SampleKey {
+            entity_type: self.entity_type.clone(),
+            entity_name: self.entity_name.clone(),
+            sample_type: self.sample_type.clone(),
+            sample_name: self.sample_name.clone(),
+        }
+    }
+
+    /// Value converted to unit from the declared unit, or for energy and power samples without one from joules or watts.
+    pub fn value_in(&self, unit: SampleUnit) -> Result<f64, UnitConversionError> {
+        self.unit
+            .or_else(|| SampleUnit::default_for(&self.sample_type, &self.metric_type))
+            .ok_or(UnitConversionError::MissingUnit)?
+            .convert(self.value, unit)
+    }
+
+    /// Copy of the sample with its value converted to unit.
+    pub fn convert_to(&self, unit: SampleUnit) -> Result<Sample, UnitConversionError> {
+        let value = self
+            .unit
+            .ok_or(UnitConversionError::MissingUnit)?
+            .convert(self.value, unit)?;
+
+        Ok(Sample {
+            value,
+            unit: Some(unit),
+            ..self.clone()
+        })
+    }
+}
+
+/// Identifies a sample within a measurement, regardless of its metric type, value and unit.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct SampleKey {
+    pub entity_type: EntityType,
+    pub entity_name: String,
+    pub sample_type: SampleType,
+    pub sample_name: String,
+}
+
+impl SampleKey {
+    pub fn matches(&self, sample: &Sample) -> bool {
+        self.entity_type == sample.entity_type
+            && self.entity_name == sample.entity_name
+            && self.sample_type == sample.sample_type
+            && self.sample_name == sample.sample_name
+    }
+}
+
+pub struct SampleBuilder {
+    sample: Sample,
+}
+
+impl SampleBuilder {
+    pub fn entity_type(mut self, entity_type: EntityType) -> Self {
+        self.sample.entity_type = entity_type;
+        self
+    }
+
+    pub fn entity_name(mut self, entity_name: impl Into<String>) -> Self {
+        self.sample.entity_name = entity_name.into();
+        self
+    }
+
+    pub fn sample_name(mut self, sample_name: impl Into<String>) -> Self {
+        self.sample.sample_name = sample_name.into();
+        self
+    }
+
+    pub fn metric_type(mut self, metric_type: MetricType) -> Self {
+        self.sample.metric_type = metric_type;
+        self
+    }
+
+    pub fn unit(mut self, unit: SampleUnit) -> Self {
+        self.sample.unit = Some(unit);
+        self
+    }
+
+    pub fn build(self) -> Sample {
+        self.sample
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn sample(value: f64, unit: Option<SampleUnit>) -> Sample {
+        Sample {
+            entity_type: EntityType::Device,
+            entity_name: "TP-Link HS110".into(),
+            sample_type: SampleType::ElectricityConsumption,
+            sample_name: "Oven".into(),
+            metric_type: MetricType::Counter,
+            value,
+            unit,
+        }
+    }
+
+    #[test]
+    fn unit_round_trips_through_json() {
+        let json = serde_json::to_string(&sample(1.5, Some(SampleUnit::KilowattHour))).unwrap();
+
+        // act
+        let deserialized = serde_json::from_str::<Sample>(&json).unwrap();
+
+        assert!(json.contains(r#""Unit":"KILOWATT_HOUR""#));
+        assert_eq!(deserialized.unit, Some(SampleUnit::KilowattHour));
+    }
+
+    #[test]
+    fn unit_defaults_to_none_and_is_not_serialized() {
+        let json = serde_json::to_string(&sample(1.5, None)).unwrap();
+
+        // act
+        let deserialized = serde_json::from_str::<Sample>(&json).unwrap();
+
+        assert!(!json.contains("Unit"));
+        assert_eq!(deserialized.unit, None);
+    }
+
+    #[test]
+    fn convert_to_converts_watt_hour_to_joule_and_back() {
+        let sample = sample(2.5, Some(SampleUnit::WattHour));
+
+        // act
+        let joule = sample.convert_to(SampleUnit::Joule).unwrap();
+
+        assert_eq!(joule.value, 9000.0);
+        assert_eq!(joule.unit, Some(SampleUnit::Joule));
+        assert_eq!(joule.convert_to(SampleUnit::WattHour).unwrap().value, 2.5);
+    }
+
+    #[test]
+    fn convert_to_converts_kilowatt_hour_to_joule_and_back() {
+        let sample = sample(2.0, Some(SampleUnit::KilowattHour));
+
+        // act
+        let joule = sample.convert_to(SampleUnit::Joule).unwrap();
+
+        assert_eq!(joule.value, 7_200_000.0);
+        assert_eq!(
+            joule.convert_to(SampleUnit::KilowattHour).unwrap().value,
+            2.0
+        );
+    }
+
+    #[test]
+    fn convert_to_converts_watt_to_kilowatt_and_back() {
+        let sample = sample(1500.0, Some(SampleUnit::Watt));
+
+        // act
+        let kilowatt = sample.convert_to(SampleUnit::Kilowatt).unwrap();
+
+        assert_eq!(kilowatt.value, 1.5);
+        assert_eq!(kilowatt.convert_to(SampleUnit::Watt).unwrap().value, 1500.0);
+    }
+
+    #[test]
+    fn convert_to_converts_temperatures() {
+        let sample = sample(20.0, Some(SampleUnit::Celsius));
+
+        // act
+        let fahrenheit = sample.convert_to(SampleUnit::Fahrenheit).unwrap();
+
+        assert_eq!(fahrenheit.value, 68.0);
+        assert_eq!(sample.convert_to(SampleUnit::Kelvin).unwrap().value, 293.15);
+    }
+
+    #[test]
+    fn convert_to_rejects_other_dimension() {
+        let sample = sample(1500.0, Some(SampleUnit::Watt));
+
+        // act
+        let result = sample.convert_to(SampleUnit::Joule);
+
+        assert_eq!(
+            result.unwrap_err(),
+            UnitConversionError::IncompatibleUnits {
+                from: SampleUnit::Watt,
+                to: SampleUnit::Joule
+            }
+        );
+    }
+
+    #[test]
+    fn convert_to_rejects_sample_without_unit() {
+        let sample = sample(1500.0, None);
+
+        // act
+        let result = sample.convert_to(SampleUnit::Joule);
+
+        assert_eq!(result.unwrap_err(), UnitConversionError::MissingUnit);
+    }
+
+    #[test]
+    fn canonical_for_returns_joule_for_energy() {
+        assert_eq!(
+            SampleUnit::canonical_for(&SampleType::ElectricityConsumption),
+            Some(SampleUnit::Joule)
+        );
+        assert_eq!(
+            SampleUnit::canonical_for(&SampleType::Temperature),
+            Some(SampleUnit::Celsius)
+        );
+        assert_eq!(SampleUnit::canonical_for(&SampleType::Humidity), None);
+    }
+
+    #[test]
+    fn validate_accepts_complete_sample() {
+        // act
+        let issues = sample(1.5, None).validate();
+
+        assert_eq!(issues, vec![]);
+    }
+
+    #[test]
+    fn validate_warns_about_empty_names() {
+        let sample = Sample {
+            entity_name: "".into(),
+            sample_name: "".into(),
+            ..sample(1.5, None)
+        };
+
+        // act
+        let issues = sample.validate();
+
+        assert_eq!(
+            issues,
+            vec![
+                ValidationIssue::warning("entityName", "is empty"),
+                ValidationIssue::warning("sampleName", "is empty"),
+            ]
+        );
+    }
+
+    #[test]
+    fn validate_rejects_invalid_types() {
+        let sample = Sample {
+            entity_type: EntityType::Invalid,
+            sample_type: SampleType::Invalid,
+            metric_type: MetricType::Invalid,
+            ..sample(1.5, None)
+        };
+
+        // act
+        let issues = sample.validate();
+
+        assert_eq!(
+            issues,
+            vec![
+                ValidationIssue::error("entityType", "is invalid"),
+                ValidationIssue::error("sampleType", "is invalid"),
+                ValidationIssue::error("metricType", "is invalid"),
+            ]
+        );
+    }
+
+    #[test]
+    fn validate_rejects_non_finite_values() {
+        for value in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+            // act
+            let issues = sample(value, None).validate();
+
+            assert_eq!(
+                issues,
+                vec![ValidationIssue::error("value", "is not finite")]
+            );
+        }
+    }
+
+    #[test]
+    fn value_in_converts_from_declared_unit() {
+        // act
+        let value = sample(1.5, Some(SampleUnit::KilowattHour)).value_in(SampleUnit::Joule);
+
+        assert_eq!(value, Ok(5_400_000.0));
+    }
+
+    #[test]
+    fn value_in_defaults_energy_to_joules_and_power_to_watts() {
+        let power = Sample {
+            sample_type: SampleType::ElectricityChargeRate,
+            metric_type: MetricType::Gauge,
+            ..sample(1500.0, None)
+        };
+
+        // act
+        let values = (
+            sample(9_000_000.0, None).value_in(SampleUnit::KilowattHour),
+            power.value_in(SampleUnit::Kilowatt),
+        );
+
+        assert_eq!(values, (Ok(2.5), Ok(1.5)));
+    }
+
+    #[test]
+    fn value_in_defaults_electricity_gauge_to_watts() {
+        let gauge = Sample {
+            metric_type: MetricType::Gauge,
+            ..sample(1500.0, None)
+        };
+
+        // act
+        let values = (
+            gauge.value_in(SampleUnit::Kilowatt),
+            gauge.value_in(SampleUnit::Joule),
+        );
+
+        assert_eq!(
+            values,
+            (
+                Ok(1.5),
+                Err(UnitConversionError::IncompatibleUnits {
+                    from: SampleUnit::Watt,
+                    to: SampleUnit::Joule
+                })
+            )
+        );
+    }
+
+    #[test]
+    fn value_in_requires_unit_for_other_sample_types() {
+        let temperature = Sample {
+            sample_type: SampleType::Temperature,
+            ..sample(21.5, None)
+        };
+
+        // act
+        let value = temperature.value_in(SampleUnit::Kelvin);
+
+        assert_eq!(value, Err(UnitConversionError::MissingUnit));
+    }
 }
@@ -1,43 +1,259 @@
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::hash::{Hash, Hasher};
 
-#[derive(Copy, Clone, PartialEq, Eq, Serialize, Deserialize, Debug)]
+/// First measurement schema version in which Energy and GasConsumption are written under their own names; producers
+/// before it wrote Energy as `SAMPLE_TYPE_GAS_CONSUMPTION` and GasConsumption as `SAMPLE_TYPE_ENERGY`.
+pub const CORRECTED_ENERGY_NAMES_SCHEMA_VERSION: u32 = 2;
+
+/// Unknown names, like `SAMPLE_TYPE_*` names added after this version, are kept as Other and serialized back as is.
+#[derive(Clone, Debug)]
 pub enum SampleType {
-    #[serde(rename = "")]
     Invalid,
-    #[serde(rename = "SAMPLE_TYPE_ELECTRICITY_CONSUMPTION")]
     ElectricityConsumption,
-    #[serde(rename = "SAMPLE_TYPE_ELECTRICITY_PRODUCTION")]
     ElectricityProduction,
-    #[serde(rename = "SAMPLE_TYPE_BATTERY_SOC")]
     BatteryStateOfCharge,
-    #[serde(rename = "SAMPLE_TYPE_GAS_CONSUMPTION")]
     Energy,
-    #[serde(rename = "SAMPLE_TYPE_FLOW")]
     Flow,
-    #[serde(rename = "SAMPLE_TYPE_ENERGY")]
     GasConsumption,
-    #[serde(rename = "SAMPLE_TYPE_HEAT_DEMAND")]
     HeatDemand,
-    #[serde(rename = "SAMPLE_TYPE_HUMIDITY")]
     Humidity,
-    #[serde(rename = "SAMPLE_TYPE_PRESSURE")]
     Pressure,
-    #[serde(rename = "SAMPLE_TYPE_TEMPERATURE")]
     Temperature,
-    #[serde(rename = "SAMPLE_TYPE_TEMPERATURE_SETPOINT")]
     TemperatureSetpoint,
-    #[serde(rename = "SAMPLE_TYPE_TIME")]
     Time,
-    #[serde(rename = "SAMPLE_TYPE_ELECTRICITY_VOLTAGE")]
     ElectricityVoltage,
-    #[serde(rename = "SAMPLE_TYPE_ELECTRICITY_CURRENT")]
     ElectricityCurrent,
-    #[serde(rename = "SAMPLE_TYPE_WATER_CONSUMPTION")]
     WaterConsumption,
-    #[serde(rename = "SAMPLE_TYPE_DISTANCE_TRAVELED")]
     DistanceTraveled,
-    #[serde(rename = "SAMPLE_TYPE_AVAILABILITY")]
     Availability,
-    #[serde(rename = "SAMPLE_TYPE_BATTERY_CHARGE_RATE")]
     ElectricityChargeRate,
+    Price,
+    Other(String),
+}
+
+impl SampleType {
+    /// Name as serialized.
+    pub fn as_str(&self) -> &str {
+        match self {
+            SampleType::Invalid => "",
+            SampleType::ElectricityConsumption => "SAMPLE_TYPE_ELECTRICITY_CONSUMPTION",
+            SampleType::ElectricityProduction => "SAMPLE_TYPE_ELECTRICITY_PRODUCTION",
+            SampleType::BatteryStateOfCharge => "SAMPLE_TYPE_BATTERY_SOC",
+            SampleType::Energy => "SAMPLE_TYPE_ENERGY",
+            SampleType::Flow => "SAMPLE_TYPE_FLOW",
+            SampleType::GasConsumption => "SAMPLE_TYPE_GAS_CONSUMPTION",
+            SampleType::HeatDemand => "SAMPLE_TYPE_HEAT_DEMAND",
+            SampleType::Humidity => "SAMPLE_TYPE_HUMIDITY",
+            SampleType::Pressure => "SAMPLE_TYPE_PRESSURE",
+            SampleType::Temperature => "SAMPLE_TYPE_TEMPERATURE",
+            SampleType::TemperatureSetpoint => "SAMPLE_TYPE_TEMPERATURE_SETPOINT",
+            SampleType::Time => "SAMPLE_TYPE_TIME",
+            SampleType::ElectricityVoltage => "SAMPLE_TYPE_ELECTRICITY_VOLTAGE",
+            SampleType::ElectricityCurrent => "SAMPLE_TYPE_ELECTRICITY_CURRENT",
+            SampleType::WaterConsumption => "SAMPLE_TYPE_WATER_CONSUMPTION",
+            SampleType::DistanceTraveled => "SAMPLE_TYPE_DISTANCE_TRAVELED",
+            SampleType::Availability => "SAMPLE_TYPE_AVAILABILITY",
+            SampleType::ElectricityChargeRate => "SAMPLE_TYPE_BATTERY_CHARGE_RATE",
+            SampleType::Price => "SAMPLE_TYPE_PRICE",
+            SampleType::Other(name) => name,
+        }
+    }
+
+    /// Named variant for name, or Other if there is none.
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            "" => SampleType::Invalid,
+            "SAMPLE_TYPE_ELECTRICITY_CONSUMPTION" => SampleType::ElectricityConsumption,
+            "SAMPLE_TYPE_ELECTRICITY_PRODUCTION" => SampleType::ElectricityProduction,
+            "SAMPLE_TYPE_BATTERY_SOC" => SampleType::BatteryStateOfCharge,
+            "SAMPLE_TYPE_GAS_CONSUMPTION" => SampleType::GasConsumption,
+            "SAMPLE_TYPE_FLOW" => SampleType::Flow,
+            "SAMPLE_TYPE_ENERGY" => SampleType::Energy,
+            "SAMPLE_TYPE_HEAT_DEMAND" => SampleType::HeatDemand,
+            "SAMPLE_TYPE_HUMIDITY" => SampleType::Humidity,
+            "SAMPLE_TYPE_PRESSURE" => SampleType::Pressure,
+            "SAMPLE_TYPE_TEMPERATURE" => SampleType::Temperature,
+            "SAMPLE_TYPE_TEMPERATURE_SETPOINT" => SampleType::TemperatureSetpoint,
+            "SAMPLE_TYPE_TIME" => SampleType::Time,
+            "SAMPLE_TYPE_ELECTRICITY_VOLTAGE" => SampleType::ElectricityVoltage,
+            "SAMPLE_TYPE_ELECTRICITY_CURRENT" => SampleType::ElectricityCurrent,
+            "SAMPLE_TYPE_WATER_CONSUMPTION" => SampleType::WaterConsumption,
+            "SAMPLE_TYPE_DISTANCE_TRAVELED" => SampleType::DistanceTraveled,
+            "SAMPLE_TYPE_AVAILABILITY" => SampleType::Availability,
+            "SAMPLE_TYPE_BATTERY_CHARGE_RATE" => SampleType::ElectricityChargeRate,
+            "SAMPLE_TYPE_PRICE" => SampleType::Price,
+            _ => SampleType::Other(name.to_string()),
+        }
+    }
+
+    /// Variant meant by a producer following schema_version, swapping `SAMPLE_TYPE_ENERGY` and
+    /// `SAMPLE_TYPE_GAS_CONSUMPTION` back for versions before CORRECTED_ENERGY_NAMES_SCHEMA_VERSION. Consumers that
+    /// must keep reading old data as written pass a version before it; deserializing always uses the current names.
+    pub fn from_wire_compat(name: &str, schema_version: u32) -> Self {
+        match (SampleType::from_name(name), schema_version) {
+            (SampleType::Energy, v) if v < CORRECTED_ENERGY_NAMES_SCHEMA_VERSION => {
+                SampleType::GasConsumption
+            }
+            (SampleType::GasConsumption, v) if v < CORRECTED_ENERGY_NAMES_SCHEMA_VERSION => {
+                SampleType::Energy
+            }
+            (sample_type, _) => sample_type,
+        }
+    }
+}
+
+impl PartialEq for SampleType {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl Eq for SampleType {}
+
+impl Hash for SampleType {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_str().hash(state);
+    }
+}
+
+impl Serialize for SampleType {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for SampleType {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let name = String::deserialize(deserializer)?;
+        Ok(SampleType::from_name(&name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::collections::HashSet;
+
+    #[test]
+    fn unknown_name_round_trips_as_other() {
+        // act
+        let sample_type =
+            serde_json::from_str::<SampleType>(r#""SAMPLE_TYPE_SOIL_MOISTURE""#).unwrap();
+
+        assert_eq!(
+            sample_type,
+            SampleType::Other("SAMPLE_TYPE_SOIL_MOISTURE".to_string())
+        );
+        assert!(matches!(sample_type, SampleType::Other(_)));
+        assert_eq!(
+            serde_json::to_string(&sample_type).unwrap(),
+            r#""SAMPLE_TYPE_SOIL_MOISTURE""#
+        );
+    }
+
+    #[test]
+    fn other_with_known_name_equals_named_variant() {
+        let other = SampleType::Other("SAMPLE_TYPE_TEMPERATURE".to_string());
+
+        // act
+        let unique = HashSet::from([other.clone(), SampleType::Temperature]);
+
+        assert_eq!(other, SampleType::Temperature);
+        assert_eq!(unique.len(), 1);
+    }
+
+    #[test]
+    fn known_names_deserialize_to_named_variants() {
+        for sample_type in [
+            SampleType::Invalid,
+            SampleType::ElectricityConsumption,
+            SampleType::ElectricityProduction,
+            SampleType::BatteryStateOfCharge,
+            SampleType::Energy,
+            SampleType::Flow,
+            SampleType::GasConsumption,
+            SampleType::HeatDemand,
+            SampleType::Humidity,
+            SampleType::Pressure,
+            SampleType::Temperature,
+            SampleType::TemperatureSetpoint,
+            SampleType::Time,
+            SampleType::ElectricityVoltage,
+            SampleType::ElectricityCurrent,
+            SampleType::WaterConsumption,
+            SampleType::DistanceTraveled,
+            SampleType::Availability,
+            SampleType::ElectricityChargeRate,
+            SampleType::Price,
+        ] {
+            let json = serde_json::to_string(&sample_type).unwrap();
+
+            // act
+            let deserialized = serde_json::from_str::<SampleType>(&json).unwrap();
+
+            assert!(!matches!(deserialized, SampleType::Other(_)));
+            assert_eq!(deserialized, sample_type);
+        }
+    }
+
+    #[test]
+    fn energy_and_gas_consumption_use_their_own_names() {
+        assert_eq!(
+            serde_json::to_string(&SampleType::Energy).unwrap(),
+            r#""SAMPLE_TYPE_ENERGY""#
+        );
+        assert_eq!(
+            serde_json::to_string(&SampleType::GasConsumption).unwrap(),
+            r#""SAMPLE_TYPE_GAS_CONSUMPTION""#
+        );
+        assert!(matches!(
+            serde_json::from_str::<SampleType>(r#""SAMPLE_TYPE_ENERGY""#).unwrap(),
+            SampleType::Energy
+        ));
+        assert!(matches!(
+            serde_json::from_str::<SampleType>(r#""SAMPLE_TYPE_GAS_CONSUMPTION""#).unwrap(),
+            SampleType::GasConsumption
+        ));
+    }
+
+    #[test]
+    fn from_wire_compat_swaps_energy_names_before_correction() {
+        assert_eq!(
+            SampleType::from_wire_compat("SAMPLE_TYPE_GAS_CONSUMPTION", 1),
+            SampleType::Energy
+        );
+        assert_eq!(
+            SampleType::from_wire_compat("SAMPLE_TYPE_ENERGY", 1),
+            SampleType::GasConsumption
+        );
+        assert_eq!(
+            SampleType::from_wire_compat("SAMPLE_TYPE_TEMPERATURE", 1),
+            SampleType::Temperature
+        );
+    }
+
+    #[test]
+    fn from_wire_compat_keeps_energy_names_after_correction() {
+        assert_eq!(
+            SampleType::from_wire_compat(
+                "SAMPLE_TYPE_GAS_CONSUMPTION",
+                CORRECTED_ENERGY_NAMES_SCHEMA_VERSION
+            ),
+            SampleType::GasConsumption
+        );
+        assert_eq!(
+            SampleType::from_wire_compat(
+                "SAMPLE_TYPE_ENERGY",
+                CORRECTED_ENERGY_NAMES_SCHEMA_VERSION
+            ),
+            SampleType::Energy
+        );
+    }
 }
@@ -0,0 +1,239 @@
+use crate::model::{MetricType, SampleType};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fmt;
+
+pub const JOULES_PER_WATT_HOUR: f64 = 3600.0;
+pub const JOULES_PER_KILOWATT_HOUR: f64 = 3_600_000.0;
+
+pub fn joules_to_kwh(joules: f64) -> f64 {
+    joules / JOULES_PER_KILOWATT_HOUR
+}
+
+pub fn kwh_to_joules(kwh: f64) -> f64 {
+    kwh * JOULES_PER_KILOWATT_HOUR
+}
+
+pub fn wh_to_joules(wh: f64) -> f64 {
+    wh * JOULES_PER_WATT_HOUR
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum SampleUnit {
+    Joule,
+    WattHour,
+    KilowattHour,
+    Watt,
+    Kilowatt,
+    Celsius,
+    Kelvin,
+    Fahrenheit,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+enum Dimension {
+    Energy,
+    Power,
+    Temperature,
+}
+
+impl SampleUnit {
+    /// Unit exporters normalize samples of sample_type to before publishing, if it has one.
+    pub fn canonical_for(sample_type: &SampleType) -> Option<SampleUnit> {
+        match SampleType::from_name(sample_type.as_str()) {
+            SampleType::ElectricityConsumption
+            | SampleType::ElectricityProduction
+            | SampleType::Energy => Some(SampleUnit::Joule),
+            SampleType::ElectricityChargeRate => Some(SampleUnit::Watt),
+            SampleType::Temperature | SampleType::TemperatureSetpoint => Some(SampleUnit::Celsius),
+            _ => None,
+        }
+    }
+
+    /// Unit of energy and power samples of sample_type that don't declare one; electricity consumption and
+    /// production gauges measure power rather than energy.
+    pub fn default_for(sample_type: &SampleType, metric_type: &MetricType) -> Option<SampleUnit> {
+        match (SampleType::from_name(sample_type.as_str()), metric_type) {
+            (
+                SampleType::ElectricityConsumption | SampleType::ElectricityProduction,
+                MetricType::Gauge,
+            ) => Some(SampleUnit::Watt),
+            _ => SampleUnit::canonical_for(sample_type)
+                .filter(|unit| unit.dimension() != Dimension::Temperature),
+        }
+    }
+
+    pub fn is_energy(&self) -> bool {
+        self.dimension() == Dimension::Energy
+    }
+
+    fn dimension(&self) -> Dimension {
+        match self {
+            SampleUnit::Joule | SampleUnit::WattHour | SampleUnit::KilowattHour => {
+                Dimension::Energy
+            }
+            SampleUnit::Watt | SampleUnit::Kilowatt => Dimension::Power,
+            SampleUnit::Celsius | SampleUnit::Kelvin | SampleUnit::Fahrenheit => {
+                Dimension::Temperature
+            }
+        }
+    }
+
+    /// Value in the base unit of the dimension: joule, watt or degrees Celsius.
+    fn in_base_unit(self, value: f64) -> f64 {
+        match self {
+            SampleUnit::Joule | SampleUnit::Watt | SampleUnit::Celsius => value,
+            SampleUnit::WattHour => wh_to_joules(value),
+            SampleUnit::KilowattHour => kwh_to_joules(value),
+            SampleUnit::Kilowatt => value * 1000.0,
+            SampleUnit::Kelvin => value - 273.15,
+            SampleUnit::Fahrenheit => (value - 32.0) * 5.0 / 9.0,
+        }
+    }
+
+    fn out_of_base_unit(self, value: f64) -> f64 {
+        match self {
+            SampleUnit::Joule | SampleUnit::Watt | SampleUnit::Celsius => value,
+            SampleUnit::WattHour => value / JOULES_PER_WATT_HOUR,
+            SampleUnit::KilowattHour => joules_to_kwh(value),
+            SampleUnit::Kilowatt => value / 1000.0,
+            SampleUnit::Kelvin => value + 273.15,
+            SampleUnit::Fahrenheit => value * 9.0 / 5.0 + 32.0,
+        }
+    }
+
+    /// Converts value in this unit to unit.
+    pub fn convert(self, value: f64, unit: SampleUnit) -> Result<f64, UnitConversionError> {
+        if self.dimension() != unit.dimension() {
+            return Err(UnitConversionError::IncompatibleUnits {
+                from: self,
+                to: unit,
+            });
+        }
+        if self == unit {
+            return Ok(value);
+        }
+
+        Ok(unit.out_of_base_unit(self.in_base_unit(value)))
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum UnitConversionError {
+    /// A sample without a unit to convert from.
+    MissingUnit,
+    /// Units measuring different quantities, like energy and power.
+    IncompatibleUnits { from: SampleUnit, to: SampleUnit },
+}
+
+impl fmt::Display for UnitConversionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UnitConversionError::MissingUnit => write!(f, "Sample has no unit to convert from"),
+            UnitConversionError::IncompatibleUnits { from, to } => {
+                write!(f, "Cannot convert {:?} to {:?}", from, to)
+            }
+        }
+    }
+}
+
+impl Error for UnitConversionError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn energy_constants_and_helpers_are_pinned() {
+        assert_eq!(JOULES_PER_WATT_HOUR, 3600.0);
+        assert_eq!(JOULES_PER_KILOWATT_HOUR, 3_600_000.0);
+        assert_eq!(joules_to_kwh(9_000_000.0), 2.5);
+        assert_eq!(kwh_to_joules(2.5), 9_000_000.0);
+        assert_eq!(wh_to_joules(2.5), 9000.0);
+    }
+
+    #[test]
+    fn conversions_round_trip_between_units_of_same_dimension() {
+        let units = [
+            SampleUnit::Joule,
+            SampleUnit::WattHour,
+            SampleUnit::KilowattHour,
+            SampleUnit::Watt,
+            SampleUnit::Kilowatt,
+            SampleUnit::Celsius,
+            SampleUnit::Kelvin,
+            SampleUnit::Fahrenheit,
+        ];
+        let values = [
+            -1e9, -273.15, -1.0, -1e-6, 0.0, 1e-6, 0.1, 1.0, 21.5, 3600.0, 1e12,
+        ];
+        for from in units {
+            for to in units.iter().filter(|to| to.dimension() == from.dimension()) {
+                for value in values {
+                    // act
+                    let round_tripped = from
+                        .convert(value, *to)
+                        .and_then(|converted| to.convert(converted, from))
+                        .unwrap();
+
+                    assert!(
+                        (round_tripped - value).abs() <= 1e-9 * value.abs().max(1.0),
+                        "{} {:?} via {:?} round-tripped to {}",
+                        value,
+                        from,
+                        to,
+                        round_tripped
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn joules_round_trip_through_kwh() {
+        for joules in [0.0, 1.0, 3_600_000.0, 9695872800.0, 1e15] {
+            // act
+            let round_tripped = kwh_to_joules(joules_to_kwh(joules));
+
+            assert!((round_tripped - joules).abs() <= 1e-9 * joules.max(1.0));
+        }
+    }
+
+    #[test]
+    fn default_for_covers_energy_and_power_but_not_temperature() {
+        assert_eq!(
+            SampleUnit::default_for(&SampleType::ElectricityConsumption, &MetricType::Counter),
+            Some(SampleUnit::Joule)
+        );
+        assert_eq!(
+            SampleUnit::default_for(&SampleType::ElectricityChargeRate, &MetricType::Gauge),
+            Some(SampleUnit::Watt)
+        );
+        assert_eq!(
+            SampleUnit::default_for(&SampleType::Temperature, &MetricType::Gauge),
+            None
+        );
+        assert_eq!(
+            SampleUnit::default_for(&SampleType::Humidity, &MetricType::Gauge),
+            None
+        );
+    }
+
+    #[test]
+    fn default_for_treats_electricity_gauges_as_power() {
+        assert_eq!(
+            SampleUnit::default_for(&SampleType::ElectricityConsumption, &MetricType::Gauge),
+            Some(SampleUnit::Watt)
+        );
+        assert_eq!(
+            SampleUnit::default_for(&SampleType::ElectricityProduction, &MetricType::Gauge),
+            Some(SampleUnit::Watt)
+        );
+        assert_eq!(
+            SampleUnit::default_for(&SampleType::ElectricityProduction, &MetricType::Counter),
+            Some(SampleUnit::Joule)
+        );
+    }
+}
@@ -1,5 +1,17 @@
-use chrono::{DateTime, Utc};
-use serde::{Deserialize, Serialize};
+use crate::model::measurement::new_uuid;
+use crate::model::{EntityType, Measurement, MetricType, Sample, SampleType};
+use chrono::{DateTime, Duration, DurationRound, NaiveDate, NaiveTime, SecondsFormat, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use tracing::warn;
+
+pub mod entsoe;
+#[cfg(test)]
+mod negative_price_tests;
+pub mod providers;
 
 #[derive(Serialize, Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
@@ -19,17 +31,80 @@ pub struct SpotPriceRequestVariables {
 #[derive(Serialize, Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct SpotPriceResponse {
+    /// Empty when the feed returned null data.
+    #[serde(default, deserialize_with = "deserialize_null_as_default")]
     pub data: SpotPriceData,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub errors: Option<Vec<GraphQlError>>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Default)]
 #[serde(rename_all = "camelCase")]
 pub struct SpotPriceData {
+    #[serde(default, deserialize_with = "deserialize_null_as_default")]
     pub market_prices_electricity: Vec<SpotPrice>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 #[serde(rename_all = "camelCase")]
+pub struct GraphQlError {
+    pub message: String,
+    /// Field names and list indices leading to the field that failed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<Vec<serde_json::Value>>,
+}
+
+impl SpotPriceResponse {
+    /// The spot prices, or an error with the messages of the feed when it returned errors and no spot prices.
+    pub fn into_result(self) -> Result<Vec<SpotPrice>, SpotPriceFeedError> {
+        let messages: Vec<String> = self
+            .errors
+            .unwrap_or_default()
+            .into_iter()
+            .map(|error| error.message)
+            .collect();
+        let spot_prices = self.data.market_prices_electricity;
+
+        if !messages.is_empty() {
+            if spot_prices.is_empty() {
+                return Err(SpotPriceFeedError { messages });
+            }
+            warn!(
+                "Spot price feed returned {} spot prices with errors: {}",
+                spot_prices.len(),
+                messages.join("; ")
+            );
+        }
+
+        Ok(spot_prices)
+    }
+}
+
+fn deserialize_null_as_default<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Default + Deserialize<'de>,
+{
+    Ok(Option::<T>::deserialize(deserializer)?.unwrap_or_default())
+}
+
+/// Errors the spot price feed returned instead of spot prices.
+#[derive(Debug, PartialEq)]
+pub struct SpotPriceFeedError {
+    pub messages: Vec<String>,
+}
+
+impl fmt::Display for SpotPriceFeedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Spot price feed failed: {}", self.messages.join("; "))
+    }
+}
+
+impl Error for SpotPriceFeedError {}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+#[non_exhaustive]
 pub struct SpotPrice {
     pub id: Option<String>,
     pub source: Option<String>,
@@ -39,28 +114,934 @@ pub struct SpotPrice {
     pub market_price_tax: f64,
     pub sourcing_markup_price: f64,
     pub energy_tax_price: f64,
+    /// Provider specific fees by name, on top of the fixed components.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extra_components: Option<HashMap<String, f64>>,
+    /// Currency code of the prices, like EUR or CZK; unknown when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub currency: Option<String>,
+    /// Amount of energy the prices are for; per kWh when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unit: Option<PriceUnit>,
+    /// Length of the slots merge_adjacent combined into this spot price; the planner splits it back into those.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub merged_slot_seconds: Option<i64>,
+}
+
+/// Builds a spot price, see SpotPrice::new.
+#[derive(Debug, Clone)]
+#[must_use]
+pub struct SpotPriceBuilder {
+    spot_price: SpotPrice,
+    has_id: bool,
+}
+
+impl SpotPriceBuilder {
+    /// Sets the id, which is a new UUID unless set.
+    pub fn id(mut self, id: Option<String>) -> Self {
+        self.spot_price.id = id;
+        self.has_id = true;
+        self
+    }
+
+    pub fn source(mut self, source: impl Into<String>) -> Self {
+        self.spot_price.source = Some(source.into());
+        self
+    }
+
+    pub fn market_price_tax(mut self, market_price_tax: f64) -> Self {
+        self.spot_price.market_price_tax = market_price_tax;
+        self
+    }
+
+    pub fn sourcing_markup_price(mut self, sourcing_markup_price: f64) -> Self {
+        self.spot_price.sourcing_markup_price = sourcing_markup_price;
+        self
+    }
+
+    pub fn energy_tax_price(mut self, energy_tax_price: f64) -> Self {
+        self.spot_price.energy_tax_price = energy_tax_price;
+        self
+    }
+
+    pub fn extra_component(mut self, name: impl Into<String>, price: f64) -> Self {
+        self.spot_price
+            .extra_components
+            .get_or_insert_with(HashMap::new)
+            .insert(name.into(), price);
+        self
+    }
+
+    pub fn currency(mut self, currency: impl Into<String>) -> Self {
+        self.spot_price.currency = Some(currency.into());
+        self
+    }
+
+    pub fn unit(mut self, unit: PriceUnit) -> Self {
+        self.spot_price.unit = Some(unit);
+        self
+    }
+
+    /// The spot price, if it passes validate.
+    pub fn build(self) -> Result<SpotPrice, SpotPriceError> {
+        let mut spot_price = self.spot_price;
+        if !self.has_id {
+            spot_price.id = Some(new_uuid());
+        }
+        spot_price.validate()?;
+        Ok(spot_price)
+    }
+}
+
+/// Amount of energy a price is for, in the currency of the spot price.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum PriceUnit {
+    #[default]
+    EurPerKwh,
+    EurPerMwh,
+}
+
+impl PriceUnit {
+    /// Factor to multiply prices in this unit with to get prices per kWh.
+    pub fn per_kwh_factor(&self) -> f64 {
+        match self {
+            PriceUnit::EurPerKwh => 1.0,
+            PriceUnit::EurPerMwh => 0.001,
+        }
+    }
+}
+
+/// Which parts of a spot price count towards its price; all of them by default.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PriceComponents {
+    pub market_price: bool,
+    pub market_price_tax: bool,
+    pub sourcing_markup_price: bool,
+    pub energy_tax_price: bool,
+    /// Names of the extra components to leave out; all extra components count otherwise.
+    pub excluded_extra_components: Vec<String>,
+}
+
+impl Default for PriceComponents {
+    fn default() -> Self {
+        Self {
+            market_price: true,
+            market_price_tax: true,
+            sourcing_markup_price: true,
+            energy_tax_price: true,
+            excluded_extra_components: vec![],
+        }
+    }
 }
 
 impl SpotPrice {
+    /// Builder for a spot price from from till till with market_price and no taxes or fees.
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new(from: DateTime<Utc>, till: DateTime<Utc>, market_price: f64) -> SpotPriceBuilder {
+        SpotPriceBuilder {
+            spot_price: SpotPrice {
+                id: None,
+                source: None,
+                from,
+                till,
+                market_price,
+                market_price_tax: 0.0,
+                sourcing_markup_price: 0.0,
+                energy_tax_price: 0.0,
+                extra_components: None,
+                currency: None,
+                unit: None,
+                merged_slot_seconds: None,
+            },
+            has_id: false,
+        }
+    }
+
+    /// Sum of all price components; negative when a negative market price outweighs the taxes and fees.
     pub fn total_price(&self) -> f64 {
         self.market_price
             + self.market_price_tax
             + self.sourcing_markup_price
             + self.energy_tax_price
+            + self.extra_components_price(|_| true)
+    }
+
+    /// Sum of the price components selected in components.
+    pub fn price_for(&self, components: &PriceComponents) -> f64 {
+        let mut price = 0.0;
+        if components.market_price {
+            price += self.market_price;
+        }
+        if components.market_price_tax {
+            price += self.market_price_tax;
+        }
+        if components.sourcing_markup_price {
+            price += self.sourcing_markup_price;
+        }
+        if components.energy_tax_price {
+            price += self.energy_tax_price;
+        }
+        price
+            + self.extra_components_price(|name| {
+                !components
+                    .excluded_extra_components
+                    .iter()
+                    .any(|excluded| excluded == name)
+            })
+    }
+
+    /// Sum of the extra components whose name is_included accepts.
+    fn extra_components_price(&self, is_included: impl Fn(&str) -> bool) -> f64 {
+        self.extra_components
+            .as_ref()
+            .map_or(0.0, |extra_components| {
+                extra_components
+                    .iter()
+                    .filter(|(name, _)| is_included(name))
+                    .map(|(_, price)| price)
+                    .sum()
+            })
+    }
+
+    /// Copy with all prices per kWh in currency, where rate converts one unit of this spot price's currency into
+    /// currency.
+    pub fn normalize_to(&self, currency: &str, rate: f64) -> SpotPrice {
+        let factor = self.unit.unwrap_or_default().per_kwh_factor() * rate;
+        SpotPrice {
+            market_price: self.market_price * factor,
+            market_price_tax: self.market_price_tax * factor,
+            sourcing_markup_price: self.sourcing_markup_price * factor,
+            energy_tax_price: self.energy_tax_price * factor,
+            extra_components: self.extra_components.as_ref().map(|extra_components| {
+                extra_components
+                    .iter()
+                    .map(|(name, price)| (name.clone(), price * factor))
+                    .collect()
+            }),
+            currency: Some(currency.to_string()),
+            unit: Some(PriceUnit::EurPerKwh),
+            ..self.clone()
+        }
+    }
+
+    /// Converts spot prices to per kWh in currency. Spot prices in another currency need a rate in rates converting
+    /// one unit of their currency into currency; spot prices without a currency are taken to be in currency.
+    pub fn normalize_prices(
+        spot_prices: &[SpotPrice],
+        currency: &str,
+        rates: &HashMap<String, f64>,
+    ) -> Result<Vec<SpotPrice>, SpotPriceError> {
+        spot_prices
+            .iter()
+            .map(|spot_price| match spot_price.currency.as_deref() {
+                None => Ok(spot_price.normalize_to(currency, 1.0)),
+                Some(spot_price_currency) if spot_price_currency == currency => {
+                    Ok(spot_price.normalize_to(currency, 1.0))
+                }
+                Some(spot_price_currency) => match rates.get(spot_price_currency) {
+                    Some(rate) => Ok(spot_price.normalize_to(currency, *rate)),
+                    None => Err(SpotPriceError::MixedCurrencies {
+                        currency: currency.to_string(),
+                        other_currency: spot_price_currency.to_string(),
+                    }),
+                },
+            })
+            .collect()
     }
 
+    /// Like with_vat_options, without VAT on negative prices as in the Netherlands.
+    pub fn with_vat(&self, vat_rate: f64, components: &PriceComponents) -> SpotPrice {
+        self.with_vat_options(vat_rate, components, false)
+    }
+
+    /// Copy with market_price_tax recomputed as vat_rate over the components selected in components; the selection of
+    /// market_price_tax itself is ignored. Negative components add no VAT unless vat_on_negative_prices is set.
+    pub fn with_vat_options(
+        &self,
+        vat_rate: f64,
+        components: &PriceComponents,
+        vat_on_negative_prices: bool,
+    ) -> SpotPrice {
+        let taxable = |price: f64| {
+            if vat_on_negative_prices {
+                price
+            } else {
+                price.max(0.0)
+            }
+        };
+
+        let mut taxable_price = 0.0;
+        if components.market_price {
+            taxable_price += taxable(self.market_price);
+        }
+        if components.sourcing_markup_price {
+            taxable_price += taxable(self.sourcing_markup_price);
+        }
+        if components.energy_tax_price {
+            taxable_price += taxable(self.energy_tax_price);
+        }
+        if let Some(extra_components) = &self.extra_components {
+            taxable_price += extra_components
+                .iter()
+                .filter(|(name, _)| {
+                    !components
+                        .excluded_extra_components
+                        .iter()
+                        .any(|excluded| excluded == *name)
+                })
+                .map(|(_, price)| taxable(*price))
+                .sum::<f64>();
+        }
+
+        SpotPrice {
+            market_price_tax: taxable_price * vat_rate,
+            ..self.clone()
+        }
+    }
+
+    /// Total price with VAT at vat_rate over all other components.
+    pub fn total_price_incl_vat(&self, vat_rate: f64) -> f64 {
+        self.with_vat(vat_rate, &PriceComponents::default())
+            .total_price()
+    }
+
+    /// Recomputes market_price_tax of all spot prices as vat_rate over all other components.
+    pub fn recalculate_tax(prices: &mut [SpotPrice], vat_rate: f64) {
+        let components = PriceComponents::default();
+        for price in prices.iter_mut() {
+            *price = price.with_vat(vat_rate, &components);
+        }
+    }
+
+    /// Checks the spot price ends after it starts and all its prices are finite.
+    pub fn validate(&self) -> Result<(), SpotPriceError> {
+        if self.till <= self.from {
+            return Err(SpotPriceError::InvalidInterval {
+                from: self.from,
+                till: self.till,
+            });
+        }
+
+        let is_finite = [
+            self.market_price,
+            self.market_price_tax,
+            self.sourcing_markup_price,
+            self.energy_tax_price,
+        ]
+        .iter()
+        .chain(
+            self.extra_components
+                .iter()
+                .flat_map(|extra| extra.values()),
+        )
+        .all(|price| price.is_finite());
+        if !is_finite {
+            return Err(SpotPriceError::NonFinitePrice { from: self.from });
+        }
+
+        Ok(())
+    }
+
+    /// Whether t falls within the spot price, including from but excluding till.
+    pub fn contains(&self, t: DateTime<Utc>) -> bool {
+        self.from <= t && t < self.till
+    }
+
+    /// Whether both spot prices cover part of the same period; touching boundaries don't overlap.
+    pub fn overlaps(&self, other: &SpotPrice) -> bool {
+        self.from < other.till && other.from < self.till
+    }
+
+    /// Whether one spot price starts exactly where the other ends.
+    pub fn is_adjacent_to(&self, other: &SpotPrice) -> bool {
+        self.till == other.from || other.till == self.from
+    }
+
+    /// Copy narrowed to the part from after until before, None when nothing of it remains.
+    pub fn clip(&self, after: DateTime<Utc>, before: DateTime<Utc>) -> Option<SpotPrice> {
+        let from = self.from.max(after);
+        let till = self.till.min(before);
+        if from >= till {
+            return None;
+        }
+
+        Some(SpotPrice {
+            from,
+            till,
+            ..self.clone()
+        })
+    }
+
+    /// Combines adjacent spot prices whose prices all differ by less than epsilon into a single spot price spanning
+    /// both, with the prices, id and source of the first; prices are expected sorted, like normalize returns them.
+    /// The length of the combined slots is kept in merged_slot_seconds, see unmerge.
+    pub fn merge_adjacent(prices: Vec<SpotPrice>, epsilon: f64) -> Vec<SpotPrice> {
+        let mut merged: Vec<SpotPrice> = Vec::with_capacity(prices.len());
+        for spot_price in prices {
+            match merged.last_mut() {
+                Some(last)
+                    if last.till == spot_price.from
+                        && last.has_same_prices(&spot_price, epsilon) =>
+                {
+                    last.merged_slot_seconds =
+                        Some(gcd(last.slot_seconds(), spot_price.slot_seconds()));
+                    last.till = spot_price.till;
+                }
+                _ => merged.push(spot_price),
+            }
+        }
+        merged
+    }
+
+    /// Splits spot prices combined by merge_adjacent back into slots of their original length, so the planner has the
+    /// same start times to choose from as for the prices before merging; only the first slot keeps the id.
+    pub fn unmerge(prices: Vec<SpotPrice>) -> Vec<SpotPrice> {
+        let mut unmerged: Vec<SpotPrice> = Vec::with_capacity(prices.len());
+        for spot_price in prices {
+            let slot_seconds = match spot_price.merged_slot_seconds {
+                Some(slot_seconds) if slot_seconds > 0 => slot_seconds,
+                _ => {
+                    unmerged.push(SpotPrice {
+                        merged_slot_seconds: None,
+                        ..spot_price
+                    });
+                    continue;
+                }
+            };
+            let mut from = spot_price.from;
+            while from < spot_price.till {
+                let till = (from + Duration::seconds(slot_seconds)).min(spot_price.till);
+                unmerged.push(SpotPrice {
+                    id: if from == spot_price.from {
+                        spot_price.id.clone()
+                    } else {
+                        None
+                    },
+                    from,
+                    till,
+                    merged_slot_seconds: None,
+                    ..spot_price.clone()
+                });
+                from = till;
+            }
+        }
+        unmerged
+    }
+
+    /// Length of the slots the spot price consists of, see merge_adjacent.
+    fn slot_seconds(&self) -> i64 {
+        self.merged_slot_seconds
+            .unwrap_or_else(|| self.duration_seconds())
+    }
+
+    /// Whether all prices of both spot prices differ by less than epsilon, in the same currency and unit.
+    fn has_same_prices(&self, other: &SpotPrice, epsilon: f64) -> bool {
+        let is_close = |a: f64, b: f64| (a - b).abs() < epsilon;
+        let has_same_extra_components = match (&self.extra_components, &other.extra_components) {
+            (None, None) => true,
+            (Some(extra_components), Some(other_extra_components)) => {
+                extra_components.len() == other_extra_components.len()
+                    && extra_components.iter().all(|(name, price)| {
+                        other_extra_components
+                            .get(name)
+                            .is_some_and(|other_price| is_close(*price, *other_price))
+                    })
+            }
+            _ => false,
+        };
+
+        is_close(self.market_price, other.market_price)
+            && is_close(self.market_price_tax, other.market_price_tax)
+            && is_close(self.sourcing_markup_price, other.sourcing_markup_price)
+            && is_close(self.energy_tax_price, other.energy_tax_price)
+            && has_same_extra_components
+            && self.currency == other.currency
+            && self.unit.unwrap_or_default() == other.unit.unwrap_or_default()
+    }
+
+    /// The parts before and after t, both without id to not duplicate it; when t lies outside the spot price one of
+    /// them is None and the other covers all of it.
+    pub fn split_at(&self, t: DateTime<Utc>) -> (Option<SpotPrice>, Option<SpotPrice>) {
+        let without_id = SpotPrice {
+            id: None,
+            ..self.clone()
+        };
+        (without_id.clip(self.from, t), without_id.clip(t, self.till))
+    }
+
+    /// The parts of the spot price within each local day in time_zone, without id.
+    pub fn split_at_local_midnights(&self, time_zone: Tz) -> Vec<SpotPrice> {
+        let mut parts = vec![];
+        let mut rest = Some(SpotPrice {
+            id: None,
+            ..self.clone()
+        });
+        while let Some(spot_price) = rest {
+            let next_midnight = spot_price
+                .from
+                .with_timezone(&time_zone)
+                .date_naive()
+                .succ_opt()
+                .and_then(|next_date| local_midnight(next_date, &time_zone));
+            match next_midnight {
+                Some(next_midnight) if next_midnight < spot_price.till => {
+                    let (before, after) = spot_price.split_at(next_midnight);
+                    parts.extend(before);
+                    rest = after;
+                }
+                _ => {
+                    parts.push(spot_price);
+                    rest = None;
+                }
+            }
+        }
+        parts
+    }
+
+    /// Currency and energy unit the prices are in, like €/kWh.
+    fn price_unit_label(&self) -> String {
+        let currency = match self.currency.as_deref() {
+            None | Some("EUR") => "€",
+            Some(currency) => currency,
+        };
+        let energy_unit = match self.unit.unwrap_or_default() {
+            PriceUnit::EurPerKwh => "kWh",
+            PriceUnit::EurPerMwh => "MWh",
+        };
+        format!("{}/{}", currency, energy_unit)
+    }
+
+    /// Tariff sample with the total price, named after the start of the spot price.
+    pub fn to_sample(&self, metric_type: MetricType) -> Sample {
+        Sample {
+            entity_type: EntityType::Tariff,
+            entity_name: self
+                .source
+                .clone()
+                .unwrap_or_else(|| "spot price".to_string()),
+            sample_type: SampleType::Price,
+            sample_name: self.from.to_rfc3339_opts(SecondsFormat::Secs, true),
+            metric_type,
+            value: self.total_price(),
+            unit: None,
+        }
+    }
+
+    pub fn is_negative_market_price(&self) -> bool {
+        self.market_price < 0.0
+    }
+
+    /// Duration rounded to the nearest second.
     pub fn duration_seconds(&self) -> i64 {
-        (self.till - self.from).num_seconds()
+        ((self.till - self.from).num_milliseconds() as f64 / 1000.0).round() as i64
+    }
+
+    /// Like normalize_with_tolerance, with a tolerance of DEFAULT_BOUNDARY_TOLERANCE_SECONDS.
+    pub fn normalize(spot_prices: Vec<SpotPrice>) -> Result<Vec<SpotPrice>, SpotPriceError> {
+        Self::normalize_with_tolerance(
+            spot_prices,
+            Duration::seconds(DEFAULT_BOUNDARY_TOLERANCE_SECONDS),
+        )
+    }
+
+    /// Rounds boundaries to the nearest second, sorts spot prices by from and drops exact duplicates. A spot price
+    /// starting within tolerance of the end of the previous one is moved to start right at that end; larger
+    /// overlaps are errors, as are empty intervals, while larger gaps are kept.
+    pub fn normalize_with_tolerance(
+        spot_prices: Vec<SpotPrice>,
+        tolerance: Duration,
+    ) -> Result<Vec<SpotPrice>, SpotPriceError> {
+        let mut spot_prices: Vec<SpotPrice> = spot_prices
+            .into_iter()
+            .map(|spot_price| SpotPrice {
+                from: round_to_second(spot_price.from),
+                till: round_to_second(spot_price.till),
+                ..spot_price
+            })
+            .collect();
+
+        spot_prices.sort_by_key(|spot_price| (spot_price.from, spot_price.till));
+        spot_prices.dedup();
+
+        for i in 1..spot_prices.len() {
+            let previous_till = spot_prices[i - 1].till;
+            if (spot_prices[i].from - previous_till).abs() <= tolerance {
+                spot_prices[i].from = previous_till;
+            }
+        }
+
+        for spot_price in &spot_prices {
+            spot_price.validate()?;
+        }
+
+        if let Some(pair) = spot_prices
+            .windows(2)
+            .find(|pair| pair[1].from < pair[0].till)
+        {
+            return Err(SpotPriceError::OverlappingIntervals {
+                first_from: pair[0].from,
+                second_from: pair[1].from,
+            });
+        }
+
+        Ok(spot_prices)
+    }
+}
+
+impl fmt::Display for SpotPrice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}–{} UTC  market {:.4}  total {:.4} {}",
+            self.from.format("%Y-%m-%d %H:%M"),
+            self.till.format("%H:%M"),
+            self.market_price,
+            self.total_price(),
+            self.price_unit_label()
+        )
+    }
+}
+
+/// Aligned table of prices in local time in time_zone, one spot price per line, ending with the minimum, maximum and
+/// average over the spot prices.
+pub fn format_price_table(prices: &[SpotPrice], time_zone: &Tz) -> String {
+    let mut table = format!(
+        "{:<16}  {:<5}  {:>8}  {:>8}\n",
+        "from", "till", "market", "total"
+    );
+    for price in prices {
+        table += &format!(
+            "{:<16}  {:<5}  {:>8.4}  {:>8.4}\n",
+            price.from.with_timezone(time_zone).format("%Y-%m-%d %H:%M"),
+            price.till.with_timezone(time_zone).format("%H:%M"),
+            price.market_price,
+            price.total_price()
+        );
+    }
+
+    if !prices.is_empty() {
+        let market_prices: Vec<f64> = prices.iter().map(|price| price.market_price).collect();
+        let total_prices: Vec<f64> = prices.iter().map(|price| price.total_price()).collect();
+        let summary = |label: &str, summarize: fn(&[f64]) -> f64| {
+            format!(
+                "{:<16}  {:<5}  {:>8.4}  {:>8.4}\n",
+                label,
+                "",
+                summarize(&market_prices),
+                summarize(&total_prices)
+            )
+        };
+        table += &summary("min", |values| {
+            values.iter().copied().fold(f64::INFINITY, f64::min)
+        });
+        table += &summary("max", |values| {
+            values.iter().copied().fold(f64::NEG_INFINITY, f64::max)
+        });
+        table += &summary("avg", |values| {
+            values.iter().sum::<f64>() / values.len() as f64
+        });
+    }
+
+    table
+}
+
+/// Measurement with the price of the spot price containing now as sample named current price, followed by a sample
+/// for each spot price starting after now.
+pub fn spot_prices_to_measurement(
+    prices: &[SpotPrice],
+    source: &str,
+    location: &str,
+    now: DateTime<Utc>,
+) -> Measurement {
+    let current_sample = prices
+        .iter()
+        .find(|price| price.contains(now))
+        .map(|price| Sample {
+            sample_name: "current price".to_string(),
+            ..price.to_sample(MetricType::Gauge)
+        });
+    let upcoming_samples = prices
+        .iter()
+        .filter(|price| price.from > now)
+        .map(|price| price.to_sample(MetricType::Gauge));
+
+    Measurement {
+        id: new_uuid(),
+        source: source.to_string(),
+        location: location.to_string(),
+        samples: current_sample.into_iter().chain(upcoming_samples).collect(),
+        measured_at_time: now,
+        schema_version: Measurement::CURRENT_SCHEMA_VERSION,
+        labels: None,
     }
 }
 
+/// Statistics over the per kWh prices of spot prices, each spot price counting once; all zero without spot prices.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct SpotPriceStats {
+    pub count: usize,
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+    pub median: f64,
+    pub standard_deviation: f64,
+    /// Start of the first spot price with the minimum price.
+    pub min_from: Option<DateTime<Utc>>,
+    /// Start of the first spot price with the maximum price.
+    pub max_from: Option<DateTime<Utc>>,
+}
+
+/// Statistics over the per kWh prices of the components selected in components.
+pub fn stats(prices: &[SpotPrice], components: &PriceComponents) -> SpotPriceStats {
+    if prices.is_empty() {
+        return SpotPriceStats::default();
+    }
+
+    let values: Vec<f64> = prices
+        .iter()
+        .map(|price| price.price_for(components) * price.unit.unwrap_or_default().per_kwh_factor())
+        .collect();
+    let count = values.len();
+
+    let mut min_index = 0;
+    let mut max_index = 0;
+    for (i, value) in values.iter().enumerate() {
+        if *value < values[min_index] {
+            min_index = i;
+        }
+        if *value > values[max_index] {
+            max_index = i;
+        }
+    }
+
+    let mean = values.iter().sum::<f64>() / count as f64;
+    let variance = values
+        .iter()
+        .map(|value| (value - mean).powi(2))
+        .sum::<f64>()
+        / count as f64;
+
+    let mut sorted_values = values.clone();
+    sorted_values.sort_by(f64::total_cmp);
+    let median = if count % 2 == 1 {
+        sorted_values[count / 2]
+    } else {
+        (sorted_values[count / 2 - 1] + sorted_values[count / 2]) / 2.0
+    };
+
+    SpotPriceStats {
+        count,
+        min: values[min_index],
+        max: values[max_index],
+        mean,
+        median,
+        standard_deviation: variance.sqrt(),
+        min_from: Some(prices[min_index].from),
+        max_from: Some(prices[max_index].from),
+    }
+}
+
+/// Statistics over the parts of prices within date in time_zone.
+pub fn stats_for_local_day(
+    prices: &[SpotPrice],
+    date: NaiveDate,
+    time_zone: Tz,
+    components: &PriceComponents,
+) -> SpotPriceStats {
+    let day = local_midnight(date, &time_zone).zip(
+        date.succ_opt()
+            .and_then(|next_date| local_midnight(next_date, &time_zone)),
+    );
+    let (day_from, day_till) = match day {
+        Some(day) => day,
+        None => return SpotPriceStats::default(),
+    };
+
+    let day_prices: Vec<SpotPrice> = prices
+        .iter()
+        .filter_map(|price| price.clip(day_from, day_till))
+        .collect();
+    stats(&day_prices, components)
+}
+
+/// Spot prices sorted by from without overlaps, to look up the spot price at a moment by binary search.
+#[derive(Debug, Clone, Copy)]
+pub struct SpotPriceIndex<'a> {
+    prices: &'a [SpotPrice],
+}
+
+impl<'a> SpotPriceIndex<'a> {
+    /// Index over prices, which have to be valid, sorted and not overlap, like normalize returns them.
+    pub fn new(prices: &'a [SpotPrice]) -> Result<Self, SpotPriceError> {
+        for price in prices {
+            price.validate()?;
+        }
+        if let Some(pair) = prices.windows(2).find(|pair| pair[1].from < pair[0].till) {
+            return Err(if pair[1].from < pair[0].from {
+                SpotPriceError::UnsortedIntervals {
+                    first_from: pair[0].from,
+                    second_from: pair[1].from,
+                }
+            } else {
+                SpotPriceError::OverlappingIntervals {
+                    first_from: pair[0].from,
+                    second_from: pair[1].from,
+                }
+            });
+        }
+
+        Ok(Self { prices })
+    }
+
+    /// Index over prices the caller already knows to be sorted and not overlapping.
+    pub(crate) fn from_sorted(prices: &'a [SpotPrice]) -> Self {
+        Self { prices }
+    }
+
+    /// Position of the spot price containing t.
+    pub fn position_at(&self, t: DateTime<Utc>) -> Option<usize> {
+        let after = self.prices.partition_point(|price| price.from <= t);
+        after
+            .checked_sub(1)
+            .filter(|&position| self.prices[position].contains(t))
+    }
+
+    /// The spot price containing t, None when t falls in a gap or outside the spot prices.
+    pub fn price_at(&self, t: DateTime<Utc>) -> Option<&'a SpotPrice> {
+        self.position_at(t).map(|position| &self.prices[position])
+    }
+
+    /// The spot prices overlapping from until till, not clipped to them.
+    pub fn slice_between(&self, from: DateTime<Utc>, till: DateTime<Utc>) -> &'a [SpotPrice] {
+        let start = self.prices.partition_point(|price| price.till <= from);
+        let end = self.prices.partition_point(|price| price.from < till);
+        &self.prices[start..end.max(start)]
+    }
+}
+
+/// Misalignment between adjacent spot price boundaries normalize smooths out.
+pub const DEFAULT_BOUNDARY_TOLERANCE_SECONDS: i64 = 1;
+
+/// Start of date in time_zone; when midnight is skipped by a daylight saving time change the day starts right after
+/// the skipped hour.
+pub(crate) fn local_midnight(date: NaiveDate, time_zone: &Tz) -> Option<DateTime<Utc>> {
+    let midnight = date.and_time(NaiveTime::MIN);
+    midnight
+        .and_local_timezone(*time_zone)
+        .earliest()
+        .map(|midnight| midnight.with_timezone(&Utc))
+        .or_else(|| {
+            (midnight - Duration::hours(1))
+                .and_local_timezone(*time_zone)
+                .earliest()
+                .map(|before_midnight| before_midnight.with_timezone(&Utc) + Duration::hours(1))
+        })
+}
+
+fn round_to_second(t: DateTime<Utc>) -> DateTime<Utc> {
+    t.duration_round(Duration::seconds(1)).unwrap_or(t)
+}
+
+fn gcd(a: i64, b: i64) -> i64 {
+    if b == 0 {
+        a.abs()
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum SpotPriceError {
+    /// A spot price ending at or before its start.
+    InvalidInterval {
+        from: DateTime<Utc>,
+        till: DateTime<Utc>,
+    },
+    /// Two different spot prices covering part of the same period.
+    OverlappingIntervals {
+        first_from: DateTime<Utc>,
+        second_from: DateTime<Utc>,
+    },
+    /// A spot price starting before the spot price listed before it.
+    UnsortedIntervals {
+        first_from: DateTime<Utc>,
+        second_from: DateTime<Utc>,
+    },
+    /// A spot price with a price that is NaN or infinite.
+    NonFinitePrice { from: DateTime<Utc> },
+    /// Spot prices in different currencies without an exchange rate between them.
+    MixedCurrencies {
+        currency: String,
+        other_currency: String,
+    },
+}
+
+impl fmt::Display for SpotPriceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SpotPriceError::InvalidInterval { from, till } => {
+                write!(f, "Spot price from {} till {} has no duration", from, till)
+            }
+            SpotPriceError::OverlappingIntervals {
+                first_from,
+                second_from,
+            } => write!(
+                f,
+                "Spot prices starting at {} and {} overlap",
+                first_from, second_from
+            ),
+            SpotPriceError::UnsortedIntervals {
+                first_from,
+                second_from,
+            } => write!(
+                f,
+                "Spot price starting at {} is listed after the one starting at {}",
+                second_from, first_from
+            ),
+            SpotPriceError::NonFinitePrice { from } => {
+                write!(f, "Spot price starting at {} has a non-finite price", from)
+            }
+            SpotPriceError::MixedCurrencies {
+                currency,
+                other_currency,
+            } => write!(
+                f,
+                "Spot prices in {} and {} can't be combined without an exchange rate",
+                currency, other_currency
+            ),
+        }
+    }
+}
+
+impl Error for SpotPriceError {}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use chrono::{Duration, TimeZone};
     use pretty_assertions::assert_eq;
-    use std::error::Error;
     use std::fs;
 
+    fn hourly_spot_price(from: DateTime<Utc>, market_price: f64) -> SpotPrice {
+        SpotPrice {
+            id: None,
+            source: None,
+            from,
+            till: from + Duration::hours(1),
+            market_price,
+            market_price_tax: 0.0,
+            sourcing_markup_price: 0.0,
+            energy_tax_price: 0.0,
+            extra_components: None,
+            currency: None,
+            unit: None,
+            merged_slot_seconds: None,
+        }
+    }
+
     #[test]
     fn deserialize_spot_price_response() -> Result<(), Box<dyn Error>> {
         let spot_price_predictions_content = fs::read_to_string("spot_price_predictions.json")?;
@@ -71,4 +1052,1180 @@ mod tests {
         assert_eq!(spot_price_response.data.market_prices_electricity.len(), 24);
         Ok(())
     }
+
+    #[test]
+    fn into_result_returns_spot_prices_without_errors() -> Result<(), Box<dyn Error>> {
+        let spot_price_predictions_content = fs::read_to_string("spot_price_predictions.json")?;
+        let spot_price_response: SpotPriceResponse =
+            serde_json::from_str(&spot_price_predictions_content)?;
+
+        // act
+        let spot_prices = spot_price_response.into_result()?;
+
+        assert_eq!(spot_prices.len(), 24);
+        Ok(())
+    }
+
+    #[test]
+    fn into_result_returns_error_messages_without_spot_prices() -> Result<(), Box<dyn Error>> {
+        let content = fs::read_to_string("spot_price_response_errors.json")?;
+        let spot_price_response: SpotPriceResponse = serde_json::from_str(&content)?;
+        assert_eq!(
+            spot_price_response
+                .errors
+                .as_ref()
+                .map(|errors| errors[1].clone()),
+            Some(GraphQlError {
+                message: "endDate must be after startDate".to_string(),
+                path: Some(vec![
+                    serde_json::json!("marketPricesElectricity"),
+                    serde_json::json!(0)
+                ]),
+            })
+        );
+
+        // act
+        let result = spot_price_response.into_result();
+
+        assert_eq!(
+            result,
+            Err(SpotPriceFeedError {
+                messages: vec![
+                    "Unable to fetch market prices from upstream".to_string(),
+                    "endDate must be after startDate".to_string(),
+                ]
+            })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn into_result_returns_partial_spot_prices_with_errors() -> Result<(), Box<dyn Error>> {
+        let content = fs::read_to_string("spot_price_response_mixed.json")?;
+        let spot_price_response: SpotPriceResponse = serde_json::from_str(&content)?;
+
+        // act
+        let spot_prices = spot_price_response.into_result()?;
+
+        assert_eq!(spot_prices.len(), 2);
+        assert_eq!(spot_prices[1].market_price, 0.162);
+        Ok(())
+    }
+
+    #[test]
+    fn normalize_sorts_shuffled_spot_prices() -> Result<(), Box<dyn Error>> {
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+
+        // act
+        let spot_prices = SpotPrice::normalize(vec![
+            hourly_spot_price(from + Duration::hours(2), 0.3),
+            hourly_spot_price(from, 0.1),
+            hourly_spot_price(from + Duration::hours(1), 0.2),
+        ])?;
+
+        assert_eq!(
+            spot_prices.iter().map(|sp| sp.from).collect::<Vec<_>>(),
+            vec![from, from + Duration::hours(1), from + Duration::hours(2)]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn normalize_drops_duplicated_spot_price() -> Result<(), Box<dyn Error>> {
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+
+        // act
+        let spot_prices = SpotPrice::normalize(vec![
+            hourly_spot_price(from, 0.1),
+            hourly_spot_price(from + Duration::hours(1), 0.2),
+            hourly_spot_price(from, 0.1),
+        ])?;
+
+        assert_eq!(spot_prices.len(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn normalize_returns_error_for_overlapping_spot_prices() {
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+
+        // act
+        let result = SpotPrice::normalize(vec![
+            hourly_spot_price(from, 0.1),
+            hourly_spot_price(from + Duration::minutes(30), 0.2),
+        ]);
+
+        assert_eq!(
+            result,
+            Err(SpotPriceError::OverlappingIntervals {
+                first_from: from,
+                second_from: from + Duration::minutes(30),
+            })
+        );
+    }
+
+    #[test]
+    fn normalize_returns_error_for_spot_price_without_duration() {
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+        let mut spot_price = hourly_spot_price(from, 0.1);
+        spot_price.till = from;
+
+        // act
+        let result = SpotPrice::normalize(vec![spot_price]);
+
+        assert_eq!(
+            result,
+            Err(SpotPriceError::InvalidInterval { from, till: from })
+        );
+    }
+
+    #[test]
+    fn normalize_keeps_gap_between_spot_prices() -> Result<(), Box<dyn Error>> {
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+
+        // act
+        let spot_prices = SpotPrice::normalize(vec![
+            hourly_spot_price(from + Duration::hours(2), 0.3),
+            hourly_spot_price(from, 0.1),
+        ])?;
+
+        assert_eq!(spot_prices.len(), 2);
+        assert_eq!(spot_prices[0].till, from + Duration::hours(1));
+        assert_eq!(spot_prices[1].from, from + Duration::hours(2));
+        Ok(())
+    }
+
+    fn jittered(spot_prices: &[SpotPrice]) -> Vec<SpotPrice> {
+        let jitter_millis = [300, -300, 120, -250, 0, 280, -10];
+        spot_prices
+            .iter()
+            .enumerate()
+            .map(|(i, spot_price)| SpotPrice {
+                from: spot_price.from
+                    + Duration::milliseconds(jitter_millis[i % jitter_millis.len()]),
+                till: spot_price.till
+                    + Duration::milliseconds(-jitter_millis[(i + 3) % jitter_millis.len()]),
+                ..spot_price.clone()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn duration_seconds_rounds_to_nearest_second() {
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+        let mut spot_price = hourly_spot_price(from, 0.1);
+        spot_price.till = from + Duration::milliseconds(3_599_600);
+
+        // act
+        let duration_seconds = spot_price.duration_seconds();
+
+        assert_eq!(duration_seconds, 3600);
+    }
+
+    #[test]
+    fn normalize_rounds_jittered_boundaries_to_clean_spot_prices() -> Result<(), Box<dyn Error>> {
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+        let clean: Vec<SpotPrice> = (0..8)
+            .map(|i| hourly_spot_price(from + Duration::hours(i), 0.1 * i as f64))
+            .collect();
+
+        // act
+        let spot_prices = SpotPrice::normalize(jittered(&clean))?;
+
+        assert_eq!(spot_prices, clean);
+        Ok(())
+    }
+
+    #[test]
+    fn normalize_treats_spot_prices_within_tolerance_as_adjacent() -> Result<(), Box<dyn Error>> {
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+        let mut later = hourly_spot_price(from + Duration::hours(1), 0.2);
+        later.from += Duration::seconds(2);
+
+        // act
+        let spot_prices = SpotPrice::normalize_with_tolerance(
+            vec![hourly_spot_price(from, 0.1), later],
+            Duration::seconds(2),
+        )?;
+
+        assert_eq!(spot_prices[1].from, from + Duration::hours(1));
+        assert_eq!(spot_prices[1].duration_seconds(), 3600);
+        Ok(())
+    }
+
+    #[test]
+    fn normalize_returns_error_for_overlap_beyond_tolerance() {
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+        let mut later = hourly_spot_price(from + Duration::hours(1), 0.2);
+        later.from -= Duration::seconds(2);
+
+        // act
+        let result = SpotPrice::normalize(vec![hourly_spot_price(from, 0.1), later]);
+
+        assert_eq!(
+            result,
+            Err(SpotPriceError::OverlappingIntervals {
+                first_from: from,
+                second_from: from + Duration::hours(1) - Duration::seconds(2),
+            })
+        );
+    }
+
+    fn spot_price_with_fees() -> SpotPrice {
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+        SpotPrice {
+            energy_tax_price: 0.10,
+            extra_components: Some(HashMap::from([
+                ("purchaseFee".to_string(), 0.02),
+                ("balancingFee".to_string(), 0.005),
+            ])),
+            ..hourly_spot_price(from, 0.20)
+        }
+    }
+
+    #[test]
+    fn total_price_includes_extra_components() {
+        let spot_price = spot_price_with_fees();
+
+        // act
+        let total_price = spot_price.total_price();
+
+        assert!((total_price - 0.325).abs() < 1e-9);
+    }
+
+    #[test]
+    fn price_for_leaves_out_excluded_extra_components() {
+        let spot_price = spot_price_with_fees();
+
+        // act
+        let price = spot_price.price_for(&PriceComponents {
+            energy_tax_price: false,
+            excluded_extra_components: vec!["balancingFee".to_string()],
+            ..PriceComponents::default()
+        });
+
+        assert!((price - 0.22).abs() < 1e-9);
+    }
+
+    #[test]
+    fn extra_components_survive_json_round_trip() -> Result<(), Box<dyn Error>> {
+        let spot_price = spot_price_with_fees();
+
+        // act
+        let json = serde_json::to_string(&spot_price)?;
+        let deserialized: SpotPrice = serde_json::from_str(&json)?;
+
+        assert!(json.contains("\"extraComponents\""));
+        assert_eq!(deserialized, spot_price);
+        Ok(())
+    }
+
+    #[test]
+    fn deserialize_spot_price_without_extra_components() -> Result<(), Box<dyn Error>> {
+        // act
+        let spot_price: SpotPrice = serde_json::from_str(
+            r#"{"id":null,"source":null,"from":"2022-04-16T00:00:00Z","till":"2022-04-16T01:00:00Z","marketPrice":0.2,"marketPriceTax":0.0,"sourcingMarkupPrice":0.0,"energyTaxPrice":0.1}"#,
+        )?;
+
+        assert_eq!(spot_price.extra_components, None);
+        assert!((spot_price.total_price() - 0.3).abs() < 1e-9);
+        Ok(())
+    }
+
+    #[test]
+    fn normalize_to_scales_prices_per_mwh_to_per_kwh() {
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+        let spot_price = SpotPrice {
+            market_price_tax: 25.2,
+            currency: Some("EUR".to_string()),
+            unit: Some(PriceUnit::EurPerMwh),
+            ..hourly_spot_price(from, 120.0)
+        };
+
+        // act
+        let normalized = spot_price.normalize_to("EUR", 1.0);
+
+        assert!((normalized.market_price - 0.12).abs() < 1e-12);
+        assert!((normalized.total_price() - 0.1452).abs() < 1e-12);
+        assert_eq!(normalized.unit, Some(PriceUnit::EurPerKwh));
+    }
+
+    #[test]
+    fn normalize_prices_converts_other_currency_with_rate() -> Result<(), Box<dyn Error>> {
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+        let spot_prices = vec![
+            SpotPrice {
+                currency: Some("EUR".to_string()),
+                ..hourly_spot_price(from, 0.20)
+            },
+            SpotPrice {
+                currency: Some("CZK".to_string()),
+                ..hourly_spot_price(from + Duration::hours(1), 5.0)
+            },
+        ];
+
+        // act
+        let normalized = SpotPrice::normalize_prices(
+            &spot_prices,
+            "EUR",
+            &HashMap::from([("CZK".to_string(), 0.04)]),
+        )?;
+
+        assert!((normalized[0].market_price - 0.20).abs() < 1e-12);
+        assert!((normalized[1].market_price - 0.20).abs() < 1e-12);
+        assert_eq!(normalized[1].currency.as_deref(), Some("EUR"));
+        Ok(())
+    }
+
+    #[test]
+    fn normalize_prices_returns_error_for_mixed_currencies_without_rate() {
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+        let spot_prices = vec![
+            SpotPrice {
+                currency: Some("EUR".to_string()),
+                ..hourly_spot_price(from, 0.20)
+            },
+            SpotPrice {
+                currency: Some("CZK".to_string()),
+                ..hourly_spot_price(from + Duration::hours(1), 5.0)
+            },
+        ];
+
+        // act
+        let result = SpotPrice::normalize_prices(&spot_prices, "EUR", &HashMap::new());
+
+        assert_eq!(
+            result,
+            Err(SpotPriceError::MixedCurrencies {
+                currency: "EUR".to_string(),
+                other_currency: "CZK".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn with_vat_recomputes_market_price_tax_for_selected_components() {
+        let spot_price = SpotPrice {
+            market_price_tax: 0.5,
+            sourcing_markup_price: 0.02,
+            ..spot_price_with_fees()
+        };
+
+        // act
+        let with_vat = spot_price.with_vat(
+            0.21,
+            &PriceComponents {
+                energy_tax_price: false,
+                ..PriceComponents::default()
+            },
+        );
+
+        // 0.21 * (0.20 + 0.02 + 0.02 + 0.005)
+        assert!((with_vat.market_price_tax - 0.05145).abs() < 1e-12);
+        assert_eq!(with_vat.market_price, 0.20);
+    }
+
+    #[test]
+    fn with_vat_at_zero_rate_clears_market_price_tax() {
+        let spot_price = SpotPrice {
+            market_price_tax: 0.042,
+            ..spot_price_with_fees()
+        };
+
+        // act
+        let with_vat = spot_price.with_vat(0.0, &PriceComponents::default());
+
+        assert_eq!(with_vat.market_price_tax, 0.0);
+    }
+
+    #[test]
+    fn with_vat_adds_no_vat_on_negative_market_price() {
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+        let spot_price = SpotPrice {
+            energy_tax_price: 0.10,
+            ..hourly_spot_price(from, -0.05)
+        };
+
+        // act
+        let without_negative_vat = spot_price.with_vat(0.21, &PriceComponents::default());
+        let with_negative_vat =
+            spot_price.with_vat_options(0.21, &PriceComponents::default(), true);
+
+        assert!((without_negative_vat.market_price_tax - 0.021).abs() < 1e-12);
+        assert!((with_negative_vat.market_price_tax - 0.0105).abs() < 1e-12);
+    }
+
+    #[test]
+    fn total_price_incl_vat_adds_vat_over_all_components() {
+        let spot_price = SpotPrice {
+            market_price_tax: 1.0,
+            ..spot_price_with_fees()
+        };
+
+        // act
+        let total_price = spot_price.total_price_incl_vat(0.21);
+
+        // 1.21 * (0.20 + 0.10 + 0.02 + 0.005)
+        assert!((total_price - 0.39325).abs() < 1e-12);
+    }
+
+    #[test]
+    fn recalculate_tax_updates_all_spot_prices() {
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+        let mut spot_prices = vec![
+            SpotPrice {
+                market_price_tax: 0.038,
+                ..hourly_spot_price(from, 0.20)
+            },
+            SpotPrice {
+                market_price_tax: 0.019,
+                ..hourly_spot_price(from + Duration::hours(1), 0.10)
+            },
+        ];
+
+        // act
+        SpotPrice::recalculate_tax(&mut spot_prices, 0.21);
+
+        assert!((spot_prices[0].market_price_tax - 0.042).abs() < 1e-12);
+        assert!((spot_prices[1].market_price_tax - 0.021).abs() < 1e-12);
+    }
+
+    #[test]
+    fn validate_accepts_valid_spot_price() {
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+
+        assert_eq!(hourly_spot_price(from, -0.05).validate(), Ok(()));
+    }
+
+    #[test]
+    fn validate_returns_error_for_spot_price_ending_at_start() {
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+        let spot_price = SpotPrice {
+            till: from,
+            ..hourly_spot_price(from, 0.20)
+        };
+
+        assert_eq!(
+            spot_price.validate(),
+            Err(SpotPriceError::InvalidInterval { from, till: from })
+        );
+    }
+
+    #[test]
+    fn validate_returns_error_for_non_finite_price() {
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+        let spot_price = SpotPrice {
+            extra_components: Some(HashMap::from([("purchaseFee".to_string(), f64::NAN)])),
+            ..hourly_spot_price(from, 0.20)
+        };
+
+        assert_eq!(
+            spot_price.validate(),
+            Err(SpotPriceError::NonFinitePrice { from })
+        );
+        assert_eq!(
+            hourly_spot_price(from, f64::INFINITY).validate(),
+            Err(SpotPriceError::NonFinitePrice { from })
+        );
+    }
+
+    #[test]
+    fn contains_includes_from_and_excludes_till() {
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+        let spot_price = hourly_spot_price(from, 0.20);
+
+        assert!(spot_price.contains(from));
+        assert!(spot_price.contains(from + Duration::minutes(59)));
+        assert!(!spot_price.contains(from + Duration::hours(1)));
+        assert!(!spot_price.contains(from - Duration::seconds(1)));
+    }
+
+    #[test]
+    fn overlaps_excludes_touching_spot_prices() {
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+        let spot_price = hourly_spot_price(from, 0.20);
+        let next = hourly_spot_price(from + Duration::hours(1), 0.20);
+        let shifted = hourly_spot_price(from + Duration::minutes(30), 0.20);
+
+        assert!(spot_price.overlaps(&spot_price));
+        assert!(spot_price.overlaps(&shifted));
+        assert!(shifted.overlaps(&spot_price));
+        assert!(!spot_price.overlaps(&next));
+        assert!(!next.overlaps(&spot_price));
+    }
+
+    #[test]
+    fn is_adjacent_to_requires_till_to_equal_other_from() {
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+        let spot_price = hourly_spot_price(from, 0.20);
+        let next = hourly_spot_price(from + Duration::hours(1), 0.20);
+        let later = hourly_spot_price(from + Duration::hours(2), 0.20);
+        let shifted = hourly_spot_price(from + Duration::minutes(30), 0.20);
+
+        assert!(spot_price.is_adjacent_to(&next));
+        assert!(next.is_adjacent_to(&spot_price));
+        assert!(!spot_price.is_adjacent_to(&later));
+        assert!(!spot_price.is_adjacent_to(&shifted));
+        assert!(!spot_price.is_adjacent_to(&spot_price));
+    }
+
+    #[test]
+    fn clip_trims_boundaries_to_overlap() {
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+        let spot_price = hourly_spot_price(from, 0.20);
+
+        // act
+        let clipped = spot_price.clip(from + Duration::minutes(15), from + Duration::minutes(45));
+
+        assert_eq!(
+            clipped,
+            Some(SpotPrice {
+                from: from + Duration::minutes(15),
+                till: from + Duration::minutes(45),
+                ..spot_price.clone()
+            })
+        );
+        assert_eq!(
+            spot_price.clip(from - Duration::hours(1), from + Duration::hours(2)),
+            Some(spot_price)
+        );
+    }
+
+    #[test]
+    fn clip_returns_none_without_overlap() {
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+        let spot_price = hourly_spot_price(from, 0.20);
+
+        assert_eq!(
+            spot_price.clip(from + Duration::hours(1), from + Duration::hours(2)),
+            None
+        );
+        assert_eq!(spot_price.clip(from - Duration::hours(1), from), None);
+        assert_eq!(
+            spot_price.clip(from + Duration::minutes(30), from + Duration::minutes(30)),
+            None
+        );
+    }
+
+    #[test]
+    fn merge_adjacent_combines_spot_prices_with_same_prices() {
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+        let spot_prices = vec![
+            SpotPrice {
+                id: Some("a".to_string()),
+                source: Some("easyEnergy".to_string()),
+                ..hourly_spot_price(from, 0.10)
+            },
+            SpotPrice {
+                id: Some("b".to_string()),
+                ..hourly_spot_price(from + Duration::hours(1), 0.10)
+            },
+            hourly_spot_price(from + Duration::hours(2), 0.10),
+            hourly_spot_price(from + Duration::hours(3), 0.20),
+        ];
+
+        // act
+        let merged = SpotPrice::merge_adjacent(spot_prices, 1e-9);
+
+        assert_eq!(merged.len(), 2);
+        assert_eq!(merged[0].from, from);
+        assert_eq!(merged[0].till, from + Duration::hours(3));
+        assert_eq!(merged[0].id.as_deref(), Some("a"));
+        assert_eq!(merged[0].source.as_deref(), Some("easyEnergy"));
+        assert_eq!(merged[0].merged_slot_seconds, Some(3600));
+        assert_eq!(merged[1].from, from + Duration::hours(3));
+        assert_eq!(merged[1].merged_slot_seconds, None);
+    }
+
+    #[test]
+    fn unmerge_splits_merged_spot_prices_into_original_slots() {
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+        let spot_prices = vec![
+            SpotPrice {
+                id: Some("a".to_string()),
+                ..hourly_spot_price(from, 0.10)
+            },
+            SpotPrice {
+                id: Some("b".to_string()),
+                ..hourly_spot_price(from + Duration::hours(1), 0.10)
+            },
+            hourly_spot_price(from + Duration::hours(2), 0.20),
+        ];
+
+        // act
+        let unmerged = SpotPrice::unmerge(SpotPrice::merge_adjacent(spot_prices.clone(), 1e-9));
+
+        assert_eq!(
+            unmerged,
+            vec![
+                spot_prices[0].clone(),
+                SpotPrice {
+                    id: None,
+                    ..spot_prices[1].clone()
+                },
+                spot_prices[2].clone(),
+            ]
+        );
+    }
+
+    #[test]
+    fn merge_adjacent_merges_only_differences_below_epsilon() {
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+        let spot_prices = vec![
+            hourly_spot_price(from, 0.5),
+            hourly_spot_price(from + Duration::hours(1), 0.5 + 0.25),
+            hourly_spot_price(from + Duration::hours(2), 0.5 + 0.25 - 1e-3),
+        ];
+
+        // act
+        let merged = SpotPrice::merge_adjacent(spot_prices, 0.25);
+
+        assert_eq!(merged.len(), 2);
+        assert_eq!(merged[0].till, from + Duration::hours(1));
+        assert_eq!(merged[1].till, from + Duration::hours(3));
+        assert_eq!(merged[1].market_price, 0.75);
+    }
+
+    #[test]
+    fn merge_adjacent_keeps_non_adjacent_spot_prices_separate() {
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+        let spot_prices = vec![
+            hourly_spot_price(from, 0.10),
+            hourly_spot_price(from + Duration::hours(2), 0.10),
+        ];
+
+        // act
+        let merged = SpotPrice::merge_adjacent(spot_prices.clone(), 1e-9);
+
+        assert_eq!(merged, spot_prices);
+    }
+
+    #[test]
+    fn merge_adjacent_keeps_spot_prices_with_different_extra_components_separate() {
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+        let spot_prices = vec![
+            hourly_spot_price(from, 0.10),
+            SpotPrice {
+                extra_components: Some(HashMap::from([("purchaseFee".to_string(), 0.02)])),
+                ..hourly_spot_price(from + Duration::hours(1), 0.10)
+            },
+        ];
+
+        // act
+        let merged = SpotPrice::merge_adjacent(spot_prices.clone(), 1e-9);
+
+        assert_eq!(merged, spot_prices);
+    }
+
+    #[test]
+    fn split_at_inside_spot_price_returns_both_parts_without_id() {
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+        let spot_price = SpotPrice {
+            id: Some("a".to_string()),
+            source: Some("easyEnergy".to_string()),
+            ..hourly_spot_price(from, 0.20)
+        };
+
+        // act
+        let (before, after) = spot_price.split_at(from + Duration::seconds(1234));
+
+        let before = before.unwrap();
+        let after = after.unwrap();
+        assert_eq!(before.from, from);
+        assert_eq!(before.till, from + Duration::seconds(1234));
+        assert_eq!(after.from, from + Duration::seconds(1234));
+        assert_eq!(after.till, from + Duration::hours(1));
+        assert_eq!(before.duration_seconds() + after.duration_seconds(), 3600);
+        assert_eq!(before.id, None);
+        assert_eq!(after.id, None);
+        assert_eq!(after.source.as_deref(), Some("easyEnergy"));
+        assert_eq!(after.market_price, 0.20);
+    }
+
+    #[test]
+    fn split_at_boundaries_returns_one_part() {
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+        let spot_price = hourly_spot_price(from, 0.20);
+
+        assert_eq!(spot_price.split_at(from), (None, Some(spot_price.clone())));
+        assert_eq!(
+            spot_price.split_at(from + Duration::hours(1)),
+            (Some(spot_price.clone()), None)
+        );
+    }
+
+    #[test]
+    fn split_at_outside_spot_price_returns_one_part() {
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+        let spot_price = hourly_spot_price(from, 0.20);
+
+        assert_eq!(
+            spot_price.split_at(from - Duration::hours(1)),
+            (None, Some(spot_price.clone()))
+        );
+        assert_eq!(
+            spot_price.split_at(from + Duration::hours(2)),
+            (Some(spot_price.clone()), None)
+        );
+    }
+
+    fn intervals(spot_prices: &[SpotPrice]) -> Vec<(DateTime<Utc>, DateTime<Utc>)> {
+        spot_prices
+            .iter()
+            .map(|spot_price| (spot_price.from, spot_price.till))
+            .collect()
+    }
+
+    #[test]
+    fn split_at_local_midnights_splits_across_daylight_saving_time_change() {
+        let from = Utc.with_ymd_and_hms(2022, 3, 26, 12, 0, 0).unwrap();
+        let spot_price = SpotPrice {
+            till: Utc.with_ymd_and_hms(2022, 3, 28, 12, 0, 0).unwrap(),
+            ..hourly_spot_price(from, 0.20)
+        };
+
+        // act
+        let parts = spot_price.split_at_local_midnights(chrono_tz::Europe::Amsterdam);
+
+        assert_eq!(
+            intervals(&parts),
+            vec![
+                (from, Utc.with_ymd_and_hms(2022, 3, 26, 23, 0, 0).unwrap()),
+                (
+                    Utc.with_ymd_and_hms(2022, 3, 26, 23, 0, 0).unwrap(),
+                    Utc.with_ymd_and_hms(2022, 3, 27, 22, 0, 0).unwrap()
+                ),
+                (
+                    Utc.with_ymd_and_hms(2022, 3, 27, 22, 0, 0).unwrap(),
+                    Utc.with_ymd_and_hms(2022, 3, 28, 12, 0, 0).unwrap()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn split_at_local_midnights_starts_day_after_skipped_midnight() {
+        // clocks in Havana jump from 00:00 to 01:00 on the 13th of March 2022
+        let from = Utc.with_ymd_and_hms(2022, 3, 13, 3, 0, 0).unwrap();
+        let spot_price = SpotPrice {
+            till: from + Duration::hours(4),
+            ..hourly_spot_price(from, 0.20)
+        };
+
+        // act
+        let parts = spot_price.split_at_local_midnights(chrono_tz::America::Havana);
+
+        assert_eq!(
+            intervals(&parts),
+            vec![
+                (from, Utc.with_ymd_and_hms(2022, 3, 13, 5, 0, 0).unwrap()),
+                (
+                    Utc.with_ymd_and_hms(2022, 3, 13, 5, 0, 0).unwrap(),
+                    from + Duration::hours(4)
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn split_at_local_midnights_keeps_spot_price_within_one_day() {
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+        let spot_price = hourly_spot_price(from, 0.20);
+
+        // act
+        let parts = spot_price.split_at_local_midnights(chrono_tz::Europe::Amsterdam);
+
+        assert_eq!(parts, vec![spot_price]);
+    }
+
+    #[test]
+    fn display_formats_spot_price_on_one_line() {
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 13, 0, 0).unwrap();
+        let spot_price = SpotPrice {
+            market_price_tax: 0.00567,
+            sourcing_markup_price: 0.017,
+            energy_tax_price: 0.08163,
+            ..hourly_spot_price(from, 0.027)
+        };
+
+        assert_eq!(
+            spot_price.to_string(),
+            "2022-04-16 13:00–14:00 UTC  market 0.0270  total 0.1313 €/kWh"
+        );
+    }
+
+    #[test]
+    fn display_shows_currency_and_unit_other_than_euro_per_kwh() {
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 13, 0, 0).unwrap();
+        let spot_price = SpotPrice {
+            currency: Some("CZK".to_string()),
+            unit: Some(PriceUnit::EurPerMwh),
+            ..hourly_spot_price(from, 2450.5)
+        };
+
+        assert_eq!(
+            spot_price.to_string(),
+            "2022-04-16 13:00–14:00 UTC  market 2450.5000  total 2450.5000 CZK/MWh"
+        );
+    }
+
+    #[test]
+    fn format_price_table_aligns_local_times_and_prices() {
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 13, 0, 0).unwrap();
+        let spot_prices = vec![
+            SpotPrice {
+                energy_tax_price: 0.1,
+                ..hourly_spot_price(from, 0.027)
+            },
+            SpotPrice {
+                energy_tax_price: 0.1,
+                ..hourly_spot_price(from + Duration::hours(1), -0.0126)
+            },
+        ];
+
+        // act
+        let table = format_price_table(&spot_prices, &chrono_tz::Europe::Amsterdam);
+
+        assert_eq!(
+            table,
+            "from              till     market     total
+2022-04-16 15:00  16:00    0.0270    0.1270
+2022-04-16 16:00  17:00   -0.0126    0.0874
+min                       -0.0126    0.0874
+max                        0.0270    0.1270
+avg                        0.0072    0.1072
+"
+        );
+    }
+
+    #[test]
+    fn format_price_table_leaves_out_footer_without_spot_prices() {
+        assert_eq!(
+            format_price_table(&[], &chrono_tz::Europe::Amsterdam),
+            "from              till     market     total\n"
+        );
+    }
+
+    fn indexed_spot_prices() -> Vec<SpotPrice> {
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+        vec![
+            hourly_spot_price(from, 0.10),
+            hourly_spot_price(from + Duration::hours(1), 0.20),
+            // gap from 02:00 till 03:00
+            hourly_spot_price(from + Duration::hours(3), 0.30),
+        ]
+    }
+
+    #[test]
+    fn price_at_includes_from_and_excludes_till() -> Result<(), Box<dyn Error>> {
+        let spot_prices = indexed_spot_prices();
+        let index = SpotPriceIndex::new(&spot_prices)?;
+        let from = spot_prices[0].from;
+
+        assert_eq!(index.price_at(from), Some(&spot_prices[0]));
+        assert_eq!(
+            index.price_at(from + Duration::minutes(59)),
+            Some(&spot_prices[0])
+        );
+        assert_eq!(
+            index.price_at(from + Duration::hours(1)),
+            Some(&spot_prices[1])
+        );
+        assert_eq!(index.price_at(from + Duration::hours(2)), None);
+        assert_eq!(
+            index.price_at(from + Duration::hours(3)),
+            Some(&spot_prices[2])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn price_at_returns_none_before_first_and_after_last() -> Result<(), Box<dyn Error>> {
+        let spot_prices = indexed_spot_prices();
+        let index = SpotPriceIndex::new(&spot_prices)?;
+        let from = spot_prices[0].from;
+
+        assert_eq!(index.price_at(from - Duration::seconds(1)), None);
+        assert_eq!(index.price_at(from + Duration::hours(4)), None);
+        assert_eq!(index.price_at(from + Duration::hours(5)), None);
+        assert_eq!(SpotPriceIndex::new(&[])?.price_at(from), None);
+        Ok(())
+    }
+
+    #[test]
+    fn slice_between_returns_overlapping_spot_prices() -> Result<(), Box<dyn Error>> {
+        let spot_prices = indexed_spot_prices();
+        let index = SpotPriceIndex::new(&spot_prices)?;
+        let from = spot_prices[0].from;
+
+        assert_eq!(
+            index.slice_between(from + Duration::minutes(30), from + Duration::hours(1)),
+            &spot_prices[0..1]
+        );
+        assert_eq!(
+            index.slice_between(from + Duration::hours(1), from + Duration::hours(4)),
+            &spot_prices[1..3]
+        );
+        assert_eq!(
+            index.slice_between(from - Duration::hours(2), from + Duration::hours(9)),
+            &spot_prices[..]
+        );
+        assert!(index
+            .slice_between(from + Duration::hours(2), from + Duration::hours(3))
+            .is_empty());
+        assert!(index
+            .slice_between(from + Duration::hours(3), from + Duration::hours(1))
+            .is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn new_returns_error_for_unsorted_or_overlapping_spot_prices() {
+        let mut spot_prices = indexed_spot_prices();
+        spot_prices.swap(0, 2);
+
+        assert_eq!(
+            SpotPriceIndex::new(&spot_prices).map(|_| ()),
+            Err(SpotPriceError::UnsortedIntervals {
+                first_from: spot_prices[0].from,
+                second_from: spot_prices[1].from,
+            })
+        );
+
+        let spot_prices = vec![
+            hourly_spot_price(Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap(), 0.10),
+            hourly_spot_price(Utc.with_ymd_and_hms(2022, 4, 16, 0, 30, 0).unwrap(), 0.20),
+        ];
+        assert_eq!(
+            SpotPriceIndex::new(&spot_prices).map(|_| ()),
+            Err(SpotPriceError::OverlappingIntervals {
+                first_from: spot_prices[0].from,
+                second_from: spot_prices[1].from,
+            })
+        );
+    }
+
+    #[test]
+    fn stats_summarizes_odd_number_of_spot_prices() {
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+        let spot_prices: Vec<SpotPrice> = [0.30, 0.10, 0.20, 0.10, 0.40]
+            .iter()
+            .enumerate()
+            .map(|(i, market_price)| {
+                hourly_spot_price(from + Duration::hours(i as i64), *market_price)
+            })
+            .collect();
+
+        // act
+        let stats = stats(&spot_prices, &PriceComponents::default());
+
+        assert_eq!(stats.count, 5);
+        assert_eq!(stats.min, 0.10);
+        assert_eq!(stats.max, 0.40);
+        assert!((stats.mean - 0.22).abs() < 1e-12);
+        assert_eq!(stats.median, 0.20);
+        // variance (0.0064 + 0.0144 + 0.0004 + 0.0144 + 0.0324) / 5 = 0.0136
+        assert!((stats.standard_deviation - 0.0136_f64.sqrt()).abs() < 1e-12);
+        assert_eq!(stats.min_from, Some(from + Duration::hours(1)));
+        assert_eq!(stats.max_from, Some(from + Duration::hours(4)));
+    }
+
+    #[test]
+    fn stats_averages_middle_prices_for_median_of_even_count() {
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+        let spot_prices: Vec<SpotPrice> = [0.40, 0.10, 0.30, 0.20]
+            .iter()
+            .enumerate()
+            .map(|(i, market_price)| SpotPrice {
+                energy_tax_price: 0.5,
+                ..hourly_spot_price(from + Duration::hours(i as i64), *market_price)
+            })
+            .collect();
+
+        // act
+        let stats = stats(
+            &spot_prices,
+            &PriceComponents {
+                energy_tax_price: false,
+                ..PriceComponents::default()
+            },
+        );
+
+        assert_eq!(stats.count, 4);
+        assert!((stats.median - 0.25).abs() < 1e-12);
+        assert!((stats.mean - 0.25).abs() < 1e-12);
+        assert_eq!(stats.max, 0.40);
+        assert_eq!(stats.max_from, Some(from));
+    }
+
+    #[test]
+    fn stats_converts_prices_per_mwh_to_per_kwh() {
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+        let spot_prices = vec![
+            hourly_spot_price(from, 0.30),
+            SpotPrice {
+                unit: Some(PriceUnit::EurPerMwh),
+                ..hourly_spot_price(from + Duration::hours(1), 100.0)
+            },
+        ];
+
+        // act
+        let stats = stats(&spot_prices, &PriceComponents::default());
+
+        assert!((stats.min - 0.10).abs() < 1e-12);
+        assert_eq!(stats.max, 0.30);
+        assert!((stats.mean - 0.20).abs() < 1e-12);
+        assert_eq!(stats.min_from, Some(from + Duration::hours(1)));
+        assert_eq!(stats.max_from, Some(from));
+    }
+
+    #[test]
+    fn stats_without_spot_prices_is_empty() {
+        // act
+        let stats = stats(&[], &PriceComponents::default());
+
+        assert_eq!(stats, SpotPriceStats::default());
+        assert_eq!(stats.mean, 0.0);
+        assert_eq!(stats.min_from, None);
+    }
+
+    #[test]
+    fn stats_for_local_day_only_counts_spot_prices_within_local_day() {
+        // 22:00 UTC is midnight in Amsterdam in summer time
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 20, 0, 0).unwrap();
+        let spot_prices: Vec<SpotPrice> = [0.50, 0.40, 0.10, 0.20]
+            .iter()
+            .enumerate()
+            .map(|(i, market_price)| {
+                hourly_spot_price(from + Duration::hours(i as i64), *market_price)
+            })
+            .collect();
+
+        // act
+        let stats = stats_for_local_day(
+            &spot_prices,
+            NaiveDate::from_ymd_opt(2022, 4, 17).unwrap(),
+            chrono_tz::Europe::Amsterdam,
+            &PriceComponents::default(),
+        );
+
+        assert_eq!(stats.count, 2);
+        assert_eq!(stats.max, 0.20);
+        assert_eq!(stats.min_from, Some(from + Duration::hours(2)));
+    }
+
+    #[test]
+    fn spot_prices_to_measurement_publishes_current_and_upcoming_prices(
+    ) -> Result<(), Box<dyn Error>> {
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 13, 0, 0).unwrap();
+        let spot_prices = vec![
+            hourly_spot_price(from - Duration::hours(1), 0.5),
+            SpotPrice {
+                source: Some("easyEnergy".to_string()),
+                energy_tax_price: 0.25,
+                ..hourly_spot_price(from, 0.125)
+            },
+            hourly_spot_price(from + Duration::hours(1), -0.125),
+        ];
+        let now = from + Duration::minutes(20);
+
+        // act
+        let measurement =
+            spot_prices_to_measurement(&spot_prices, "jarvis-spot-price-exporter", "My Home", now);
+
+        assert_eq!(measurement.id.len(), 36);
+        let mut json = serde_json::to_value(&measurement)?;
+        json["Id"] = serde_json::json!("id");
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "Id": "id",
+                "Source": "jarvis-spot-price-exporter",
+                "Location": "My Home",
+                "Samples": [
+                    {
+                        "EntityType": "ENTITY_TYPE_TARIFF",
+                        "EntityName": "easyEnergy",
+                        "SampleType": "SAMPLE_TYPE_PRICE",
+                        "SampleName": "current price",
+                        "MetricType": "METRIC_TYPE_GAUGE",
+                        "Value": 0.375
+                    },
+                    {
+                        "EntityType": "ENTITY_TYPE_TARIFF",
+                        "EntityName": "spot price",
+                        "SampleType": "SAMPLE_TYPE_PRICE",
+                        "SampleName": "2022-04-16T14:00:00Z",
+                        "MetricType": "METRIC_TYPE_GAUGE",
+                        "Value": -0.125
+                    }
+                ],
+                "MeasuredAtTime": "2022-04-16T13:20:00Z",
+                "SchemaVersion": 2
+            })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn to_sample_names_sample_after_start_of_spot_price() {
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 13, 0, 0).unwrap();
+
+        // act
+        let sample = hourly_spot_price(from, 0.2).to_sample(MetricType::Gauge);
+
+        assert_eq!(sample.entity_type, EntityType::Tariff);
+        assert_eq!(sample.sample_type, SampleType::Price);
+        assert_eq!(sample.sample_name, "2022-04-16T13:00:00Z");
+        assert_eq!(sample.value, 0.2);
+    }
+
+    #[test]
+    fn build_gives_spot_price_new_uuid_as_id() -> Result<(), Box<dyn Error>> {
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+
+        // act
+        let spot_price = SpotPrice::new(from, from + Duration::hours(1), 0.2)
+            .source("easyEnergy")
+            .energy_tax_price(0.1)
+            .build()?;
+        let other_spot_price = SpotPrice::new(from, from + Duration::hours(1), 0.2).build()?;
+
+        let id = spot_price.id.clone().unwrap();
+        assert_eq!(id.len(), 36);
+        assert_eq!(&id[14..15], "4");
+        assert_ne!(spot_price.id, other_spot_price.id);
+        assert_eq!(spot_price.source.as_deref(), Some("easyEnergy"));
+        assert!((spot_price.total_price() - 0.3).abs() < 1e-12);
+        Ok(())
+    }
+
+    #[test]
+    fn build_keeps_id_set_on_builder() -> Result<(), Box<dyn Error>> {
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+
+        // act
+        let spot_price = SpotPrice::new(from, from + Duration::hours(1), 0.2)
+            .id(None)
+            .build()?;
+
+        assert_eq!(spot_price, hourly_spot_price(from, 0.2));
+        Ok(())
+    }
+
+    #[test]
+    fn build_returns_error_for_inverted_interval() {
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+
+        // act
+        let result = SpotPrice::new(from + Duration::hours(1), from, 0.2).build();
+
+        assert_eq!(
+            result,
+            Err(SpotPriceError::InvalidInterval {
+                from: from + Duration::hours(1),
+                till: from,
+            })
+        );
+    }
 }
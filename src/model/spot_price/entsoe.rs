@@ -0,0 +1,356 @@
+use super::providers::{apply_markup, PriceMarkupConfig};
+use super::{PriceUnit, SpotPrice};
+use chrono::{DateTime, Duration, NaiveDateTime, Utc};
+use roxmltree::{Document, Node};
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+/// Spot prices per kWh from an ENTSO-E Publication_MarketDocument with day-ahead prices in EUR/MWh. Positions left
+/// out of a period repeat the price of the position before them, as ENTSO-E leaves out unchanged prices.
+pub fn parse_entsoe_day_ahead(
+    xml: &str,
+    markup: PriceMarkupConfig,
+) -> Result<Vec<SpotPrice>, EntsoeParseError> {
+    let document =
+        Document::parse(xml).map_err(|error| EntsoeParseError::InvalidXml(error.to_string()))?;
+    let root = document.root_element();
+    if root.has_tag_name("Acknowledgement_MarketDocument") {
+        let reason = root
+            .descendants()
+            .find(|node| node.has_tag_name("text"))
+            .and_then(|text| text.text())
+            .unwrap_or_default();
+        return Err(EntsoeParseError::NoData(reason.trim().to_string()));
+    }
+
+    let mut spot_prices = vec![];
+    for time_series in children(root, "TimeSeries") {
+        let currency = text(time_series, "currency_Unit.name")?;
+        let measure_unit = text(time_series, "price_Measure_Unit.name")?;
+        if measure_unit != "MWH" {
+            return Err(EntsoeParseError::UnsupportedUnit(measure_unit.to_string()));
+        }
+
+        for period in children(time_series, "Period") {
+            let time_interval = child(period, "timeInterval")?;
+            let start = parse_time(text(time_interval, "start")?)?;
+            let end = parse_time(text(time_interval, "end")?)?;
+            let resolution = parse_resolution(text(period, "resolution")?)?;
+
+            let positions =
+                ((end - start).num_seconds() / resolution.num_seconds()).max(0) as usize;
+            let mut prices: Vec<Option<f64>> = vec![None; positions];
+            for point in children(period, "Point") {
+                let position: usize = parse_value(point, "position")?;
+                if position == 0 || position > positions {
+                    return Err(EntsoeParseError::InvalidValue {
+                        element: "position".to_string(),
+                        value: position.to_string(),
+                    });
+                }
+                prices[position - 1] = Some(parse_value(point, "price.amount")?);
+            }
+
+            let mut previous_price = None;
+            for (i, price) in prices.into_iter().enumerate() {
+                let price = price
+                    .or(previous_price)
+                    .ok_or(EntsoeParseError::MissingPosition(i + 1))?;
+                previous_price = Some(price);
+
+                let from = start + resolution * i as i32;
+                spot_prices.push(SpotPrice {
+                    id: None,
+                    source: Some("entsoe".to_string()),
+                    from,
+                    till: from + resolution,
+                    market_price: price * PriceUnit::EurPerMwh.per_kwh_factor(),
+                    market_price_tax: 0.0,
+                    sourcing_markup_price: 0.0,
+                    energy_tax_price: 0.0,
+                    extra_components: None,
+                    currency: Some(currency.to_string()),
+                    unit: Some(PriceUnit::EurPerKwh),
+                    merged_slot_seconds: None,
+                });
+            }
+        }
+    }
+
+    apply_markup(&mut spot_prices, markup);
+    Ok(spot_prices)
+}
+
+/// Child elements of node called name, whatever their namespace.
+fn children<'a, 'input>(
+    node: Node<'a, 'input>,
+    name: &'a str,
+) -> impl Iterator<Item = Node<'a, 'input>> + 'a {
+    node.children()
+        .filter(move |child| child.is_element() && child.has_tag_name(name))
+}
+
+fn child<'a, 'input>(
+    node: Node<'a, 'input>,
+    name: &str,
+) -> Result<Node<'a, 'input>, EntsoeParseError> {
+    node.children()
+        .find(|child| child.is_element() && child.has_tag_name(name))
+        .ok_or_else(|| EntsoeParseError::MissingElement(name.to_string()))
+}
+
+/// Trimmed text of the first child element of node called name.
+fn text<'a>(node: Node<'a, '_>, name: &str) -> Result<&'a str, EntsoeParseError> {
+    Ok(child(node, name)?.text().unwrap_or_default().trim())
+}
+
+fn parse_value<T: FromStr>(node: Node, name: &str) -> Result<T, EntsoeParseError> {
+    let value = text(node, name)?;
+    value.parse().map_err(|_| EntsoeParseError::InvalidValue {
+        element: name.to_string(),
+        value: value.to_string(),
+    })
+}
+
+/// ENTSO-E times come without seconds, like 2023-03-25T23:00Z.
+fn parse_time(value: &str) -> Result<DateTime<Utc>, EntsoeParseError> {
+    let value = value.trim();
+    NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%MZ")
+        .map(|time| DateTime::<Utc>::from_naive_utc_and_offset(time, Utc))
+        .or_else(|_| DateTime::parse_from_rfc3339(value).map(|time| time.with_timezone(&Utc)))
+        .map_err(|_| EntsoeParseError::InvalidValue {
+            element: "time".to_string(),
+            value: value.to_string(),
+        })
+}
+
+/// Resolutions in minutes, like PT60M and PT15M.
+fn parse_resolution(value: &str) -> Result<Duration, EntsoeParseError> {
+    let value = value.trim();
+    value
+        .strip_prefix("PT")
+        .and_then(|minutes| minutes.strip_suffix('M'))
+        .and_then(|minutes| minutes.parse::<i64>().ok())
+        .filter(|minutes| *minutes > 0)
+        .map(Duration::minutes)
+        .ok_or_else(|| EntsoeParseError::UnsupportedResolution(value.to_string()))
+}
+
+#[derive(Debug, PartialEq)]
+pub enum EntsoeParseError {
+    /// A document that isn't well-formed XML.
+    InvalidXml(String),
+    MissingElement(String),
+    InvalidValue {
+        element: String,
+        value: String,
+    },
+    UnsupportedResolution(String),
+    /// A price unit other than MWH.
+    UnsupportedUnit(String),
+    /// A position at the start of a period without a price to repeat.
+    MissingPosition(usize),
+    /// An acknowledgement instead of prices, with its reason.
+    NoData(String),
+}
+
+impl fmt::Display for EntsoeParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EntsoeParseError::InvalidXml(error) => write!(f, "Invalid XML: {}", error),
+            EntsoeParseError::MissingElement(name) => write!(f, "Missing element {}", name),
+            EntsoeParseError::InvalidValue { element, value } => {
+                write!(f, "Invalid value {} for element {}", value, element)
+            }
+            EntsoeParseError::UnsupportedResolution(resolution) => {
+                write!(f, "Unsupported resolution {}", resolution)
+            }
+            EntsoeParseError::UnsupportedUnit(unit) => write!(f, "Unsupported price unit {}", unit),
+            EntsoeParseError::MissingPosition(position) => {
+                write!(f, "No price for position {}", position)
+            }
+            EntsoeParseError::NoData(reason) => write!(f, "No prices published: {}", reason),
+        }
+    }
+}
+
+impl Error for EntsoeParseError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use pretty_assertions::assert_eq;
+    use std::fs;
+
+    fn markup() -> PriceMarkupConfig {
+        PriceMarkupConfig {
+            vat_rate: 0.21,
+            sourcing_markup_price: 0.017,
+            energy_tax_price: 0.12599,
+        }
+    }
+
+    #[test]
+    fn parse_entsoe_day_ahead_reads_hourly_prices() -> Result<(), Box<dyn Error>> {
+        let xml = fs::read_to_string("entsoe_day_ahead.xml")?;
+
+        // act
+        let spot_prices = parse_entsoe_day_ahead(&xml, markup())?;
+
+        // the clocks moved forward that night, so the day has 23 hours
+        assert_eq!(spot_prices.len(), 23);
+        assert_eq!(
+            spot_prices[0].from,
+            Utc.with_ymd_and_hms(2023, 3, 25, 23, 0, 0).unwrap()
+        );
+        assert_eq!(
+            spot_prices[0].till,
+            Utc.with_ymd_and_hms(2023, 3, 26, 0, 0, 0).unwrap()
+        );
+        assert_eq!(
+            spot_prices[22].till,
+            Utc.with_ymd_and_hms(2023, 3, 26, 22, 0, 0).unwrap()
+        );
+        assert!((spot_prices[0].market_price - 0.09812).abs() < 1e-12);
+        assert!((spot_prices[0].market_price_tax - 0.0206052).abs() < 1e-12);
+        assert_eq!(spot_prices[0].sourcing_markup_price, 0.017);
+        assert_eq!(spot_prices[0].energy_tax_price, 0.12599);
+        assert_eq!(spot_prices[0].currency.as_deref(), Some("EUR"));
+        assert_eq!(SpotPrice::normalize(spot_prices.clone())?, spot_prices);
+        Ok(())
+    }
+
+    #[test]
+    fn parse_entsoe_day_ahead_repeats_price_for_left_out_position() -> Result<(), Box<dyn Error>> {
+        let xml = fs::read_to_string("entsoe_day_ahead.xml")?;
+
+        // act
+        let spot_prices = parse_entsoe_day_ahead(&xml, markup())?;
+
+        assert!((spot_prices[3].market_price - 0.0854).abs() < 1e-12);
+        assert!((spot_prices[4].market_price - 0.0854).abs() < 1e-12);
+        assert_eq!(
+            spot_prices[4].from,
+            Utc.with_ymd_and_hms(2023, 3, 26, 3, 0, 0).unwrap()
+        );
+        Ok(())
+    }
+
+    fn quarter_hour_document(points: &str) -> String {
+        format!(
+            r#"<Publication_MarketDocument xmlns="urn:iec62325.351:tc57wg16:451-3:publicationdocument:7:3">
+    <TimeSeries>
+        <currency_Unit.name>EUR</currency_Unit.name>
+        <price_Measure_Unit.name>MWH</price_Measure_Unit.name>
+        <curveType>A03</curveType>
+        <Period>
+            <timeInterval>
+                <start>2025-10-01T22:00Z</start>
+                <end>2025-10-01T23:00Z</end>
+            </timeInterval>
+            <resolution>PT15M</resolution>
+            {}
+        </Period>
+    </TimeSeries>
+</Publication_MarketDocument>"#,
+            points
+        )
+    }
+
+    #[test]
+    fn parse_entsoe_day_ahead_reads_quarter_hour_prices() -> Result<(), Box<dyn Error>> {
+        let xml = quarter_hour_document(
+            "<Point><position>1</position><price.amount>-4.5</price.amount></Point>
+            <Point><position>3</position><price.amount>12</price.amount></Point>",
+        );
+
+        // act
+        let spot_prices = parse_entsoe_day_ahead(&xml, PriceMarkupConfig::default())?;
+
+        let from = Utc.with_ymd_and_hms(2025, 10, 1, 22, 0, 0).unwrap();
+        assert_eq!(spot_prices.len(), 4);
+        for (i, (spot_price, market_price)) in spot_prices
+            .iter()
+            .zip([-0.0045, -0.0045, 0.012, 0.012])
+            .enumerate()
+        {
+            assert_eq!(spot_price.from, from + Duration::minutes(15 * i as i64));
+            assert!((spot_price.market_price - market_price).abs() < 1e-12);
+        }
+        assert_eq!(spot_prices[3].till, from + Duration::hours(1));
+        Ok(())
+    }
+
+    #[test]
+    fn parse_entsoe_day_ahead_returns_error_without_first_position() {
+        let xml = quarter_hour_document(
+            "<Point><position>2</position><price.amount>12</price.amount></Point>",
+        );
+
+        // act
+        let result = parse_entsoe_day_ahead(&xml, PriceMarkupConfig::default());
+
+        assert_eq!(result, Err(EntsoeParseError::MissingPosition(1)));
+    }
+
+    #[test]
+    fn parse_entsoe_day_ahead_returns_error_for_unsupported_resolution() {
+        let xml = quarter_hour_document("").replace("PT15M", "P1D");
+
+        // act
+        let result = parse_entsoe_day_ahead(&xml, PriceMarkupConfig::default());
+
+        assert_eq!(
+            result,
+            Err(EntsoeParseError::UnsupportedResolution("P1D".to_string()))
+        );
+    }
+
+    #[test]
+    fn parse_entsoe_day_ahead_returns_error_for_malformed_xml() {
+        let xml = quarter_hour_document("<Point><position>1</position></Period>");
+
+        // act
+        let result = parse_entsoe_day_ahead(&xml, PriceMarkupConfig::default());
+
+        assert!(matches!(result, Err(EntsoeParseError::InvalidXml(_))));
+    }
+
+    #[test]
+    fn parse_entsoe_day_ahead_reads_commented_and_cdata_values() -> Result<(), Box<dyn Error>> {
+        let xml = quarter_hour_document(
+            "<!-- <Point><position>1</position><price.amount>999</price.amount></Point> -->
+            <Point><position>1</position><price.amount><![CDATA[ 12 ]]></price.amount></Point>",
+        );
+
+        // act
+        let spot_prices = parse_entsoe_day_ahead(&xml, PriceMarkupConfig::default())?;
+
+        assert_eq!(spot_prices.len(), 4);
+        assert!((spot_prices[0].market_price - 0.012).abs() < 1e-12);
+        Ok(())
+    }
+
+    #[test]
+    fn parse_entsoe_day_ahead_returns_error_for_acknowledgement() {
+        let xml = r#"<Acknowledgement_MarketDocument xmlns="urn:iec62325.351:tc57wg16:451-1:acknowledgementdocument:7:0">
+    <mRID>a7c2f1e0-6f4d-4c1b-9b4e-2d8f0c3a1b5e</mRID>
+    <Reason>
+        <code>999</code>
+        <text>No matching data found for Data item Day-ahead Prices [12.1.D]</text>
+    </Reason>
+</Acknowledgement_MarketDocument>"#;
+
+        // act
+        let result = parse_entsoe_day_ahead(xml, PriceMarkupConfig::default());
+
+        assert_eq!(
+            result,
+            Err(EntsoeParseError::NoData(
+                "No matching data found for Data item Day-ahead Prices [12.1.D]".to_string()
+            ))
+        );
+    }
+}
@@ -0,0 +1,264 @@
+use super::*;
+use crate::model::{
+    LoadProfile, LoadProfileSection, PlanningRequest, PlanningResponse, PlanningStrategy,
+    SpotPricePlanner, SpotPricePlannerConfig,
+};
+use chrono::TimeZone;
+use pretty_assertions::assert_eq;
+
+fn from() -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2023, 7, 2, 10, 0, 0).unwrap()
+}
+
+/// Hourly spot prices with market_prices and fixed taxes of taxes per kWh.
+fn hourly_spot_prices(market_prices: &[f64], taxes: f64) -> Vec<SpotPrice> {
+    market_prices
+        .iter()
+        .enumerate()
+        .map(|(i, market_price)| {
+            SpotPrice::new(
+                from() + Duration::hours(i as i64),
+                from() + Duration::hours(i as i64 + 1),
+                *market_price,
+            )
+            .id(None)
+            .energy_tax_price(taxes)
+            .build()
+            .unwrap()
+        })
+        .collect()
+}
+
+/// Midday curve dipping below zero.
+const DIPPING_MARKET_PRICES: [f64; 6] = [0.10, 0.02, -0.05, -0.12, -0.03, 0.08];
+
+fn one_kilowatt_load_profile(hours: i64) -> LoadProfile {
+    LoadProfile {
+        sections: vec![LoadProfileSection {
+            duration_seconds: hours * 3600,
+            power_draw_watt: 1000.0,
+            end_power_draw_watt: None,
+            id: None,
+        }],
+    }
+}
+
+fn plan(request: PlanningRequest) -> Result<PlanningResponse, Box<dyn Error>> {
+    SpotPricePlanner::new(SpotPricePlannerConfig::default())
+        .get_best_spot_prices(&request)?
+        .into_planned()
+        .ok_or_else(|| Box::<dyn Error>::from("not plannable"))
+}
+
+#[test]
+fn total_price_is_negative_zero_or_positive_depending_on_taxes() {
+    let spot_prices = hourly_spot_prices(&[-0.20, -0.098, -0.05], 0.098);
+
+    assert!((spot_prices[0].total_price() + 0.102).abs() < 1e-12);
+    assert!(spot_prices[1].total_price().abs() < 1e-12);
+    assert!((spot_prices[2].total_price() - 0.048).abs() < 1e-12);
+    assert!(spot_prices[0].is_negative_market_price());
+}
+
+#[test]
+fn total_price_incl_vat_adds_no_vat_over_negative_market_price() {
+    let spot_price = &hourly_spot_prices(&[-0.20], 0.10)[0];
+
+    // act
+    let total_price = spot_price.total_price_incl_vat(0.21);
+
+    // -0.20 + 0.10 + 0.21 * 0.10
+    assert!((total_price + 0.079).abs() < 1e-12);
+}
+
+#[test]
+fn lowest_price_plans_most_negative_block() -> Result<(), Box<dyn Error>> {
+    // act
+    let response = plan(PlanningRequest {
+        spot_prices: hourly_spot_prices(&DIPPING_MARKET_PRICES, 0.098),
+        load_profile: one_kilowatt_load_profile(2),
+        ..PlanningRequest::default()
+    })?;
+
+    assert_eq!(response.planned_from, Some(from() + Duration::hours(2)));
+    // (-0.05 + 0.098) + (-0.12 + 0.098)
+    assert!((response.total_price(None) - 0.026).abs() < 1e-9);
+    Ok(())
+}
+
+#[test]
+fn lowest_price_plans_block_with_negative_total_price() -> Result<(), Box<dyn Error>> {
+    // act
+    let response = plan(PlanningRequest {
+        spot_prices: hourly_spot_prices(&DIPPING_MARKET_PRICES, 0.0),
+        load_profile: one_kilowatt_load_profile(2),
+        ..PlanningRequest::default()
+    })?;
+
+    assert_eq!(response.planned_from, Some(from() + Duration::hours(2)));
+    assert!((response.total_price(None) + 0.17).abs() < 1e-9);
+    assert!(
+        (response
+            .average_price_per_kwh(PriceComponents::default())
+            .unwrap()
+            + 0.085)
+            .abs()
+            < 1e-9
+    );
+    Ok(())
+}
+
+#[test]
+fn highest_price_plans_least_negative_block() -> Result<(), Box<dyn Error>> {
+    // act
+    let response = plan(PlanningRequest {
+        spot_prices: hourly_spot_prices(&[-0.30, -0.10, -0.02, -0.25], 0.0),
+        load_profile: one_kilowatt_load_profile(1),
+        planning_strategy: PlanningStrategy::HighestPrice,
+        ..PlanningRequest::default()
+    })?;
+
+    assert_eq!(response.planned_from, Some(from() + Duration::hours(2)));
+    Ok(())
+}
+
+#[test]
+fn non_consecutive_strategy_plans_most_negative_spot_prices() -> Result<(), Box<dyn Error>> {
+    // act
+    let response = plan(PlanningRequest {
+        spot_prices: hourly_spot_prices(&[-0.30, 0.10, -0.02, -0.25], 0.05),
+        load_profile: one_kilowatt_load_profile(2),
+        planning_strategy: PlanningStrategy::LowestPriceNonConsecutive,
+        ..PlanningRequest::default()
+    })?;
+
+    assert_eq!(
+        response
+            .spot_prices
+            .iter()
+            .map(|spot_price| spot_price.from)
+            .collect::<Vec<_>>(),
+        vec![from(), from() + Duration::hours(3)]
+    );
+    Ok(())
+}
+
+#[test]
+fn percentile_strategy_cuts_off_within_negative_prices() -> Result<(), Box<dyn Error>> {
+    // act
+    let response = plan(PlanningRequest {
+        spot_prices: hourly_spot_prices(&DIPPING_MARKET_PRICES, 0.098),
+        load_profile: one_kilowatt_load_profile(1),
+        planning_strategy: PlanningStrategy::LowestPricePercentile { percentile: 25.0 },
+        ..PlanningRequest::default()
+    })?;
+
+    assert_eq!(response.planned_from, Some(from() + Duration::hours(3)));
+    assert!(!response.percentile_fallback_used);
+    Ok(())
+}
+
+#[test]
+fn median_multiplier_only_drops_spikes_above_negative_median() -> Result<(), Box<dyn Error>> {
+    // act
+    let response = plan(PlanningRequest {
+        spot_prices: hourly_spot_prices(&[-0.30, -0.25, -0.20, -0.15, 0.40, -0.28], 0.0),
+        load_profile: one_kilowatt_load_profile(2),
+        max_price_median_multiplier: Some(2.0),
+        planning_strategy: PlanningStrategy::LowestPriceNonConsecutive,
+        ..PlanningRequest::default()
+    })?;
+
+    // the median is -0.225, so only the 0.40 spike lies above the cut-off of 0
+    assert!(!response.spike_fallback_used);
+    assert_eq!(
+        response
+            .spot_prices
+            .iter()
+            .map(|spot_price| spot_price.from)
+            .collect::<Vec<_>>(),
+        vec![from(), from() + Duration::hours(5)]
+    );
+    Ok(())
+}
+
+#[test]
+fn evaluate_plan_reports_positive_savings_against_negative_reference_cost(
+) -> Result<(), Box<dyn Error>> {
+    let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig::default());
+    let request = PlanningRequest {
+        spot_prices: hourly_spot_prices(&DIPPING_MARKET_PRICES, 0.0),
+        load_profile: one_kilowatt_load_profile(2),
+        ..PlanningRequest::default()
+    };
+    let response = plan(request.clone())?;
+
+    // act
+    let evaluation =
+        spot_price_planner.evaluate_plan(&request, &response, from() + Duration::hours(3));
+
+    // the reference run costs -0.12 - 0.03 = -0.15, the plan -0.17
+    assert!((evaluation.reference_cost.unwrap() + 0.15).abs() < 1e-9);
+    assert!((evaluation.savings_vs_reference.unwrap() - 0.02).abs() < 1e-9);
+    assert!((evaluation.savings_vs_reference_percentage.unwrap() - 13.333333333).abs() < 1e-6);
+    Ok(())
+}
+
+#[test]
+fn cheapest_window_without_duration_is_none() -> Result<(), Box<dyn Error>> {
+    let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig::default());
+
+    // act
+    let window = spot_price_planner.cheapest_window(
+        &hourly_spot_prices(&DIPPING_MARKET_PRICES, 0.0),
+        Duration::zero(),
+        None,
+        None,
+    )?;
+
+    assert!(window.is_none());
+    Ok(())
+}
+
+#[test]
+fn cheapest_window_averages_negative_prices() -> Result<(), Box<dyn Error>> {
+    let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig::default());
+
+    // act
+    let window = spot_price_planner
+        .cheapest_window(
+            &hourly_spot_prices(&DIPPING_MARKET_PRICES, 0.0),
+            Duration::hours(3),
+            None,
+            None,
+        )?
+        .unwrap();
+
+    assert_eq!(window.from, from() + Duration::hours(2));
+    assert!((window.average_price_per_kwh + 0.2 / 3.0).abs() < 1e-9);
+    Ok(())
+}
+
+#[test]
+fn stats_handle_prices_summing_to_zero() {
+    let spot_prices = hourly_spot_prices(&[-0.10, 0.10, -0.10, 0.10], 0.0);
+
+    // act
+    let stats = stats(&spot_prices, &PriceComponents::default());
+
+    assert_eq!(stats.mean, 0.0);
+    assert_eq!(stats.median, 0.0);
+    assert!((stats.standard_deviation - 0.1).abs() < 1e-12);
+    assert_eq!(stats.min, -0.10);
+    assert_eq!(stats.min_from, Some(from()));
+}
+
+#[test]
+fn merge_adjacent_keeps_negative_and_positive_prices_apart() {
+    let spot_prices = hourly_spot_prices(&[-0.05, 0.05], 0.0);
+
+    // act
+    let merged = SpotPrice::merge_adjacent(spot_prices.clone(), 0.01);
+
+    assert_eq!(merged, spot_prices);
+}
@@ -0,0 +1,379 @@
+use super::{PriceComponents, PriceUnit, SpotPrice};
+use chrono::{DateTime, LocalResult, NaiveDateTime, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fmt;
+
+/// Prices per kWh added to day-ahead market prices, which price APIs publish without taxes or fees.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct PriceMarkupConfig {
+    /// VAT rate over the market price, like 0.21; fills market_price_tax.
+    pub vat_rate: f64,
+    pub sourcing_markup_price: f64,
+    pub energy_tax_price: f64,
+}
+
+/// Sets the taxes and fees of prices from markup.
+pub fn apply_markup(prices: &mut [SpotPrice], markup: PriceMarkupConfig) {
+    let vat_components = PriceComponents {
+        market_price: true,
+        market_price_tax: false,
+        sourcing_markup_price: false,
+        energy_tax_price: false,
+        excluded_extra_components: vec![],
+    };
+    for price in prices.iter_mut() {
+        price.sourcing_markup_price = markup.sourcing_markup_price;
+        price.energy_tax_price = markup.energy_tax_price;
+        *price = price.with_vat(markup.vat_rate, &vat_components);
+    }
+}
+
+#[derive(Deserialize)]
+struct AwattarResponse {
+    data: Vec<AwattarPrice>,
+}
+
+#[derive(Deserialize)]
+struct AwattarPrice {
+    start_timestamp: i64,
+    end_timestamp: i64,
+    marketprice: f64,
+    unit: String,
+}
+
+/// Spot prices per kWh from an aWATTar marketdata response, with taxes and fees left at 0.
+pub fn from_awattar(json: &str) -> Result<Vec<SpotPrice>, ProviderParseError> {
+    let response: AwattarResponse = serde_json::from_str(json)
+        .map_err(|error| ProviderParseError::InvalidJson(error.to_string()))?;
+
+    response
+        .data
+        .into_iter()
+        .map(|price| {
+            let (currency, per_kwh_factor) = parse_unit(&price.unit)?;
+            Ok(SpotPrice {
+                id: None,
+                source: Some("awattar".to_string()),
+                from: from_timestamp_millis(price.start_timestamp)?,
+                till: from_timestamp_millis(price.end_timestamp)?,
+                market_price: price.marketprice * per_kwh_factor,
+                market_price_tax: 0.0,
+                sourcing_markup_price: 0.0,
+                energy_tax_price: 0.0,
+                extra_components: None,
+                currency: Some(currency),
+                unit: Some(PriceUnit::EurPerKwh),
+                merged_slot_seconds: None,
+            })
+        })
+        .collect()
+}
+
+#[derive(Deserialize)]
+struct NordpoolResponse {
+    data: NordpoolData,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct NordpoolData {
+    rows: Vec<NordpoolRow>,
+    units: Vec<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct NordpoolRow {
+    start_time: NaiveDateTime,
+    end_time: NaiveDateTime,
+    #[serde(default)]
+    is_extra_row: bool,
+    columns: Vec<NordpoolColumn>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct NordpoolColumn {
+    name: String,
+    value: String,
+}
+
+/// Spot prices per kWh for area from a Nord Pool day-ahead response, with taxes and fees left at 0. Nord Pool reports
+/// times as CET wall-clock time, so on the night the clocks go back the repeated hour is told apart by its order;
+/// hours without a price, like the one skipped when the clocks go forward, are left out.
+pub fn from_nordpool(json: &str, area: &str) -> Result<Vec<SpotPrice>, ProviderParseError> {
+    let response: NordpoolResponse = serde_json::from_str(json)
+        .map_err(|error| ProviderParseError::InvalidJson(error.to_string()))?;
+    let unit = response
+        .data
+        .units
+        .first()
+        .ok_or_else(|| ProviderParseError::UnsupportedUnit(String::new()))?;
+    let (currency, per_kwh_factor) = parse_unit(unit)?;
+
+    let mut spot_prices: Vec<SpotPrice> = vec![];
+    for row in response.data.rows.iter().filter(|row| !row.is_extra_row) {
+        let value = &row
+            .columns
+            .iter()
+            .find(|column| column.name == area)
+            .ok_or_else(|| ProviderParseError::UnknownArea(area.to_string()))?
+            .value;
+        let market_price = match parse_nordpool_value(value) {
+            Some(market_price) => market_price,
+            None => continue,
+        };
+
+        let previous_from = spot_prices.last().map(|spot_price| spot_price.from);
+        let from = match cet_to_utc(row.start_time, previous_from) {
+            Some(from) => from,
+            None => continue,
+        };
+
+        spot_prices.push(SpotPrice {
+            id: None,
+            source: Some("nordpool".to_string()),
+            from,
+            // rows span whole hours, so their wall-clock length is their actual length
+            till: from + (row.end_time - row.start_time),
+            market_price: market_price * per_kwh_factor,
+            market_price_tax: 0.0,
+            sourcing_markup_price: 0.0,
+            energy_tax_price: 0.0,
+            extra_components: None,
+            currency: Some(currency.clone()),
+            unit: Some(PriceUnit::EurPerKwh),
+            merged_slot_seconds: None,
+        });
+    }
+
+    Ok(spot_prices)
+}
+
+/// The moment of CET wall-clock time local; of a repeated hour the first one after after.
+fn cet_to_utc(local: NaiveDateTime, after: Option<DateTime<Utc>>) -> Option<DateTime<Utc>> {
+    match chrono_tz::CET.from_local_datetime(&local) {
+        LocalResult::Single(time) => Some(time.with_timezone(&Utc)),
+        LocalResult::Ambiguous(earliest, latest) => {
+            let earliest = earliest.with_timezone(&Utc);
+            if after.is_some_and(|after| earliest <= after) {
+                Some(latest.with_timezone(&Utc))
+            } else {
+                Some(earliest)
+            }
+        }
+        LocalResult::None => None,
+    }
+}
+
+/// Nord Pool values use a decimal comma and spaces between thousands, like 1 234,56; missing values are a dash.
+fn parse_nordpool_value(value: &str) -> Option<f64> {
+    value
+        .replace([' ', '\u{a0}'], "")
+        .replace(',', ".")
+        .parse()
+        .ok()
+}
+
+/// Currency and factor to get prices per kWh for units like EUR/MWh.
+fn parse_unit(unit: &str) -> Result<(String, f64), ProviderParseError> {
+    match unit.split_once('/') {
+        Some((currency, energy_unit)) if energy_unit.eq_ignore_ascii_case("mwh") => Ok((
+            currency.to_uppercase(),
+            PriceUnit::EurPerMwh.per_kwh_factor(),
+        )),
+        Some((currency, energy_unit)) if energy_unit.eq_ignore_ascii_case("kwh") => Ok((
+            currency.to_uppercase(),
+            PriceUnit::EurPerKwh.per_kwh_factor(),
+        )),
+        _ => Err(ProviderParseError::UnsupportedUnit(unit.to_string())),
+    }
+}
+
+fn from_timestamp_millis(timestamp: i64) -> Result<DateTime<Utc>, ProviderParseError> {
+    DateTime::from_timestamp_millis(timestamp).ok_or_else(|| ProviderParseError::InvalidValue {
+        field: "timestamp".to_string(),
+        value: timestamp.to_string(),
+    })
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ProviderParseError {
+    InvalidJson(String),
+    /// A price unit other than per MWh or kWh.
+    UnsupportedUnit(String),
+    /// An area without prices in the response.
+    UnknownArea(String),
+    InvalidValue {
+        field: String,
+        value: String,
+    },
+}
+
+impl fmt::Display for ProviderParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProviderParseError::InvalidJson(error) => write!(f, "Invalid json: {}", error),
+            ProviderParseError::UnsupportedUnit(unit) => {
+                write!(f, "Unsupported price unit {}", unit)
+            }
+            ProviderParseError::UnknownArea(area) => write!(f, "No prices for area {}", area),
+            ProviderParseError::InvalidValue { field, value } => {
+                write!(f, "Invalid value {} for {}", value, field)
+            }
+        }
+    }
+}
+
+impl Error for ProviderParseError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use pretty_assertions::assert_eq;
+    use std::fs;
+
+    #[test]
+    fn from_awattar_reads_hourly_prices() -> Result<(), Box<dyn Error>> {
+        let json = fs::read_to_string("awattar_marketdata.json")?;
+
+        // act
+        let spot_prices = from_awattar(&json)?;
+
+        // the clocks moved forward that night, so the day has 23 hours
+        assert_eq!(spot_prices.len(), 23);
+        let first = &spot_prices[0];
+        assert_eq!(
+            first.from,
+            Utc.with_ymd_and_hms(2023, 3, 25, 23, 0, 0).unwrap()
+        );
+        assert_eq!(
+            first.till,
+            Utc.with_ymd_and_hms(2023, 3, 26, 0, 0, 0).unwrap()
+        );
+        assert!((first.market_price - 0.10497).abs() < 1e-12);
+        assert_eq!(first.market_price_tax, 0.0);
+        assert_eq!(first.source.as_deref(), Some("awattar"));
+        assert_eq!(first.currency.as_deref(), Some("EUR"));
+        let last = &spot_prices[22];
+        assert_eq!(
+            last.from,
+            Utc.with_ymd_and_hms(2023, 3, 26, 21, 0, 0).unwrap()
+        );
+        assert_eq!(
+            last.till,
+            Utc.with_ymd_and_hms(2023, 3, 26, 22, 0, 0).unwrap()
+        );
+        assert!((last.market_price - 0.10802).abs() < 1e-12);
+        Ok(())
+    }
+
+    #[test]
+    fn from_nordpool_reads_cet_hours_across_end_of_daylight_saving_time(
+    ) -> Result<(), Box<dyn Error>> {
+        let json = fs::read_to_string("nordpool_dayahead.json")?;
+
+        // act
+        let spot_prices = from_nordpool(&json, "NL")?;
+
+        // the clocks went back that night, so the day has 25 hours and the extra rows are left out
+        assert_eq!(spot_prices.len(), 25);
+        let first = &spot_prices[0];
+        assert_eq!(
+            first.from,
+            Utc.with_ymd_and_hms(2022, 10, 29, 22, 0, 0).unwrap()
+        );
+        assert_eq!(
+            first.till,
+            Utc.with_ymd_and_hms(2022, 10, 29, 23, 0, 0).unwrap()
+        );
+        assert!((first.market_price - 0.14215).abs() < 1e-12);
+        assert_eq!(first.source.as_deref(), Some("nordpool"));
+        assert_eq!(
+            spot_prices[2].from,
+            Utc.with_ymd_and_hms(2022, 10, 30, 0, 0, 0).unwrap()
+        );
+        assert_eq!(
+            spot_prices[3].from,
+            Utc.with_ymd_and_hms(2022, 10, 30, 1, 0, 0).unwrap()
+        );
+        let last = &spot_prices[24];
+        assert_eq!(
+            last.from,
+            Utc.with_ymd_and_hms(2022, 10, 30, 22, 0, 0).unwrap()
+        );
+        assert_eq!(
+            last.till,
+            Utc.with_ymd_and_hms(2022, 10, 30, 23, 0, 0).unwrap()
+        );
+        assert!((last.market_price - 0.12006).abs() < 1e-12);
+        assert!(spot_prices
+            .iter()
+            .all(|spot_price| spot_price.till - spot_price.from == Duration::hours(1)));
+        Ok(())
+    }
+
+    #[test]
+    fn from_nordpool_returns_error_for_unknown_area() -> Result<(), Box<dyn Error>> {
+        let json = fs::read_to_string("nordpool_dayahead.json")?;
+
+        // act
+        let result = from_nordpool(&json, "DE-LU");
+
+        assert_eq!(
+            result,
+            Err(ProviderParseError::UnknownArea("DE-LU".to_string()))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn from_nordpool_leaves_out_hour_skipped_when_clocks_go_forward() -> Result<(), Box<dyn Error>>
+    {
+        let json = r#"{"data": {"Units": ["EUR/MWh"], "Rows": [
+            {"StartTime": "2023-03-26T01:00:00", "EndTime": "2023-03-26T02:00:00", "IsExtraRow": false,
+             "Columns": [{"Name": "NL", "Value": "1 012,50"}]},
+            {"StartTime": "2023-03-26T02:00:00", "EndTime": "2023-03-26T03:00:00", "IsExtraRow": false,
+             "Columns": [{"Name": "NL", "Value": "-"}]},
+            {"StartTime": "2023-03-26T03:00:00", "EndTime": "2023-03-26T04:00:00", "IsExtraRow": false,
+             "Columns": [{"Name": "NL", "Value": "-3,25"}]}
+        ]}}"#;
+
+        // act
+        let spot_prices = from_nordpool(json, "NL")?;
+
+        assert_eq!(spot_prices.len(), 2);
+        assert_eq!(spot_prices[0].till, spot_prices[1].from);
+        assert_eq!(
+            spot_prices[1].from,
+            Utc.with_ymd_and_hms(2023, 3, 26, 1, 0, 0).unwrap()
+        );
+        assert!((spot_prices[0].market_price - 1.0125).abs() < 1e-12);
+        assert!((spot_prices[1].market_price + 0.00325).abs() < 1e-12);
+        Ok(())
+    }
+
+    #[test]
+    fn apply_markup_sets_taxes_and_fees() -> Result<(), Box<dyn Error>> {
+        let json = fs::read_to_string("awattar_marketdata.json")?;
+        let mut spot_prices = from_awattar(&json)?;
+
+        // act
+        apply_markup(
+            &mut spot_prices,
+            PriceMarkupConfig {
+                vat_rate: 0.21,
+                sourcing_markup_price: 0.017,
+                energy_tax_price: 0.12599,
+            },
+        );
+
+        assert!((spot_prices[0].market_price_tax - 0.0220437).abs() < 1e-12);
+        assert_eq!(spot_prices[0].sourcing_markup_price, 0.017);
+        assert_eq!(spot_prices[0].energy_tax_price, 0.12599);
+        Ok(())
+    }
+}
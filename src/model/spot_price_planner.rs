@@ -1,19 +1,73 @@
+use crate::config_client::Migrate;
 use crate::model::spot_price::*;
+use crate::model::{joules_to_kwh, JOULES_PER_KILOWATT_HOUR};
 use chrono::prelude::*;
 use chrono::{naive::NaiveTime, DateTime, Duration, Utc, Weekday};
 use chrono_tz::Tz;
-use serde::{Deserialize, Serialize};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::borrow::Cow;
+use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::error::Error;
-use tracing::{debug, info};
+use std::fmt;
+use std::ops::Range;
+use tracing::{debug, info, warn};
 
-#[derive(Copy, Clone, PartialEq, Eq, Serialize, Deserialize, Debug)]
+/// Serialized in camelCase; the PascalCase LowestPrice and HighestPrice from before are still accepted for one more
+/// release.
+#[derive(Copy, Clone, PartialEq, Serialize, Deserialize, Debug, Default)]
 pub enum PlanningStrategy {
+    #[default]
+    #[serde(rename = "lowestPrice", alias = "LowestPrice")]
     LowestPrice,
+    #[serde(rename = "highestPrice", alias = "HighestPrice")]
     HighestPrice,
+    /// Cheapest set of spot prices covering the load, not necessarily adjacent; for loads that can pause and resume.
+    #[serde(rename = "lowestPriceNonConsecutive")]
+    LowestPriceNonConsecutive,
+    /// Most expensive set of spot prices covering the load, not necessarily adjacent.
+    #[serde(rename = "highestPriceNonConsecutive")]
+    HighestPriceNonConsecutive,
+    /// Cheapest consecutive block in which every spot price has a negative market price; nothing is planned otherwise.
+    #[serde(rename = "onlyNegativePrices")]
+    OnlyNegativePrices,
+    /// Cheapest spot prices at or below the given percentile (0-100) of the plannable prices, not necessarily adjacent;
+    /// falls back to the cheapest consecutive block when they don't cover the load.
+    #[serde(rename = "lowestPricePercentile")]
+    LowestPricePercentile { percentile: f64 },
+    /// Most expensive spot prices within the given top percentage (0-100) of the plannable prices, not necessarily
+    /// adjacent; falls back to the most expensive consecutive block when they don't cover the load.
+    #[serde(rename = "highestPricePercentile")]
+    HighestPricePercentile { percentile: f64 },
+    /// Consecutive block in which the expected production covers most of the load's energy; equally covered
+    /// blocks are ranked on lowest price.
+    #[serde(rename = "maximizeSelfConsumption")]
+    MaximizeSelfConsumption,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+/// Which of several equally priced candidate blocks gets planned.
+#[derive(Copy, Clone, PartialEq, Eq, Serialize, Deserialize, Debug, Default)]
+pub enum TieBreaker {
+    #[default]
+    #[serde(rename = "earliestStart")]
+    EarliestStart,
+    #[serde(rename = "latestStart")]
+    LatestStart,
+}
+
+impl PlanningStrategy {
+    /// Whether the strategy looks for the most expensive spot prices rather than the cheapest.
+    pub fn prefers_highest(&self) -> bool {
+        matches!(
+            self,
+            PlanningStrategy::HighestPrice
+                | PlanningStrategy::HighestPriceNonConsecutive
+                | PlanningStrategy::HighestPricePercentile { .. }
+        )
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 #[serde(rename_all = "camelCase")]
 pub struct LoadProfile {
     pub sections: Vec<LoadProfileSection>,
@@ -23,6 +77,26 @@ impl LoadProfile {
     pub fn total_duration_seconds(&self) -> i64 {
         self.sections.iter().map(|s| s.duration_seconds).sum()
     }
+
+    pub fn total_energy_kwh(&self) -> f64 {
+        joules_to_kwh(
+            self.sections
+                .iter()
+                .map(|s| s.total_power_draw_watt_seconds())
+                .sum::<f64>(),
+        )
+    }
+
+    /// Highest power draw of any section, including the end of ramps.
+    pub fn peak_power_draw_watt(&self) -> f64 {
+        self.sections
+            .iter()
+            .map(|s| {
+                s.end_power_draw_watt
+                    .map_or(s.power_draw_watt, |end| end.max(s.power_draw_watt))
+            })
+            .fold(0.0, f64::max)
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -30,1443 +104,9433 @@ impl LoadProfile {
 pub struct LoadProfileSection {
     pub duration_seconds: i64,
     pub power_draw_watt: f64,
+    /// When set the draw ramps linearly from power_draw_watt to this value over the section's duration.
+    #[serde(default)]
+    pub end_power_draw_watt: Option<f64>,
+    /// Names the section in per-section plans; defaults to its index.
+    #[serde(default)]
+    pub id: Option<String>,
 }
 
 impl LoadProfileSection {
     pub fn total_power_draw_watt_seconds(&self) -> f64 {
-        self.duration_seconds as f64 * self.power_draw_watt
+        self.watt_seconds_between(0, self.duration_seconds)
+    }
+
+    /// Power draw in watt at seconds after the start of the section.
+    fn power_draw_watt_at(&self, seconds: i64) -> f64 {
+        match self.end_power_draw_watt {
+            Some(end_power_draw_watt) if self.duration_seconds > 0 => {
+                self.power_draw_watt
+                    + (end_power_draw_watt - self.power_draw_watt) * seconds as f64
+                        / self.duration_seconds as f64
+            }
+            _ => self.power_draw_watt,
+        }
+    }
+
+    /// Energy in watt-seconds drawn between seconds from and till after the start of the section; exact for
+    /// linear ramps since the trapezoid rule integrates them without error.
+    fn watt_seconds_between(&self, from: i64, till: i64) -> f64 {
+        (till - from) as f64 * (self.power_draw_watt_at(from) + self.power_draw_watt_at(till)) / 2.0
     }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+/// Forces a plan once the deadline gets close, so a load that never met the price threshold still runs in time.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DeadlinePolicy {
+    /// Moment the load has to be done by.
+    pub deadline: DateTime<Utc>,
+    /// Margin on top of the load's duration; once now is within duration plus margin of the deadline the price
+    /// threshold gets ignored.
+    #[serde(rename = "relaxAfterSeconds", with = "duration_seconds")]
+    pub relax_after: Duration,
+    /// Ignore the plannable time slots as well when forcing a plan.
+    #[serde(default)]
+    pub ignore_time_slots: bool,
+}
+
+impl DeadlinePolicy {
+    pub fn is_urgent(&self, now: DateTime<Utc>, load_profile: &LoadProfile) -> bool {
+        now + Duration::seconds(load_profile.total_duration_seconds()) + self.relax_after
+            >= self.deadline
+    }
+}
+
+mod duration_seconds {
+    use chrono::Duration;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_i64(duration.num_seconds())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        Ok(Duration::seconds(i64::deserialize(deserializer)?))
+    }
+}
+
+/// Field names are camelCase; the snake_case spot_prices, load_profile and planning_strategy from before are still
+/// accepted for one more release.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
 pub struct PlanningRequest {
+    #[serde(alias = "spot_prices")]
     pub spot_prices: Vec<SpotPrice>,
+    #[serde(alias = "load_profile")]
     pub load_profile: LoadProfile,
+    #[serde(alias = "planning_strategy")]
     pub planning_strategy: PlanningStrategy,
     pub after: Option<DateTime<Utc>>,
     pub before: Option<DateTime<Utc>>,
+    /// Blocks with a higher average price per kWh for the load are never planned; overrides the planner config.
+    #[serde(default)]
+    pub max_acceptable_total_price_per_kwh: Option<f64>,
+    /// Minimum length of each uninterrupted run for the non-consecutive strategies.
+    #[serde(default)]
+    pub min_block_duration_seconds: Option<i64>,
+    /// When no block fits inside the plannable time slots, plan ignoring them while still respecting after and before.
+    #[serde(default)]
+    pub enable_fallback: bool,
+    /// Price components to rank blocks on; overrides the planner config.
+    #[serde(default)]
+    pub price_components: Option<PriceComponents>,
+    /// The load never starts before this moment; unlike after it can lie within a spot price.
+    #[serde(default)]
+    pub start_after: Option<DateTime<Utc>>,
+    /// The load has to be done by this moment; unlike before the last spot price may extend past it.
+    #[serde(default)]
+    pub finish_by: Option<DateTime<Utc>>,
+    /// How to choose between equally priced blocks; overrides the planner config.
+    #[serde(default)]
+    pub tie_breaker: Option<TieBreaker>,
+    /// Power left for the load per spot price, keyed by its from; spot prices missing from the map are unconstrained.
+    #[serde(default)]
+    pub available_power_watt: HashMap<DateTime<Utc>, f64>,
+    /// Refuse to plan while the spot prices leave part of the plannable time between after and before uncovered.
+    #[serde(default)]
+    pub require_full_coverage: bool,
+    /// Maximum number of uninterrupted runs per local calendar day for the non-consecutive strategies.
+    #[serde(default)]
+    pub max_runs_per_local_day: Option<usize>,
+    /// Plan regardless of the price threshold once the deadline gets close.
+    #[serde(default)]
+    pub deadline_policy: Option<DeadlinePolicy>,
+    /// Forecast solar production per spot price, keyed by its from; spot prices missing from the map produce nothing.
+    #[serde(default)]
+    pub expected_production_watt: HashMap<DateTime<Utc>, f64>,
+    /// The present; spot prices before it are treated as if before after. Defaults to the planner's now, and when
+    /// neither is set nothing is dropped and the deadline policy uses the system clock.
+    #[serde(default)]
+    pub now: Option<DateTime<Utc>>,
+    /// Leave out spot prices costing more per kWh than this multiple of the median of the plannable spot prices; for a
+    /// zero or negative median, more than the median plus the multiple minus one times its size.
+    #[serde(default)]
+    pub max_price_median_multiplier: Option<f64>,
+    /// Minimum rest between two uninterrupted runs for the non-consecutive strategies and between sections that don't
+    /// directly follow each other.
+    #[serde(default)]
+    pub min_gap_between_runs_seconds: Option<i64>,
+    /// Report why every other candidate start was rejected in the outcome's explanation.
+    #[serde(default)]
+    pub explain: bool,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+/// Field names are camelCase; the snake_case spot_prices and load_profile from before are still accepted for one more
+/// release. Serializes with the derived totalEnergyKwh and averagePricePerKwh as well, which are ignored when
+/// deserializing.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
 pub struct PlanningResponse {
+    #[serde(alias = "spot_prices")]
     pub spot_prices: Vec<SpotPrice>,
+    #[serde(alias = "load_profile")]
     pub load_profile: LoadProfile,
+    /// Moment the load is planned to start; can lie within the first spot price.
+    #[serde(default)]
+    pub planned_from: Option<DateTime<Utc>>,
+    /// Moment the load is planned to finish.
+    #[serde(default)]
+    pub planned_till: Option<DateTime<Utc>>,
+    /// Periods the load actually runs in; more than one when the planned spot prices are not consecutive.
+    #[serde(default)]
+    pub segments: Vec<(DateTime<Utc>, DateTime<Utc>)>,
+    /// Seconds of the last spot price the load actually runs in; the rest of that slot is not paid for.
+    #[serde(default)]
+    pub last_slot_used_seconds: Option<i64>,
+    /// Set when the plannable time slots had to be ignored to find a block.
+    #[serde(default)]
+    pub fallback_used: bool,
+    /// Set when the spot prices within the percentile did not cover the load and a consecutive block was planned.
+    #[serde(default)]
+    pub percentile_fallback_used: bool,
+    /// Set when the deadline policy forced this plan, ignoring the price threshold.
+    #[serde(default)]
+    pub forced: bool,
+    /// Set when the load only fitted after adding back the cheapest of the spot prices above the median multiple.
+    #[serde(default)]
+    pub spike_fallback_used: bool,
+    /// Energy expected to come from own production; only set when the request has a production forecast.
+    #[serde(default)]
+    pub self_consumed_kwh: Option<f64>,
+    /// Energy expected to come from the grid; only set when the request has a production forecast.
+    #[serde(default)]
+    pub grid_kwh: Option<f64>,
+    /// Price components the block was ranked on; used by total_price and cost_breakdown.
+    #[serde(default)]
+    pub price_components: PriceComponents,
+    /// Why the other candidate starts were rejected; only filled when the request asks to explain.
+    #[serde(default)]
+    pub explanation: Vec<CandidateRejection>,
 }
 
-impl PlanningResponse {
-    pub fn total_price(&self, get_price_fn: Option<fn(&SpotPrice) -> f64>) -> f64 {
-        total_price_for_load(&self.spot_prices, &self.load_profile, get_price_fn)
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ChargeDischargeRequest {
+    pub spot_prices: Vec<SpotPrice>,
+    pub charge_load_profile: LoadProfile,
+    pub discharge_load_profile: LoadProfile,
+    /// Minimum profit per kWh to act on; the average discharge price after losses minus the average charge price.
+    #[serde(alias = "min_spread_per_kwh")]
+    pub min_price_spread_per_kwh: f64,
+    /// Round-trip efficiency between 0 and 1 applied to the discharge revenue; lossless when unset.
+    #[serde(default)]
+    pub efficiency: Option<f64>,
+    pub after: Option<DateTime<Utc>>,
+    pub before: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase", rename_all_fields = "camelCase")]
+pub enum ChargeDischargeResponse {
+    Windows {
+        charge: Box<PlanningResponse>,
+        discharge: Box<PlanningResponse>,
+        price_spread_per_kwh: f64,
+    },
+    /// The most profitable pair of windows doesn't exceed the minimum price spread.
+    BelowSpreadThreshold { best_price_spread_per_kwh: f64 },
+    /// No pair of windows fits in the plannable spot prices.
+    NoAction,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum NotPlannableReason {
+    /// No spot prices fall inside the plannable time slots and the after/before bounds.
+    NoPlannablePrices,
+    /// The plannable spot prices do not form a block long enough for the load.
+    NotEnoughConsecutiveDuration,
+    /// Blocks exist, but all of them exceed the maximum acceptable price.
+    PriceThresholdNotMet,
+}
+
+impl fmt::Display for NotPlannableReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NotPlannableReason::NoPlannablePrices => write!(f, "no plannable spot prices"),
+            NotPlannableReason::NotEnoughConsecutiveDuration => {
+                write!(f, "not enough consecutive duration for the load")
+            }
+            NotPlannableReason::PriceThresholdNotMet => {
+                write!(f, "no block below the maximum acceptable price")
+            }
+        }
     }
 }
 
-fn total_price_for_load(
-    spot_prices: &[SpotPrice],
-    load_profile: &LoadProfile,
-    get_price_fn: Option<fn(&SpotPrice) -> f64>,
-) -> f64 {
-    if !spot_prices.is_empty() && !load_profile.sections.is_empty() {
-        let total_required_seconds = load_profile.total_duration_seconds() as usize;
+/// Why a candidate start was not planned.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum RejectionReason {
+    /// The spot price lies outside the plannable time slots or the after/before bounds.
+    OutsideTimeSlots,
+    /// The load would start before start_after or finish after finish_by.
+    OutsideWindow,
+    /// The plannable spot prices from here on don't form a block long enough for the load.
+    InsufficientConsecutiveDuration,
+    /// The block costs more than the maximum acceptable price; the price delta is the excess.
+    AbovePriceThreshold,
+    /// The load would draw more than the available power in one of the spot prices.
+    ExceedsAvailablePower,
+    /// The block is acceptable but the planned one is better; the price delta is the difference with it.
+    LostOnPrice,
+}
 
-        let mut spot_price_per_second: Vec<f64> = vec![];
-        for spot_price in spot_prices {
-            let price_per_second =
-                get_price_fn.unwrap_or(|sp| sp.total_price())(spot_price) / (3600_f64 * 1000_f64);
+/// A candidate start that wasn't planned; one per spot price.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CandidateRejection {
+    pub from: DateTime<Utc>,
+    pub reason: RejectionReason,
+    #[serde(default)]
+    pub price_delta: Option<f64>,
+}
 
-            let seconds_still_needed = std::cmp::min(
-                spot_price.duration_seconds() as usize,
-                total_required_seconds - spot_price_per_second.len(),
-            );
-            spot_price_per_second.append(&mut vec![price_per_second; seconds_still_needed]);
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase", rename_all_fields = "camelCase")]
+pub enum PlanningOutcome {
+    Planned(PlanningResponse),
+    NotPlannable {
+        reason: NotPlannableReason,
+        /// Why each candidate start was rejected; only filled when the request asks to explain.
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        explanation: Vec<CandidateRejection>,
+    },
+    /// The spot prices don't reach into every plannable part of the requested window yet.
+    CoverageIncomplete {
+        uncovered_intervals: Vec<(DateTime<Utc>, DateTime<Utc>)>,
+    },
+}
+
+impl PlanningOutcome {
+    pub fn planned(&self) -> Option<&PlanningResponse> {
+        match self {
+            PlanningOutcome::Planned(response) => Some(response),
+            PlanningOutcome::NotPlannable { .. } | PlanningOutcome::CoverageIncomplete { .. } => {
+                None
+            }
         }
-        assert_eq!(spot_price_per_second.len(), total_required_seconds);
+    }
 
-        let mut power_draw_per_second: Vec<f64> = vec![];
-        for section in &load_profile.sections {
-            power_draw_per_second.append(&mut vec![
-                section.power_draw_watt;
-                section.duration_seconds as usize
-            ]);
+    /// Why the candidate starts that weren't planned got rejected, when the request asked to explain.
+    pub fn explanation(&self) -> &[CandidateRejection] {
+        match self {
+            PlanningOutcome::Planned(response) => &response.explanation,
+            PlanningOutcome::NotPlannable { explanation, .. } => explanation,
+            PlanningOutcome::CoverageIncomplete { .. } => &[],
         }
-        assert_eq!(power_draw_per_second.len(), total_required_seconds);
+    }
 
-        // dot product of each vector item
-        spot_price_per_second
-            .iter()
-            .zip(power_draw_per_second.iter())
-            .map(|(x, y)| x * y)
-            .sum()
-    } else {
-        0.0
+    pub fn into_planned(self) -> Option<PlanningResponse> {
+        match self {
+            PlanningOutcome::Planned(response) => Some(response),
+            PlanningOutcome::NotPlannable { .. } | PlanningOutcome::CoverageIncomplete { .. } => {
+                None
+            }
+        }
     }
 }
 
+/// A request planned together with other appliances by plan_multiple; higher priorities are planned first.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct NamedPlanningRequest {
+    pub name: String,
+    pub priority: i32,
+    pub request: PlanningRequest,
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
-pub struct TimeSlot {
-    pub from: NaiveTime,
-    pub till: NaiveTime,
+pub struct NamedPlanningOutcome {
+    pub name: String,
+    pub outcome: PlanningOutcome,
+    /// Set when earlier planned appliances pushed this one out of the block it would get on its own.
+    pub displaced: bool,
+}
+
+/// A load profile section planned into its own window by get_best_spot_prices_per_section.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PlannedSection {
+    pub id: String,
+    pub from: DateTime<Utc>,
+    pub till: DateTime<Utc>,
+    pub total_price: f64,
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SectionPlanningResponse {
+    /// Planned sections in load profile order.
+    pub sections: Vec<PlannedSection>,
+    pub total_price: f64,
+}
+
+impl SectionPlanningResponse {
+    pub fn section(&self, id: &str) -> Option<&PlannedSection> {
+        self.sections.iter().find(|section| section.id == id)
+    }
+}
+
+/// Result of re-planning against a previous plan.
 #[derive(Serialize, Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
-pub struct SpotPricePlannerConfig {
-    pub plannable_local_time_slots: HashMap<Weekday, Vec<TimeSlot>>,
-    pub local_time_zone: String,
-    pub load_profile: LoadProfile,
+pub struct PlanningDecision {
+    pub outcome: PlanningOutcome,
+    /// Set when the outcome is the previous plan, re-priced with the current spot prices.
+    pub kept_previous: bool,
 }
 
-impl SpotPricePlannerConfig {
-    pub fn get_local_time_zone(&self) -> Result<Tz, Box<dyn Error>> {
-        Ok(self.local_time_zone.parse::<Tz>()?)
+/// What a plan saves compared to starting at a reference moment and to the most expensive window.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PlanEvaluation {
+    pub plan_cost: f64,
+    /// Cost of running the load from the reference start; None when the spot prices don't cover that run.
+    pub reference_cost: Option<f64>,
+    /// Cost of the most expensive consecutive block within the plannable spot prices.
+    pub worst_case_cost: Option<f64>,
+    pub savings_vs_reference: Option<f64>,
+    pub savings_vs_reference_percentage: Option<f64>,
+    pub savings_vs_worst_case: Option<f64>,
+    pub savings_vs_worst_case_percentage: Option<f64>,
+}
+
+impl PlanEvaluation {
+    fn new(plan_cost: f64, reference_cost: Option<f64>, worst_case_cost: Option<f64>) -> Self {
+        let savings = |cost: Option<f64>| cost.map(|cost| cost - plan_cost);
+        // relative to the size of the cost, so savings stay positive when the cost is negative
+        let percentage = |cost: Option<f64>| {
+            cost.filter(|cost| *cost != 0.0)
+                .map(|cost| (cost - plan_cost) / cost.abs() * 100.0)
+        };
+
+        Self {
+            plan_cost,
+            reference_cost,
+            worst_case_cost,
+            savings_vs_reference: savings(reference_cost),
+            savings_vs_reference_percentage: percentage(reference_cost),
+            savings_vs_worst_case: savings(worst_case_cost),
+            savings_vs_worst_case_percentage: percentage(worst_case_cost),
+        }
     }
 }
 
-pub struct SpotPricePlanner {
-    pub config: SpotPricePlannerConfig,
+impl fmt::Display for PlanEvaluation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "plan costs {:.4}", self.plan_cost)?;
+        if let (Some(savings), Some(percentage)) = (
+            self.savings_vs_reference,
+            self.savings_vs_reference_percentage,
+        ) {
+            write!(
+                f,
+                ", saving {:.4} ({:.1}%) on starting right away",
+                savings, percentage
+            )?;
+        }
+        if let (Some(savings), Some(percentage)) = (
+            self.savings_vs_worst_case,
+            self.savings_vs_worst_case_percentage,
+        ) {
+            write!(
+                f,
+                ", saving {:.4} ({:.1}%) on the most expensive window",
+                savings, percentage
+            )?;
+        }
+        Ok(())
+    }
 }
 
-impl SpotPricePlanner {
-    pub fn new(config: SpotPricePlannerConfig) -> Self {
-        Self { config }
+/// Returned when there are too few spot prices left before the deadline to run the load at all.
+#[derive(Debug)]
+pub struct NotEnoughPricesError {
+    pub required_seconds: i64,
+    pub available_seconds: i64,
+}
+
+impl fmt::Display for NotEnoughPricesError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Not enough spot prices to plan load: {} seconds required, {} seconds available",
+            self.required_seconds, self.available_seconds
+        )
     }
+}
 
-    pub fn get_plannable_spot_prices(
-        &self,
-        spot_prices: &[SpotPrice],
-        after: &Option<DateTime<Utc>>,
-        before: &Option<DateTime<Utc>>,
-    ) -> Result<Vec<SpotPrice>, Box<dyn Error>> {
-        let local_time_zone = self.config.get_local_time_zone()?;
+impl Error for NotEnoughPricesError {}
 
-        info!(
-            "Determining plannable spot prices after {:?} and before {:?}",
-            after, before
-        );
-        debug!("spot_prices:\n{:?}", spot_prices);
+#[derive(Debug, Clone, PartialEq)]
+pub enum PlannerConfigProblem {
+    InvalidLocalTimeZone(String),
+    NoLoadProfileSections,
+    NonPositiveSectionDuration {
+        section: usize,
+        duration_seconds: i64,
+    },
+    NegativeSectionPowerDraw {
+        section: usize,
+        power_draw_watt: f64,
+    },
+    NonPositivePlanningGranularity(i64),
+    /// A time slot with till at or before from that is not marked as wrapping past midnight.
+    InvertedTimeSlot {
+        weekday: Weekday,
+        from: NaiveTime,
+        till: NaiveTime,
+    },
+    /// A holiday time slot with till at or before from that is not marked as wrapping past midnight.
+    InvertedHolidayTimeSlot {
+        from: NaiveTime,
+        till: NaiveTime,
+    },
+}
 
-        let plannable_spot_prices = spot_prices
-            .iter()
-            .filter(|&spot_price| {
-                let local_from = spot_price.from.with_timezone(&local_time_zone);
-                let local_till = spot_price.till.with_timezone(&local_time_zone);
+impl fmt::Display for PlannerConfigProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlannerConfigProblem::InvalidLocalTimeZone(local_time_zone) => {
+                write!(f, "invalid local time zone {}", local_time_zone)
+            }
+            PlannerConfigProblem::NoLoadProfileSections => {
+                write!(f, "load profile has no sections")
+            }
+            PlannerConfigProblem::NonPositiveSectionDuration {
+                section,
+                duration_seconds,
+            } => write!(
+                f,
+                "load profile section {} has non-positive duration of {} seconds",
+                section, duration_seconds
+            ),
+            PlannerConfigProblem::NegativeSectionPowerDraw {
+                section,
+                power_draw_watt,
+            } => write!(
+                f,
+                "load profile section {} has negative power draw of {} watt",
+                section, power_draw_watt
+            ),
+            PlannerConfigProblem::NonPositivePlanningGranularity(granularity) => write!(
+                f,
+                "planning granularity of {} seconds is not positive",
+                granularity
+            ),
+            PlannerConfigProblem::InvertedTimeSlot {
+                weekday,
+                from,
+                till,
+            } => write!(
+                f,
+                "time slot {}-{} on {} ends before it starts; set wrapsMidnight if intended",
+                from, till, weekday
+            ),
+            PlannerConfigProblem::InvertedHolidayTimeSlot { from, till } => write!(
+                f,
+                "holiday time slot {}-{} ends before it starts; set wrapsMidnight if intended",
+                from, till
+            ),
+        }
+    }
+}
 
-                if let Some(a) = after {
-                    if spot_price.from < *a {
-                        return false;
-                    }
-                }
+/// All problems found while validating a SpotPricePlannerConfig.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlannerConfigError {
+    pub problems: Vec<PlannerConfigProblem>,
+}
 
-                if let Some(b) = before {
-                    if spot_price.till > *b {
-                        return false;
-                    }
-                }
+impl fmt::Display for PlannerConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let problems: Vec<String> = self.problems.iter().map(|p| p.to_string()).collect();
+        write!(f, "Invalid planner config: {}", problems.join("; "))
+    }
+}
 
-                if let Some(plannable_local_time_slots) = self
-                    .config
-                    .plannable_local_time_slots
-                    .get(&local_from.weekday())
-                {
-                    plannable_local_time_slots.iter().any(|time_slot| {
-                        let time_slot_from = local_from
-                            .date_naive()
-                            .and_hms_opt(
-                                time_slot.from.hour(),
-                                time_slot.from.minute(),
-                                time_slot.from.second(),
-                            )
-                            .unwrap()
-                            .and_local_timezone(local_time_zone)
-                            .unwrap();
-
-                        let time_slot_till = if time_slot.till.hour() > 0 {
-                            local_from
-                                .date_naive()
-                                .and_hms_opt(
-                                    time_slot.till.hour(),
-                                    time_slot.till.minute(),
-                                    time_slot.till.second(),
-                                )
-                                .unwrap()
-                                .and_local_timezone(local_time_zone)
-                                .unwrap()
-                        } else {
-                            local_from
-                                .date_naive()
-                                .and_hms_opt(
-                                    time_slot.till.hour(),
-                                    time_slot.till.minute(),
-                                    time_slot.till.second(),
-                                )
-                                .unwrap()
-                                .and_local_timezone(local_time_zone)
-                                .unwrap()
-                                + Duration::days(1)
-                        };
+impl Error for PlannerConfigError {}
 
-                        local_from >= time_slot_from
-                            && local_from < time_slot_till
-                            && local_till > time_slot_from
-                            && local_till <= time_slot_till
-                    })
-                } else {
-                    false
-                }
-            })
-            .cloned()
-            .collect();
+impl Serialize for PlanningResponse {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        #[derive(Serialize)]
+        #[serde(rename_all = "camelCase")]
+        struct PublishedPlanningResponse<'a> {
+            spot_prices: &'a [SpotPrice],
+            load_profile: &'a LoadProfile,
+            planned_from: Option<DateTime<Utc>>,
+            planned_till: Option<DateTime<Utc>>,
+            segments: &'a [(DateTime<Utc>, DateTime<Utc>)],
+            last_slot_used_seconds: Option<i64>,
+            fallback_used: bool,
+            percentile_fallback_used: bool,
+            forced: bool,
+            spike_fallback_used: bool,
+            self_consumed_kwh: Option<f64>,
+            grid_kwh: Option<f64>,
+            price_components: PriceComponents,
+            #[serde(skip_serializing_if = "<[_]>::is_empty")]
+            explanation: &'a [CandidateRejection],
+            total_energy_kwh: f64,
+            average_price_per_kwh: Option<f64>,
+        }
 
-        debug!("plannable_spot_prices:\n{:?}", plannable_spot_prices);
+        PublishedPlanningResponse {
+            spot_prices: &self.spot_prices,
+            load_profile: &self.load_profile,
+            planned_from: self.planned_from,
+            planned_till: self.planned_till,
+            segments: &self.segments,
+            last_slot_used_seconds: self.last_slot_used_seconds,
+            fallback_used: self.fallback_used,
+            percentile_fallback_used: self.percentile_fallback_used,
+            forced: self.forced,
+            spike_fallback_used: self.spike_fallback_used,
+            self_consumed_kwh: self.self_consumed_kwh,
+            grid_kwh: self.grid_kwh,
+            price_components: self.price_components.clone(),
+            explanation: &self.explanation,
+            total_energy_kwh: self.total_energy_kwh(),
+            average_price_per_kwh: self.average_price_per_kwh(self.price_components.clone()),
+        }
+        .serialize(serializer)
+    }
+}
 
-        Ok(plannable_spot_prices)
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SlotCost {
+    pub from: DateTime<Utc>,
+    pub till: DateTime<Utc>,
+    pub allocated_seconds: i64,
+    pub energy_kwh: f64,
+    pub cost: f64,
+}
+
+/// A consecutive window planned without a load profile, priced per kWh.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PlannedWindow {
+    pub from: DateTime<Utc>,
+    pub till: DateTime<Utc>,
+    pub average_price_per_kwh: f64,
+    pub spot_prices: Vec<SpotPrice>,
+}
+
+impl PlanningResponse {
+    fn for_spot_prices(
+        spot_prices: Vec<SpotPrice>,
+        skip_seconds: i64,
+        load_profile: &LoadProfile,
+        price_components: PriceComponents,
+    ) -> Self {
+        let planned_from = spot_prices
+            .first()
+            .map(|first| first.from + Duration::seconds(skip_seconds));
+        let segments = planned_from
+            .map(|from| segments_for_load(&spot_prices, from, load_profile))
+            .unwrap_or_default();
+        let planned_till = segments.last().map(|segment| segment.1);
+        let last_slot_used_seconds = match (planned_from, planned_till, spot_prices.last()) {
+            (Some(from), Some(till), Some(last)) => {
+                Some((till - std::cmp::max(last.from, from)).num_seconds())
+            }
+            _ => None,
+        };
+
+        PlanningResponse {
+            spot_prices,
+            load_profile: load_profile.clone(),
+            planned_from,
+            planned_till,
+            segments,
+            last_slot_used_seconds,
+            fallback_used: false,
+            percentile_fallback_used: false,
+            forced: false,
+            spike_fallback_used: false,
+            self_consumed_kwh: None,
+            grid_kwh: None,
+            price_components,
+            explanation: vec![],
+        }
     }
 
-    pub fn get_best_spot_prices(
-        &self,
-        request: &PlanningRequest,
-    ) -> Result<PlanningResponse, Box<dyn Error>> {
-        let plannable_spot_prices: Vec<SpotPrice> =
-            self.get_plannable_spot_prices(&request.spot_prices, &request.after, &request.before)?;
-
-        if !plannable_spot_prices.is_empty() {
-            let total_required_seconds = request.load_profile.total_duration_seconds();
-            let mut best_spot_prices: Vec<SpotPrice> = vec![];
-
-            // loop spot prices
-            let mut spot_prices_iter = plannable_spot_prices.iter();
-            while let Some(spot_price) = spot_prices_iter.next() {
-                let mut selected_spot_prices: Vec<SpotPrice> = vec![spot_price.clone()];
-                let mut selected_seconds = spot_price.duration_seconds();
-
-                let mut look_ahead_iter = spot_prices_iter.clone();
-
-                // peek enough consecutive prices to reach total seconds for profile
-                while selected_seconds < total_required_seconds {
-                    if let Some(next_spot_price) = look_ahead_iter.next() {
-                        selected_seconds += next_spot_price.duration_seconds();
-                        selected_spot_prices.push(next_spot_price.clone());
-                    } else {
-                        break;
-                    }
-                }
+    fn skip_seconds(&self) -> i64 {
+        match (self.planned_from, self.spot_prices.first()) {
+            (Some(planned_from), Some(first)) if planned_from > first.from => {
+                (planned_from - first.from).num_seconds()
+            }
+            _ => 0,
+        }
+    }
 
-                // not enough remaining spot prices to get to the required seconds
-                if selected_seconds < total_required_seconds {
-                    break;
-                }
+    /// Cost attributed to each contributing spot price; adds up to total_price. The optional
+    /// get_price_fn overrides price_components.
+    pub fn cost_breakdown(&self, get_price_fn: Option<fn(&SpotPrice) -> f64>) -> Vec<SlotCost> {
+        let total_required_seconds = self.load_profile.total_duration_seconds();
+        let mut allocated_seconds_so_far = 0;
+        let mut slot_costs: Vec<SlotCost> = vec![];
 
-                if best_spot_prices.is_empty() {
-                    // first one, so most applicable yet
-                    best_spot_prices = selected_spot_prices;
-                } else {
-                    // compare to previous best/worst
-                    let total_price_previous =
-                        total_price_for_load(&best_spot_prices, &request.load_profile, None);
-                    let total_price_current =
-                        total_price_for_load(&selected_spot_prices, &request.load_profile, None);
-
-                    match request.planning_strategy {
-                        PlanningStrategy::LowestPrice => {
-                            if total_price_current < total_price_previous {
-                                best_spot_prices = selected_spot_prices;
-                            }
-                        }
-                        PlanningStrategy::HighestPrice => {
-                            if total_price_current > total_price_previous {
-                                best_spot_prices = selected_spot_prices;
-                            }
-                        }
-                    }
-                }
+        for (i, spot_price) in self.spot_prices.iter().enumerate() {
+            if allocated_seconds_so_far >= total_required_seconds {
+                break;
             }
 
-            Ok(PlanningResponse {
-                spot_prices: best_spot_prices,
-                load_profile: request.load_profile.clone(),
-            })
-        } else {
-            Ok(PlanningResponse {
-                spot_prices: plannable_spot_prices,
-                load_profile: request.load_profile.clone(),
-            })
+            let available_seconds = if i == 0 {
+                spot_price.duration_seconds() - self.skip_seconds()
+            } else {
+                spot_price.duration_seconds()
+            };
+            let allocated_seconds = std::cmp::min(
+                available_seconds.max(0),
+                total_required_seconds - allocated_seconds_so_far,
+            );
+            let watt_seconds = load_watt_seconds_between(
+                &self.load_profile,
+                allocated_seconds_so_far,
+                allocated_seconds_so_far + allocated_seconds,
+            );
+            let price = match get_price_fn {
+                Some(get_price_fn) => get_price_fn(spot_price),
+                None => spot_price.price_for(&self.price_components),
+            };
+
+            slot_costs.push(SlotCost {
+                from: spot_price.from,
+                till: spot_price.till,
+                allocated_seconds,
+                energy_kwh: joules_to_kwh(watt_seconds),
+                cost: price * joules_to_kwh(watt_seconds),
+            });
+            allocated_seconds_so_far += allocated_seconds;
         }
+
+        slot_costs
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use pretty_assertions::assert_eq;
+    /// Total price of the planned load; the optional get_price_fn overrides price_components.
+    pub fn total_price(&self, get_price_fn: Option<fn(&SpotPrice) -> f64>) -> f64 {
+        let skip_seconds = self.skip_seconds();
 
-    #[test]
-    fn total_price_for_load_returns_zero_for_empty_spot_prices() {
-        // act
-        let total_price = total_price_for_load(
-            &vec![],
-            &LoadProfile {
-                sections: vec![LoadProfileSection {
-                    duration_seconds: 7200,
-                    power_draw_watt: 2000.0,
-                }],
-            },
-            None,
-        );
+        match get_price_fn {
+            Some(get_price_fn) => total_price_for_load_from(
+                &self.spot_prices,
+                skip_seconds,
+                &self.load_profile,
+                &get_price_fn,
+            ),
+            None => total_price_for_load_from(
+                &self.spot_prices,
+                skip_seconds,
+                &self.load_profile,
+                &|sp: &SpotPrice| sp.price_for(&self.price_components),
+            ),
+        }
+    }
 
-        assert_eq!(total_price, 0.0);
+    /// Energy the planned load uses; 0 for an empty plan.
+    pub fn total_energy_kwh(&self) -> f64 {
+        if self.spot_prices.is_empty() {
+            return 0.0;
+        }
+        self.load_profile.total_energy_kwh()
     }
 
-    #[test]
-    fn total_price_for_load_returns_zero_for_empty_load_profile() {
-        // act
-        let total_price = total_price_for_load(
-            &vec![SpotPrice {
-                id: None,
-                source: None,
-                from: Utc.with_ymd_and_hms(2022, 4, 14, 11, 0, 0).unwrap(),
-                till: Utc.with_ymd_and_hms(2022, 4, 14, 12, 0, 0).unwrap(),
-                market_price: 0.202,
-                market_price_tax: 0.0424053,
-                sourcing_markup_price: 0.017,
-                energy_tax_price: 0.081,
-            }],
-            &LoadProfile { sections: vec![] },
-            None,
+    /// Total price of the load on the given price components divided by its energy; None for an empty plan or a
+    /// load without energy.
+    pub fn average_price_per_kwh(&self, components: PriceComponents) -> Option<f64> {
+        let total_energy_kwh = self.total_energy_kwh();
+        if total_energy_kwh <= 0.0 {
+            return None;
+        }
+
+        let total_price = total_price_for_load_from(
+            &self.spot_prices,
+            self.skip_seconds(),
+            &self.load_profile,
+            &|sp: &SpotPrice| sp.price_for(&components),
         );
+        Some(total_price / total_energy_kwh)
+    }
 
-        assert_eq!(total_price, 0.0);
+    /// Whether the load is planned to be running at t; gaps between non-consecutive spot prices count as inactive.
+    pub fn is_active_at(&self, t: DateTime<Utc>) -> bool {
+        self.active_segments()
+            .iter()
+            .any(|(from, till)| t >= *from && t < *till)
     }
 
-    #[test]
-    fn total_price_for_load_returns_total_draw_times_total_price_for_equal_length_spot_price_and_load_profile_section(
-    ) {
-        // act
-        let total_price = total_price_for_load(
-            &vec![SpotPrice {
-                id: None,
-                source: None,
-                from: Utc.with_ymd_and_hms(2022, 4, 14, 11, 0, 0).unwrap(),
-                till: Utc.with_ymd_and_hms(2022, 4, 14, 12, 0, 0).unwrap(),
-                market_price: 0.202,
-                market_price_tax: 0.0424053,
-                sourcing_markup_price: 0.017,
-                energy_tax_price: 0.081,
-            }],
-            &LoadProfile {
-                sections: vec![LoadProfileSection {
-                    duration_seconds: 3600,
-                    power_draw_watt: 2000.0,
-                }],
-            },
-            None,
-        );
+    /// First moment after t at which the load switches on or off, with whether it runs from then on.
+    pub fn next_transition_after(&self, t: DateTime<Utc>) -> Option<(DateTime<Utc>, bool)> {
+        self.active_segments().into_iter().find_map(|(from, till)| {
+            if from > t {
+                Some((from, true))
+            } else if till > t {
+                Some((till, false))
+            } else {
+                None
+            }
+        })
+    }
+
+    /// The periods the load runs in grouped per local day, split at local midnight.
+    pub fn runs_per_local_day(&self, time_zone: &Tz) -> Vec<(NaiveDate, Vec<Interval>)> {
+        let mut runs_per_day: Vec<(NaiveDate, Vec<Interval>)> = vec![];
+        for (from, till) in self.active_segments() {
+            let mut run_from = from;
+            while run_from < till {
+                let date = run_from.with_timezone(time_zone).date_naive();
+                let next_midnight = date
+                    .succ_opt()
+                    .and_then(|next_date| local_midnight(next_date, time_zone))
+                    .unwrap_or(till);
+                let run_till = till.min(next_midnight);
 
-        assert_eq!(total_price, 0.6848106000000072); // round error, should be 0.6848106
+                match runs_per_day.last_mut() {
+                    Some((last_date, runs)) if *last_date == date => {
+                        runs.push((run_from, run_till))
+                    }
+                    _ => runs_per_day.push((date, vec![(run_from, run_till)])),
+                }
+                run_from = run_till;
+            }
+        }
+        runs_per_day
     }
 
-    #[test]
-    fn total_price_for_load_returns_total_draw_times_total_price_for_more_spot_prices_than_needed()
-    {
-        // act
-        let total_price = total_price_for_load(
-            &vec![
-                SpotPrice {
-                    id: None,
-                    source: None,
-                    from: Utc.with_ymd_and_hms(2022, 4, 14, 11, 0, 0).unwrap(),
-                    till: Utc.with_ymd_and_hms(2022, 4, 14, 12, 0, 0).unwrap(),
-                    market_price: 0.202,
-                    market_price_tax: 0.0424053,
-                    sourcing_markup_price: 0.017,
-                    energy_tax_price: 0.081,
-                },
-                SpotPrice {
-                    id: None,
-                    source: None,
-                    from: Utc.with_ymd_and_hms(2022, 4, 14, 12, 0, 0).unwrap(),
-                    till: Utc.with_ymd_and_hms(2022, 4, 14, 13, 0, 0).unwrap(),
-                    market_price: 0.195,
-                    market_price_tax: 0.0409899,
-                    sourcing_markup_price: 0.017,
-                    energy_tax_price: 0.081,
-                },
-            ],
-            &LoadProfile {
-                sections: vec![
+    /// Half-open periods the load runs in; derived from the spot prices for responses without segments.
+    fn active_segments(&self) -> Vec<(DateTime<Utc>, DateTime<Utc>)> {
+        if !self.segments.is_empty() {
+            return self.segments.clone();
+        }
+
+        self.planned_from
+            .map(|planned_from| {
+                segments_for_load(&self.spot_prices, planned_from, &self.load_profile)
+            })
+            .unwrap_or_default()
+    }
+}
+
+fn total_price_for_load(
+    spot_prices: &[SpotPrice],
+    load_profile: &LoadProfile,
+    get_price_fn: Option<fn(&SpotPrice) -> f64>,
+) -> f64 {
+    total_price_for_load_from(
+        spot_prices,
+        0,
+        load_profile,
+        &get_price_fn.unwrap_or(|sp| sp.total_price()),
+    )
+}
+
+/// Price of running the load from start, None when the spot prices don't cover it without gaps.
+fn total_price_for_load_starting_at(
+    spot_prices: &[SpotPrice],
+    start: DateTime<Utc>,
+    load_profile: &LoadProfile,
+    get_price_fn: &dyn Fn(&SpotPrice) -> f64,
+) -> Option<f64> {
+    let first = SpotPriceIndex::from_sorted(spot_prices).position_at(start)?;
+    let till = start + Duration::seconds(load_profile.total_duration_seconds());
+
+    let mut covered_till = spot_prices[first].till;
+    for spot_price in &spot_prices[first + 1..] {
+        if covered_till >= till || spot_price.from > covered_till {
+            break;
+        }
+        covered_till = spot_price.till;
+    }
+    if covered_till < till {
+        return None;
+    }
+
+    Some(total_price_for_load_from(
+        &spot_prices[first..],
+        (start - spot_prices[first].from).num_seconds(),
+        load_profile,
+        get_price_fn,
+    ))
+}
+
+/// Prices are summed as nano-euros per kWh times milliwatt-seconds, so totals are exact integers that don't depend
+/// on the order spot prices are added in; they only become euros at the API boundary.
+const NANOS_PER_EURO: f64 = 1_000_000_000.0;
+const MILLIWATT_SECONDS_PER_KWH: f64 = JOULES_PER_KILOWATT_HOUR * 1000.0;
+
+/// Exact cost of watt_seconds at price per kWh, see NANOS_PER_EURO.
+fn price_units(price: f64, watt_seconds: f64) -> i128 {
+    (price * NANOS_PER_EURO).round() as i128 * (watt_seconds * 1000.0).round() as i128
+}
+
+fn price_from_units(price_units: i128) -> f64 {
+    price_units as f64 / (NANOS_PER_EURO * MILLIWATT_SECONDS_PER_KWH)
+}
+
+/// Same as total_price_for_load, but starts the load skip_seconds into the first spot price.
+/// Sums price times energy over the overlap of each spot price with the load profile sections.
+fn total_price_for_load_from(
+    spot_prices: &[SpotPrice],
+    skip_seconds: i64,
+    load_profile: &LoadProfile,
+    get_price_fn: &dyn Fn(&SpotPrice) -> f64,
+) -> f64 {
+    let total_required_seconds = load_profile.total_duration_seconds();
+    let mut allocated_seconds = 0;
+    let mut total_price_units = 0;
+
+    for (i, spot_price) in spot_prices.iter().enumerate() {
+        if allocated_seconds >= total_required_seconds {
+            break;
+        }
+
+        let available_seconds = if i == 0 {
+            spot_price.duration_seconds() - skip_seconds
+        } else {
+            spot_price.duration_seconds()
+        };
+        let seconds = std::cmp::min(
+            available_seconds.max(0),
+            total_required_seconds - allocated_seconds,
+        );
+        let watt_seconds =
+            load_watt_seconds_between(load_profile, allocated_seconds, allocated_seconds + seconds);
+
+        total_price_units += price_units(get_price_fn(spot_price), watt_seconds);
+        allocated_seconds += seconds;
+    }
+
+    price_from_units(total_price_units)
+}
+
+/// Periods the load runs in when it goes through the spot prices in order, starting at from;
+/// adjacent spot prices are joined into a single segment.
+fn segments_for_load(
+    spot_prices: &[SpotPrice],
+    from: DateTime<Utc>,
+    load_profile: &LoadProfile,
+) -> Vec<(DateTime<Utc>, DateTime<Utc>)> {
+    let mut remaining_seconds = load_profile.total_duration_seconds();
+    let mut segments: Vec<(DateTime<Utc>, DateTime<Utc>)> = vec![];
+    for spot_price in spot_prices {
+        if remaining_seconds <= 0 {
+            break;
+        }
+        let start = std::cmp::max(spot_price.from, from);
+        let available_seconds = (spot_price.till - start).num_seconds();
+        if available_seconds <= 0 {
+            continue;
+        }
+        let used_seconds = std::cmp::min(available_seconds, remaining_seconds);
+        let till = start + Duration::seconds(used_seconds);
+        remaining_seconds -= used_seconds;
+
+        match segments.last_mut() {
+            Some(last) if last.1 == start => last.1 = till,
+            _ => segments.push((start, till)),
+        }
+    }
+
+    segments
+}
+
+/// Energy in watt-seconds the load draws between seconds from and till after its start.
+fn load_watt_seconds_between(load_profile: &LoadProfile, from: i64, till: i64) -> f64 {
+    let mut section_start = 0;
+    let mut watt_seconds = 0.0;
+    for section in &load_profile.sections {
+        let section_end = section_start + section.duration_seconds;
+        let overlap_from = std::cmp::max(section_start, from);
+        let overlap_till = std::cmp::min(section_end, till);
+        if overlap_till > overlap_from {
+            watt_seconds += section
+                .watt_seconds_between(overlap_from - section_start, overlap_till - section_start);
+        }
+        section_start = section_end;
+    }
+
+    watt_seconds
+}
+
+/// Highest power draw in watt of the load between seconds from and till after its start.
+fn load_peak_watt_between(load_profile: &LoadProfile, from: i64, till: i64) -> f64 {
+    let mut section_start = 0;
+    let mut peak_watt: f64 = 0.0;
+    for section in &load_profile.sections {
+        let section_end = section_start + section.duration_seconds;
+        let overlap_from = std::cmp::max(section_start, from);
+        let overlap_till = std::cmp::min(section_end, till);
+        if overlap_till > overlap_from {
+            // a linear ramp peaks at one of the ends of the overlap
+            peak_watt = peak_watt
+                .max(section.power_draw_watt_at(overlap_from - section_start))
+                .max(section.power_draw_watt_at(overlap_till - section_start));
+        }
+        section_start = section_end;
+    }
+
+    peak_watt
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TimeSlot {
+    pub from: NaiveTime,
+    pub till: NaiveTime,
+    /// Marks a till at or before from as intended to continue past midnight; validation rejects such slots otherwise.
+    #[serde(default)]
+    pub wraps_midnight: bool,
+}
+
+impl TimeSlot {
+    /// Whether till lies at or before from without the slot being marked as wrapping past midnight.
+    fn is_inverted(&self) -> bool {
+        self.till <= self.from && self.till != NaiveTime::MIN && !self.wraps_midnight
+    }
+
+    /// Local start and end of the time slot starting on date; a till of 00:00 means the end of
+    /// the day and any other till at or before from wraps past midnight into the next day.
+    pub fn local_interval_on(
+        &self,
+        date: NaiveDate,
+        time_zone: &Tz,
+    ) -> Option<(DateTime<Tz>, DateTime<Tz>)> {
+        let till_date = if self.till == NaiveTime::MIN || self.till <= self.from {
+            date.succ_opt()?
+        } else {
+            date
+        };
+
+        let from = date
+            .and_time(self.from)
+            .and_local_timezone(*time_zone)
+            .earliest()?;
+        let till = till_date
+            .and_time(self.till)
+            .and_local_timezone(*time_zone)
+            .earliest()?;
+
+        Some((from, till))
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SpotPricePlannerConfig {
+    /// Time slots per weekday in which planning is allowed; an empty map means always plannable, while a weekday
+    /// with an empty list or missing from a non-empty map is never plannable. Besides weekdays the config accepts
+    /// the groups daily, weekdays and weekend, which get expanded on deserialization. The v1 name
+    /// plannableLocalTimeSlots is still accepted.
+    #[serde(
+        default,
+        rename = "plannableTimeSlots",
+        alias = "plannableLocalTimeSlots",
+        deserialize_with = "deserialize_plannable_time_slots"
+    )]
+    pub plannable_local_time_slots: HashMap<Weekday, Vec<TimeSlot>>,
+    #[serde(default = "default_local_time_zone")]
+    pub local_time_zone: String,
+    #[serde(default)]
+    pub load_profile: LoadProfile,
+    /// Step in seconds between candidate start times within a spot price; when unset planning aligns to spot price boundaries.
+    #[serde(default)]
+    pub planning_granularity_seconds: Option<i64>,
+    /// Default for PlanningRequest::max_acceptable_total_price_per_kwh.
+    #[serde(default)]
+    pub max_acceptable_total_price_per_kwh: Option<f64>,
+    /// Default for PlanningRequest::price_components.
+    #[serde(default)]
+    pub price_components: PriceComponents,
+    /// Local dates on which holiday_time_slots apply instead of the weekday's time slots.
+    #[serde(default)]
+    pub holidays: Vec<NaiveDate>,
+    #[serde(default)]
+    pub holiday_time_slots: Vec<TimeSlot>,
+    /// Include spot prices partially outside after/before or the time slots, narrowed to the part inside them; the
+    /// first candidate block then starts right at the lower bound, the later of after and now.
+    #[serde(default)]
+    pub clip_to_bounds: bool,
+    /// Default for PlanningRequest::tie_breaker.
+    #[serde(default)]
+    pub tie_breaker: TieBreaker,
+    /// Blocks whose total prices differ by at most this many euros count as equally priced; 1e-9 when unset.
+    #[serde(default)]
+    pub tie_breaker_epsilon: Option<f64>,
+    /// Spot prices starting at most this many seconds from the end of the previous one count as adjacent to it;
+    /// DEFAULT_BOUNDARY_TOLERANCE_SECONDS when unset.
+    #[serde(default)]
+    pub boundary_tolerance_seconds: Option<i64>,
+}
+
+const DEFAULT_TIE_BREAKER_EPSILON: f64 = 1e-9;
+
+const WEEKDAY_GROUPS: [(&str, &[Weekday]); 3] = [
+    (
+        "daily",
+        &[
+            Weekday::Mon,
+            Weekday::Tue,
+            Weekday::Wed,
+            Weekday::Thu,
+            Weekday::Fri,
+            Weekday::Sat,
+            Weekday::Sun,
+        ],
+    ),
+    (
+        "weekdays",
+        &[
+            Weekday::Mon,
+            Weekday::Tue,
+            Weekday::Wed,
+            Weekday::Thu,
+            Weekday::Fri,
+        ],
+    ),
+    ("weekend", &[Weekday::Sat, Weekday::Sun]),
+];
+
+/// Weekdays covered by a plannable time slots group key like weekdays, None for other keys.
+pub fn weekdays_in_group(group: &str) -> Option<&'static [Weekday]> {
+    WEEKDAY_GROUPS
+        .iter()
+        .find(|(name, _)| *name == group)
+        .map(|(_, weekdays)| *weekdays)
+}
+
+fn deserialize_plannable_time_slots<'de, D>(
+    deserializer: D,
+) -> Result<HashMap<Weekday, Vec<TimeSlot>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let entries: HashMap<String, Vec<TimeSlot>> = HashMap::deserialize(deserializer)?;
+
+    // daily first, so the narrower groups and then explicit weekdays override it
+    let mut time_slots: HashMap<Weekday, Vec<TimeSlot>> = HashMap::new();
+    for (group, weekdays) in WEEKDAY_GROUPS {
+        if let Some(group_time_slots) = entries.get(group) {
+            for weekday in weekdays {
+                time_slots.insert(*weekday, group_time_slots.clone());
+            }
+        }
+    }
+
+    for (key, weekday_time_slots) in entries {
+        if weekdays_in_group(&key).is_some() {
+            continue;
+        }
+        let weekday = key
+            .parse::<Weekday>()
+            .map_err(|_| de::Error::custom(format!("unknown weekday or weekday group {}", key)))?;
+        time_slots.insert(weekday, weekday_time_slots);
+    }
+
+    Ok(time_slots)
+}
+
+fn default_local_time_zone() -> String {
+    "UTC".to_string()
+}
+
+impl Default for SpotPricePlannerConfig {
+    fn default() -> Self {
+        Self {
+            plannable_local_time_slots: HashMap::new(),
+            local_time_zone: default_local_time_zone(),
+            load_profile: LoadProfile::default(),
+            planning_granularity_seconds: None,
+            max_acceptable_total_price_per_kwh: None,
+            price_components: PriceComponents::default(),
+            holidays: vec![],
+            holiday_time_slots: vec![],
+            clip_to_bounds: false,
+            tie_breaker: TieBreaker::default(),
+            tie_breaker_epsilon: None,
+            boundary_tolerance_seconds: None,
+        }
+    }
+}
+
+impl Migrate for SpotPricePlannerConfig {
+    const CURRENT_VERSION: u32 = 2;
+
+    fn migrate(
+        mut value: serde_yaml::Value,
+        from_version: u32,
+    ) -> Result<serde_yaml::Value, Box<dyn Error>> {
+        if from_version < 2 {
+            // v2 renamed plannableLocalTimeSlots to plannableTimeSlots
+            if let serde_yaml::Value::Mapping(mapping) = &mut value {
+                if let Some(time_slots) = mapping.remove("plannableLocalTimeSlots") {
+                    mapping.insert("plannableTimeSlots".into(), time_slots);
+                }
+            }
+        }
+
+        Ok(value)
+    }
+}
+
+impl SpotPricePlannerConfig {
+    pub fn get_local_time_zone(&self) -> Result<Tz, Box<dyn Error>> {
+        Ok(self.local_time_zone.parse::<Tz>()?)
+    }
+
+    /// Time slots starting on the local date; the holiday time slots on holidays, otherwise the weekday's.
+    fn time_slots_on(&self, date: NaiveDate) -> &[TimeSlot] {
+        if self.holidays.contains(&date) {
+            &self.holiday_time_slots
+        } else {
+            self.plannable_local_time_slots
+                .get(&date.weekday())
+                .map_or(&[], |time_slots| time_slots.as_slice())
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), PlannerConfigError> {
+        let mut problems = vec![];
+
+        if self.get_local_time_zone().is_err() {
+            problems.push(PlannerConfigProblem::InvalidLocalTimeZone(
+                self.local_time_zone.clone(),
+            ));
+        }
+
+        if self.load_profile.sections.is_empty() {
+            problems.push(PlannerConfigProblem::NoLoadProfileSections);
+        }
+        for (section, load_profile_section) in self.load_profile.sections.iter().enumerate() {
+            if load_profile_section.duration_seconds <= 0 {
+                problems.push(PlannerConfigProblem::NonPositiveSectionDuration {
+                    section,
+                    duration_seconds: load_profile_section.duration_seconds,
+                });
+            }
+            let min_power_draw_watt = load_profile_section
+                .end_power_draw_watt
+                .map_or(load_profile_section.power_draw_watt, |end| {
+                    end.min(load_profile_section.power_draw_watt)
+                });
+            if min_power_draw_watt < 0.0 {
+                problems.push(PlannerConfigProblem::NegativeSectionPowerDraw {
+                    section,
+                    power_draw_watt: min_power_draw_watt,
+                });
+            }
+        }
+
+        if let Some(granularity) = self.planning_granularity_seconds {
+            if granularity <= 0 {
+                problems.push(PlannerConfigProblem::NonPositivePlanningGranularity(
+                    granularity,
+                ));
+            }
+        }
+
+        // sorted so problems are reported in a stable order
+        let mut weekdays: Vec<&Weekday> = self.plannable_local_time_slots.keys().collect();
+        weekdays.sort_by_key(|weekday| weekday.num_days_from_monday());
+        for weekday in weekdays {
+            for time_slot in &self.plannable_local_time_slots[weekday] {
+                if time_slot.is_inverted() {
+                    problems.push(PlannerConfigProblem::InvertedTimeSlot {
+                        weekday: *weekday,
+                        from: time_slot.from,
+                        till: time_slot.till,
+                    });
+                }
+            }
+        }
+        for time_slot in &self.holiday_time_slots {
+            if time_slot.is_inverted() {
+                problems.push(PlannerConfigProblem::InvertedHolidayTimeSlot {
+                    from: time_slot.from,
+                    till: time_slot.till,
+                });
+            }
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(PlannerConfigError { problems })
+        }
+    }
+}
+
+pub struct SpotPricePlanner {
+    pub config: SpotPricePlannerConfig,
+    /// The configured time zone parsed once, along with the name it was parsed from.
+    local_time_zone: Option<(String, Tz)>,
+    /// Default for PlanningRequest::now.
+    now: Option<DateTime<Utc>>,
+}
+
+impl SpotPricePlanner {
+    /// Creates a planner without validating the config; for configs with only per-request load profiles.
+    pub fn new(config: SpotPricePlannerConfig) -> Self {
+        let local_time_zone = config
+            .get_local_time_zone()
+            .ok()
+            .map(|time_zone| (config.local_time_zone.clone(), time_zone));
+
+        Self {
+            config,
+            local_time_zone,
+            now: None,
+        }
+    }
+
+    /// Plans requests without a now of their own as of now.
+    pub fn with_now(mut self, now: DateTime<Utc>) -> Self {
+        self.now = Some(now);
+        self
+    }
+
+    /// The request with now filled in from the planner and after moved up to now.
+    fn resolve_now<'a>(&self, request: &'a PlanningRequest) -> Cow<'a, PlanningRequest> {
+        match request.now.or(self.now) {
+            Some(now) => Cow::Owned(PlanningRequest {
+                now: Some(now),
+                after: Some(request.after.map_or(now, |after| after.max(now))),
+                ..request.clone()
+            }),
+            None => Cow::Borrowed(request),
+        }
+    }
+
+    pub fn try_new(config: SpotPricePlannerConfig) -> Result<Self, PlannerConfigError> {
+        config.validate()?;
+
+        Ok(Self::new(config))
+    }
+
+    /// The cached time zone, unless the config has been pointed at another one since.
+    fn local_time_zone(&self) -> Result<Tz, Box<dyn Error>> {
+        match &self.local_time_zone {
+            Some((name, time_zone)) if *name == self.config.local_time_zone => Ok(*time_zone),
+            _ => self.config.get_local_time_zone(),
+        }
+    }
+
+    /// The time slots as UTC intervals per local date they start on, for every local date the spot prices touch
+    /// and the day before.
+    fn time_slot_intervals(
+        &self,
+        spot_prices: &[SpotPrice],
+        local_time_zone: &Tz,
+    ) -> HashMap<NaiveDate, Vec<Interval>> {
+        let local_date = |t: DateTime<Utc>| t.with_timezone(local_time_zone).date_naive();
+        let first_date = spot_prices.iter().map(|sp| sp.from).min().map(local_date);
+        let last_date = spot_prices.iter().map(|sp| sp.till).max().map(local_date);
+        let (mut date, last_date) = match (first_date, last_date) {
+            (Some(first_date), Some(last_date)) => {
+                (first_date.pred_opt().unwrap_or(first_date), last_date)
+            }
+            _ => return HashMap::new(),
+        };
+
+        let mut time_slot_intervals = HashMap::new();
+        while date <= last_date {
+            let intervals = self
+                .config
+                .time_slots_on(date)
+                .iter()
+                .filter_map(|time_slot| time_slot.local_interval_on(date, local_time_zone))
+                .map(|(from, till)| (from.with_timezone(&Utc), till.with_timezone(&Utc)))
+                .collect();
+            time_slot_intervals.insert(date, intervals);
+            date = match date.succ_opt() {
+                Some(next_date) => next_date,
+                None => break,
+            };
+        }
+        time_slot_intervals
+    }
+
+    pub fn get_plannable_spot_prices(
+        &self,
+        spot_prices: &[SpotPrice],
+        after: &Option<DateTime<Utc>>,
+        before: &Option<DateTime<Utc>>,
+    ) -> Result<Vec<SpotPrice>, Box<dyn Error>> {
+        let local_time_zone = self.local_time_zone()?;
+
+        info!(
+            "Determining plannable spot prices after {:?} and before {:?}",
+            after, before
+        );
+        debug!(
+            "spot_prices:\n{}",
+            format_price_table(spot_prices, &local_time_zone)
+        );
+
+        let time_slot_intervals = self.time_slot_intervals(spot_prices, &local_time_zone);
+        let plannable_spot_prices: Vec<SpotPrice> = spot_prices
+            .iter()
+            .flat_map(|spot_price| {
+                self.plannable_parts(
+                    spot_price,
+                    after,
+                    before,
+                    &local_time_zone,
+                    &time_slot_intervals,
+                )
+            })
+            .collect();
+
+        debug!(
+            "plannable_spot_prices:\n{}",
+            format_price_table(&plannable_spot_prices, &local_time_zone)
+        );
+
+        Ok(plannable_spot_prices)
+    }
+
+    /// The parts of spot_price that are plannable: the whole spot price or nothing, unless clip_to_bounds is set.
+    fn plannable_parts(
+        &self,
+        spot_price: &SpotPrice,
+        after: &Option<DateTime<Utc>>,
+        before: &Option<DateTime<Utc>>,
+        local_time_zone: &Tz,
+        time_slot_intervals: &HashMap<NaiveDate, Vec<Interval>>,
+    ) -> Vec<SpotPrice> {
+        let clip_to_bounds = self.config.clip_to_bounds;
+        let spot_price = if clip_to_bounds {
+            match spot_price.clip(
+                after.unwrap_or(spot_price.from),
+                before.unwrap_or(spot_price.till),
+            ) {
+                Some(clipped) => clipped,
+                None => return vec![],
+            }
+        } else if is_within_bounds(spot_price, after, before) {
+            spot_price.clone()
+        } else {
+            return vec![];
+        };
+
+        let date = spot_price.from.with_timezone(local_time_zone).date_naive();
+        if self.config.plannable_local_time_slots.is_empty()
+            && !self.config.holidays.contains(&date)
+        {
+            return vec![spot_price];
+        }
+
+        // slots wrapping past midnight on the previous day can cover this spot price as well
+        let time_slot_intervals = std::iter::once(date)
+            .chain(date.pred_opt())
+            .filter_map(|date| time_slot_intervals.get(&date))
+            .flatten();
+
+        if !clip_to_bounds {
+            let is_covered = time_slot_intervals
+                .clone()
+                .any(|(time_slot_from, time_slot_till)| {
+                    spot_price.from >= *time_slot_from && spot_price.till <= *time_slot_till
+                });
+            return if is_covered { vec![spot_price] } else { vec![] };
+        }
+
+        // keep the overlap with each time slot, merging overlapping time slots
+        let overlaps: Vec<(DateTime<Utc>, DateTime<Utc>)> = time_slot_intervals
+            .filter_map(|(time_slot_from, time_slot_till)| {
+                spot_price.clip(*time_slot_from, *time_slot_till)
+            })
+            .map(|clipped| (clipped.from, clipped.till))
+            .collect();
+
+        merge_intervals(overlaps)
+            .into_iter()
+            .filter_map(|(from, till)| spot_price.clip(from, till))
+            .collect()
+    }
+
+    pub fn get_best_spot_prices(
+        &self,
+        request: &PlanningRequest,
+    ) -> Result<PlanningOutcome, Box<dyn Error>> {
+        let request = self.resolve_now(request);
+        let request = request.as_ref();
+        let now = request.now.unwrap_or_else(Utc::now);
+
+        if let Some(outcome) = self.coverage_incomplete(request)? {
+            return Ok(outcome);
+        }
+
+        if let Some(deadline_policy) = request
+            .deadline_policy
+            .filter(|deadline_policy| deadline_policy.is_urgent(now, &request.load_profile))
+        {
+            if let Some(mut response) = self.forced_plan(request, &deadline_policy, now)? {
+                if request.explain {
+                    response.explanation = self.explain(request, Some(&response))?;
+                }
+                return Ok(PlanningOutcome::Planned(response));
+            }
+        }
+
+        if let Some(mut response) = self
+            .get_best_spot_prices_ranked(request, 1)?
+            .into_iter()
+            .next()
+        {
+            if request.explain {
+                response.explanation = self.explain(request, Some(&response))?;
+            }
+            return Ok(PlanningOutcome::Planned(response));
+        }
+
+        // explain with the spot prices the last planning attempt considered
+        let spot_prices = self.normalize_spot_prices(&request.spot_prices)?;
+        let considered_spot_prices = if request.enable_fallback {
+            bounded_spot_prices(&spot_prices, request)
+        } else {
+            self.get_plannable_spot_prices(&spot_prices, &request.after, &request.before)?
+        };
+
+        let mut outcome = self.not_plannable(&considered_spot_prices, request);
+        if let PlanningOutcome::NotPlannable { explanation, .. } = &mut outcome {
+            if request.explain {
+                *explanation = self.explain(request, None)?;
+            }
+        }
+        Ok(outcome)
+    }
+
+    /// Why each spot price, other than the ones the planned load starts or runs in, wasn't picked as the start of the
+    /// block. Only the consecutive lowest and highest price strategies evaluate the block from each start; for the
+    /// other strategies plannable spot prices left out of the plan lost on price.
+    fn explain(
+        &self,
+        request: &PlanningRequest,
+        planned: Option<&PlanningResponse>,
+    ) -> Result<Vec<CandidateRejection>, Box<dyn Error>> {
+        let spot_prices = self.normalize_spot_prices(&request.spot_prices)?;
+        let plannable_spot_prices =
+            self.get_plannable_spot_prices(&spot_prices, &request.after, &request.before)?;
+        let criteria = self.block_criteria(request, true);
+        let total_required_seconds = request.load_profile.total_duration_seconds();
+        let planned_total_price = planned.map(|response| response.total_price(None));
+        let is_consecutive = matches!(
+            request.planning_strategy,
+            PlanningStrategy::LowestPrice | PlanningStrategy::HighestPrice
+        );
+
+        let mut explanation = vec![];
+        for spot_price in &spot_prices {
+            let is_planned = planned.is_some_and(|response| match response.planned_from {
+                Some(planned_from) if is_consecutive => spot_price.contains(planned_from),
+                _ => response
+                    .spot_prices
+                    .iter()
+                    .any(|planned| spot_price.contains(planned.from)),
+            });
+            if is_planned {
+                continue;
+            }
+
+            let rejection =
+                |reason: RejectionReason, price_delta: Option<f64>| CandidateRejection {
+                    from: spot_price.from,
+                    reason,
+                    price_delta,
+                };
+            let start = match plannable_spot_prices
+                .iter()
+                .position(|plannable| spot_price.contains(plannable.from))
+            {
+                Some(start) => start,
+                None => {
+                    explanation.push(rejection(RejectionReason::OutsideTimeSlots, None));
+                    continue;
+                }
+            };
+            if !is_consecutive {
+                explanation.push(rejection(RejectionReason::LostOnPrice, None));
+                continue;
+            }
+
+            let skip_seconds = match criteria.start_skip_seconds(&plannable_spot_prices[start]) {
+                Some(skip_seconds) => skip_seconds,
+                None => {
+                    explanation.push(rejection(RejectionReason::OutsideWindow, None));
+                    continue;
+                }
+            };
+            let planned_from = plannable_spot_prices[start].from + Duration::seconds(skip_seconds);
+            if !criteria.finishes_in_time(planned_from, total_required_seconds) {
+                explanation.push(rejection(RejectionReason::OutsideWindow, None));
+                continue;
+            }
+
+            let mut selected_seconds =
+                plannable_spot_prices[start].duration_seconds() - skip_seconds;
+            let mut end = start + 1;
+            while end < plannable_spot_prices.len()
+                && selected_seconds < total_required_seconds
+                && plannable_spot_prices[end - 1].is_adjacent_to(&plannable_spot_prices[end])
+            {
+                selected_seconds += plannable_spot_prices[end].duration_seconds();
+                end += 1;
+            }
+            if selected_seconds < total_required_seconds {
+                explanation.push(rejection(
+                    RejectionReason::InsufficientConsecutiveDuration,
+                    None,
+                ));
+                continue;
+            }
+
+            let block = &plannable_spot_prices[start..end];
+            if !criteria.fits_available_power(block, skip_seconds) {
+                explanation.push(rejection(RejectionReason::ExceedsAvailablePower, None));
+                continue;
+            }
+
+            let total_price = criteria.total_price(block, skip_seconds);
+            explanation.push(match criteria.max_total_price {
+                Some(max_total_price) if !criteria.is_acceptable(total_price) => rejection(
+                    RejectionReason::AbovePriceThreshold,
+                    Some(total_price - max_total_price),
+                ),
+                _ => rejection(
+                    RejectionReason::LostOnPrice,
+                    planned_total_price
+                        .map(|planned_total_price| total_price - planned_total_price),
+                ),
+            });
+        }
+
+        for rejection in &explanation {
+            debug!(
+                "Candidate starting at {} rejected: {:?} {:?}",
+                rejection.from, rejection.reason, rejection.price_delta
+            );
+        }
+
+        Ok(explanation)
+    }
+
+    /// The n best candidate plans ordered by the strategy, at most one per starting spot price; strategies that
+    /// combine non-adjacent spot prices yield a single candidate.
+    pub fn get_best_spot_prices_ranked(
+        &self,
+        request: &PlanningRequest,
+        n: usize,
+    ) -> Result<Vec<PlanningResponse>, Box<dyn Error>> {
+        let request = self.resolve_now(request);
+        let request = request.as_ref();
+
+        // the block search relies on sorted, non-overlapping spot prices
+        let spot_prices = self.normalize_spot_prices(&request.spot_prices)?;
+        let plannable_spot_prices: Vec<SpotPrice> =
+            self.get_plannable_spot_prices(&spot_prices, &request.after, &request.before)?;
+
+        let responses = self.plan_avoiding_spikes(&plannable_spot_prices, request, n);
+        if !responses.is_empty() || !request.enable_fallback {
+            return Ok(responses);
+        }
+
+        let bounded_spot_prices = bounded_spot_prices(&spot_prices, request);
+
+        let required_seconds = request.load_profile.total_duration_seconds();
+        let available_seconds: i64 = bounded_spot_prices
+            .iter()
+            .map(|spot_price| spot_price.duration_seconds())
+            .sum();
+        if available_seconds < required_seconds {
+            return Err(Box::new(NotEnoughPricesError {
+                required_seconds,
+                available_seconds,
+            }));
+        }
+
+        info!("No block fits inside the plannable time slots, falling back to ignoring them");
+        let mut responses = self.plan_avoiding_spikes(&bounded_spot_prices, request, n);
+        for response in &mut responses {
+            response.fallback_used = true;
+        }
+
+        Ok(responses)
+    }
+
+    #[deprecated(note = "use get_best_spot_prices, which returns a PlanningOutcome")]
+    pub fn get_best_spot_prices_legacy(
+        &self,
+        request: &PlanningRequest,
+    ) -> Result<PlanningResponse, Box<dyn Error>> {
+        match self.get_best_spot_prices(request)? {
+            PlanningOutcome::Planned(response) => Ok(response),
+            PlanningOutcome::NotPlannable { .. } | PlanningOutcome::CoverageIncomplete { .. } => {
+                Ok(PlanningResponse::for_spot_prices(
+                    vec![],
+                    0,
+                    &request.load_profile,
+                    request
+                        .price_components
+                        .clone()
+                        .unwrap_or_else(|| self.config.price_components.clone()),
+                ))
+            }
+        }
+    }
+
+    /// CoverageIncomplete when the request requires full coverage and the spot prices don't provide it.
+    fn coverage_incomplete(
+        &self,
+        request: &PlanningRequest,
+    ) -> Result<Option<PlanningOutcome>, Box<dyn Error>> {
+        if !request.require_full_coverage {
+            return Ok(None);
+        }
+
+        let uncovered_intervals = self.uncovered_intervals(request)?;
+        if uncovered_intervals.is_empty() {
+            return Ok(None);
+        }
+
+        warn!(
+            "Spot prices don't cover {:?} yet, wait for the next price publication before planning",
+            uncovered_intervals
+        );
+        Ok(Some(PlanningOutcome::CoverageIncomplete {
+            uncovered_intervals,
+        }))
+    }
+
+    /// Like get_best_spot_prices, but plans within windows of the given length sliding over the spot prices by step
+    /// and keeps the best of them, bounding the work per search to the window. The outcome equals
+    /// get_best_spot_prices as long as window minus step is at least the load's duration. Strategies combining
+    /// spot prices across the horizon, the median multiplier, an urgent deadline and explaining an unplannable
+    /// load fall back to planning the whole horizon at once.
+    pub fn get_best_spot_prices_windowed(
+        &self,
+        request: &PlanningRequest,
+        window: Duration,
+        step: Duration,
+    ) -> Result<PlanningOutcome, Box<dyn Error>> {
+        if window <= Duration::zero() || step <= Duration::zero() {
+            return Err(Box::<dyn Error>::from(
+                "Window and step have to be positive",
+            ));
+        }
+
+        let request = self.resolve_now(request);
+        let request = request.as_ref();
+        let now = request.now.unwrap_or_else(Utc::now);
+
+        let is_windowable = matches!(
+            request.planning_strategy,
+            PlanningStrategy::LowestPrice
+                | PlanningStrategy::HighestPrice
+                | PlanningStrategy::OnlyNegativePrices
+        ) && request.max_price_median_multiplier.is_none()
+            && request.deadline_policy.is_none_or(|deadline_policy| {
+                !deadline_policy.is_urgent(now, &request.load_profile)
+            });
+        if !is_windowable {
+            return self.get_best_spot_prices(request);
+        }
+
+        if let Some(outcome) = self.coverage_incomplete(request)? {
+            return Ok(outcome);
+        }
+
+        let spot_prices = self.normalize_spot_prices(&request.spot_prices)?;
+        let (first_from, last_till) = match (spot_prices.first(), spot_prices.last()) {
+            (Some(first), Some(last)) => (first.from, last.till),
+            _ => return self.get_best_spot_prices(request),
+        };
+
+        let prefers_highest = request.planning_strategy.prefers_highest();
+        let tie_breaker = request.tie_breaker.unwrap_or(self.config.tie_breaker);
+        let tie_breaker_epsilon = self.tie_breaker_epsilon();
+
+        let mut best: Option<(PlanningResponse, f64)> = None;
+        let mut window_from = first_from;
+        loop {
+            let window_till = window_from + window;
+            let window_request = PlanningRequest {
+                spot_prices: spot_prices
+                    .iter()
+                    .filter(|spot_price| {
+                        spot_price.from >= window_from && spot_price.till <= window_till
+                    })
+                    .cloned()
+                    .collect(),
+                require_full_coverage: false,
+                enable_fallback: false,
+                deadline_policy: None,
+                ..request.clone()
+            };
+
+            if let Some(response) = self
+                .get_best_spot_prices_ranked(&window_request, 1)?
+                .into_iter()
+                .next()
+            {
+                let total_price = response.total_price(None);
+                let is_better = match &best {
+                    None => true,
+                    Some((best_response, best_price)) => {
+                        if (total_price - best_price).abs() <= tie_breaker_epsilon {
+                            match tie_breaker {
+                                TieBreaker::EarliestStart => {
+                                    response.planned_from < best_response.planned_from
+                                }
+                                TieBreaker::LatestStart => {
+                                    response.planned_from > best_response.planned_from
+                                }
+                            }
+                        } else {
+                            (total_price > *best_price) == prefers_highest
+                        }
+                    }
+                };
+                if is_better {
+                    best = Some((response, total_price));
+                }
+            }
+
+            if window_till >= last_till {
+                break;
+            }
+            window_from += step;
+        }
+
+        match best {
+            Some((response, _)) => Ok(PlanningOutcome::Planned(response)),
+            None => self.get_best_spot_prices(request),
+        }
+    }
+
+    /// Plans the load once in each of the given number of local days, starting with the local day of after or else
+    /// of the first spot price; every day is planned on its own, so a day without enough spot prices is not
+    /// plannable while the other days still get planned.
+    pub fn get_daily_plans(
+        &self,
+        request: &PlanningRequest,
+        days: u32,
+    ) -> Result<Vec<(NaiveDate, PlanningOutcome)>, Box<dyn Error>> {
+        let request = self.resolve_now(request);
+        let request = request.as_ref();
+        let time_zone = self.local_time_zone()?;
+
+        let first_from = request
+            .after
+            .or_else(|| {
+                request
+                    .spot_prices
+                    .iter()
+                    .map(|spot_price| spot_price.from)
+                    .min()
+            })
+            .unwrap_or_else(Utc::now);
+        let first_date = first_from.with_timezone(&time_zone).date_naive();
+
+        let mut daily_plans = vec![];
+        for date in first_date.iter_days().take(days as usize) {
+            let day_from = local_midnight(date, &time_zone);
+            let day_till = date
+                .succ_opt()
+                .and_then(|next_date| local_midnight(next_date, &time_zone));
+            let day_request = PlanningRequest {
+                after: request.after.max(day_from),
+                before: match (request.before, day_till) {
+                    (Some(before), Some(day_till)) => Some(before.min(day_till)),
+                    (before, day_till) => before.or(day_till),
+                },
+                ..request.clone()
+            };
+            daily_plans.push((date, self.get_best_spot_prices(&day_request)?));
+        }
+
+        Ok(daily_plans)
+    }
+
+    /// Like get_best_spot_prices, with the request's now set to now.
+    pub fn get_best_spot_prices_at(
+        &self,
+        request: &PlanningRequest,
+        now: DateTime<Utc>,
+    ) -> Result<PlanningOutcome, Box<dyn Error>> {
+        self.get_best_spot_prices(&PlanningRequest {
+            now: Some(now),
+            ..request.clone()
+        })
+    }
+
+    /// Block starting from now and finishing by the deadline, planned without the price threshold.
+    fn forced_plan(
+        &self,
+        request: &PlanningRequest,
+        deadline_policy: &DeadlinePolicy,
+        now: DateTime<Utc>,
+    ) -> Result<Option<PlanningResponse>, Box<dyn Error>> {
+        let forced_request = PlanningRequest {
+            start_after: Some(
+                request
+                    .start_after
+                    .map_or(now, |start_after| start_after.max(now)),
+            ),
+            finish_by: Some(
+                request
+                    .finish_by
+                    .map_or(deadline_policy.deadline, |finish_by| {
+                        finish_by.min(deadline_policy.deadline)
+                    }),
+            ),
+            ..request.clone()
+        };
+
+        let spot_prices = self.normalize_spot_prices(&request.spot_prices)?;
+        let spot_prices = if deadline_policy.ignore_time_slots {
+            bounded_spot_prices(&spot_prices, &forced_request)
+        } else {
+            self.get_plannable_spot_prices(&spot_prices, &request.after, &request.before)?
+        };
+
+        let response = self
+            .plan(&spot_prices, &forced_request, false, 1)
+            .into_iter()
+            .next()
+            .map(|mut response| {
+                response.forced = true;
+                response
+            });
+        if response.is_some() {
+            info!(
+                "Deadline {} is close, planning regardless of the price threshold",
+                deadline_policy.deadline
+            );
+        }
+
+        Ok(response)
+    }
+
+    /// Plannable parts of the window between after and before, restricted to the time slots, without a spot price.
+    fn uncovered_intervals(
+        &self,
+        request: &PlanningRequest,
+    ) -> Result<Vec<Interval>, Box<dyn Error>> {
+        let local_time_zone = self.local_time_zone()?;
+        let spot_prices = self.normalize_spot_prices(&request.spot_prices)?;
+
+        let window_from = request
+            .after
+            .or_else(|| spot_prices.first().map(|spot_price| spot_price.from));
+        let window_till = request
+            .before
+            .or_else(|| spot_prices.last().map(|spot_price| spot_price.till));
+        let (window_from, window_till) = match (window_from, window_till) {
+            (Some(from), Some(till)) if from < till => (from, till),
+            _ => return Ok(vec![]),
+        };
+
+        // without time slots a whole local day is plannable
+        let whole_day = [TimeSlot {
+            from: NaiveTime::MIN,
+            till: NaiveTime::MIN,
+            wraps_midnight: false,
+        }];
+
+        // start a day early for time slots wrapping past midnight
+        let mut required = vec![];
+        let mut date = window_from.with_timezone(&local_time_zone).date_naive();
+        date = date.pred_opt().unwrap_or(date);
+        let last_date = window_till.with_timezone(&local_time_zone).date_naive();
+        while date <= last_date {
+            let time_slots = if self.config.plannable_local_time_slots.is_empty()
+                && !self.config.holidays.contains(&date)
+            {
+                &whole_day[..]
+            } else {
+                self.config.time_slots_on(date)
+            };
+            required.extend(
+                time_slots
+                    .iter()
+                    .filter_map(|time_slot| time_slot.local_interval_on(date, &local_time_zone))
+                    .map(|(from, till)| {
+                        (
+                            from.with_timezone(&Utc).max(window_from),
+                            till.with_timezone(&Utc).min(window_till),
+                        )
+                    })
+                    .filter(|(from, till)| from < till),
+            );
+            date = match date.succ_opt() {
+                Some(next_date) => next_date,
+                None => break,
+            };
+        }
+
+        let covered = merge_intervals(
+            spot_prices
+                .iter()
+                .map(|spot_price| (spot_price.from, spot_price.till))
+                .collect(),
+        );
+
+        let mut uncovered = vec![];
+        for (from, till) in merge_intervals(required) {
+            let mut gap_from = from;
+            for (covered_from, covered_till) in covered
+                .iter()
+                .filter(|(covered_from, covered_till)| *covered_from < till && *covered_till > from)
+            {
+                if *covered_from > gap_from {
+                    uncovered.push((gap_from, *covered_from));
+                }
+                gap_from = gap_from.max(*covered_till);
+            }
+            if gap_from < till {
+                uncovered.push((gap_from, till));
+            }
+        }
+
+        Ok(uncovered)
+    }
+
+    fn not_plannable(
+        &self,
+        plannable_spot_prices: &[SpotPrice],
+        request: &PlanningRequest,
+    ) -> PlanningOutcome {
+        let reason = if plannable_spot_prices.is_empty() {
+            NotPlannableReason::NoPlannablePrices
+        } else if self
+            .plan(plannable_spot_prices, request, false, 1)
+            .is_empty()
+        {
+            NotPlannableReason::NotEnoughConsecutiveDuration
+        } else {
+            NotPlannableReason::PriceThresholdNotMet
+        };
+        info!("Load is not plannable: {}", reason);
+
+        PlanningOutcome::NotPlannable {
+            reason,
+            explanation: vec![],
+        }
+    }
+
+    fn block_criteria<'a>(
+        &'a self,
+        request: &'a PlanningRequest,
+        apply_max_price: bool,
+    ) -> BlockCriteria<'a> {
+        BlockCriteria {
+            load_profile: &request.load_profile,
+            planning_strategy: request.planning_strategy,
+            // the threshold is per kWh, blocks are compared on their total price for the load
+            max_total_price: request
+                .max_acceptable_total_price_per_kwh
+                .or(self.config.max_acceptable_total_price_per_kwh)
+                .filter(|_| apply_max_price)
+                .map(|max_price_per_kwh| {
+                    max_price_per_kwh * request.load_profile.total_energy_kwh()
+                }),
+            price_components: request
+                .price_components
+                .as_ref()
+                .unwrap_or(&self.config.price_components),
+            start_after: request.start_after,
+            finish_by: request.finish_by,
+            tie_breaker: request.tie_breaker.unwrap_or(self.config.tie_breaker),
+            tie_breaker_epsilon: self.tie_breaker_epsilon(),
+            available_power_watt: Some(SpotPriceValues::new(
+                &request.available_power_watt,
+                &request.spot_prices,
+            )),
+        }
+    }
+
+    /// The n best plans for the request, best first.
+    fn plan(
+        &self,
+        plannable_spot_prices: &[SpotPrice],
+        request: &PlanningRequest,
+        apply_max_price: bool,
+        n: usize,
+    ) -> Vec<PlanningResponse> {
+        let criteria = self.block_criteria(request, apply_max_price);
+        let price_components = criteria.price_components;
+
+        let mut percentile_fallback_used = false;
+        let candidates: Vec<(Vec<SpotPrice>, i64)> = match request.planning_strategy {
+            PlanningStrategy::LowestPrice | PlanningStrategy::HighestPrice => {
+                self.ranked_consecutive_spot_prices(plannable_spot_prices, &criteria, n)
+            }
+            PlanningStrategy::OnlyNegativePrices => {
+                // dropping the other spot prices leaves gaps no block can span
+                let negative_spot_prices: Vec<SpotPrice> = plannable_spot_prices
+                    .iter()
+                    .filter(|spot_price| spot_price.is_negative_market_price())
+                    .cloned()
+                    .collect();
+                ranked_consecutive_spot_prices(&negative_spot_prices, &criteria, n)
+            }
+            PlanningStrategy::MaximizeSelfConsumption => self_consumption_ranked_spot_prices(
+                plannable_spot_prices,
+                &criteria,
+                &SpotPriceValues::new(&request.expected_production_watt, &request.spot_prices),
+                n,
+            ),
+            PlanningStrategy::LowestPriceNonConsecutive
+            | PlanningStrategy::HighestPriceNonConsecutive => {
+                // runs can't start part way into a spot price, so only whole slots within the window qualify;
+                // which part of the load lands in a slot depends on the selection, so the peak draw has to fit
+                let plannable_spot_prices: Vec<SpotPrice> = plannable_spot_prices
+                    .iter()
+                    .filter(|spot_price| {
+                        criteria.is_within_window(spot_price)
+                            && criteria.may_fit_available_power(spot_price)
+                    })
+                    .cloned()
+                    .collect();
+                let plannable_spot_prices = &plannable_spot_prices[..];
+                let min_block_duration_seconds = request
+                    .min_block_duration_seconds
+                    .filter(|min_block_duration_seconds| *min_block_duration_seconds > 0);
+                // the time zone was already checked while determining the plannable spot prices
+                let max_runs_per_local_day = request
+                    .max_runs_per_local_day
+                    .map(|max_runs| (max_runs, self.local_time_zone().unwrap_or(Tz::UTC)));
+                let min_gap = request
+                    .min_gap_between_runs_seconds
+                    .filter(|min_gap_seconds| *min_gap_seconds > 0)
+                    .map(Duration::seconds);
+                let selected_spot_prices = if min_block_duration_seconds.is_none()
+                    && max_runs_per_local_day.is_none()
+                    && min_gap.is_none()
+                {
+                    best_non_consecutive_spot_prices(plannable_spot_prices, &criteria)
+                } else {
+                    // a minimum of a single second leaves the runs unconstrained in length
+                    (
+                        best_non_consecutive_spot_prices_with_min_block(
+                            plannable_spot_prices,
+                            &criteria,
+                            min_block_duration_seconds.unwrap_or(1),
+                            max_runs_per_local_day,
+                            min_gap,
+                        ),
+                        0,
+                    )
+                };
+                vec![selected_spot_prices]
+            }
+            PlanningStrategy::LowestPricePercentile { percentile }
+            | PlanningStrategy::HighestPricePercentile { percentile } => {
+                let window_spot_prices: Vec<SpotPrice> = plannable_spot_prices
+                    .iter()
+                    .filter(|spot_price| criteria.is_within_window(spot_price))
+                    .cloned()
+                    .collect();
+                let power_spot_prices: Vec<SpotPrice> = window_spot_prices
+                    .iter()
+                    .filter(|spot_price| criteria.may_fit_available_power(spot_price))
+                    .cloned()
+                    .collect();
+                let prices: Vec<f64> = window_spot_prices
+                    .iter()
+                    .map(|spot_price| criteria.spot_price(spot_price))
+                    .collect();
+                let qualifying_spot_prices: Vec<SpotPrice> = if criteria.prefers_highest() {
+                    price_percentile(&prices, 100.0 - percentile).map_or(vec![], |cut_off| {
+                        power_spot_prices
+                            .iter()
+                            .filter(|spot_price| criteria.spot_price(spot_price) >= cut_off)
+                            .cloned()
+                            .collect()
+                    })
+                } else {
+                    price_percentile(&prices, percentile).map_or(vec![], |cut_off| {
+                        power_spot_prices
+                            .iter()
+                            .filter(|spot_price| criteria.spot_price(spot_price) <= cut_off)
+                            .cloned()
+                            .collect()
+                    })
+                };
+
+                let selected_spot_prices =
+                    best_non_consecutive_spot_prices(&qualifying_spot_prices, &criteria);
+                if selected_spot_prices.0.is_empty() {
+                    percentile_fallback_used = true;
+                    self.ranked_consecutive_spot_prices(plannable_spot_prices, &criteria, n)
+                } else {
+                    vec![selected_spot_prices]
+                }
+            }
+        };
+
+        candidates
+            .into_iter()
+            .filter(|(spot_prices, _)| !spot_prices.is_empty())
+            .take(n)
+            .map(|(spot_prices, skip_seconds)| {
+                let mut response = PlanningResponse::for_spot_prices(
+                    spot_prices,
+                    skip_seconds,
+                    &request.load_profile,
+                    price_components.clone(),
+                );
+                response.percentile_fallback_used = percentile_fallback_used;
+                if !request.expected_production_watt.is_empty() {
+                    let self_consumed_kwh = joules_to_kwh(self_consumed_watt_seconds(
+                        &response.spot_prices,
+                        skip_seconds,
+                        &request.load_profile,
+                        &SpotPriceValues::new(
+                            &request.expected_production_watt,
+                            &request.spot_prices,
+                        ),
+                    ));
+                    response.self_consumed_kwh = Some(self_consumed_kwh);
+                    response.grid_kwh =
+                        Some(request.load_profile.total_energy_kwh() - self_consumed_kwh);
+                }
+                response
+            })
+            .collect()
+    }
+
+    /// Plans without the spot prices above the request's multiple of the median price; when the load doesn't fit
+    /// without them, the cheapest ones are added back one at a time.
+    fn plan_avoiding_spikes(
+        &self,
+        plannable_spot_prices: &[SpotPrice],
+        request: &PlanningRequest,
+        n: usize,
+    ) -> Vec<PlanningResponse> {
+        let multiplier = match request.max_price_median_multiplier {
+            Some(multiplier) => multiplier,
+            None => return self.plan(plannable_spot_prices, request, true, n),
+        };
+
+        let price_components = request
+            .price_components
+            .clone()
+            .unwrap_or_else(|| self.config.price_components.clone());
+        let prices: Vec<f64> = plannable_spot_prices
+            .iter()
+            .map(|spot_price| spot_price.price_for(&price_components))
+            .collect();
+        // a spike lies further above the median than the multiple would for a positive median, also when the median
+        // is zero or negative
+        let cut_off = match price_percentile(&prices, 50.0) {
+            Some(median) => median + (multiplier - 1.0) * median.abs(),
+            None => return vec![],
+        };
+
+        let (mut spot_prices, mut spikes): (Vec<SpotPrice>, Vec<SpotPrice>) = plannable_spot_prices
+            .iter()
+            .cloned()
+            .partition(|spot_price| spot_price.price_for(&price_components) <= cut_off);
+        let responses = self.plan(&spot_prices, request, true, n);
+        if !responses.is_empty() || spikes.is_empty() {
+            return responses;
+        }
+
+        spikes.sort_by(|a, b| {
+            a.price_for(&price_components)
+                .total_cmp(&b.price_for(&price_components))
+        });
+        for spike in spikes {
+            spot_prices.push(spike);
+            spot_prices.sort_by_key(|spot_price| spot_price.from);
+
+            let mut responses = self.plan(&spot_prices, request, true, n);
+            if !responses.is_empty() {
+                info!(
+                    "Load doesn't fit without spot prices above {} per kWh, adding back the cheapest of them",
+                    cut_off
+                );
+                for response in &mut responses {
+                    response.spike_fallback_used = true;
+                }
+                return responses;
+            }
+        }
+
+        vec![]
+    }
+
+    fn ranked_consecutive_spot_prices(
+        &self,
+        plannable_spot_prices: &[SpotPrice],
+        criteria: &BlockCriteria,
+        n: usize,
+    ) -> Vec<(Vec<SpotPrice>, i64)> {
+        match self.config.planning_granularity_seconds {
+            Some(granularity) if granularity > 0 => ranked_consecutive_spot_prices_at_granularity(
+                plannable_spot_prices,
+                criteria,
+                granularity,
+                n,
+            ),
+            _ => ranked_consecutive_spot_prices(plannable_spot_prices, criteria, n),
+        }
+    }
+
+    /// Compares the cost of response with running the same load from reference_start and with the most expensive
+    /// consecutive block; all costs use the price components the response was ranked on.
+    pub fn evaluate_plan(
+        &self,
+        request: &PlanningRequest,
+        response: &PlanningResponse,
+        reference_start: DateTime<Utc>,
+    ) -> PlanEvaluation {
+        let spot_prices = self
+            .normalize_spot_prices(&request.spot_prices)
+            .unwrap_or_else(|_| {
+                let mut spot_prices = request.spot_prices.clone();
+                spot_prices.sort_by_key(|spot_price| spot_price.from);
+                spot_prices
+            });
+        let get_price_fn =
+            |spot_price: &SpotPrice| spot_price.price_for(&response.price_components);
+
+        let reference_cost = total_price_for_load_starting_at(
+            &spot_prices,
+            reference_start,
+            &response.load_profile,
+            &get_price_fn,
+        );
+
+        let worst_case_request = PlanningRequest {
+            load_profile: response.load_profile.clone(),
+            planning_strategy: PlanningStrategy::HighestPrice,
+            price_components: Some(response.price_components.clone()),
+            ..request.clone()
+        };
+        let worst_case_cost = self
+            .get_plannable_spot_prices(&spot_prices, &request.after, &request.before)
+            .ok()
+            .and_then(|plannable_spot_prices| {
+                self.plan(&plannable_spot_prices, &worst_case_request, false, 1)
+                    .into_iter()
+                    .next()
+            })
+            .map(|worst_case| worst_case.total_price(None));
+
+        let evaluation =
+            PlanEvaluation::new(response.total_price(None), reference_cost, worst_case_cost);
+        info!("Evaluated plan: {}", evaluation);
+
+        evaluation
+    }
+
+    /// Keeps the previous plan while all of its spot prices are still plannable and the best plan doesn't improve on
+    /// its total price by more than switch_threshold euros; avoids flip-flopping between nearly equal blocks.
+    pub fn get_best_spot_prices_with_previous(
+        &self,
+        request: &PlanningRequest,
+        previous: Option<&PlanningResponse>,
+        switch_threshold: f64,
+    ) -> Result<PlanningDecision, Box<dyn Error>> {
+        let outcome = self.get_best_spot_prices(request)?;
+        let (previous, best) = match (previous, outcome.planned()) {
+            (Some(previous), Some(best)) if !previous.spot_prices.is_empty() => (previous, best),
+            _ => {
+                return Ok(PlanningDecision {
+                    outcome,
+                    kept_previous: false,
+                })
+            }
+        };
+
+        // the previous plan is only feasible while every one of its spot prices is still plannable
+        let spot_prices = self.normalize_spot_prices(&request.spot_prices)?;
+        let plannable_spot_prices =
+            self.get_plannable_spot_prices(&spot_prices, &request.after, &request.before)?;
+        let plannable_index = SpotPriceIndex::from_sorted(&plannable_spot_prices);
+        let current_spot_prices: Option<Vec<SpotPrice>> = previous
+            .spot_prices
+            .iter()
+            .map(|previous_spot_price| {
+                plannable_index
+                    .price_at(previous_spot_price.from)
+                    .filter(|spot_price| {
+                        spot_price.from == previous_spot_price.from
+                            && spot_price.till == previous_spot_price.till
+                    })
+                    .cloned()
+            })
+            .collect();
+        let is_feasible = |previous: &PlanningResponse| {
+            previous.planned_from.is_some_and(|planned_from| {
+                request
+                    .start_after
+                    .is_none_or(|start_after| planned_from >= start_after)
+            }) && previous.planned_till.is_some_and(|planned_till| {
+                request
+                    .finish_by
+                    .is_none_or(|finish_by| planned_till <= finish_by)
+            })
+        };
+        let current_spot_prices = match current_spot_prices {
+            Some(current_spot_prices) if is_feasible(previous) => current_spot_prices,
+            _ => {
+                info!("Previous plan is no longer feasible, switching to the best plan");
+                return Ok(PlanningDecision {
+                    outcome,
+                    kept_previous: false,
+                });
+            }
+        };
+
+        let repriced = PlanningResponse::for_spot_prices(
+            current_spot_prices,
+            previous.skip_seconds(),
+            &request.load_profile,
+            best.price_components.clone(),
+        );
+        let improvement = if request.planning_strategy.prefers_highest() {
+            best.total_price(None) - repriced.total_price(None)
+        } else {
+            repriced.total_price(None) - best.total_price(None)
+        };
+
+        if improvement > switch_threshold {
+            info!(
+                "Best plan improves on the previous plan by {}, switching",
+                improvement
+            );
+            Ok(PlanningDecision {
+                outcome,
+                kept_previous: false,
+            })
+        } else {
+            Ok(PlanningDecision {
+                outcome: PlanningOutcome::Planned(repriced),
+                kept_previous: true,
+            })
+        }
+    }
+
+    /// Plans each load profile section into its own uninterrupted window at the lowest combined price; sections
+    /// keep their order but may be separated by gaps. Returns None when the sections don't fit in the plannable
+    /// spot prices.
+    pub fn get_best_spot_prices_per_section(
+        &self,
+        request: &PlanningRequest,
+    ) -> Result<Option<SectionPlanningResponse>, Box<dyn Error>> {
+        let request = self.resolve_now(request);
+        let request = request.as_ref();
+        let spot_prices = self.normalize_spot_prices(&request.spot_prices)?;
+        let plannable_spot_prices =
+            self.get_plannable_spot_prices(&spot_prices, &request.after, &request.before)?;
+        let price_components = request
+            .price_components
+            .clone()
+            .unwrap_or_else(|| self.config.price_components.clone());
+        let get_price_fn = |spot_price: &SpotPrice| spot_price.price_for(&price_components);
+
+        let earliest = match plannable_spot_prices.first() {
+            Some(spot_price) => spot_price.from,
+            None => return Ok(None),
+        };
+        let sections = &request.load_profile.sections;
+        let min_gap = Duration::seconds(
+            request
+                .min_gap_between_runs_seconds
+                .unwrap_or_default()
+                .max(0),
+        );
+
+        let mut memo = HashMap::new();
+        let (total_price, starts) = match best_section_starts(
+            &plannable_spot_prices,
+            sections,
+            // the first section has no previous run to rest after, starting right at earliest is never plannable
+            earliest - min_gap,
+            min_gap,
+            &get_price_fn,
+            &mut memo,
+        ) {
+            Some(best) => best,
+            None => return Ok(None),
+        };
+
+        let planned_sections = sections
+            .iter()
+            .zip(starts)
+            .enumerate()
+            .map(|(i, (section, from))| PlannedSection {
+                id: section.id.clone().unwrap_or_else(|| i.to_string()),
+                from,
+                till: from + Duration::seconds(section.duration_seconds),
+                total_price: section_price_from(
+                    &plannable_spot_prices,
+                    from,
+                    section,
+                    &get_price_fn,
+                )
+                .unwrap_or_default(),
+            })
+            .collect();
+
+        Ok(Some(SectionPlanningResponse {
+            sections: planned_sections,
+            total_price,
+        }))
+    }
+
+    /// Plans several appliances in priority order, keeping their combined draw per spot price within
+    /// max_concurrent_power_watt; without a limit spot prices already used by another appliance are skipped.
+    /// Outcomes are returned in the order they were planned.
+    pub fn plan_multiple(
+        &self,
+        mut requests: Vec<NamedPlanningRequest>,
+        max_concurrent_power_watt: Option<f64>,
+    ) -> Result<Vec<NamedPlanningOutcome>, Box<dyn Error>> {
+        // stable, so equal priorities keep their given order
+        requests.sort_by_key(|named_request| std::cmp::Reverse(named_request.priority));
+
+        // spot prices planned so far with the peak draw of the appliance planned in them
+        let mut allocated: Vec<(Vec<SpotPrice>, f64)> = vec![];
+        let mut outcomes: Vec<NamedPlanningOutcome> = vec![];
+        for NamedPlanningRequest {
+            name, mut request, ..
+        } in requests
+        {
+            let individual_outcome = self.get_best_spot_prices(&request)?;
+
+            let peak_power_draw_watt = request.load_profile.peak_power_draw_watt();
+            match max_concurrent_power_watt {
+                Some(max_power_watt) => {
+                    for spot_price in &request.spot_prices {
+                        let headroom = max_power_watt
+                            - overlapping_peak_watt(&allocated, spot_price).sum::<f64>();
+                        let available = request
+                            .available_power_watt
+                            .entry(spot_price.from)
+                            .or_insert(headroom);
+                        *available = available.min(headroom);
+                    }
+                }
+                None => request.spot_prices.retain(|spot_price| {
+                    overlapping_peak_watt(&allocated, spot_price)
+                        .next()
+                        .is_none()
+                }),
+            }
+            let outcome = self.get_best_spot_prices(&request)?;
+
+            let displaced = match (individual_outcome.planned(), outcome.planned()) {
+                (Some(individual), Some(planned)) => individual.segments != planned.segments,
+                (Some(_), None) => true,
+                _ => false,
+            };
+            if let Some(planned) = outcome.planned() {
+                allocated.push((planned.spot_prices.clone(), peak_power_draw_watt));
+            }
+
+            outcomes.push(NamedPlanningOutcome {
+                name,
+                outcome,
+                displaced,
+            });
+        }
+
+        Ok(outcomes)
+    }
+
+    /// The cheapest consecutive window of the given duration within the plannable time slots.
+    pub fn cheapest_window(
+        &self,
+        spot_prices: &[SpotPrice],
+        duration: Duration,
+        after: Option<DateTime<Utc>>,
+        before: Option<DateTime<Utc>>,
+    ) -> Result<Option<PlannedWindow>, Box<dyn Error>> {
+        self.window(
+            spot_prices,
+            duration,
+            after,
+            before,
+            PlanningStrategy::LowestPrice,
+        )
+    }
+
+    /// The most expensive consecutive window of the given duration within the plannable time slots.
+    pub fn most_expensive_window(
+        &self,
+        spot_prices: &[SpotPrice],
+        duration: Duration,
+        after: Option<DateTime<Utc>>,
+        before: Option<DateTime<Utc>>,
+    ) -> Result<Option<PlannedWindow>, Box<dyn Error>> {
+        self.window(
+            spot_prices,
+            duration,
+            after,
+            before,
+            PlanningStrategy::HighestPrice,
+        )
+    }
+
+    fn window(
+        &self,
+        spot_prices: &[SpotPrice],
+        duration: Duration,
+        after: Option<DateTime<Utc>>,
+        before: Option<DateTime<Utc>>,
+        planning_strategy: PlanningStrategy,
+    ) -> Result<Option<PlannedWindow>, Box<dyn Error>> {
+        // a constant 1 watt load makes the total price proportional to the average price per kWh
+        let load_profile = LoadProfile {
+            sections: vec![LoadProfileSection {
+                duration_seconds: duration.num_seconds(),
+                power_draw_watt: 1.0,
+                end_power_draw_watt: None,
+                id: None,
+            }],
+        };
+        let energy_kwh = load_profile.total_energy_kwh();
+        if energy_kwh <= 0.0 {
+            return Ok(None);
+        }
+
+        let outcome = self.get_best_spot_prices(&PlanningRequest {
+            spot_prices: spot_prices.to_vec(),
+            load_profile,
+            planning_strategy,
+            after,
+            before,
+            ..PlanningRequest::default()
+        })?;
+
+        Ok(outcome.into_planned().and_then(|response| {
+            Some(PlannedWindow {
+                from: response.planned_from?,
+                till: response.planned_till?,
+                average_price_per_kwh: response.total_price(None) / energy_kwh,
+                spot_prices: response.spot_prices,
+            })
+        }))
+    }
+
+    fn tie_breaker_epsilon(&self) -> f64 {
+        self.config
+            .tie_breaker_epsilon
+            .unwrap_or(DEFAULT_TIE_BREAKER_EPSILON)
+    }
+
+    /// Spot prices ordered and per kWh, with merged spot prices split back into their slots; mixing currencies is
+    /// refused, as there are no exchange rates to go by.
+    fn normalize_spot_prices(
+        &self,
+        spot_prices: &[SpotPrice],
+    ) -> Result<Vec<SpotPrice>, SpotPriceError> {
+        let spot_prices = match spot_prices
+            .iter()
+            .find_map(|spot_price| spot_price.currency.as_deref())
+        {
+            Some(currency) => SpotPrice::normalize_prices(spot_prices, currency, &HashMap::new())?,
+            None => spot_prices.to_vec(),
+        };
+        let tolerance_seconds = self
+            .config
+            .boundary_tolerance_seconds
+            .unwrap_or(DEFAULT_BOUNDARY_TOLERANCE_SECONDS);
+        SpotPrice::normalize_with_tolerance(
+            spot_prices,
+            Duration::seconds(tolerance_seconds.max(0)),
+        )
+        .map(SpotPrice::unmerge)
+    }
+
+    /// Plans a charge window and a later, non-overlapping discharge window maximizing the price spread times energy.
+    pub fn get_charge_discharge_windows(
+        &self,
+        request: &ChargeDischargeRequest,
+    ) -> Result<ChargeDischargeResponse, Box<dyn Error>> {
+        let efficiency = request.efficiency.unwrap_or(1.0);
+        if !(0.0..=1.0).contains(&efficiency) {
+            return Err(Box::<dyn Error>::from(format!(
+                "Efficiency {} is not between 0 and 1",
+                efficiency
+            )));
+        }
+
+        let spot_prices = self.normalize_spot_prices(&request.spot_prices)?;
+        let plannable_spot_prices: Vec<SpotPrice> =
+            self.get_plannable_spot_prices(&spot_prices, &request.after, &request.before)?;
+
+        let charge_energy_kwh = request.charge_load_profile.total_energy_kwh();
+        let discharge_energy_kwh = request.discharge_load_profile.total_energy_kwh();
+        if charge_energy_kwh <= 0.0 || discharge_energy_kwh <= 0.0 {
+            return Ok(ChargeDischargeResponse::NoAction);
+        }
+
+        let price_components = &self.config.price_components;
+        let charge_blocks = consecutive_blocks(
+            &plannable_spot_prices,
+            &BlockCriteria {
+                load_profile: &request.charge_load_profile,
+                planning_strategy: PlanningStrategy::LowestPrice,
+                max_total_price: None,
+                price_components,
+                start_after: None,
+                finish_by: None,
+                tie_breaker: self.config.tie_breaker,
+                tie_breaker_epsilon: self.tie_breaker_epsilon(),
+                available_power_watt: None,
+            },
+        );
+        let discharge_blocks = consecutive_blocks(
+            &plannable_spot_prices,
+            &BlockCriteria {
+                load_profile: &request.discharge_load_profile,
+                planning_strategy: PlanningStrategy::HighestPrice,
+                max_total_price: None,
+                price_components,
+                start_after: None,
+                finish_by: None,
+                tie_breaker: self.config.tie_breaker,
+                tie_breaker_epsilon: self.tie_breaker_epsilon(),
+                available_power_watt: None,
+            },
+        );
+
+        let mut best: Option<(Range<usize>, Range<usize>, f64, f64)> = None;
+        for (charge_range, _, charge_price) in &charge_blocks {
+            for (discharge_range, _, discharge_price) in &discharge_blocks {
+                // discharge has to start once charging is done
+                if discharge_range.start < charge_range.end {
+                    continue;
+                }
+
+                let price_spread_per_kwh = discharge_price / discharge_energy_kwh * efficiency
+                    - charge_price / charge_energy_kwh;
+                let value = price_spread_per_kwh * charge_energy_kwh.min(discharge_energy_kwh);
+                if best
+                    .as_ref()
+                    .is_none_or(|(_, _, _, best_value)| value > *best_value)
+                {
+                    best = Some((
+                        charge_range.clone(),
+                        discharge_range.clone(),
+                        price_spread_per_kwh,
+                        value,
+                    ));
+                }
+            }
+        }
+
+        match best {
+            Some((charge_range, discharge_range, price_spread_per_kwh, _))
+                if price_spread_per_kwh > request.min_price_spread_per_kwh =>
+            {
+                Ok(ChargeDischargeResponse::Windows {
+                    charge: Box::new(PlanningResponse::for_spot_prices(
+                        plannable_spot_prices[charge_range].to_vec(),
+                        0,
+                        &request.charge_load_profile,
+                        price_components.clone(),
+                    )),
+                    discharge: Box::new(PlanningResponse::for_spot_prices(
+                        plannable_spot_prices[discharge_range].to_vec(),
+                        0,
+                        &request.discharge_load_profile,
+                        price_components.clone(),
+                    )),
+                    price_spread_per_kwh,
+                })
+            }
+            Some((_, _, price_spread_per_kwh, _)) => {
+                info!(
+                    "Best price spread of {} per kWh doesn't exceed the minimum of {}, no charge/discharge action",
+                    price_spread_per_kwh, request.min_price_spread_per_kwh
+                );
+                Ok(ChargeDischargeResponse::BelowSpreadThreshold {
+                    best_price_spread_per_kwh: price_spread_per_kwh,
+                })
+            }
+            None => Ok(ChargeDischargeResponse::NoAction),
+        }
+    }
+}
+
+/// Peak draw of each appliance planned so far in part of spot_price.
+fn overlapping_peak_watt<'a>(
+    allocated: &'a [(Vec<SpotPrice>, f64)],
+    spot_price: &'a SpotPrice,
+) -> impl Iterator<Item = f64> + 'a {
+    allocated
+        .iter()
+        .filter(move |(planned, _)| planned.iter().any(|planned| planned.overlaps(spot_price)))
+        .map(|(_, peak_power_draw_watt)| *peak_power_draw_watt)
+}
+
+/// Index ranges of all runs of adjacent plannable spot prices long enough for the load, with the seconds skipped
+/// in the first spot price to honour start_after and their total price.
+fn consecutive_blocks(
+    plannable_spot_prices: &[SpotPrice],
+    criteria: &BlockCriteria,
+) -> Vec<(Range<usize>, i64, f64)> {
+    let total_required_seconds = criteria.load_profile.total_duration_seconds();
+    let mut blocks: Vec<(Range<usize>, i64, f64)> = vec![];
+    if total_required_seconds <= 0 {
+        return blocks;
+    }
+
+    for start in 0..plannable_spot_prices.len() {
+        let skip_seconds = match criteria.start_skip_seconds(&plannable_spot_prices[start]) {
+            Some(skip_seconds) => skip_seconds,
+            None => continue,
+        };
+        let planned_from = plannable_spot_prices[start].from + Duration::seconds(skip_seconds);
+        if !criteria.finishes_in_time(planned_from, total_required_seconds) {
+            // later starts finish even later
+            break;
+        }
+
+        let mut selected_seconds = plannable_spot_prices[start].duration_seconds() - skip_seconds;
+        let mut end = start + 1;
+        let mut has_gap = false;
+        while end < plannable_spot_prices.len() && selected_seconds < total_required_seconds {
+            if !plannable_spot_prices[end - 1].is_adjacent_to(&plannable_spot_prices[end]) {
+                has_gap = true;
+                break;
+            }
+            selected_seconds += plannable_spot_prices[end].duration_seconds();
+            end += 1;
+        }
+
+        if selected_seconds < total_required_seconds {
+            if has_gap {
+                // block interrupted by a non-plannable period, a later start might still fit
+                continue;
+            }
+            // not enough remaining spot prices to get to the required seconds
+            break;
+        }
+
+        if !criteria.fits_available_power(&plannable_spot_prices[start..end], skip_seconds) {
+            continue;
+        }
+
+        let total_price = criteria.total_price(&plannable_spot_prices[start..end], skip_seconds);
+        blocks.push((start..end, skip_seconds, total_price));
+    }
+
+    blocks
+}
+
+/// What makes a candidate block acceptable and better than another.
+struct BlockCriteria<'a> {
+    load_profile: &'a LoadProfile,
+    planning_strategy: PlanningStrategy,
+    max_total_price: Option<f64>,
+    price_components: &'a PriceComponents,
+    start_after: Option<DateTime<Utc>>,
+    finish_by: Option<DateTime<Utc>>,
+    tie_breaker: TieBreaker,
+    tie_breaker_epsilon: f64,
+    available_power_watt: Option<SpotPriceValues<'a>>,
+}
+
+/// Values given per request spot price, keyed by its from, looked up by the moment they apply to so the parts of a
+/// spot price that got clipped or unmerged still find the value of the spot price they came from.
+struct SpotPriceValues<'a> {
+    values: &'a HashMap<DateTime<Utc>, f64>,
+    /// The request's spot prices that have a value, sorted by from.
+    spot_prices: Vec<SpotPrice>,
+}
+
+impl<'a> SpotPriceValues<'a> {
+    fn new(values: &'a HashMap<DateTime<Utc>, f64>, spot_prices: &[SpotPrice]) -> Self {
+        let mut spot_prices: Vec<SpotPrice> = spot_prices
+            .iter()
+            .filter(|spot_price| values.contains_key(&spot_price.from))
+            .cloned()
+            .collect();
+        spot_prices.sort_by_key(|spot_price| spot_price.from);
+
+        Self {
+            values,
+            spot_prices,
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.spot_prices.is_empty()
+    }
+
+    /// The value of the spot price containing t.
+    fn at(&self, t: DateTime<Utc>) -> Option<f64> {
+        SpotPriceIndex::from_sorted(&self.spot_prices)
+            .price_at(t)
+            .and_then(|spot_price| self.values.get(&spot_price.from))
+            .copied()
+    }
+}
+
+impl BlockCriteria<'_> {
+    /// Seconds to skip at the start of spot_price to not start before start_after, None if it ends before then.
+    fn start_skip_seconds(&self, spot_price: &SpotPrice) -> Option<i64> {
+        match self.start_after {
+            Some(start_after) if spot_price.till <= start_after => None,
+            Some(start_after) if spot_price.from < start_after => {
+                Some((start_after - spot_price.from).num_seconds())
+            }
+            _ => Some(0),
+        }
+    }
+
+    fn finishes_in_time(&self, planned_from: DateTime<Utc>, duration_seconds: i64) -> bool {
+        self.finish_by
+            .is_none_or(|finish_by| planned_from + Duration::seconds(duration_seconds) <= finish_by)
+    }
+
+    /// Whether the load started skip_seconds into the first spot price never draws more than the available power.
+    fn fits_available_power(&self, spot_prices: &[SpotPrice], skip_seconds: i64) -> bool {
+        let available_power_watt = match &self.available_power_watt {
+            Some(available_power_watt) if !available_power_watt.is_empty() => available_power_watt,
+            _ => return true,
+        };
+
+        let total_required_seconds = self.load_profile.total_duration_seconds();
+        let mut allocated_seconds = 0;
+        for (i, spot_price) in spot_prices.iter().enumerate() {
+            let available_seconds = if i == 0 {
+                spot_price.duration_seconds() - skip_seconds
+            } else {
+                spot_price.duration_seconds()
+            };
+            let seconds = std::cmp::min(
+                available_seconds.max(0),
+                total_required_seconds - allocated_seconds,
+            );
+            if let Some(available) = available_power_watt.at(spot_price.from) {
+                let peak = load_peak_watt_between(
+                    self.load_profile,
+                    allocated_seconds,
+                    allocated_seconds + seconds,
+                );
+                if peak > available {
+                    return false;
+                }
+            }
+            allocated_seconds += seconds;
+        }
+
+        true
+    }
+
+    /// Whether any part of the load could run in the spot price without exceeding its available power; used where
+    /// the part of the load landing in a spot price isn't known up front.
+    fn may_fit_available_power(&self, spot_price: &SpotPrice) -> bool {
+        self.available_power_watt
+            .as_ref()
+            .and_then(|available_power_watt| available_power_watt.at(spot_price.from))
+            .is_none_or(|available| self.load_profile.peak_power_draw_watt() <= available)
+    }
+
+    fn is_within_window(&self, spot_price: &SpotPrice) -> bool {
+        is_within_bounds(spot_price, &self.start_after, &self.finish_by)
+    }
+
+    fn spot_price(&self, spot_price: &SpotPrice) -> f64 {
+        spot_price.price_for(self.price_components)
+    }
+
+    fn total_price(&self, spot_prices: &[SpotPrice], skip_seconds: i64) -> f64 {
+        total_price_for_load_from(spot_prices, skip_seconds, self.load_profile, &|sp| {
+            self.spot_price(sp)
+        })
+    }
+
+    fn is_acceptable(&self, total_price: f64) -> bool {
+        self.max_total_price.is_none_or(|max| total_price <= max)
+    }
+
+    fn prefers_highest(&self) -> bool {
+        self.planning_strategy.prefers_highest()
+    }
+
+    fn is_better(&self, total_price: f64, best_total_price: f64) -> bool {
+        self.compare(total_price, best_total_price) == Ordering::Less
+    }
+
+    fn is_tie(&self, total_price: f64, other_total_price: f64) -> bool {
+        (total_price - other_total_price).abs() <= self.tie_breaker_epsilon
+    }
+
+    /// Whether a candidate starting later than the best so far replaces it, applying the tie breaker.
+    fn is_better_later_start(&self, total_price: f64, best_total_price: f64) -> bool {
+        if self.is_tie(total_price, best_total_price) {
+            self.tie_breaker == TieBreaker::LatestStart
+        } else {
+            self.is_better(total_price, best_total_price)
+        }
+    }
+
+    /// Orders total prices from best to worst for the strategy.
+    fn compare(&self, total_price: f64, other_total_price: f64) -> Ordering {
+        let ordering = total_price.total_cmp(&other_total_price);
+        if self.prefers_highest() {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    }
+}
+
+/// Price at the given percentile (0-100), interpolating linearly between the closest ranks.
+fn price_percentile(prices: &[f64], percentile: f64) -> Option<f64> {
+    if prices.is_empty() {
+        return None;
+    }
+
+    let mut sorted_prices = prices.to_vec();
+    sorted_prices.sort_by(|a, b| a.total_cmp(b));
+
+    let rank = percentile.clamp(0.0, 100.0) / 100.0 * (sorted_prices.len() - 1) as f64;
+    let lower = rank.floor() as usize;
+    let upper = rank.ceil() as usize;
+
+    Some(
+        sorted_prices[lower]
+            + (sorted_prices[upper] - sorted_prices[lower]) * (rank - lower as f64),
+    )
+}
+
+/// Spot prices within after and before, ignoring the plannable time slots.
+fn bounded_spot_prices(spot_prices: &[SpotPrice], request: &PlanningRequest) -> Vec<SpotPrice> {
+    spot_prices
+        .iter()
+        .filter(|spot_price| is_within_bounds(spot_price, &request.after, &request.before))
+        .cloned()
+        .collect()
+}
+
+type SectionStarts = Option<(f64, Vec<DateTime<Utc>>)>;
+
+/// Cheapest start for each of the sections, each starting at or after the end of the previous one; a section starts
+/// either right where the previous one ended or at the start of a spot price at least min_gap later.
+fn best_section_starts(
+    spot_prices: &[SpotPrice],
+    sections: &[LoadProfileSection],
+    earliest: DateTime<Utc>,
+    min_gap: Duration,
+    get_price_fn: &dyn Fn(&SpotPrice) -> f64,
+    memo: &mut HashMap<(usize, DateTime<Utc>), SectionStarts>,
+) -> SectionStarts {
+    let section = match sections.first() {
+        Some(section) => section,
+        None => return Some((0.0, vec![])),
+    };
+    if let Some(best) = memo.get(&(sections.len(), earliest)) {
+        return best.clone();
+    }
+
+    let starts = std::iter::once(earliest).chain(
+        spot_prices
+            .iter()
+            .map(|spot_price| spot_price.from)
+            .filter(|from| *from > earliest && *from >= earliest + min_gap),
+    );
+
+    let mut best: SectionStarts = None;
+    for start in starts {
+        let price = match section_price_from(spot_prices, start, section, get_price_fn) {
+            Some(price) => price,
+            None => continue,
+        };
+        let till = start + Duration::seconds(section.duration_seconds);
+        if let Some((remaining_price, remaining_starts)) = best_section_starts(
+            spot_prices,
+            &sections[1..],
+            till,
+            min_gap,
+            get_price_fn,
+            memo,
+        ) {
+            let total_price = price + remaining_price;
+            if best
+                .as_ref()
+                .is_none_or(|(best_price, _)| total_price < *best_price)
+            {
+                let mut starts = vec![start];
+                starts.extend(remaining_starts);
+                best = Some((total_price, starts));
+            }
+        }
+    }
+
+    memo.insert((sections.len(), earliest), best.clone());
+    best
+}
+
+/// Price of running the section from start, None when the spot prices don't cover it without gaps.
+fn section_price_from(
+    spot_prices: &[SpotPrice],
+    start: DateTime<Utc>,
+    section: &LoadProfileSection,
+    get_price_fn: &dyn Fn(&SpotPrice) -> f64,
+) -> Option<f64> {
+    let first = SpotPriceIndex::from_sorted(spot_prices).position_at(start)?;
+
+    let mut allocated_seconds = 0;
+    let mut total_price_units = 0;
+    let mut previous_till = start;
+    for spot_price in &spot_prices[first..] {
+        if allocated_seconds >= section.duration_seconds {
+            break;
+        }
+        if spot_price.from > previous_till {
+            return None;
+        }
+
+        let seconds = std::cmp::min(
+            (spot_price.till - previous_till.max(spot_price.from)).num_seconds(),
+            section.duration_seconds - allocated_seconds,
+        );
+        let watt_seconds =
+            section.watt_seconds_between(allocated_seconds, allocated_seconds + seconds);
+
+        total_price_units += price_units(get_price_fn(spot_price), watt_seconds);
+        allocated_seconds += seconds;
+        previous_till = spot_price.till;
+    }
+
+    (allocated_seconds >= section.duration_seconds).then(|| price_from_units(total_price_units))
+}
+
+type Interval = (DateTime<Utc>, DateTime<Utc>);
+
+/// Sorts the intervals and merges the ones that overlap or touch.
+fn merge_intervals(mut intervals: Vec<Interval>) -> Vec<Interval> {
+    intervals.sort();
+
+    let mut merged: Vec<Interval> = vec![];
+    for (from, till) in intervals {
+        match merged.last_mut() {
+            Some(last) if from <= last.1 => last.1 = last.1.max(till),
+            _ => merged.push((from, till)),
+        }
+    }
+    merged
+}
+
+fn is_within_bounds(
+    spot_price: &SpotPrice,
+    after: &Option<DateTime<Utc>>,
+    before: &Option<DateTime<Utc>>,
+) -> bool {
+    after.is_none_or(|a| spot_price.from >= a) && before.is_none_or(|b| spot_price.till <= b)
+}
+
+/// The n best acceptable consecutive blocks, best first, with how many seconds into the first spot price the load
+/// starts; on ties the earliest block ranks first.
+fn ranked_consecutive_spot_prices(
+    plannable_spot_prices: &[SpotPrice],
+    criteria: &BlockCriteria,
+    n: usize,
+) -> Vec<(Vec<SpotPrice>, i64)> {
+    let candidates = consecutive_blocks(plannable_spot_prices, criteria)
+        .into_iter()
+        .filter(|(_, _, total_price)| criteria.is_acceptable(*total_price))
+        .collect();
+
+    rank_blocks(plannable_spot_prices, criteria, candidates, n)
+}
+
+/// Like ranked_consecutive_spot_prices, but evaluates start times every granularity seconds within each spot
+/// price, keeping the best start per spot price.
+fn ranked_consecutive_spot_prices_at_granularity(
+    plannable_spot_prices: &[SpotPrice],
+    criteria: &BlockCriteria,
+    granularity_seconds: i64,
+    n: usize,
+) -> Vec<(Vec<SpotPrice>, i64)> {
+    let total_required_seconds = criteria.load_profile.total_duration_seconds();
+    let mut candidates: Vec<(Range<usize>, i64, f64)> = vec![];
+    if total_required_seconds <= 0 {
+        return vec![];
+    }
+
+    for (start, spot_price) in plannable_spot_prices.iter().enumerate() {
+        let mut skip_seconds = match criteria.start_skip_seconds(spot_price) {
+            Some(skip_seconds) => skip_seconds,
+            None => continue,
+        };
+        let mut best_for_start: Option<(Range<usize>, i64, f64)> = None;
+        let mut later_starts_fit = true;
+        while skip_seconds < spot_price.duration_seconds() {
+            let planned_from = spot_price.from + Duration::seconds(skip_seconds);
+            if !criteria.finishes_in_time(planned_from, total_required_seconds) {
+                // later starts finish even later
+                later_starts_fit = false;
+                break;
+            }
+
+            let mut selected_seconds = spot_price.duration_seconds() - skip_seconds;
+            let mut end = start + 1;
+
+            let mut has_gap = false;
+            while end < plannable_spot_prices.len() && selected_seconds < total_required_seconds {
+                if !plannable_spot_prices[end - 1].is_adjacent_to(&plannable_spot_prices[end]) {
+                    has_gap = true;
+                    break;
+                }
+                selected_seconds += plannable_spot_prices[end].duration_seconds();
+                end += 1;
+            }
+
+            if selected_seconds < total_required_seconds {
+                // later starts within this spot price hit the same gap; without a gap the remaining spot prices
+                // can't get to the required seconds at all
+                later_starts_fit = has_gap;
+                break;
+            }
+
+            if !criteria.fits_available_power(&plannable_spot_prices[start..end], skip_seconds) {
+                skip_seconds += granularity_seconds;
+                continue;
+            }
+
+            let total_price =
+                criteria.total_price(&plannable_spot_prices[start..end], skip_seconds);
+
+            let is_better = match &best_for_start {
+                _ if !criteria.is_acceptable(total_price) => false,
+                None => true,
+                Some((_, _, best_total_price)) => {
+                    criteria.is_better_later_start(total_price, *best_total_price)
+                }
+            };
+            if is_better {
+                best_for_start = Some((start..end, skip_seconds, total_price));
+            }
+
+            skip_seconds += granularity_seconds;
+        }
+
+        candidates.extend(best_for_start);
+        if !later_starts_fit {
+            break;
+        }
+    }
+
+    rank_blocks(plannable_spot_prices, criteria, candidates, n)
+}
+
+/// Consecutive blocks ranked on the energy the expected production covers, then on price.
+fn self_consumption_ranked_spot_prices(
+    plannable_spot_prices: &[SpotPrice],
+    criteria: &BlockCriteria,
+    expected_production_watt: &SpotPriceValues,
+    n: usize,
+) -> Vec<(Vec<SpotPrice>, i64)> {
+    let mut candidates: Vec<(Range<usize>, i64, f64, f64)> =
+        consecutive_blocks(plannable_spot_prices, criteria)
+            .into_iter()
+            .filter(|(_, _, total_price)| criteria.is_acceptable(*total_price))
+            .map(|(range, skip_seconds, total_price)| {
+                let self_consumed = self_consumed_watt_seconds(
+                    &plannable_spot_prices[range.clone()],
+                    skip_seconds,
+                    criteria.load_profile,
+                    expected_production_watt,
+                );
+                (range, skip_seconds, total_price, self_consumed)
+            })
+            .collect();
+
+    // the sort is stable, keeping earlier blocks first on a complete tie
+    candidates.sort_by(|a, b| b.3.total_cmp(&a.3).then_with(|| criteria.compare(a.2, b.2)));
+
+    candidates
+        .into_iter()
+        .take(n)
+        .map(|(range, skip_seconds, _, _)| (plannable_spot_prices[range].to_vec(), skip_seconds))
+        .collect()
+}
+
+/// Energy of the load covered by the expected production; per spot price the smaller of the load's energy and the
+/// production over the seconds the load runs in it.
+fn self_consumed_watt_seconds(
+    spot_prices: &[SpotPrice],
+    skip_seconds: i64,
+    load_profile: &LoadProfile,
+    expected_production_watt: &SpotPriceValues,
+) -> f64 {
+    let total_required_seconds = load_profile.total_duration_seconds();
+    let mut allocated_seconds = 0;
+    let mut self_consumed = 0.0;
+
+    for (i, spot_price) in spot_prices.iter().enumerate() {
+        if allocated_seconds >= total_required_seconds {
+            break;
+        }
+
+        let available_seconds = if i == 0 {
+            spot_price.duration_seconds() - skip_seconds
+        } else {
+            spot_price.duration_seconds()
+        };
+        let seconds = std::cmp::min(
+            available_seconds.max(0),
+            total_required_seconds - allocated_seconds,
+        );
+        let load_watt_seconds =
+            load_watt_seconds_between(load_profile, allocated_seconds, allocated_seconds + seconds);
+        let production_watt_seconds = expected_production_watt
+            .at(spot_price.from)
+            .map_or(0.0, |production_watt| {
+                production_watt.max(0.0) * seconds as f64
+            });
+
+        self_consumed += load_watt_seconds.min(production_watt_seconds);
+        allocated_seconds += seconds;
+    }
+
+    self_consumed
+}
+
+/// Orders candidate blocks from best to worst, ordering blocks priced within the tie breaker epsilon of each other
+/// by start, and clones the n best.
+fn rank_blocks(
+    plannable_spot_prices: &[SpotPrice],
+    criteria: &BlockCriteria,
+    mut candidates: Vec<(Range<usize>, i64, f64)>,
+    n: usize,
+) -> Vec<(Vec<SpotPrice>, i64)> {
+    candidates.sort_by(|(_, _, a), (_, _, b)| criteria.compare(*a, *b));
+
+    // ties are grouped relative to the best price of the group, keeping the ordering total
+    let mut group_start = 0;
+    while group_start < candidates.len() {
+        let group_price = candidates[group_start].2;
+        let group_end = candidates[group_start..]
+            .iter()
+            .position(|(_, _, total_price)| !criteria.is_tie(*total_price, group_price))
+            .map_or(candidates.len(), |offset| group_start + offset);
+
+        let group = &mut candidates[group_start..group_end];
+        group.sort_by_key(|(range, skip_seconds, _)| (range.start, *skip_seconds));
+        if criteria.tie_breaker == TieBreaker::LatestStart {
+            group.reverse();
+        }
+
+        group_start = group_end;
+    }
+
+    // only the winning blocks get cloned
+    candidates
+        .into_iter()
+        .take(n)
+        .map(|(range, skip_seconds, _)| (plannable_spot_prices[range].to_vec(), skip_seconds))
+        .collect()
+}
+
+/// Selects the best ranked spot prices covering the load, with the seconds to skip at the start of the first one.
+fn best_non_consecutive_spot_prices(
+    plannable_spot_prices: &[SpotPrice],
+    criteria: &BlockCriteria,
+) -> (Vec<SpotPrice>, i64) {
+    let total_required_seconds = criteria.load_profile.total_duration_seconds();
+
+    // rank by price; the stable sort keeps earlier slots first on ties, or later ones for the latest start
+    let mut ranked_spot_prices: Vec<&SpotPrice> = plannable_spot_prices.iter().collect();
+    if criteria.tie_breaker == TieBreaker::LatestStart {
+        ranked_spot_prices.reverse();
+    }
+    ranked_spot_prices.sort_by(|a, b| {
+        let ordering = criteria.spot_price(a).total_cmp(&criteria.spot_price(b));
+        if criteria.prefers_highest() {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    });
+
+    let mut selected_spot_prices: Vec<SpotPrice> = vec![];
+    let mut selected_seconds = 0;
+    for spot_price in ranked_spot_prices {
+        if selected_seconds >= total_required_seconds {
+            break;
+        }
+        selected_seconds += spot_price.duration_seconds();
+        selected_spot_prices.push(spot_price.clone());
+    }
+
+    // not enough plannable spot prices to get to the required seconds
+    if selected_seconds < total_required_seconds {
+        return (vec![], 0);
+    }
+
+    selected_spot_prices.sort_by_key(|spot_price| spot_price.from);
+
+    // the load runs through the selected spot prices in time order, so only the first or the last one can be used
+    // partially; start late in the first one when that is the better one to cut short
+    let mut skip_seconds = 0;
+    let mut total_price = criteria.total_price(&selected_spot_prices, 0);
+    let excess_seconds = selected_seconds - total_required_seconds;
+    if excess_seconds > 0 && excess_seconds < selected_spot_prices[0].duration_seconds() {
+        let skipping_total_price = criteria.total_price(&selected_spot_prices, excess_seconds);
+        if criteria.is_better(skipping_total_price, total_price) {
+            skip_seconds = excess_seconds;
+            total_price = skipping_total_price;
+        }
+    }
+
+    if !criteria.is_acceptable(total_price) {
+        return (vec![], 0);
+    }
+
+    (selected_spot_prices, skip_seconds)
+}
+
+/// (selected seconds, seconds of the current run or 0, runs started today, end of the last run while resting)
+type MinBlockState = (i64, i64, usize, Option<DateTime<Utc>>);
+
+/// Selects spot prices covering the load where every uninterrupted run lasts at least
+/// min_block_duration_seconds and runs are at least min_gap apart, using dynamic programming over the time ordered
+/// spot prices.
+fn best_non_consecutive_spot_prices_with_min_block(
+    plannable_spot_prices: &[SpotPrice],
+    criteria: &BlockCriteria,
+    min_block_duration_seconds: i64,
+    max_runs_per_local_day: Option<(usize, Tz)>,
+    min_gap: Option<Duration>,
+) -> Vec<SpotPrice> {
+    let total_required_seconds = criteria.load_profile.total_duration_seconds();
+    // a load shorter than the minimum can only ever run as a single block
+    let min_run_seconds = std::cmp::min(min_block_duration_seconds, total_required_seconds);
+    let prefer_highest = criteria.prefers_highest();
+    let local_date = |spot_price: &SpotPrice| {
+        max_runs_per_local_day
+            .map(|(_, time_zone)| spot_price.from.with_timezone(&time_zone).date_naive())
+    };
+
+    let mut states: HashMap<MinBlockState, (i128, Vec<usize>)> =
+        HashMap::from([((0, 0, 0, None), (0, vec![]))]);
+
+    for (i, spot_price) in plannable_spot_prices.iter().enumerate() {
+        let continues_run = i > 0 && plannable_spot_prices[i - 1].is_adjacent_to(spot_price);
+        let starts_local_day =
+            i > 0 && local_date(spot_price) != local_date(&plannable_spot_prices[i - 1]);
+        let price = criteria.spot_price(spot_price);
+
+        let mut next_states: HashMap<MinBlockState, (i128, Vec<usize>)> = HashMap::new();
+        for (
+            &(selected_seconds, run_seconds, runs_today, last_run_till),
+            (total_price, selected),
+        ) in &states
+        {
+            let run_is_complete = run_seconds == 0 || run_seconds >= min_run_seconds;
+            let runs_today = if starts_local_day { 0 } else { runs_today };
+            // once rested long enough it no longer matters when the last run ended
+            let last_run_till = last_run_till.filter(|last_run_till| {
+                min_gap.is_some_and(|min_gap| spot_price.from - *last_run_till < min_gap)
+            });
+
+            // skip this spot price, which ends the current run
+            if run_is_complete {
+                insert_if_better(
+                    &mut next_states,
+                    (selected_seconds, 0, runs_today, last_run_till),
+                    (*total_price, selected.clone()),
+                    prefer_highest,
+                );
+            }
+
+            // take this spot price, continuing the current run or starting a new one
+            let starts_run = !continues_run || run_seconds == 0;
+            if selected_seconds >= total_required_seconds
+                || (run_seconds > 0 && !continues_run && !run_is_complete)
+                || (starts_run && last_run_till.is_some())
+            {
+                continue;
+            }
+            // a run carried past local midnight counts towards the new day as well
+            let runs = if max_runs_per_local_day.is_none()
+                || (continues_run && run_seconds > 0 && !starts_local_day)
+            {
+                runs_today
+            } else {
+                runs_today + 1
+            };
+            if max_runs_per_local_day.is_some_and(|(max_runs, _)| runs > max_runs) {
+                continue;
+            }
+            let previous_run_seconds = if continues_run { run_seconds } else { 0 };
+            let used_seconds = std::cmp::min(
+                spot_price.duration_seconds(),
+                total_required_seconds - selected_seconds,
+            );
+            let total_price = total_price
+                + price_units(
+                    price,
+                    load_watt_seconds_between(
+                        criteria.load_profile,
+                        selected_seconds,
+                        selected_seconds + used_seconds,
+                    ),
+                );
+            let mut selected = selected.clone();
+            selected.push(i);
+            insert_if_better(
+                &mut next_states,
+                (
+                    selected_seconds + used_seconds,
+                    std::cmp::min(previous_run_seconds + used_seconds, min_run_seconds),
+                    runs,
+                    min_gap.map(|_| spot_price.till),
+                ),
+                (total_price, selected),
+                prefer_highest,
+            );
+        }
+        states = next_states;
+    }
+
+    let mut best: HashMap<(), (i128, Vec<usize>)> = HashMap::new();
+    for ((selected_seconds, run_seconds, _, _), candidate) in states {
+        if selected_seconds == total_required_seconds
+            && (run_seconds == 0 || run_seconds >= min_run_seconds)
+        {
+            insert_if_better(&mut best, (), candidate, prefer_highest);
+        }
+    }
+
+    match best.remove(&()) {
+        Some((total_price, _)) if !criteria.is_acceptable(price_from_units(total_price)) => vec![],
+        Some((_, selected)) => selected
+            .into_iter()
+            .map(|i| plannable_spot_prices[i].clone())
+            .collect(),
+        None => vec![],
+    }
+}
+
+/// Keeps the cheapest (or most expensive) candidate per state, preferring earlier spot prices on ties.
+fn insert_if_better<K: Eq + std::hash::Hash>(
+    states: &mut HashMap<K, (i128, Vec<usize>)>,
+    state: K,
+    candidate: (i128, Vec<usize>),
+    prefer_highest: bool,
+) {
+    let is_better = match states.get(&state) {
+        None => true,
+        Some((price, selected)) => {
+            let ordering = if prefer_highest {
+                price.cmp(&candidate.0)
+            } else {
+                candidate.0.cmp(price)
+            };
+            ordering.then_with(|| candidate.1.cmp(selected)) == std::cmp::Ordering::Less
+        }
+    };
+    if is_better {
+        states.insert(state, candidate);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn hourly_spot_prices(from: DateTime<Utc>, market_prices: &[f64]) -> Vec<SpotPrice> {
+        market_prices
+            .iter()
+            .enumerate()
+            .map(|(i, market_price)| {
+                SpotPrice::new(
+                    from + Duration::hours(i as i64),
+                    from + Duration::hours(i as i64 + 1),
+                    *market_price,
+                )
+                .id(None)
+                .build()
+                .unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn total_power_draw_watt_seconds_integrates_ramped_section() {
+        let section = LoadProfileSection {
+            duration_seconds: 600,
+            power_draw_watt: 500.0,
+            end_power_draw_watt: Some(2000.0),
+            id: None,
+        };
+
+        // act
+        let watt_seconds = section.total_power_draw_watt_seconds();
+
+        assert_eq!(watt_seconds, 750000.0);
+    }
+
+    #[test]
+    fn total_price_for_load_for_ramped_section_equals_two_step_approximation_aligned_with_slots() {
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+        let spot_prices = hourly_spot_prices(from, &[0.10, 0.30]);
+        let ramped = LoadProfile {
+            sections: vec![LoadProfileSection {
+                duration_seconds: 7200,
+                power_draw_watt: 0.0,
+                end_power_draw_watt: Some(2000.0),
+                id: None,
+            }],
+        };
+        // average draw of the ramp within each hour
+        let two_step = LoadProfile {
+            sections: vec![
+                LoadProfileSection {
+                    duration_seconds: 3600,
+                    power_draw_watt: 500.0,
+                    end_power_draw_watt: None,
+                    id: None,
+                },
+                LoadProfileSection {
+                    duration_seconds: 3600,
+                    power_draw_watt: 1500.0,
+                    end_power_draw_watt: None,
+                    id: None,
+                },
+            ],
+        };
+
+        // act
+        let ramped_price = total_price_for_load(&spot_prices, &ramped, None);
+        let two_step_price = total_price_for_load(&spot_prices, &two_step, None);
+
+        assert!((ramped_price - two_step_price).abs() < 1e-9);
+        assert!((ramped_price - 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn total_price_for_load_for_ramped_section_differs_from_coarse_step_approximation() {
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+        let spot_prices = hourly_spot_prices(from, &[0.10, 0.30]);
+        let ramped = LoadProfile {
+            sections: vec![LoadProfileSection {
+                duration_seconds: 7200,
+                power_draw_watt: 0.0,
+                end_power_draw_watt: Some(2000.0),
+                id: None,
+            }],
+        };
+        // same energy, but the ramp approximated by a single step at its halfway point
+        let two_step = LoadProfile {
+            sections: vec![
+                LoadProfileSection {
+                    duration_seconds: 3600,
+                    power_draw_watt: 0.0,
+                    end_power_draw_watt: None,
+                    id: None,
+                },
+                LoadProfileSection {
+                    duration_seconds: 3600,
+                    power_draw_watt: 2000.0,
+                    end_power_draw_watt: None,
+                    id: None,
+                },
+            ],
+        };
+
+        // act
+        let ramped_price = total_price_for_load(&spot_prices, &ramped, None);
+        let two_step_price = total_price_for_load(&spot_prices, &two_step, None);
+
+        assert_eq!(ramped.total_energy_kwh(), two_step.total_energy_kwh());
+        assert!((ramped_price - (0.5 * 0.10 + 1.5 * 0.30)).abs() < 1e-9);
+        assert!((two_step_price - 2.0 * 0.30).abs() < 1e-9);
+    }
+
+    #[test]
+    fn total_price_for_load_returns_zero_for_empty_spot_prices() {
+        // act
+        let total_price = total_price_for_load(
+            &[],
+            &LoadProfile {
+                sections: vec![LoadProfileSection {
+                    duration_seconds: 7200,
+                    power_draw_watt: 2000.0,
+                    end_power_draw_watt: None,
+                    id: None,
+                }],
+            },
+            None,
+        );
+
+        assert_eq!(total_price, 0.0);
+    }
+
+    #[test]
+    fn total_price_for_load_returns_zero_for_empty_load_profile() {
+        // act
+        let total_price = total_price_for_load(
+            &[SpotPrice::new(
+                Utc.with_ymd_and_hms(2022, 4, 14, 11, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2022, 4, 14, 12, 0, 0).unwrap(),
+                0.202,
+            )
+            .id(None)
+            .market_price_tax(0.0424053)
+            .sourcing_markup_price(0.017)
+            .energy_tax_price(0.081)
+            .build()
+            .unwrap()],
+            &LoadProfile { sections: vec![] },
+            None,
+        );
+
+        assert_eq!(total_price, 0.0);
+    }
+
+    #[test]
+    fn total_price_for_load_returns_total_draw_times_total_price_for_equal_length_spot_price_and_load_profile_section(
+    ) {
+        // act
+        let total_price = total_price_for_load(
+            &[SpotPrice::new(
+                Utc.with_ymd_and_hms(2022, 4, 14, 11, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2022, 4, 14, 12, 0, 0).unwrap(),
+                0.202,
+            )
+            .id(None)
+            .market_price_tax(0.0424053)
+            .sourcing_markup_price(0.017)
+            .energy_tax_price(0.081)
+            .build()
+            .unwrap()],
+            &LoadProfile {
+                sections: vec![LoadProfileSection {
+                    duration_seconds: 3600,
+                    power_draw_watt: 2000.0,
+                    end_power_draw_watt: None,
+                    id: None,
+                }],
+            },
+            None,
+        );
+
+        assert_eq!(total_price, 0.6848106);
+    }
+
+    #[test]
+    fn total_price_for_load_returns_total_draw_times_total_price_for_more_spot_prices_than_needed()
+    {
+        // act
+        let total_price = total_price_for_load(
+            &[
+                SpotPrice::new(
+                    Utc.with_ymd_and_hms(2022, 4, 14, 11, 0, 0).unwrap(),
+                    Utc.with_ymd_and_hms(2022, 4, 14, 12, 0, 0).unwrap(),
+                    0.202,
+                )
+                .id(None)
+                .market_price_tax(0.0424053)
+                .sourcing_markup_price(0.017)
+                .energy_tax_price(0.081)
+                .build()
+                .unwrap(),
+                SpotPrice::new(
+                    Utc.with_ymd_and_hms(2022, 4, 14, 12, 0, 0).unwrap(),
+                    Utc.with_ymd_and_hms(2022, 4, 14, 13, 0, 0).unwrap(),
+                    0.195,
+                )
+                .id(None)
+                .market_price_tax(0.0409899)
+                .sourcing_markup_price(0.017)
+                .energy_tax_price(0.081)
+                .build()
+                .unwrap(),
+            ],
+            &LoadProfile {
+                sections: vec![
+                    LoadProfileSection {
+                        duration_seconds: 3600,
+                        power_draw_watt: 2000.0,
+                        end_power_draw_watt: None,
+                        id: None,
+                    },
+                    LoadProfileSection {
+                        duration_seconds: 1800,
+                        power_draw_watt: 8000.0,
+                        end_power_draw_watt: None,
+                        id: None,
+                    },
+                ],
+            },
+            None,
+        );
+
+        assert_eq!(total_price, 2.0207702);
+    }
+
+    #[test]
+    fn get_plannable_spot_prices_returns_only_spot_prices_fitting_in_plannable_time_slots(
+    ) -> Result<(), Box<dyn Error>> {
+        let load_profile = LoadProfile {
+            sections: vec![LoadProfileSection {
+                duration_seconds: 7200,
+                power_draw_watt: 2000.0,
+                end_power_draw_watt: None,
+                id: None,
+            }],
+        };
+
+        let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig {
+            load_profile: load_profile.clone(),
+            plannable_local_time_slots: HashMap::from([(
+                Weekday::Thu,
+                vec![TimeSlot {
+                    from: NaiveTime::from_hms_opt(14, 0, 0).unwrap(),
+                    till: NaiveTime::from_hms_opt(16, 0, 0).unwrap(),
+                    wraps_midnight: false,
+                }],
+            )]),
+            local_time_zone: "Europe/Amsterdam".to_string(),
+            ..SpotPricePlannerConfig::default()
+        });
+
+        let future_spot_prices: Vec<SpotPrice> = vec![
+            SpotPrice::new(
+                Utc.with_ymd_and_hms(2022, 4, 14, 11, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2022, 4, 14, 12, 0, 0).unwrap(),
+                0.202,
+            )
+            .id(None)
+            .market_price_tax(0.0424053)
+            .sourcing_markup_price(0.017)
+            .energy_tax_price(0.081)
+            .build()
+            .unwrap(),
+            SpotPrice::new(
+                Utc.with_ymd_and_hms(2022, 4, 14, 12, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2022, 4, 14, 13, 0, 0).unwrap(),
+                0.195,
+            )
+            .id(None)
+            .market_price_tax(0.0409899)
+            .sourcing_markup_price(0.017)
+            .energy_tax_price(0.081)
+            .build()
+            .unwrap(),
+            SpotPrice::new(
+                Utc.with_ymd_and_hms(2022, 4, 14, 13, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2022, 4, 14, 14, 0, 0).unwrap(),
+                0.194,
+            )
+            .id(None)
+            .market_price_tax(0.0406644)
+            .sourcing_markup_price(0.017)
+            .energy_tax_price(0.081)
+            .build()
+            .unwrap(),
+            SpotPrice::new(
+                Utc.with_ymd_and_hms(2022, 4, 14, 14, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2022, 4, 14, 15, 0, 0).unwrap(),
+                0.192,
+            )
+            .id(None)
+            .market_price_tax(0.0403179)
+            .sourcing_markup_price(0.017)
+            .energy_tax_price(0.081)
+            .build()
+            .unwrap(),
+        ];
+
+        // act
+        let plannable_spot_prices =
+            spot_price_planner.get_plannable_spot_prices(&future_spot_prices, &None, &None)?;
+
+        assert_eq!(plannable_spot_prices.len(), 2);
+        assert_eq!(
+            plannable_spot_prices[0].from,
+            Utc.with_ymd_and_hms(2022, 4, 14, 12, 0, 0).unwrap()
+        );
+        assert_eq!(
+            plannable_spot_prices[0].till,
+            Utc.with_ymd_and_hms(2022, 4, 14, 13, 0, 0).unwrap()
+        );
+        assert_eq!(
+            plannable_spot_prices[1].from,
+            Utc.with_ymd_and_hms(2022, 4, 14, 13, 0, 0).unwrap()
+        );
+        assert_eq!(
+            plannable_spot_prices[1].till,
+            Utc.with_ymd_and_hms(2022, 4, 14, 14, 0, 0).unwrap()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn get_plannable_spot_prices_returns_only_spot_prices_fitting_in_plannable_time_slots_when_includes_next_day(
+    ) -> Result<(), Box<dyn Error>> {
+        let load_profile = LoadProfile {
+            sections: vec![LoadProfileSection {
+                duration_seconds: 18000,
+                power_draw_watt: 2000.0,
+                end_power_draw_watt: None,
+                id: None,
+            }],
+        };
+
+        let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig {
+            load_profile: load_profile.clone(),
+            plannable_local_time_slots: HashMap::from([
+                (
+                    Weekday::Thu,
+                    vec![
+                        TimeSlot {
+                            from: NaiveTime::from_hms_opt(14, 0, 0).unwrap(),
+                            till: NaiveTime::from_hms_opt(16, 0, 0).unwrap(),
+                            wraps_midnight: false,
+                        },
+                        TimeSlot {
+                            from: NaiveTime::from_hms_opt(23, 0, 0).unwrap(),
+                            till: NaiveTime::from_hms_opt(0, 0, 0).unwrap(),
+                            wraps_midnight: false,
+                        },
+                    ],
+                ),
+                (
+                    Weekday::Fri,
+                    vec![TimeSlot {
+                        from: NaiveTime::from_hms_opt(0, 0, 0).unwrap(),
+                        till: NaiveTime::from_hms_opt(2, 0, 0).unwrap(),
+                        wraps_midnight: false,
+                    }],
+                ),
+            ]),
+            local_time_zone: "Europe/Amsterdam".to_string(),
+            ..SpotPricePlannerConfig::default()
+        });
+
+        let future_spot_prices: Vec<SpotPrice> = vec![
+            SpotPrice::new(
+                Utc.with_ymd_and_hms(2022, 4, 14, 20, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2022, 4, 14, 21, 0, 0).unwrap(),
+                0.265,
+            )
+            .id(None)
+            .market_price_tax(0.0557466)
+            .sourcing_markup_price(0.017)
+            .energy_tax_price(0.081)
+            .build()
+            .unwrap(),
+            SpotPrice::new(
+                Utc.with_ymd_and_hms(2022, 4, 14, 21, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2022, 4, 14, 22, 0, 0).unwrap(),
+                0.254,
+            )
+            .id(None)
+            .market_price_tax(0.0532728)
+            .sourcing_markup_price(0.017)
+            .energy_tax_price(0.081)
+            .build()
+            .unwrap(),
+            SpotPrice::new(
+                Utc.with_ymd_and_hms(2022, 4, 14, 22, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2022, 4, 14, 23, 0, 0).unwrap(),
+                0.231,
+            )
+            .id(None)
+            .market_price_tax(0.0484281)
+            .sourcing_markup_price(0.017)
+            .energy_tax_price(0.081)
+            .build()
+            .unwrap(),
+            SpotPrice::new(
+                Utc.with_ymd_and_hms(2022, 4, 14, 23, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2022, 4, 15, 0, 0, 0).unwrap(),
+                0.215,
+            )
+            .id(None)
+            .market_price_tax(0.045129)
+            .sourcing_markup_price(0.017)
+            .energy_tax_price(0.081)
+            .build()
+            .unwrap(),
+            SpotPrice::new(
+                Utc.with_ymd_and_hms(2022, 4, 15, 0, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2022, 4, 15, 1, 0, 0).unwrap(),
+                0.217,
+            )
+            .id(None)
+            .market_price_tax(0.04557)
+            .sourcing_markup_price(0.017)
+            .energy_tax_price(0.081)
+            .build()
+            .unwrap(),
+            SpotPrice::new(
+                Utc.with_ymd_and_hms(2022, 4, 15, 1, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2022, 4, 15, 2, 0, 0).unwrap(),
+                0.208,
+            )
+            .id(None)
+            .market_price_tax(0.0437535)
+            .sourcing_markup_price(0.017)
+            .energy_tax_price(0.081)
+            .build()
+            .unwrap(),
+        ];
+
+        // act
+        let plannable_spot_prices =
+            spot_price_planner.get_plannable_spot_prices(&future_spot_prices, &None, &None)?;
+
+        assert_eq!(plannable_spot_prices.len(), 3);
+        assert_eq!(
+            plannable_spot_prices[0].from,
+            Utc.with_ymd_and_hms(2022, 4, 14, 21, 0, 0).unwrap()
+        );
+        assert_eq!(
+            plannable_spot_prices[0].till,
+            Utc.with_ymd_and_hms(2022, 4, 14, 22, 0, 0).unwrap()
+        );
+        assert_eq!(
+            plannable_spot_prices[1].from,
+            Utc.with_ymd_and_hms(2022, 4, 14, 22, 0, 0).unwrap()
+        );
+        assert_eq!(
+            plannable_spot_prices[1].till,
+            Utc.with_ymd_and_hms(2022, 4, 14, 23, 0, 0).unwrap()
+        );
+
+        assert_eq!(
+            plannable_spot_prices[2].from,
+            Utc.with_ymd_and_hms(2022, 4, 14, 23, 0, 0).unwrap()
+        );
+        assert_eq!(
+            plannable_spot_prices[2].till,
+            Utc.with_ymd_and_hms(2022, 4, 15, 0, 0, 0).unwrap()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn get_plannable_spot_prices_returns_all_spot_prices_when_plannable_time_slots_are_empty(
+    ) -> Result<(), Box<dyn Error>> {
+        let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig {
+            load_profile: LoadProfile {
+                sections: vec![LoadProfileSection {
+                    duration_seconds: 3600,
+                    power_draw_watt: 2000.0,
+                    end_power_draw_watt: None,
+                    id: None,
+                }],
+            },
+            ..SpotPricePlannerConfig::default()
+        });
+
+        let future_spot_prices: Vec<SpotPrice> = vec![
+            SpotPrice::new(
+                Utc.with_ymd_and_hms(2022, 4, 14, 11, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2022, 4, 14, 12, 0, 0).unwrap(),
+                0.202,
+            )
+            .id(None)
+            .market_price_tax(0.0424053)
+            .sourcing_markup_price(0.017)
+            .energy_tax_price(0.081)
+            .build()
+            .unwrap(),
+            SpotPrice::new(
+                Utc.with_ymd_and_hms(2022, 4, 16, 12, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2022, 4, 16, 13, 0, 0).unwrap(),
+                0.195,
+            )
+            .id(None)
+            .market_price_tax(0.0409899)
+            .sourcing_markup_price(0.017)
+            .energy_tax_price(0.081)
+            .build()
+            .unwrap(),
+        ];
+
+        // act
+        let plannable_spot_prices =
+            spot_price_planner.get_plannable_spot_prices(&future_spot_prices, &None, &None)?;
+
+        assert_eq!(plannable_spot_prices.len(), 2);
+
+        // act
+        let plannable_spot_prices = spot_price_planner.get_plannable_spot_prices(
+            &future_spot_prices,
+            &Some(Utc.with_ymd_and_hms(2022, 4, 15, 0, 0, 0).unwrap()),
+            &None,
+        )?;
+
+        assert_eq!(plannable_spot_prices.len(), 1);
+        assert_eq!(
+            plannable_spot_prices[0].from,
+            Utc.with_ymd_and_hms(2022, 4, 16, 12, 0, 0).unwrap()
+        );
+
+        Ok(())
+    }
+
+    fn valid_planner_config() -> SpotPricePlannerConfig {
+        SpotPricePlannerConfig {
+            plannable_local_time_slots: HashMap::from([(
+                Weekday::Mon,
+                vec![time_slot((22, 0), (0, 0)), time_slot((0, 0), (7, 0))],
+            )]),
+            local_time_zone: "Europe/Amsterdam".to_string(),
+            load_profile: LoadProfile {
+                sections: vec![LoadProfileSection {
+                    duration_seconds: 3600,
+                    power_draw_watt: 2000.0,
+                    end_power_draw_watt: None,
+                    id: None,
+                }],
+            },
+            ..SpotPricePlannerConfig::default()
+        }
+    }
+
+    fn time_slot_hours(config: &SpotPricePlannerConfig, weekday: Weekday) -> Vec<(u32, u32)> {
+        config.plannable_local_time_slots[&weekday]
+            .iter()
+            .map(|time_slot| (time_slot.from.hour(), time_slot.till.hour()))
+            .collect()
+    }
+
+    #[test]
+    fn deserialize_expands_weekday_groups() -> Result<(), Box<dyn Error>> {
+        // act
+        let config: SpotPricePlannerConfig = serde_yaml::from_str(
+            "plannableTimeSlots:\n  weekdays:\n    - from: 0:00:00\n      till: 7:00:00\n    - from: 23:00:00\n      till: 0:00:00\n",
+        )?;
+
+        assert_eq!(config.plannable_local_time_slots.len(), 5);
+        assert_eq!(
+            time_slot_hours(&config, Weekday::Mon),
+            vec![(0, 7), (23, 0)]
+        );
+        assert_eq!(
+            time_slot_hours(&config, Weekday::Fri),
+            vec![(0, 7), (23, 0)]
+        );
+        assert!(!config
+            .plannable_local_time_slots
+            .contains_key(&Weekday::Sat));
+
+        Ok(())
+    }
+
+    #[test]
+    fn deserialize_accepts_v1_plannable_local_time_slots() -> Result<(), Box<dyn Error>> {
+        // act
+        let config: SpotPricePlannerConfig = serde_yaml::from_str(
+            "plannableLocalTimeSlots:\n  Sat:\n    - from: 0:00:00\n      till: 7:00:00\n",
+        )?;
+
+        assert_eq!(config.plannable_local_time_slots.len(), 1);
+        assert_eq!(time_slot_hours(&config, Weekday::Sat), vec![(0, 7)]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn deserialize_lets_explicit_weekday_override_weekday_group() -> Result<(), Box<dyn Error>> {
+        // act
+        let config: SpotPricePlannerConfig = serde_yaml::from_str(
+            "plannableTimeSlots:\n  Sun:\n    - from: 10:00:00\n      till: 16:00:00\n  weekend:\n    - from: 0:00:00\n      till: 7:00:00\n",
+        )?;
+
+        assert_eq!(config.plannable_local_time_slots.len(), 2);
+        assert_eq!(time_slot_hours(&config, Weekday::Sat), vec![(0, 7)]);
+        assert_eq!(time_slot_hours(&config, Weekday::Sun), vec![(10, 16)]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn deserialize_mixes_daily_weekend_and_weekday_entries() -> Result<(), Box<dyn Error>> {
+        // act
+        let config: SpotPricePlannerConfig = serde_yaml::from_str(
+            "plannableTimeSlots:\n  daily:\n    - from: 0:00:00\n      till: 7:00:00\n  weekend:\n    - from: 0:00:00\n      till: 12:00:00\n  Wed:\n    - from: 1:00:00\n      till: 5:00:00\n",
+        )?;
+
+        assert_eq!(config.plannable_local_time_slots.len(), 7);
+        assert_eq!(time_slot_hours(&config, Weekday::Mon), vec![(0, 7)]);
+        assert_eq!(time_slot_hours(&config, Weekday::Wed), vec![(1, 5)]);
+        assert_eq!(time_slot_hours(&config, Weekday::Sat), vec![(0, 12)]);
+        assert_eq!(time_slot_hours(&config, Weekday::Sun), vec![(0, 12)]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn deserialize_fails_for_unknown_weekday_group() {
+        // act
+        let result: Result<SpotPricePlannerConfig, _> = serde_yaml::from_str(
+            "plannableTimeSlots:\n  workdays:\n    - from: 0:00:00\n      till: 7:00:00\n",
+        );
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn validate_succeeds_for_valid_config() {
+        assert_eq!(valid_planner_config().validate(), Ok(()));
+    }
+
+    #[test]
+    fn validate_fails_for_unknown_local_time_zone() {
+        let config = SpotPricePlannerConfig {
+            local_time_zone: "Europe/Atlantis".to_string(),
+            ..valid_planner_config()
+        };
+
+        assert_eq!(
+            config.validate(),
+            Err(PlannerConfigError {
+                problems: vec![PlannerConfigProblem::InvalidLocalTimeZone(
+                    "Europe/Atlantis".to_string()
+                )]
+            })
+        );
+    }
+
+    #[test]
+    fn validate_fails_for_load_profile_without_sections() {
+        let config = SpotPricePlannerConfig {
+            load_profile: LoadProfile::default(),
+            ..valid_planner_config()
+        };
+
+        assert_eq!(
+            config.validate(),
+            Err(PlannerConfigError {
+                problems: vec![PlannerConfigProblem::NoLoadProfileSections]
+            })
+        );
+    }
+
+    #[test]
+    fn validate_fails_for_section_without_duration() {
+        let config = SpotPricePlannerConfig {
+            load_profile: LoadProfile {
+                sections: vec![LoadProfileSection {
+                    duration_seconds: 0,
+                    power_draw_watt: 2000.0,
+                    end_power_draw_watt: None,
+                    id: None,
+                }],
+            },
+            ..valid_planner_config()
+        };
+
+        assert_eq!(
+            config.validate(),
+            Err(PlannerConfigError {
+                problems: vec![PlannerConfigProblem::NonPositiveSectionDuration {
+                    section: 0,
+                    duration_seconds: 0
+                }]
+            })
+        );
+    }
+
+    #[test]
+    fn validate_fails_for_section_ramping_to_negative_power_draw() {
+        let config = SpotPricePlannerConfig {
+            load_profile: LoadProfile {
+                sections: vec![LoadProfileSection {
+                    duration_seconds: 600,
+                    power_draw_watt: 500.0,
+                    end_power_draw_watt: Some(-100.0),
+                    id: None,
+                }],
+            },
+            ..valid_planner_config()
+        };
+
+        assert_eq!(
+            config.validate(),
+            Err(PlannerConfigError {
+                problems: vec![PlannerConfigProblem::NegativeSectionPowerDraw {
+                    section: 0,
+                    power_draw_watt: -100.0
+                }]
+            })
+        );
+    }
+
+    #[test]
+    fn validate_fails_for_inverted_time_slot_unless_marked_as_wrapping() {
+        let config = SpotPricePlannerConfig {
+            plannable_local_time_slots: HashMap::from([(
+                Weekday::Tue,
+                vec![time_slot((16, 0), (14, 0))],
+            )]),
+            ..valid_planner_config()
+        };
+        let wrapping_config = SpotPricePlannerConfig {
+            plannable_local_time_slots: HashMap::from([(
+                Weekday::Tue,
+                vec![TimeSlot {
+                    wraps_midnight: true,
+                    ..time_slot((16, 0), (14, 0))
+                }],
+            )]),
+            ..valid_planner_config()
+        };
+
+        assert_eq!(
+            config.validate(),
+            Err(PlannerConfigError {
+                problems: vec![PlannerConfigProblem::InvertedTimeSlot {
+                    weekday: Weekday::Tue,
+                    from: NaiveTime::from_hms_opt(16, 0, 0).unwrap(),
+                    till: NaiveTime::from_hms_opt(14, 0, 0).unwrap(),
+                }]
+            })
+        );
+        assert_eq!(wrapping_config.validate(), Ok(()));
+    }
+
+    #[test]
+    fn validate_reports_all_problems() {
+        let config = SpotPricePlannerConfig {
+            local_time_zone: "Europe/Atlantis".to_string(),
+            load_profile: LoadProfile {
+                sections: vec![
+                    LoadProfileSection {
+                        duration_seconds: -60,
+                        power_draw_watt: 2000.0,
+                        end_power_draw_watt: None,
+                        id: None,
+                    },
+                    LoadProfileSection {
+                        duration_seconds: 60,
+                        power_draw_watt: -1.0,
+                        end_power_draw_watt: None,
+                        id: None,
+                    },
+                ],
+            },
+            planning_granularity_seconds: Some(-900),
+            plannable_local_time_slots: HashMap::from([
+                (Weekday::Wed, vec![time_slot((8, 0), (8, 0))]),
+                (Weekday::Mon, vec![time_slot((16, 0), (14, 0))]),
+            ]),
+            ..SpotPricePlannerConfig::default()
+        };
+
+        // act
+        let error = config.validate().unwrap_err();
+
+        assert_eq!(
+            error.problems,
+            vec![
+                PlannerConfigProblem::InvalidLocalTimeZone("Europe/Atlantis".to_string()),
+                PlannerConfigProblem::NonPositiveSectionDuration {
+                    section: 0,
+                    duration_seconds: -60
+                },
+                PlannerConfigProblem::NegativeSectionPowerDraw {
+                    section: 1,
+                    power_draw_watt: -1.0
+                },
+                PlannerConfigProblem::NonPositivePlanningGranularity(-900),
+                PlannerConfigProblem::InvertedTimeSlot {
+                    weekday: Weekday::Mon,
+                    from: NaiveTime::from_hms_opt(16, 0, 0).unwrap(),
+                    till: NaiveTime::from_hms_opt(14, 0, 0).unwrap(),
+                },
+                PlannerConfigProblem::InvertedTimeSlot {
+                    weekday: Weekday::Wed,
+                    from: NaiveTime::from_hms_opt(8, 0, 0).unwrap(),
+                    till: NaiveTime::from_hms_opt(8, 0, 0).unwrap(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn try_new_returns_error_for_invalid_config() {
+        let result = SpotPricePlanner::try_new(SpotPricePlannerConfig {
+            load_profile: LoadProfile::default(),
+            ..valid_planner_config()
+        });
+
+        assert!(result.is_err());
+        assert!(SpotPricePlanner::try_new(valid_planner_config()).is_ok());
+    }
+
+    #[test]
+    fn try_new_returns_error_for_inverted_holiday_time_slot() {
+        // act
+        let result = SpotPricePlanner::try_new(SpotPricePlannerConfig {
+            holiday_time_slots: vec![time_slot((16, 0), (14, 0))],
+            ..valid_planner_config()
+        });
+
+        assert_eq!(
+            result.err().map(|error| error.problems),
+            Some(vec![PlannerConfigProblem::InvertedHolidayTimeSlot {
+                from: NaiveTime::from_hms_opt(16, 0, 0).unwrap(),
+                till: NaiveTime::from_hms_opt(14, 0, 0).unwrap(),
+            }])
+        );
+    }
+
+    #[test]
+    fn get_plannable_spot_prices_with_before() -> Result<(), Box<dyn Error>> {
+        let load_profile = LoadProfile {
+            sections: vec![LoadProfileSection {
+                duration_seconds: 18000,
+                power_draw_watt: 2000.0,
+                end_power_draw_watt: None,
+                id: None,
+            }],
+        };
+
+        let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig {
+            load_profile: load_profile.clone(),
+            plannable_local_time_slots: HashMap::from([
+                (
+                    Weekday::Thu,
+                    vec![
+                        TimeSlot {
+                            from: NaiveTime::from_hms_opt(0, 0, 0).unwrap(),
+                            till: NaiveTime::from_hms_opt(7, 0, 0).unwrap(),
+                            wraps_midnight: false,
+                        },
+                        TimeSlot {
+                            from: NaiveTime::from_hms_opt(23, 0, 0).unwrap(),
+                            till: NaiveTime::from_hms_opt(0, 0, 0).unwrap(),
+                            wraps_midnight: false,
+                        },
+                    ],
+                ),
+                (
+                    Weekday::Fri,
+                    vec![
+                        TimeSlot {
+                            from: NaiveTime::from_hms_opt(0, 0, 0).unwrap(),
+                            till: NaiveTime::from_hms_opt(7, 0, 0).unwrap(),
+                            wraps_midnight: false,
+                        },
+                        TimeSlot {
+                            from: NaiveTime::from_hms_opt(23, 0, 0).unwrap(),
+                            till: NaiveTime::from_hms_opt(0, 0, 0).unwrap(),
+                            wraps_midnight: false,
+                        },
+                    ],
+                ),
+            ]),
+            local_time_zone: "Europe/Amsterdam".to_string(),
+            ..SpotPricePlannerConfig::default()
+        });
+
+        let future_spot_prices: Vec<SpotPrice> = vec![
+            SpotPrice::new(
+                Utc.with_ymd_and_hms(2022, 4, 21, 19, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2022, 4, 21, 20, 0, 0).unwrap(),
+                0.224,
+            )
+            .id(None)
+            .market_price_tax(0.0469581)
+            .sourcing_markup_price(0.017)
+            .energy_tax_price(0.081)
+            .build()
+            .unwrap(),
+            SpotPrice::new(
+                Utc.with_ymd_and_hms(2022, 4, 21, 20, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2022, 4, 21, 21, 0, 0).unwrap(),
+                0.22,
+            )
+            .id(None)
+            .market_price_tax(0.0462924)
+            .sourcing_markup_price(0.017)
+            .energy_tax_price(0.081)
+            .build()
+            .unwrap(),
+            SpotPrice::new(
+                Utc.with_ymd_and_hms(2022, 4, 21, 21, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2022, 4, 21, 22, 0, 0).unwrap(),
+                0.2,
+            )
+            .id(None)
+            .market_price_tax(0.0419391)
+            .sourcing_markup_price(0.017)
+            .energy_tax_price(0.081)
+            .build()
+            .unwrap(),
+            SpotPrice::new(
+                Utc.with_ymd_and_hms(2022, 4, 21, 22, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2022, 4, 21, 23, 0, 0).unwrap(),
+                0.193,
+            )
+            .id(None)
+            .market_price_tax(0.040614)
+            .sourcing_markup_price(0.017)
+            .energy_tax_price(0.081)
+            .build()
+            .unwrap(),
+            SpotPrice::new(
+                Utc.with_ymd_and_hms(2022, 4, 21, 23, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2022, 4, 22, 0, 0, 0).unwrap(),
+                0.206,
+            )
+            .id(None)
+            .market_price_tax(0.04326)
+            .sourcing_markup_price(0.017)
+            .energy_tax_price(0.081)
+            .build()
+            .unwrap(),
+            SpotPrice::new(
+                Utc.with_ymd_and_hms(2022, 4, 22, 0, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2022, 4, 22, 1, 0, 0).unwrap(),
+                0.187,
+            )
+            .id(None)
+            .market_price_tax(0.0393078)
+            .sourcing_markup_price(0.017)
+            .energy_tax_price(0.081)
+            .build()
+            .unwrap(),
+            SpotPrice::new(
+                Utc.with_ymd_and_hms(2022, 4, 22, 1, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2022, 4, 22, 2, 0, 0).unwrap(),
+                0.187,
+            )
+            .id(None)
+            .market_price_tax(0.0392721)
+            .sourcing_markup_price(0.017)
+            .energy_tax_price(0.081)
+            .build()
+            .unwrap(),
+            SpotPrice::new(
+                Utc.with_ymd_and_hms(2022, 4, 22, 2, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2022, 4, 22, 3, 0, 0).unwrap(),
+                0.179,
+            )
+            .id(None)
+            .market_price_tax(0.0376761)
+            .sourcing_markup_price(0.017)
+            .energy_tax_price(0.081)
+            .build()
+            .unwrap(),
+            SpotPrice::new(
+                Utc.with_ymd_and_hms(2022, 4, 22, 3, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2022, 4, 22, 4, 0, 0).unwrap(),
+                0.176,
+            )
+            .id(None)
+            .market_price_tax(0.0369789)
+            .sourcing_markup_price(0.017)
+            .energy_tax_price(0.081)
+            .build()
+            .unwrap(),
+            SpotPrice::new(
+                Utc.with_ymd_and_hms(2022, 4, 22, 4, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2022, 4, 22, 5, 0, 0).unwrap(),
+                0.19,
+            )
+            .id(None)
+            .market_price_tax(0.03981180000000001)
+            .sourcing_markup_price(0.017)
+            .energy_tax_price(0.081)
+            .build()
+            .unwrap(),
+            SpotPrice::new(
+                Utc.with_ymd_and_hms(2022, 4, 22, 5, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2022, 4, 22, 6, 0, 0).unwrap(),
+                0.218,
+            )
+            .id(None)
+            .market_price_tax(0.0457947)
+            .sourcing_markup_price(0.017)
+            .energy_tax_price(0.081)
+            .build()
+            .unwrap(),
+            SpotPrice::new(
+                Utc.with_ymd_and_hms(2022, 4, 22, 6, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2022, 4, 22, 7, 0, 0).unwrap(),
+                0.24,
+            )
+            .id(None)
+            .market_price_tax(0.0503895)
+            .sourcing_markup_price(0.017)
+            .energy_tax_price(0.081)
+            .build()
+            .unwrap(),
+            SpotPrice::new(
+                Utc.with_ymd_and_hms(2022, 4, 22, 7, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2022, 4, 22, 8, 0, 0).unwrap(),
+                0.244,
+            )
+            .id(None)
+            .market_price_tax(0.051260999999999994)
+            .sourcing_markup_price(0.017)
+            .energy_tax_price(0.081)
+            .build()
+            .unwrap(),
+            SpotPrice::new(
+                Utc.with_ymd_and_hms(2022, 4, 22, 8, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2022, 4, 22, 9, 0, 0).unwrap(),
+                0.221,
+            )
+            .id(None)
+            .market_price_tax(0.0464205)
+            .sourcing_markup_price(0.017)
+            .energy_tax_price(0.081)
+            .build()
+            .unwrap(),
+            SpotPrice::new(
+                Utc.with_ymd_and_hms(2022, 4, 22, 9, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2022, 4, 22, 10, 0, 0).unwrap(),
+                0.197,
+            )
+            .id(None)
+            .market_price_tax(0.0412776)
+            .sourcing_markup_price(0.017)
+            .energy_tax_price(0.081)
+            .build()
+            .unwrap(),
+            SpotPrice::new(
+                Utc.with_ymd_and_hms(2022, 4, 22, 10, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2022, 4, 22, 11, 0, 0).unwrap(),
+                0.157,
+            )
+            .id(None)
+            .market_price_tax(0.0330561)
+            .sourcing_markup_price(0.017)
+            .energy_tax_price(0.081)
+            .build()
+            .unwrap(),
+            SpotPrice::new(
+                Utc.with_ymd_and_hms(2022, 4, 22, 11, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2022, 4, 22, 12, 0, 0).unwrap(),
+                0.15,
+            )
+            .id(None)
+            .market_price_tax(0.03141599999999999)
+            .sourcing_markup_price(0.017)
+            .energy_tax_price(0.081)
+            .build()
+            .unwrap(),
+            SpotPrice::new(
+                Utc.with_ymd_and_hms(2022, 4, 22, 12, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2022, 4, 22, 13, 0, 0).unwrap(),
+                0.102,
+            )
+            .id(None)
+            .market_price_tax(0.02142)
+            .sourcing_markup_price(0.017)
+            .energy_tax_price(0.081)
+            .build()
+            .unwrap(),
+            SpotPrice::new(
+                Utc.with_ymd_and_hms(2022, 4, 22, 13, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2022, 4, 22, 14, 0, 0).unwrap(),
+                0.1,
+            )
+            .id(None)
+            .market_price_tax(0.021)
+            .sourcing_markup_price(0.017)
+            .energy_tax_price(0.081)
+            .build()
+            .unwrap(),
+            SpotPrice::new(
+                Utc.with_ymd_and_hms(2022, 4, 22, 14, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2022, 4, 22, 15, 0, 0).unwrap(),
+                0.087,
+            )
+            .id(None)
+            .market_price_tax(0.0182217)
+            .sourcing_markup_price(0.017)
+            .energy_tax_price(0.081)
+            .build()
+            .unwrap(),
+            SpotPrice::new(
+                Utc.with_ymd_and_hms(2022, 4, 22, 15, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2022, 4, 22, 16, 0, 0).unwrap(),
+                0.119,
+            )
+            .id(None)
+            .market_price_tax(0.0249837)
+            .sourcing_markup_price(0.017)
+            .energy_tax_price(0.081)
+            .build()
+            .unwrap(),
+            SpotPrice::new(
+                Utc.with_ymd_and_hms(2022, 4, 22, 16, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2022, 4, 22, 17, 0, 0).unwrap(),
+                0.167,
+            )
+            .id(None)
+            .market_price_tax(0.03507)
+            .sourcing_markup_price(0.017)
+            .energy_tax_price(0.081)
+            .build()
+            .unwrap(),
+            SpotPrice::new(
+                Utc.with_ymd_and_hms(2022, 4, 22, 17, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2022, 4, 22, 18, 0, 0).unwrap(),
+                0.185,
+            )
+            .id(None)
+            .market_price_tax(0.038829)
+            .sourcing_markup_price(0.017)
+            .energy_tax_price(0.081)
+            .build()
+            .unwrap(),
+            SpotPrice::new(
+                Utc.with_ymd_and_hms(2022, 4, 22, 18, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2022, 4, 22, 19, 0, 0).unwrap(),
+                0.21,
+            )
+            .id(None)
+            .market_price_tax(0.0440181)
+            .sourcing_markup_price(0.017)
+            .energy_tax_price(0.081)
+            .build()
+            .unwrap(),
+            SpotPrice::new(
+                Utc.with_ymd_and_hms(2022, 4, 22, 19, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2022, 4, 22, 20, 0, 0).unwrap(),
+                0.21,
+            )
+            .id(None)
+            .market_price_tax(0.0440937)
+            .sourcing_markup_price(0.017)
+            .energy_tax_price(0.081)
+            .build()
+            .unwrap(),
+            SpotPrice::new(
+                Utc.with_ymd_and_hms(2022, 4, 22, 20, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2022, 4, 22, 21, 0, 0).unwrap(),
+                0.21,
+            )
+            .id(None)
+            .market_price_tax(0.0440286)
+            .sourcing_markup_price(0.017)
+            .energy_tax_price(0.081)
+            .build()
+            .unwrap(),
+            SpotPrice::new(
+                Utc.with_ymd_and_hms(2022, 4, 22, 21, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2022, 4, 22, 22, 0, 0).unwrap(),
+                0.192,
+            )
+            .id(None)
+            .market_price_tax(0.04032)
+            .sourcing_markup_price(0.017)
+            .energy_tax_price(0.081)
+            .build()
+            .unwrap(),
+            SpotPrice::new(
+                Utc.with_ymd_and_hms(2022, 4, 22, 22, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2022, 4, 22, 23, 0, 0).unwrap(),
+                0.178,
+            )
+            .id(None)
+            .market_price_tax(0.0372855)
+            .sourcing_markup_price(0.017)
+            .energy_tax_price(0.081)
+            .build()
+            .unwrap(),
+        ];
+
+        // act
+        let plannable_spot_prices = spot_price_planner.get_plannable_spot_prices(
+            &future_spot_prices,
+            &Some(Utc.with_ymd_and_hms(2022, 4, 21, 21, 32, 28).unwrap()),
+            &Some(Utc.with_ymd_and_hms(2022, 4, 22, 7, 32, 28).unwrap()),
+        )?;
+
+        assert_eq!(plannable_spot_prices.len(), 7);
+        assert_eq!(
+            plannable_spot_prices[0].from,
+            Utc.with_ymd_and_hms(2022, 4, 21, 22, 0, 0).unwrap()
+        );
+        assert_eq!(
+            plannable_spot_prices[6].till,
+            Utc.with_ymd_and_hms(2022, 4, 22, 5, 0, 0).unwrap()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn get_best_spot_prices_returns_cheapest_combined_block_spot_of_prices_amounting_to_enough_duration_ordered_by_time_for_lowest_price_strategy(
+    ) -> Result<(), Box<dyn Error>> {
+        let load_profile = LoadProfile {
+            sections: vec![LoadProfileSection {
+                duration_seconds: 18000,
+                power_draw_watt: 2000.0,
+                end_power_draw_watt: None,
+                id: None,
+            }],
+        };
+
+        let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig {
+            load_profile: load_profile.clone(),
+            plannable_local_time_slots: HashMap::from([(
+                Weekday::Sat,
+                vec![TimeSlot {
+                    from: NaiveTime::from_hms_opt(0, 0, 0).unwrap(),
+                    till: NaiveTime::from_hms_opt(0, 0, 0).unwrap(),
+                    wraps_midnight: false,
+                }],
+            )]),
+            local_time_zone: "Europe/Amsterdam".to_string(),
+            ..SpotPricePlannerConfig::default()
+        });
+
+        let request = PlanningRequest {
+            spot_prices: vec![
+                SpotPrice::new(
+                    Utc.with_ymd_and_hms(2022, 4, 16, 5, 0, 0).unwrap(),
+                    Utc.with_ymd_and_hms(2022, 4, 16, 6, 0, 0).unwrap(),
+                    0.189,
+                )
+                .id(None)
+                .market_price_tax(0.03968579999999999)
+                .sourcing_markup_price(0.017)
+                .energy_tax_price(0.081)
+                .build()
+                .unwrap(),
+                SpotPrice::new(
+                    Utc.with_ymd_and_hms(2022, 4, 16, 6, 0, 0).unwrap(),
+                    Utc.with_ymd_and_hms(2022, 4, 16, 7, 0, 0).unwrap(),
+                    0.191,
+                )
+                .id(None)
+                .market_price_tax(0.0401352)
+                .sourcing_markup_price(0.017)
+                .energy_tax_price(0.081)
+                .build()
+                .unwrap(),
+                SpotPrice::new(
+                    Utc.with_ymd_and_hms(2022, 4, 16, 7, 0, 0).unwrap(),
+                    Utc.with_ymd_and_hms(2022, 4, 16, 8, 0, 0).unwrap(),
+                    0.19,
+                )
+                .id(None)
+                .market_price_tax(0.039816)
+                .sourcing_markup_price(0.017)
+                .energy_tax_price(0.081)
+                .build()
+                .unwrap(),
+                SpotPrice::new(
+                    Utc.with_ymd_and_hms(2022, 4, 16, 8, 0, 0).unwrap(),
+                    Utc.with_ymd_and_hms(2022, 4, 16, 9, 0, 0).unwrap(),
+                    0.173,
+                )
+                .id(None)
+                .market_price_tax(0.0362502)
+                .sourcing_markup_price(0.017)
+                .energy_tax_price(0.081)
+                .build()
+                .unwrap(),
+                SpotPrice::new(
+                    Utc.with_ymd_and_hms(2022, 4, 16, 9, 0, 0).unwrap(),
+                    Utc.with_ymd_and_hms(2022, 4, 16, 10, 0, 0).unwrap(),
+                    0.147,
+                )
+                .id(None)
+                .market_price_tax(0.030781800000000005)
+                .sourcing_markup_price(0.017)
+                .energy_tax_price(0.081)
+                .build()
+                .unwrap(),
+                SpotPrice::new(
+                    Utc.with_ymd_and_hms(2022, 4, 16, 10, 0, 0).unwrap(),
+                    Utc.with_ymd_and_hms(2022, 4, 16, 11, 0, 0).unwrap(),
+                    0.122,
+                )
+                .id(None)
+                .market_price_tax(0.0256179)
+                .sourcing_markup_price(0.017)
+                .energy_tax_price(0.081)
+                .build()
+                .unwrap(),
+                SpotPrice::new(
+                    Utc.with_ymd_and_hms(2022, 4, 16, 11, 0, 0).unwrap(),
+                    Utc.with_ymd_and_hms(2022, 4, 16, 12, 0, 0).unwrap(),
+                    0.069,
+                )
+                .id(None)
+                .market_price_tax(0.0145446)
+                .sourcing_markup_price(0.017)
+                .energy_tax_price(0.081)
+                .build()
+                .unwrap(),
+                SpotPrice::new(
+                    Utc.with_ymd_and_hms(2022, 4, 16, 12, 0, 0).unwrap(),
+                    Utc.with_ymd_and_hms(2022, 4, 16, 13, 0, 0).unwrap(),
+                    0.025,
+                )
+                .id(None)
+                .market_price_tax(0.0052605)
+                .sourcing_markup_price(0.017)
+                .energy_tax_price(0.081)
+                .build()
+                .unwrap(),
+                SpotPrice::new(
+                    Utc.with_ymd_and_hms(2022, 4, 16, 13, 0, 0).unwrap(),
+                    Utc.with_ymd_and_hms(2022, 4, 16, 14, 0, 0).unwrap(),
+                    0.027,
+                )
+                .id(None)
+                .market_price_tax(0.0056364)
+                .sourcing_markup_price(0.017)
+                .energy_tax_price(0.081)
+                .build()
+                .unwrap(),
+                SpotPrice::new(
+                    Utc.with_ymd_and_hms(2022, 4, 16, 14, 0, 0).unwrap(),
+                    Utc.with_ymd_and_hms(2022, 4, 16, 15, 0, 0).unwrap(),
+                    0.04,
+                )
+                .id(None)
+                .market_price_tax(0.0084672)
+                .sourcing_markup_price(0.017)
+                .energy_tax_price(0.081)
+                .build()
+                .unwrap(),
+                SpotPrice::new(
+                    Utc.with_ymd_and_hms(2022, 4, 16, 15, 0, 0).unwrap(),
+                    Utc.with_ymd_and_hms(2022, 4, 16, 16, 0, 0).unwrap(),
+                    0.066,
+                )
+                .id(None)
+                .market_price_tax(0.013826400000000004)
+                .sourcing_markup_price(0.017)
+                .energy_tax_price(0.081)
+                .build()
+                .unwrap(),
+                SpotPrice::new(
+                    Utc.with_ymd_and_hms(2022, 4, 16, 16, 0, 0).unwrap(),
+                    Utc.with_ymd_and_hms(2022, 4, 16, 17, 0, 0).unwrap(),
+                    0.108,
+                )
+                .id(None)
+                .market_price_tax(0.0226191)
+                .sourcing_markup_price(0.017)
+                .energy_tax_price(0.081)
+                .build()
+                .unwrap(),
+                SpotPrice::new(
+                    Utc.with_ymd_and_hms(2022, 4, 16, 17, 0, 0).unwrap(),
+                    Utc.with_ymd_and_hms(2022, 4, 16, 18, 0, 0).unwrap(),
+                    0.171,
+                )
+                .id(None)
+                .market_price_tax(0.0359499)
+                .sourcing_markup_price(0.017)
+                .energy_tax_price(0.081)
+                .build()
+                .unwrap(),
+                SpotPrice::new(
+                    Utc.with_ymd_and_hms(2022, 4, 16, 18, 0, 0).unwrap(),
+                    Utc.with_ymd_and_hms(2022, 4, 16, 19, 0, 0).unwrap(),
+                    0.195,
+                )
+                .id(None)
+                .market_price_tax(0.0409668)
+                .sourcing_markup_price(0.017)
+                .energy_tax_price(0.081)
+                .build()
+                .unwrap(),
+                SpotPrice::new(
+                    Utc.with_ymd_and_hms(2022, 4, 16, 19, 0, 0).unwrap(),
+                    Utc.with_ymd_and_hms(2022, 4, 16, 20, 0, 0).unwrap(),
+                    0.206,
+                )
+                .id(None)
+                .market_price_tax(0.0432201)
+                .sourcing_markup_price(0.017)
+                .energy_tax_price(0.081)
+                .build()
+                .unwrap(),
+                SpotPrice::new(
+                    Utc.with_ymd_and_hms(2022, 4, 16, 20, 0, 0).unwrap(),
+                    Utc.with_ymd_and_hms(2022, 4, 16, 21, 0, 0).unwrap(),
+                    0.194,
+                )
+                .id(None)
+                .market_price_tax(0.0408387)
+                .sourcing_markup_price(0.017)
+                .energy_tax_price(0.081)
+                .build()
+                .unwrap(),
+                SpotPrice::new(
+                    Utc.with_ymd_and_hms(2022, 4, 16, 21, 0, 0).unwrap(),
+                    Utc.with_ymd_and_hms(2022, 4, 16, 22, 0, 0).unwrap(),
+                    0.176,
+                )
+                .id(None)
+                .market_price_tax(0.0369264)
+                .sourcing_markup_price(0.017)
+                .energy_tax_price(0.081)
+                .build()
+                .unwrap(),
+                SpotPrice::new(
+                    Utc.with_ymd_and_hms(2022, 4, 16, 22, 0, 0).unwrap(),
+                    Utc.with_ymd_and_hms(2022, 4, 16, 23, 0, 0).unwrap(),
+                    0.167,
+                )
+                .id(None)
+                .market_price_tax(0.0350448)
+                .sourcing_markup_price(0.017)
+                .energy_tax_price(0.081)
+                .build()
+                .unwrap(),
+            ],
+            load_profile,
+            planning_strategy: PlanningStrategy::LowestPrice,
+            after: None,
+            before: None,
+            ..PlanningRequest::default()
+        };
+
+        // act
+        let response = spot_price_planner
+            .get_best_spot_prices(&request)?
+            .into_planned()
+            .ok_or("not plannable")?;
+
+        assert_eq!(response.total_price(None), 1.5294702);
+
+        assert_eq!(response.spot_prices.len(), 5);
+        assert_eq!(
+            response.spot_prices[0].from,
+            Utc.with_ymd_and_hms(2022, 4, 16, 11, 0, 0).unwrap()
+        );
+        assert_eq!(response.spot_prices[0].market_price, 0.069);
+
+        assert_eq!(
+            response.spot_prices[1].from,
+            Utc.with_ymd_and_hms(2022, 4, 16, 12, 0, 0).unwrap()
+        );
+        assert_eq!(response.spot_prices[1].market_price, 0.025);
+
+        assert_eq!(
+            response.spot_prices[2].from,
+            Utc.with_ymd_and_hms(2022, 4, 16, 13, 0, 0).unwrap()
+        );
+        assert_eq!(response.spot_prices[2].market_price, 0.027);
+
+        assert_eq!(
+            response.spot_prices[3].from,
+            Utc.with_ymd_and_hms(2022, 4, 16, 14, 0, 0).unwrap()
+        );
+        assert_eq!(response.spot_prices[3].market_price, 0.04);
+
+        assert_eq!(
+            response.spot_prices[4].from,
+            Utc.with_ymd_and_hms(2022, 4, 16, 15, 0, 0).unwrap()
+        );
+        assert_eq!(response.spot_prices[4].market_price, 0.066);
+
+        Ok(())
+    }
+
+    #[test]
+    fn get_best_spot_prices_returns_most_expensive_combined_block_spot_of_prices_amounting_to_enough_duration_ordered_by_time_for_highest_price_strategy(
+    ) -> Result<(), Box<dyn Error>> {
+        let load_profile = LoadProfile {
+            sections: vec![
+                LoadProfileSection {
+                    duration_seconds: 7200,
+                    power_draw_watt: 2000.0,
+                    end_power_draw_watt: None,
+                    id: None,
+                },
+                LoadProfileSection {
+                    duration_seconds: 1800,
+                    power_draw_watt: 8000.0,
+                    end_power_draw_watt: None,
+                    id: None,
+                },
+            ],
+        };
+
+        let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig {
+            load_profile: load_profile.clone(),
+            plannable_local_time_slots: HashMap::from([(
+                Weekday::Sat,
+                vec![TimeSlot {
+                    from: NaiveTime::from_hms_opt(0, 0, 0).unwrap(),
+                    till: NaiveTime::from_hms_opt(0, 0, 0).unwrap(),
+                    wraps_midnight: false,
+                }],
+            )]),
+            local_time_zone: "Europe/Amsterdam".to_string(),
+            ..SpotPricePlannerConfig::default()
+        });
+
+        let request = PlanningRequest {
+            spot_prices: vec![
+                SpotPrice::new(
+                    Utc.with_ymd_and_hms(2022, 4, 16, 5, 0, 0).unwrap(),
+                    Utc.with_ymd_and_hms(2022, 4, 16, 6, 0, 0).unwrap(),
+                    0.189,
+                )
+                .id(None)
+                .market_price_tax(0.03968579999999999)
+                .sourcing_markup_price(0.017)
+                .energy_tax_price(0.081)
+                .build()
+                .unwrap(),
+                SpotPrice::new(
+                    Utc.with_ymd_and_hms(2022, 4, 16, 6, 0, 0).unwrap(),
+                    Utc.with_ymd_and_hms(2022, 4, 16, 7, 0, 0).unwrap(),
+                    0.191,
+                )
+                .id(None)
+                .market_price_tax(0.0401352)
+                .sourcing_markup_price(0.017)
+                .energy_tax_price(0.081)
+                .build()
+                .unwrap(),
+                SpotPrice::new(
+                    Utc.with_ymd_and_hms(2022, 4, 16, 7, 0, 0).unwrap(),
+                    Utc.with_ymd_and_hms(2022, 4, 16, 8, 0, 0).unwrap(),
+                    0.19,
+                )
+                .id(None)
+                .market_price_tax(0.039816)
+                .sourcing_markup_price(0.017)
+                .energy_tax_price(0.081)
+                .build()
+                .unwrap(),
+                SpotPrice::new(
+                    Utc.with_ymd_and_hms(2022, 4, 16, 8, 0, 0).unwrap(),
+                    Utc.with_ymd_and_hms(2022, 4, 16, 9, 0, 0).unwrap(),
+                    0.173,
+                )
+                .id(None)
+                .market_price_tax(0.0362502)
+                .sourcing_markup_price(0.017)
+                .energy_tax_price(0.081)
+                .build()
+                .unwrap(),
+                SpotPrice::new(
+                    Utc.with_ymd_and_hms(2022, 4, 16, 9, 0, 0).unwrap(),
+                    Utc.with_ymd_and_hms(2022, 4, 16, 10, 0, 0).unwrap(),
+                    0.147,
+                )
+                .id(None)
+                .market_price_tax(0.030781800000000005)
+                .sourcing_markup_price(0.017)
+                .energy_tax_price(0.081)
+                .build()
+                .unwrap(),
+                SpotPrice::new(
+                    Utc.with_ymd_and_hms(2022, 4, 16, 10, 0, 0).unwrap(),
+                    Utc.with_ymd_and_hms(2022, 4, 16, 11, 0, 0).unwrap(),
+                    0.122,
+                )
+                .id(None)
+                .market_price_tax(0.0256179)
+                .sourcing_markup_price(0.017)
+                .energy_tax_price(0.081)
+                .build()
+                .unwrap(),
+                SpotPrice::new(
+                    Utc.with_ymd_and_hms(2022, 4, 16, 11, 0, 0).unwrap(),
+                    Utc.with_ymd_and_hms(2022, 4, 16, 12, 0, 0).unwrap(),
+                    0.069,
+                )
+                .id(None)
+                .market_price_tax(0.0145446)
+                .sourcing_markup_price(0.017)
+                .energy_tax_price(0.081)
+                .build()
+                .unwrap(),
+                SpotPrice::new(
+                    Utc.with_ymd_and_hms(2022, 4, 16, 12, 0, 0).unwrap(),
+                    Utc.with_ymd_and_hms(2022, 4, 16, 13, 0, 0).unwrap(),
+                    0.025,
+                )
+                .id(None)
+                .market_price_tax(0.0052605)
+                .sourcing_markup_price(0.017)
+                .energy_tax_price(0.081)
+                .build()
+                .unwrap(),
+                SpotPrice::new(
+                    Utc.with_ymd_and_hms(2022, 4, 16, 13, 0, 0).unwrap(),
+                    Utc.with_ymd_and_hms(2022, 4, 16, 14, 0, 0).unwrap(),
+                    0.027,
+                )
+                .id(None)
+                .market_price_tax(0.0056364)
+                .sourcing_markup_price(0.017)
+                .energy_tax_price(0.081)
+                .build()
+                .unwrap(),
+                SpotPrice::new(
+                    Utc.with_ymd_and_hms(2022, 4, 16, 14, 0, 0).unwrap(),
+                    Utc.with_ymd_and_hms(2022, 4, 16, 15, 0, 0).unwrap(),
+                    0.04,
+                )
+                .id(None)
+                .market_price_tax(0.0084672)
+                .sourcing_markup_price(0.017)
+                .energy_tax_price(0.081)
+                .build()
+                .unwrap(),
+                SpotPrice::new(
+                    Utc.with_ymd_and_hms(2022, 4, 16, 15, 0, 0).unwrap(),
+                    Utc.with_ymd_and_hms(2022, 4, 16, 16, 0, 0).unwrap(),
+                    0.066,
+                )
+                .id(None)
+                .market_price_tax(0.013826400000000004)
+                .sourcing_markup_price(0.017)
+                .energy_tax_price(0.081)
+                .build()
+                .unwrap(),
+                SpotPrice::new(
+                    Utc.with_ymd_and_hms(2022, 4, 16, 16, 0, 0).unwrap(),
+                    Utc.with_ymd_and_hms(2022, 4, 16, 17, 0, 0).unwrap(),
+                    0.108,
+                )
+                .id(None)
+                .market_price_tax(0.0226191)
+                .sourcing_markup_price(0.017)
+                .energy_tax_price(0.081)
+                .build()
+                .unwrap(),
+                SpotPrice::new(
+                    Utc.with_ymd_and_hms(2022, 4, 16, 17, 0, 0).unwrap(),
+                    Utc.with_ymd_and_hms(2022, 4, 16, 18, 0, 0).unwrap(),
+                    0.171,
+                )
+                .id(None)
+                .market_price_tax(0.0359499)
+                .sourcing_markup_price(0.017)
+                .energy_tax_price(0.081)
+                .build()
+                .unwrap(),
+                SpotPrice::new(
+                    Utc.with_ymd_and_hms(2022, 4, 16, 18, 0, 0).unwrap(),
+                    Utc.with_ymd_and_hms(2022, 4, 16, 19, 0, 0).unwrap(),
+                    0.195,
+                )
+                .id(None)
+                .market_price_tax(0.0409668)
+                .sourcing_markup_price(0.017)
+                .energy_tax_price(0.081)
+                .build()
+                .unwrap(),
+                SpotPrice::new(
+                    Utc.with_ymd_and_hms(2022, 4, 16, 19, 0, 0).unwrap(),
+                    Utc.with_ymd_and_hms(2022, 4, 16, 20, 0, 0).unwrap(),
+                    0.206,
+                )
+                .id(None)
+                .market_price_tax(0.0432201)
+                .sourcing_markup_price(0.017)
+                .energy_tax_price(0.081)
+                .build()
+                .unwrap(),
+                SpotPrice::new(
+                    Utc.with_ymd_and_hms(2022, 4, 16, 20, 0, 0).unwrap(),
+                    Utc.with_ymd_and_hms(2022, 4, 16, 21, 0, 0).unwrap(),
+                    0.194,
+                )
+                .id(None)
+                .market_price_tax(0.0408387)
+                .sourcing_markup_price(0.017)
+                .energy_tax_price(0.081)
+                .build()
+                .unwrap(),
+                SpotPrice::new(
+                    Utc.with_ymd_and_hms(2022, 4, 16, 21, 0, 0).unwrap(),
+                    Utc.with_ymd_and_hms(2022, 4, 16, 22, 0, 0).unwrap(),
+                    0.176,
+                )
+                .id(None)
+                .market_price_tax(0.0369264)
+                .sourcing_markup_price(0.017)
+                .energy_tax_price(0.081)
+                .build()
+                .unwrap(),
+                SpotPrice::new(
+                    Utc.with_ymd_and_hms(2022, 4, 16, 22, 0, 0).unwrap(),
+                    Utc.with_ymd_and_hms(2022, 4, 16, 23, 0, 0).unwrap(),
+                    0.167,
+                )
+                .id(None)
+                .market_price_tax(0.0350448)
+                .sourcing_markup_price(0.017)
+                .energy_tax_price(0.081)
+                .build()
+                .unwrap(),
+            ],
+            load_profile,
+            planning_strategy: PlanningStrategy::HighestPrice,
+            after: None,
+            before: None,
+            ..PlanningRequest::default()
+        };
+
+        // act
+        let response = spot_price_planner
+            .get_best_spot_prices(&request)?
+            .into_planned()
+            .ok_or("not plannable")?;
+
+        assert_eq!(response.total_price(None), 2.6937286);
+
+        assert_eq!(response.spot_prices.len(), 3);
+        assert_eq!(
+            response.spot_prices[0].from,
+            Utc.with_ymd_and_hms(2022, 4, 16, 18, 0, 0).unwrap()
+        );
+        assert_eq!(response.spot_prices[0].market_price, 0.195);
+
+        assert_eq!(
+            response.spot_prices[1].from,
+            Utc.with_ymd_and_hms(2022, 4, 16, 19, 0, 0).unwrap()
+        );
+        assert_eq!(response.spot_prices[1].market_price, 0.206);
+
+        assert_eq!(
+            response.spot_prices[2].from,
+            Utc.with_ymd_and_hms(2022, 4, 16, 20, 0, 0).unwrap()
+        );
+        assert_eq!(response.spot_prices[2].market_price, 0.194);
+
+        Ok(())
+    }
+
+    #[test]
+    fn get_best_spot_prices_picks_cheapest_slots_from_both_valleys_for_non_consecutive_strategy(
+    ) -> Result<(), Box<dyn Error>> {
+        let load_profile = LoadProfile {
+            sections: vec![LoadProfileSection {
+                duration_seconds: 4 * 3600,
+                power_draw_watt: 2000.0,
+                end_power_draw_watt: None,
+                id: None,
+            }],
+        };
+        let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig {
+            load_profile: load_profile.clone(),
+            ..SpotPricePlannerConfig::default()
+        });
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+        let spot_prices = hourly_spot_prices(
+            from,
+            &[0.30, 0.05, 0.06, 0.30, 0.30, 0.30, 0.04, 0.05, 0.30],
+        );
+
+        // act
+        let consecutive = spot_price_planner
+            .get_best_spot_prices(&PlanningRequest {
+                spot_prices: spot_prices.clone(),
+                load_profile: load_profile.clone(),
+                planning_strategy: PlanningStrategy::LowestPrice,
+                after: None,
+                before: None,
+                ..PlanningRequest::default()
+            })?
+            .into_planned()
+            .ok_or("not plannable")?;
+        let non_consecutive = spot_price_planner
+            .get_best_spot_prices(&PlanningRequest {
+                spot_prices,
+                load_profile,
+                planning_strategy: PlanningStrategy::LowestPriceNonConsecutive,
+                after: None,
+                before: None,
+                ..PlanningRequest::default()
+            })?
+            .into_planned()
+            .ok_or("not plannable")?;
+
+        assert_eq!(
+            non_consecutive
+                .spot_prices
+                .iter()
+                .map(|sp| sp.from)
+                .collect::<Vec<DateTime<Utc>>>(),
+            vec![
+                from + Duration::hours(1),
+                from + Duration::hours(2),
+                from + Duration::hours(6),
+                from + Duration::hours(7),
+            ]
+        );
+        assert!(non_consecutive.total_price(None) < consecutive.total_price(None));
+
+        Ok(())
+    }
+
+    #[test]
+    fn get_best_spot_prices_starts_late_when_latest_slot_is_cheapest_for_non_consecutive_strategy(
+    ) -> Result<(), Box<dyn Error>> {
+        let load_profile = LoadProfile {
+            sections: vec![LoadProfileSection {
+                duration_seconds: 5400,
+                power_draw_watt: 2000.0,
+                end_power_draw_watt: None,
+                id: None,
+            }],
+        };
+        let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig::default());
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+
+        // act
+        let response = spot_price_planner
+            .get_best_spot_prices(&PlanningRequest {
+                spot_prices: hourly_spot_prices(from, &[0.30, 0.20, 0.10]),
+                load_profile,
+                planning_strategy: PlanningStrategy::LowestPriceNonConsecutive,
+                ..PlanningRequest::default()
+            })?
+            .into_planned()
+            .ok_or("not plannable")?;
+
+        assert_eq!(
+            response
+                .spot_prices
+                .iter()
+                .map(|sp| sp.from)
+                .collect::<Vec<DateTime<Utc>>>(),
+            vec![from + Duration::hours(1), from + Duration::hours(2)]
+        );
+        assert_eq!(response.planned_from, Some(from + Duration::minutes(90)));
+        // half an hour at 0.20 and a full hour at 0.10 for 2 kW
+        assert!((response.total_price(None) - 0.40).abs() < 1e-9);
+
+        Ok(())
+    }
+
+    #[test]
+    fn get_best_spot_prices_prefers_earlier_slots_on_ties_for_non_consecutive_strategy(
+    ) -> Result<(), Box<dyn Error>> {
+        let load_profile = LoadProfile {
+            sections: vec![LoadProfileSection {
+                duration_seconds: 3600,
+                power_draw_watt: 2000.0,
+                end_power_draw_watt: None,
+                id: None,
+            }],
+        };
+        let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig::default());
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+
+        // act
+        let response = spot_price_planner
+            .get_best_spot_prices(&PlanningRequest {
+                spot_prices: hourly_spot_prices(from, &[0.30, 0.10, 0.20, 0.10]),
+                load_profile,
+                planning_strategy: PlanningStrategy::LowestPriceNonConsecutive,
+                after: None,
+                before: None,
+                ..PlanningRequest::default()
+            })?
+            .into_planned()
+            .ok_or("not plannable")?;
+
+        assert_eq!(response.spot_prices.len(), 1);
+        assert_eq!(response.spot_prices[0].from, from + Duration::hours(1));
+
+        Ok(())
+    }
+
+    #[test]
+    fn get_best_spot_prices_starts_mid_slot_when_cheaper_at_planning_granularity(
+    ) -> Result<(), Box<dyn Error>> {
+        // a short low power warm-up followed by an hour of high power draw
+        let load_profile = LoadProfile {
+            sections: vec![
+                LoadProfileSection {
+                    duration_seconds: 1800,
+                    power_draw_watt: 100.0,
+                    end_power_draw_watt: None,
+                    id: None,
+                },
+                LoadProfileSection {
+                    duration_seconds: 3600,
+                    power_draw_watt: 4000.0,
+                    end_power_draw_watt: None,
+                    id: None,
+                },
+            ],
+        };
+        let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig {
+            planning_granularity_seconds: Some(900),
+            ..SpotPricePlannerConfig::default()
+        });
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+
+        // act
+        let response = spot_price_planner
+            .get_best_spot_prices(&PlanningRequest {
+                spot_prices: hourly_spot_prices(from, &[0.50, 0.10, 0.50, 0.50]),
+                load_profile,
+                planning_strategy: PlanningStrategy::LowestPrice,
+                after: None,
+                before: None,
+                ..PlanningRequest::default()
+            })?
+            .into_planned()
+            .ok_or("not plannable")?;
+
+        assert_eq!(response.planned_from, Some(from + Duration::minutes(30)));
+        assert_eq!(response.planned_till, Some(from + Duration::hours(2)));
+        assert_eq!(response.spot_prices.len(), 2);
+        assert_eq!(response.spot_prices[0].from, from);
+        assert!((response.total_price(None) - 0.425).abs() < 1e-9);
+
+        Ok(())
+    }
+
+    #[test]
+    fn validate_fails_for_non_positive_planning_granularity() {
+        let config = SpotPricePlannerConfig {
+            planning_granularity_seconds: Some(0),
+            ..valid_planner_config()
+        };
+
+        // act
+        let result = config.validate();
+
+        assert_eq!(
+            result,
+            Err(PlannerConfigError {
+                problems: vec![PlannerConfigProblem::NonPositivePlanningGranularity(0)]
+            })
+        );
+    }
+
+    #[test]
+    fn get_best_spot_prices_sets_planned_from_and_till_for_load_partially_filling_final_slot(
+    ) -> Result<(), Box<dyn Error>> {
+        let load_profile = LoadProfile {
+            sections: vec![LoadProfileSection {
+                duration_seconds: 5400,
+                power_draw_watt: 2000.0,
+                end_power_draw_watt: None,
+                id: None,
+            }],
+        };
+        let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig::default());
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+
+        // act
+        let response = spot_price_planner
+            .get_best_spot_prices(&PlanningRequest {
+                spot_prices: hourly_spot_prices(from, &[0.30, 0.10, 0.10, 0.30]),
+                load_profile,
+                planning_strategy: PlanningStrategy::LowestPrice,
+                after: None,
+                before: None,
+                ..PlanningRequest::default()
+            })?
+            .into_planned()
+            .ok_or("not plannable")?;
+
+        assert_eq!(response.planned_from, Some(from + Duration::hours(1)));
+        assert_eq!(
+            response.planned_till,
+            Some(from + Duration::hours(2) + Duration::minutes(30))
+        );
+        assert!(!response.is_active_at(from + Duration::minutes(59)));
+        assert!(response.is_active_at(from + Duration::hours(1)));
+        assert!(response.is_active_at(from + Duration::hours(2) + Duration::minutes(29)));
+        assert!(!response.is_active_at(from + Duration::hours(2) + Duration::minutes(30)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn next_transition_after_returns_boundaries_of_consecutive_plan() -> Result<(), Box<dyn Error>>
+    {
+        let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig::default());
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+        let planned_from = from + Duration::hours(1);
+        let planned_till = from + Duration::hours(2) + Duration::minutes(30);
+
+        // act
+        let response = spot_price_planner
+            .get_best_spot_prices(&PlanningRequest {
+                spot_prices: hourly_spot_prices(from, &[0.30, 0.10, 0.10, 0.30]),
+                load_profile: LoadProfile {
+                    sections: vec![LoadProfileSection {
+                        duration_seconds: 5400,
+                        power_draw_watt: 2000.0,
+                        end_power_draw_watt: None,
+                        id: None,
+                    }],
+                },
+                ..PlanningRequest::default()
+            })?
+            .into_planned()
+            .ok_or("not plannable")?;
+
+        assert_eq!(
+            response.next_transition_after(from),
+            Some((planned_from, true))
+        );
+        assert!(response.is_active_at(planned_from));
+        assert_eq!(
+            response.next_transition_after(planned_from),
+            Some((planned_till, false))
+        );
+        assert!(!response.is_active_at(planned_till));
+        assert_eq!(response.next_transition_after(planned_till), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn next_transition_after_switches_off_for_gap_in_non_consecutive_plan(
+    ) -> Result<(), Box<dyn Error>> {
+        let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig::default());
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+
+        // act
+        let response = spot_price_planner
+            .get_best_spot_prices(&PlanningRequest {
+                spot_prices: hourly_spot_prices(from, &[0.10, 0.30, 0.30, 0.10]),
+                load_profile: LoadProfile {
+                    sections: vec![LoadProfileSection {
+                        duration_seconds: 7200,
+                        power_draw_watt: 1000.0,
+                        end_power_draw_watt: None,
+                        id: None,
+                    }],
+                },
+                planning_strategy: PlanningStrategy::LowestPriceNonConsecutive,
+                ..PlanningRequest::default()
+            })?
+            .into_planned()
+            .ok_or("not plannable")?;
+
+        let in_gap = from + Duration::hours(2);
+        assert!(response.is_active_at(from));
+        assert!(!response.is_active_at(in_gap));
+        assert_eq!(
+            response.next_transition_after(from),
+            Some((from + Duration::hours(1), false))
+        );
+        assert_eq!(
+            response.next_transition_after(in_gap),
+            Some((from + Duration::hours(3), true))
+        );
+        assert_eq!(
+            response.next_transition_after(from + Duration::hours(3)),
+            Some((from + Duration::hours(4), false))
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn planning_response_deserializes_without_planned_timestamps() -> Result<(), Box<dyn Error>> {
+        // act
+        let response: PlanningResponse =
+            serde_json::from_str(r#"{"spot_prices":[],"load_profile":{"sections":[]}}"#)?;
+
+        assert_eq!(response.planned_from, None);
+        assert_eq!(response.planned_till, None);
+        assert!(!response.is_active_at(Utc::now()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn get_best_spot_prices_ignores_unused_tail_of_last_slot_when_ranking_blocks(
+    ) -> Result<(), Box<dyn Error>> {
+        let load_profile = LoadProfile {
+            sections: vec![LoadProfileSection {
+                duration_seconds: 5400,
+                power_draw_watt: 1000.0,
+                end_power_draw_watt: None,
+                id: None,
+            }],
+        };
+        let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig::default());
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+
+        // summing whole slots would favour the block starting at 03:00 (0.23 vs 0.24)
+        let spot_prices = hourly_spot_prices(from, &[0.12, 0.12, 0.50, 0.20, 0.03]);
+
+        // act
+        let response = spot_price_planner
+            .get_best_spot_prices(&PlanningRequest {
+                spot_prices,
+                load_profile,
+                planning_strategy: PlanningStrategy::LowestPrice,
+                after: None,
+                before: None,
+                ..PlanningRequest::default()
+            })?
+            .into_planned()
+            .ok_or("not plannable")?;
+
+        assert_eq!(response.planned_from, Some(from));
+        assert_eq!(response.spot_prices.len(), 2);
+        assert_eq!(response.last_slot_used_seconds, Some(1800));
+        assert!((response.total_price(None) - 0.18).abs() < 1e-9);
+
+        Ok(())
+    }
+
+    #[test]
+    fn get_best_spot_prices_only_plans_blocks_below_max_acceptable_price(
+    ) -> Result<(), Box<dyn Error>> {
+        let load_profile = LoadProfile {
+            sections: vec![LoadProfileSection {
+                duration_seconds: 7200,
+                power_draw_watt: 1000.0,
+                end_power_draw_watt: None,
+                id: None,
+            }],
+        };
+        let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig::default());
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+
+        // act
+        let response = spot_price_planner
+            .get_best_spot_prices(&PlanningRequest {
+                spot_prices: hourly_spot_prices(from, &[0.55, 0.30, 0.45, 0.60]),
+                load_profile,
+                planning_strategy: PlanningStrategy::HighestPrice,
+                max_acceptable_total_price_per_kwh: Some(0.40),
+                ..PlanningRequest::default()
+            })?
+            .into_planned()
+            .ok_or("not plannable")?;
+
+        // the most expensive block still at or below 0.40/kWh on average
+        assert_eq!(response.planned_from, Some(from + Duration::hours(1)));
+        assert_eq!(response.spot_prices.len(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn get_best_spot_prices_returns_no_spot_prices_when_all_blocks_exceed_max_acceptable_price(
+    ) -> Result<(), Box<dyn Error>> {
+        let load_profile = LoadProfile {
+            sections: vec![LoadProfileSection {
+                duration_seconds: 7200,
+                power_draw_watt: 1000.0,
+                end_power_draw_watt: None,
+                id: None,
+            }],
+        };
+        let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig {
+            max_acceptable_total_price_per_kwh: Some(0.40),
+            ..SpotPricePlannerConfig::default()
+        });
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+
+        // act
+        let outcome = spot_price_planner.get_best_spot_prices(&PlanningRequest {
+            spot_prices: hourly_spot_prices(from, &[0.50, 0.45, 0.41, 0.60]),
+            load_profile,
+            ..PlanningRequest::default()
+        })?;
+
+        assert!(matches!(
+            outcome,
+            PlanningOutcome::NotPlannable {
+                reason: NotPlannableReason::PriceThresholdNotMet,
+                ..
+            }
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn get_best_spot_prices_falls_back_to_ignoring_time_slots_when_no_block_fits(
+    ) -> Result<(), Box<dyn Error>> {
+        let load_profile = LoadProfile {
+            sections: vec![LoadProfileSection {
+                duration_seconds: 7200,
+                power_draw_watt: 1000.0,
+                end_power_draw_watt: None,
+                id: None,
+            }],
+        };
+        let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig {
+            plannable_local_time_slots: HashMap::from([(
+                Weekday::Sat,
+                vec![TimeSlot {
+                    from: NaiveTime::from_hms_opt(0, 0, 0).unwrap(),
+                    till: NaiveTime::from_hms_opt(1, 0, 0).unwrap(),
+                    wraps_midnight: false,
+                }],
+            )]),
+            ..SpotPricePlannerConfig::default()
+        });
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+        let request = PlanningRequest {
+            spot_prices: hourly_spot_prices(from, &[0.30, 0.20, 0.10, 0.40, 0.05]),
+            load_profile,
+            before: Some(from + Duration::hours(4)),
+            ..PlanningRequest::default()
+        };
+
+        // act
+        let without_fallback = spot_price_planner.get_best_spot_prices(&request)?;
+        let with_fallback = spot_price_planner
+            .get_best_spot_prices(&PlanningRequest {
+                enable_fallback: true,
+                ..request
+            })?
+            .into_planned()
+            .ok_or("not plannable")?;
+
+        assert!(matches!(
+            without_fallback,
+            PlanningOutcome::NotPlannable {
+                reason: NotPlannableReason::NotEnoughConsecutiveDuration,
+                ..
+            }
+        ));
+        assert!(with_fallback.fallback_used);
+        assert_eq!(with_fallback.planned_from, Some(from + Duration::hours(1)));
+        assert_eq!(with_fallback.spot_prices.len(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn get_best_spot_prices_returns_not_enough_prices_error_when_fallback_cannot_fit_load() {
+        let load_profile = LoadProfile {
+            sections: vec![LoadProfileSection {
+                duration_seconds: 7200,
+                power_draw_watt: 1000.0,
+                end_power_draw_watt: None,
+                id: None,
+            }],
+        };
+        let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig::default());
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+
+        // act
+        let result = spot_price_planner.get_best_spot_prices(&PlanningRequest {
+            spot_prices: hourly_spot_prices(from, &[0.30, 0.20, 0.10]),
+            load_profile,
+            before: Some(from + Duration::hours(1)),
+            enable_fallback: true,
+            ..PlanningRequest::default()
+        });
+
+        let error = result.unwrap_err();
+        let not_enough_prices = error.downcast_ref::<NotEnoughPricesError>().unwrap();
+        assert_eq!(not_enough_prices.required_seconds, 7200);
+        assert_eq!(not_enough_prices.available_seconds, 3600);
+    }
+
+    #[test]
+    fn cheapest_window_matches_get_best_spot_prices() -> Result<(), Box<dyn Error>> {
+        let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig::default());
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+        let spot_prices = hourly_spot_prices(from, &[0.30, 0.10, 0.20, 0.40, 0.05, 0.15]);
+        let load_profile = LoadProfile {
+            sections: vec![LoadProfileSection {
+                duration_seconds: 7200,
+                power_draw_watt: 2000.0,
+                end_power_draw_watt: None,
+                id: None,
+            }],
+        };
+
+        // act
+        let window = spot_price_planner
+            .cheapest_window(&spot_prices, Duration::hours(2), None, None)?
+            .ok_or("no window")?;
+        let response = spot_price_planner
+            .get_best_spot_prices(&PlanningRequest {
+                spot_prices,
+                load_profile,
+                ..PlanningRequest::default()
+            })?
+            .into_planned()
+            .ok_or("not plannable")?;
+
+        assert_eq!(Some(window.from), response.planned_from);
+        assert_eq!(Some(window.till), response.planned_till);
+        assert_eq!(window.spot_prices.len(), response.spot_prices.len());
+        assert!((window.average_price_per_kwh - 0.10).abs() < 1e-9);
+        assert!((window.average_price_per_kwh - response.total_price(None) / 4.0).abs() < 1e-9);
+
+        Ok(())
+    }
+
+    #[test]
+    fn most_expensive_window_matches_get_best_spot_prices() -> Result<(), Box<dyn Error>> {
+        let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig::default());
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+        let spot_prices = hourly_spot_prices(from, &[0.30, 0.10, 0.20, 0.40, 0.05, 0.15]);
+
+        // act
+        let window = spot_price_planner
+            .most_expensive_window(&spot_prices, Duration::minutes(90), None, None)?
+            .ok_or("no window")?;
+        let response = spot_price_planner
+            .get_best_spot_prices(&PlanningRequest {
+                spot_prices,
+                load_profile: LoadProfile {
+                    sections: vec![LoadProfileSection {
+                        duration_seconds: 5400,
+                        power_draw_watt: 1000.0,
+                        end_power_draw_watt: None,
+                        id: None,
+                    }],
+                },
+                planning_strategy: PlanningStrategy::HighestPrice,
+                ..PlanningRequest::default()
+            })?
+            .into_planned()
+            .ok_or("not plannable")?;
+
+        assert_eq!(Some(window.from), response.planned_from);
+        assert_eq!(Some(window.till), response.planned_till);
+        assert!((window.average_price_per_kwh - response.total_price(None) / 1.5).abs() < 1e-9);
+
+        Ok(())
+    }
+
+    #[test]
+    fn cheapest_window_returns_none_when_no_window_fits() -> Result<(), Box<dyn Error>> {
+        let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig::default());
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+
+        // act
+        let window = spot_price_planner.cheapest_window(
+            &hourly_spot_prices(from, &[0.30, 0.10]),
+            Duration::hours(3),
+            None,
+            None,
+        )?;
+
+        assert_eq!(window, None);
+
+        Ok(())
+    }
+
+    const DAY_CURVE: [f64; 24] = [
+        0.21, 0.19, 0.18, 0.17, 0.16, 0.18, 0.22, 0.27, 0.29, 0.25, 0.20, 0.15, 0.12, 0.10, 0.11,
+        0.14, 0.19, 0.26, 0.31, 0.33, 0.28, 0.24, 0.22, 0.20,
+    ];
+
+    #[test]
+    fn price_percentile_interpolates_between_closest_ranks() {
+        // act
+        let p30 = price_percentile(&DAY_CURVE, 30.0).unwrap();
+        let p80 = price_percentile(&DAY_CURVE, 80.0).unwrap();
+
+        assert!((p30 - 0.179).abs() < 1e-9);
+        assert!((p80 - 0.264).abs() < 1e-9);
+        assert_eq!(price_percentile(&DAY_CURVE, 0.0), Some(0.10));
+        assert_eq!(price_percentile(&DAY_CURVE, 100.0), Some(0.33));
+        assert_eq!(price_percentile(&[], 30.0), None);
+    }
+
+    #[test]
+    fn get_best_spot_prices_plans_cheapest_spot_prices_within_percentile(
+    ) -> Result<(), Box<dyn Error>> {
+        let load_profile = LoadProfile {
+            sections: vec![LoadProfileSection {
+                duration_seconds: 10800,
+                power_draw_watt: 1000.0,
+                end_power_draw_watt: None,
+                id: None,
+            }],
+        };
+        let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig::default());
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+
+        // act
+        let response = spot_price_planner
+            .get_best_spot_prices(&PlanningRequest {
+                spot_prices: hourly_spot_prices(from, &DAY_CURVE),
+                load_profile,
+                planning_strategy: PlanningStrategy::LowestPricePercentile { percentile: 30.0 },
+                ..PlanningRequest::default()
+            })?
+            .into_planned()
+            .ok_or("not plannable")?;
+
+        assert!(!response.percentile_fallback_used);
+        assert_eq!(
+            response
+                .spot_prices
+                .iter()
+                .map(|spot_price| spot_price.from)
+                .collect::<Vec<_>>(),
+            vec![
+                from + Duration::hours(12),
+                from + Duration::hours(13),
+                from + Duration::hours(14)
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn get_best_spot_prices_plans_most_expensive_spot_prices_within_percentile(
+    ) -> Result<(), Box<dyn Error>> {
+        let load_profile = LoadProfile {
+            sections: vec![LoadProfileSection {
+                duration_seconds: 7200,
+                power_draw_watt: 1000.0,
+                end_power_draw_watt: None,
+                id: None,
+            }],
+        };
+        let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig::default());
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+
+        // act
+        let response = spot_price_planner
+            .get_best_spot_prices(&PlanningRequest {
+                spot_prices: hourly_spot_prices(from, &DAY_CURVE),
+                load_profile,
+                planning_strategy: PlanningStrategy::HighestPricePercentile { percentile: 20.0 },
+                ..PlanningRequest::default()
+            })?
+            .into_planned()
+            .ok_or("not plannable")?;
+
+        assert_eq!(response.planned_from, Some(from + Duration::hours(18)));
+        assert_eq!(response.planned_till, Some(from + Duration::hours(20)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn get_best_spot_prices_falls_back_to_consecutive_block_when_percentile_does_not_cover_load(
+    ) -> Result<(), Box<dyn Error>> {
+        let load_profile = LoadProfile {
+            sections: vec![LoadProfileSection {
+                duration_seconds: 8 * 3600,
+                power_draw_watt: 1000.0,
+                end_power_draw_watt: None,
+                id: None,
+            }],
+        };
+        let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig::default());
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+
+        // act
+        let response = spot_price_planner
+            .get_best_spot_prices(&PlanningRequest {
+                spot_prices: hourly_spot_prices(from, &DAY_CURVE),
+                load_profile,
+                planning_strategy: PlanningStrategy::LowestPricePercentile { percentile: 30.0 },
+                ..PlanningRequest::default()
+            })?
+            .into_planned()
+            .ok_or("not plannable")?;
+
+        // only 7 hours are at or below the 30th percentile
+        assert!(response.percentile_fallback_used);
+        assert_eq!(response.planned_from, Some(from + Duration::hours(9)));
+        assert_eq!(response.planned_till, Some(from + Duration::hours(17)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn get_best_spot_prices_applies_tie_breaker_on_flat_prices() -> Result<(), Box<dyn Error>> {
+        let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig::default());
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+        let request = PlanningRequest {
+            spot_prices: hourly_spot_prices(from, &[0.20; 6]),
+            load_profile: LoadProfile {
+                sections: vec![LoadProfileSection {
+                    duration_seconds: 7200,
+                    power_draw_watt: 1000.0,
+                    end_power_draw_watt: None,
+                    id: None,
+                }],
+            },
+            ..PlanningRequest::default()
+        };
+
+        // act
+        let earliest = spot_price_planner
+            .get_best_spot_prices(&request)?
+            .into_planned()
+            .ok_or("not plannable")?;
+        let latest = spot_price_planner
+            .get_best_spot_prices(&PlanningRequest {
+                tie_breaker: Some(TieBreaker::LatestStart),
+                ..request
+            })?
+            .into_planned()
+            .ok_or("not plannable")?;
+
+        assert_eq!(earliest.planned_from, Some(from));
+        assert_eq!(latest.planned_from, Some(from + Duration::hours(4)));
+        assert_eq!(latest.planned_till, Some(from + Duration::hours(6)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn get_best_spot_prices_treats_prices_within_configured_epsilon_as_ties(
+    ) -> Result<(), Box<dyn Error>> {
+        let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig {
+            tie_breaker: TieBreaker::LatestStart,
+            tie_breaker_epsilon: Some(0.01),
+            ..SpotPricePlannerConfig::default()
+        });
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+        let request = PlanningRequest {
+            spot_prices: hourly_spot_prices(from, &[0.200, 0.200, 0.205, 0.300]),
+            load_profile: LoadProfile {
+                sections: vec![LoadProfileSection {
+                    duration_seconds: 3600,
+                    power_draw_watt: 1000.0,
+                    end_power_draw_watt: None,
+                    id: None,
+                }],
+            },
+            ..PlanningRequest::default()
+        };
+
+        // act
+        let latest = spot_price_planner
+            .get_best_spot_prices(&request)?
+            .into_planned()
+            .ok_or("not plannable")?;
+        let earliest = spot_price_planner
+            .get_best_spot_prices(&PlanningRequest {
+                tie_breaker: Some(TieBreaker::EarliestStart),
+                ..request
+            })?
+            .into_planned()
+            .ok_or("not plannable")?;
+
+        assert_eq!(latest.planned_from, Some(from + Duration::hours(2)));
+        assert_eq!(earliest.planned_from, Some(from));
+
+        Ok(())
+    }
+
+    #[test]
+    fn get_best_spot_prices_applies_tie_breaker_at_planning_granularity(
+    ) -> Result<(), Box<dyn Error>> {
+        let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig {
+            planning_granularity_seconds: Some(900),
+            tie_breaker: TieBreaker::LatestStart,
+            ..SpotPricePlannerConfig::default()
+        });
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+
+        // act
+        let response = spot_price_planner
+            .get_best_spot_prices(&PlanningRequest {
+                spot_prices: hourly_spot_prices(from, &[0.20; 3]),
+                load_profile: LoadProfile {
+                    sections: vec![LoadProfileSection {
+                        duration_seconds: 5400,
+                        power_draw_watt: 1000.0,
+                        end_power_draw_watt: None,
+                        id: None,
+                    }],
+                },
+                ..PlanningRequest::default()
+            })?
+            .into_planned()
+            .ok_or("not plannable")?;
+
+        assert_eq!(
+            response.planned_from,
+            Some(from + Duration::hours(1) + Duration::minutes(30))
+        );
+        assert_eq!(response.planned_till, Some(from + Duration::hours(3)));
+
+        Ok(())
+    }
+
+    fn two_step_load_profile() -> LoadProfile {
+        LoadProfile {
+            sections: vec![
+                LoadProfileSection {
+                    duration_seconds: 3600,
+                    power_draw_watt: 2000.0,
+                    end_power_draw_watt: None,
+                    id: None,
+                },
+                LoadProfileSection {
+                    duration_seconds: 3600,
+                    power_draw_watt: 8000.0,
+                    end_power_draw_watt: None,
+                    id: None,
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn get_best_spot_prices_rejects_cheapest_block_exceeding_available_power(
+    ) -> Result<(), Box<dyn Error>> {
+        let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig::default());
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+        let request = PlanningRequest {
+            spot_prices: hourly_spot_prices(from, &[0.30, 0.10, 0.10, 0.20, 0.20, 0.40]),
+            load_profile: two_step_load_profile(),
+            ..PlanningRequest::default()
+        };
+
+        // act
+        let unconstrained = spot_price_planner
+            .get_best_spot_prices(&request)?
+            .into_planned()
+            .ok_or("not plannable")?;
+        let constrained = spot_price_planner
+            .get_best_spot_prices(&PlanningRequest {
+                // too little headroom for the 8 kW section, enough for the 2 kW one
+                available_power_watt: HashMap::from([(from + Duration::hours(2), 5000.0)]),
+                ..request
+            })?
+            .into_planned()
+            .ok_or("not plannable")?;
+
+        assert_eq!(unconstrained.planned_from, Some(from + Duration::hours(1)));
+        assert_eq!(constrained.planned_from, Some(from));
+        assert!((constrained.total_price(None) - (2.0 * 0.30 + 8.0 * 0.10)).abs() < 1e-9);
+
+        Ok(())
+    }
+
+    #[test]
+    fn get_best_spot_prices_allows_low_power_section_in_constrained_slot(
+    ) -> Result<(), Box<dyn Error>> {
+        let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig::default());
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+
+        // act
+        let response = spot_price_planner
+            .get_best_spot_prices(&PlanningRequest {
+                spot_prices: hourly_spot_prices(from, &[0.30, 0.10, 0.10, 0.20, 0.20, 0.40]),
+                load_profile: two_step_load_profile(),
+                available_power_watt: HashMap::from([(from + Duration::hours(1), 5000.0)]),
+                ..PlanningRequest::default()
+            })?
+            .into_planned()
+            .ok_or("not plannable")?;
+
+        assert_eq!(response.planned_from, Some(from + Duration::hours(1)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn get_best_spot_prices_applies_available_power_to_clipped_spot_price(
+    ) -> Result<(), Box<dyn Error>> {
+        let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig {
+            clip_to_bounds: true,
+            ..SpotPricePlannerConfig::default()
+        });
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 10, 0, 0).unwrap();
+
+        // act
+        let response = spot_price_planner
+            .get_best_spot_prices(&PlanningRequest {
+                spot_prices: hourly_spot_prices(from, &[0.01, 0.30, 0.30]),
+                load_profile: LoadProfile {
+                    sections: vec![LoadProfileSection {
+                        duration_seconds: 1800,
+                        power_draw_watt: 1000.0,
+                        end_power_draw_watt: None,
+                        id: None,
+                    }],
+                },
+                after: Some(from + Duration::minutes(30)),
+                available_power_watt: HashMap::from([(from, 0.0)]),
+                ..PlanningRequest::default()
+            })?
+            .into_planned()
+            .ok_or("not plannable")?;
+
+        assert_eq!(response.planned_from, Some(from + Duration::hours(1)));
+
+        Ok(())
+    }
+
+    fn previous_plan(
+        spot_price_planner: &SpotPricePlanner,
+        from: DateTime<Utc>,
+    ) -> Result<PlanningResponse, Box<dyn Error>> {
+        Ok(spot_price_planner
+            .get_best_spot_prices(&PlanningRequest {
+                spot_prices: hourly_spot_prices(from, &[0.30, 0.10, 0.10, 0.30, 0.20, 0.20]),
+                load_profile: LoadProfile {
+                    sections: vec![LoadProfileSection {
+                        duration_seconds: 7200,
+                        power_draw_watt: 1000.0,
+                        end_power_draw_watt: None,
+                        id: None,
+                    }],
+                },
+                ..PlanningRequest::default()
+            })?
+            .into_planned()
+            .ok_or("not plannable")?)
+    }
+
+    fn updated_request(from: DateTime<Utc>) -> PlanningRequest {
+        // the second valley became slightly cheaper than the first, 0.19 vs 0.20 in total
+        PlanningRequest {
+            spot_prices: hourly_spot_prices(from, &[0.30, 0.10, 0.10, 0.30, 0.095, 0.095]),
+            load_profile: LoadProfile {
+                sections: vec![LoadProfileSection {
+                    duration_seconds: 7200,
+                    power_draw_watt: 1000.0,
+                    end_power_draw_watt: None,
+                    id: None,
+                }],
+            },
+            ..PlanningRequest::default()
+        }
+    }
+
+    #[test]
+    fn get_best_spot_prices_with_previous_keeps_previous_plan_below_switch_threshold(
+    ) -> Result<(), Box<dyn Error>> {
+        let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig::default());
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+        let previous = previous_plan(&spot_price_planner, from)?;
+
+        // act
+        let decision = spot_price_planner.get_best_spot_prices_with_previous(
+            &updated_request(from),
+            Some(&previous),
+            0.05,
+        )?;
+
+        assert!(decision.kept_previous);
+        assert_eq!(
+            decision
+                .outcome
+                .planned()
+                .ok_or("not plannable")?
+                .planned_from,
+            Some(from + Duration::hours(1))
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn get_best_spot_prices_with_previous_switches_above_switch_threshold(
+    ) -> Result<(), Box<dyn Error>> {
+        let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig::default());
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+        let previous = previous_plan(&spot_price_planner, from)?;
+
+        // act
+        let decision = spot_price_planner.get_best_spot_prices_with_previous(
+            &updated_request(from),
+            Some(&previous),
+            0.005,
+        )?;
+
+        assert!(!decision.kept_previous);
+        assert_eq!(
+            decision
+                .outcome
+                .planned()
+                .ok_or("not plannable")?
+                .planned_from,
+            Some(from + Duration::hours(4))
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn get_best_spot_prices_with_previous_switches_when_previous_plan_expired(
+    ) -> Result<(), Box<dyn Error>> {
+        let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig::default());
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+        let previous = previous_plan(&spot_price_planner, from)?;
+
+        // act
+        let decision = spot_price_planner.get_best_spot_prices_with_previous(
+            &PlanningRequest {
+                after: Some(from + Duration::hours(2)),
+                ..updated_request(from)
+            },
+            Some(&previous),
+            1.0,
+        )?;
+
+        assert!(!decision.kept_previous);
+        assert_eq!(
+            decision
+                .outcome
+                .planned()
+                .ok_or("not plannable")?
+                .planned_from,
+            Some(from + Duration::hours(4))
+        );
+
+        Ok(())
+    }
+
+    fn valley_requests(from: DateTime<Utc>) -> Vec<NamedPlanningRequest> {
+        let request = PlanningRequest {
+            spot_prices: hourly_spot_prices(from, &[0.30, 0.10, 0.10, 0.30, 0.20, 0.20, 0.40]),
+            load_profile: LoadProfile {
+                sections: vec![LoadProfileSection {
+                    duration_seconds: 7200,
+                    power_draw_watt: 2000.0,
+                    end_power_draw_watt: None,
+                    id: None,
+                }],
+            },
+            ..PlanningRequest::default()
+        };
+
+        vec![
+            NamedPlanningRequest {
+                name: "dishwasher".to_string(),
+                priority: 1,
+                request: request.clone(),
+            },
+            NamedPlanningRequest {
+                name: "boiler".to_string(),
+                priority: 2,
+                request,
+            },
+        ]
+    }
+
+    #[test]
+    fn plan_multiple_moves_lower_priority_load_out_of_booked_valley() -> Result<(), Box<dyn Error>>
+    {
+        let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig::default());
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+
+        // act
+        let outcomes = spot_price_planner.plan_multiple(valley_requests(from), None)?;
+
+        assert_eq!(outcomes[0].name, "boiler");
+        assert!(!outcomes[0].displaced);
+        assert_eq!(
+            outcomes[0]
+                .outcome
+                .planned()
+                .ok_or("not plannable")?
+                .planned_from,
+            Some(from + Duration::hours(1))
+        );
+        assert_eq!(outcomes[1].name, "dishwasher");
+        assert!(outcomes[1].displaced);
+        assert_eq!(
+            outcomes[1]
+                .outcome
+                .planned()
+                .ok_or("not plannable")?
+                .planned_from,
+            Some(from + Duration::hours(4))
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn plan_multiple_shares_valley_while_within_power_cap() -> Result<(), Box<dyn Error>> {
+        let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig::default());
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+
+        // act
+        let shared = spot_price_planner.plan_multiple(valley_requests(from), Some(5000.0))?;
+        let capped = spot_price_planner.plan_multiple(valley_requests(from), Some(3000.0))?;
+
+        assert!(!shared[1].displaced);
+        assert_eq!(
+            shared[1]
+                .outcome
+                .planned()
+                .ok_or("not plannable")?
+                .planned_from,
+            Some(from + Duration::hours(1))
+        );
+        assert!(capped[1].displaced);
+        assert_eq!(
+            capped[1]
+                .outcome
+                .planned()
+                .ok_or("not plannable")?
+                .planned_from,
+            Some(from + Duration::hours(4))
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn plan_multiple_keeps_clipped_spot_prices_within_power_cap() -> Result<(), Box<dyn Error>> {
+        let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig {
+            clip_to_bounds: true,
+            ..SpotPricePlannerConfig::default()
+        });
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+        let mut requests = valley_requests(from);
+        for named_request in &mut requests {
+            named_request.request.after = Some(from + Duration::hours(1) + Duration::minutes(30));
+        }
+        requests[0].request.load_profile.sections[0].duration_seconds = 1800;
+
+        // act
+        let outcomes = spot_price_planner.plan_multiple(requests, Some(3000.0))?;
+
+        assert_eq!(
+            outcomes[0]
+                .outcome
+                .planned()
+                .ok_or("not plannable")?
+                .planned_from,
+            Some(from + Duration::hours(1) + Duration::minutes(30))
+        );
+        assert!(outcomes[1].displaced);
+        assert_eq!(
+            outcomes[1]
+                .outcome
+                .planned()
+                .ok_or("not plannable")?
+                .planned_from,
+            Some(from + Duration::hours(4))
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn get_best_spot_prices_plans_shuffled_spot_prices() -> Result<(), Box<dyn Error>> {
+        let load_profile = LoadProfile {
+            sections: vec![LoadProfileSection {
+                duration_seconds: 7200,
+                power_draw_watt: 1000.0,
+                end_power_draw_watt: None,
+                id: None,
+            }],
+        };
+        let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig::default());
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+        let mut spot_prices = hourly_spot_prices(from, &[0.30, 0.10, 0.40, 0.10, 0.20]);
+        spot_prices.reverse();
+
+        // act
+        let response = spot_price_planner
+            .get_best_spot_prices(&PlanningRequest {
+                spot_prices,
+                load_profile,
+                ..PlanningRequest::default()
+            })?
+            .into_planned()
+            .ok_or("not plannable")?;
+
+        assert_eq!(response.planned_from, Some(from + Duration::hours(3)));
+        assert_eq!(response.planned_till, Some(from + Duration::hours(5)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn get_best_spot_prices_returns_error_for_overlapping_spot_prices() {
+        let load_profile = LoadProfile {
+            sections: vec![LoadProfileSection {
+                duration_seconds: 3600,
+                power_draw_watt: 1000.0,
+                end_power_draw_watt: None,
+                id: None,
+            }],
+        };
+        let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig::default());
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+        let mut spot_prices = hourly_spot_prices(from, &[0.30, 0.10]);
+        spot_prices[1].from = from + Duration::minutes(30);
+
+        // act
+        let result = spot_price_planner.get_best_spot_prices(&PlanningRequest {
+            spot_prices,
+            load_profile,
+            ..PlanningRequest::default()
+        });
+
+        assert!(result
+            .unwrap_err()
+            .downcast_ref::<SpotPriceError>()
+            .is_some());
+    }
+
+    #[test]
+    fn get_best_spot_prices_rejects_block_that_cannot_finish_by_deadline(
+    ) -> Result<(), Box<dyn Error>> {
+        let load_profile = LoadProfile {
+            sections: vec![LoadProfileSection {
+                duration_seconds: 9000,
+                power_draw_watt: 1000.0,
+                end_power_draw_watt: None,
+                id: None,
+            }],
+        };
+        let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig::default());
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 2, 0, 0).unwrap();
+        let request = PlanningRequest {
+            spot_prices: hourly_spot_prices(from, &[0.30, 0.30, 0.05, 0.05, 0.05, 0.40]),
+            load_profile,
+            start_after: Some(from + Duration::hours(2) + Duration::minutes(45)),
+            finish_by: Some(from + Duration::hours(5)),
+            ..PlanningRequest::default()
+        };
+
+        // act
+        let outcome = spot_price_planner.get_best_spot_prices(&request)?;
+        let with_later_deadline = spot_price_planner
+            .get_best_spot_prices(&PlanningRequest {
+                finish_by: Some(from + Duration::hours(5) + Duration::minutes(30)),
+                ..request
+            })?
+            .into_planned()
+            .ok_or("not plannable")?;
+
+        // the spot prices all end before 07:00, but a load starting at 04:45 runs until 07:15
+        assert!(matches!(
+            outcome,
+            PlanningOutcome::NotPlannable {
+                reason: NotPlannableReason::NotEnoughConsecutiveDuration,
+                ..
+            }
+        ));
+        assert_eq!(
+            with_later_deadline.planned_from,
+            Some(from + Duration::hours(2) + Duration::minutes(45))
+        );
+        assert_eq!(
+            with_later_deadline.planned_till,
+            Some(from + Duration::hours(5) + Duration::minutes(15))
+        );
+        assert_eq!(with_later_deadline.spot_prices.len(), 4);
+
+        Ok(())
+    }
+
+    #[test]
+    fn get_best_spot_prices_non_consecutive_only_uses_spot_prices_within_start_after_and_finish_by(
+    ) -> Result<(), Box<dyn Error>> {
+        let load_profile = LoadProfile {
+            sections: vec![LoadProfileSection {
+                duration_seconds: 7200,
+                power_draw_watt: 1000.0,
+                end_power_draw_watt: None,
+                id: None,
+            }],
+        };
+        let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig::default());
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+
+        // act
+        let response = spot_price_planner
+            .get_best_spot_prices(&PlanningRequest {
+                spot_prices: hourly_spot_prices(from, &[0.05, 0.30, 0.20, 0.40, 0.10, 0.01]),
+                load_profile,
+                planning_strategy: PlanningStrategy::LowestPriceNonConsecutive,
+                start_after: Some(from + Duration::minutes(30)),
+                finish_by: Some(from + Duration::hours(5) + Duration::minutes(30)),
+                ..PlanningRequest::default()
+            })?
+            .into_planned()
+            .ok_or("not plannable")?;
+
+        assert_eq!(response.spot_prices.len(), 2);
+        assert_eq!(response.spot_prices[0].from, from + Duration::hours(2));
+        assert_eq!(response.spot_prices[1].from, from + Duration::hours(4));
+
+        Ok(())
+    }
+
+    fn mixed_granularity_spot_prices() -> Result<Vec<SpotPrice>, Box<dyn Error>> {
+        let spot_price_response: SpotPriceResponse = serde_json::from_str(
+            &std::fs::read_to_string("spot_price_predictions_mixed.json")?,
+        )?;
+
+        Ok(spot_price_response.data.market_prices_electricity)
+    }
+
+    #[test]
+    fn get_best_spot_prices_plans_block_on_quarter_hour_boundaries() -> Result<(), Box<dyn Error>> {
+        let load_profile = LoadProfile {
+            sections: vec![LoadProfileSection {
+                duration_seconds: 4500,
+                power_draw_watt: 2000.0,
+                end_power_draw_watt: None,
+                id: None,
+            }],
+        };
+        let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig::default());
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+
+        // act
+        let response = spot_price_planner
+            .get_best_spot_prices(&PlanningRequest {
+                spot_prices: mixed_granularity_spot_prices()?,
+                load_profile,
+                before: Some(from + Duration::hours(5)),
+                ..PlanningRequest::default()
+            })?
+            .into_planned()
+            .ok_or("not plannable")?;
+
+        assert_eq!(
+            response.planned_from,
+            Some(from + Duration::hours(2) + Duration::minutes(15))
+        );
+        assert_eq!(
+            response.planned_till,
+            Some(from + Duration::hours(3) + Duration::minutes(30))
+        );
+        assert_eq!(response.spot_prices.len(), 5);
+
+        Ok(())
+    }
+
+    #[test]
+    fn get_best_spot_prices_plans_block_spanning_quarter_hour_and_hourly_prices(
+    ) -> Result<(), Box<dyn Error>> {
+        let load_profile = LoadProfile {
+            sections: vec![LoadProfileSection {
+                duration_seconds: 4500,
+                power_draw_watt: 2000.0,
+                end_power_draw_watt: None,
+                id: None,
+            }],
+        };
+        let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig::default());
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+
+        // act
+        let response = spot_price_planner
+            .get_best_spot_prices(&PlanningRequest {
+                spot_prices: mixed_granularity_spot_prices()?,
+                load_profile,
+                ..PlanningRequest::default()
+            })?
+            .into_planned()
+            .ok_or("not plannable")?;
+
+        assert_eq!(
+            response.planned_from,
+            Some(from + Duration::hours(5) + Duration::minutes(45))
+        );
+        assert_eq!(response.planned_till, Some(from + Duration::hours(7)));
+        assert_eq!(response.spot_prices.len(), 2);
+        assert_eq!(response.last_slot_used_seconds, Some(3600));
+
+        Ok(())
+    }
+
+    #[test]
+    fn get_plannable_spot_prices_evaluates_quarter_hours_against_time_slot_boundaries(
+    ) -> Result<(), Box<dyn Error>> {
+        let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig {
+            plannable_local_time_slots: HashMap::from([(
+                Weekday::Sat,
+                vec![TimeSlot {
+                    from: NaiveTime::from_hms_opt(2, 15, 0).unwrap(),
+                    till: NaiveTime::from_hms_opt(3, 0, 0).unwrap(),
+                    wraps_midnight: false,
+                }],
+            )]),
+            ..SpotPricePlannerConfig::default()
+        });
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+
+        // act
+        let plannable_spot_prices = spot_price_planner.get_plannable_spot_prices(
+            &mixed_granularity_spot_prices()?,
+            &None,
+            &None,
+        )?;
+
+        assert_eq!(
+            plannable_spot_prices
+                .iter()
+                .map(|sp| sp.from)
+                .collect::<Vec<DateTime<Utc>>>(),
+            vec![
+                from + Duration::hours(2) + Duration::minutes(15),
+                from + Duration::hours(2) + Duration::minutes(30),
+                from + Duration::hours(2) + Duration::minutes(45),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn get_best_spot_prices_ranked_starts_with_best_spot_prices() -> Result<(), Box<dyn Error>> {
+        let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig::default());
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+        let request = PlanningRequest {
+            spot_prices: mixed_granularity_spot_prices()?,
+            load_profile: LoadProfile {
+                sections: vec![LoadProfileSection {
+                    duration_seconds: 4500,
+                    power_draw_watt: 2000.0,
+                    end_power_draw_watt: None,
+                    id: None,
+                }],
+            },
+            before: Some(from + Duration::hours(5)),
+            ..PlanningRequest::default()
+        };
+
+        // act
+        let ranked = spot_price_planner.get_best_spot_prices_ranked(&request, 3)?;
+        let best = spot_price_planner
+            .get_best_spot_prices(&request)?
+            .into_planned()
+            .ok_or("not plannable")?;
+
+        assert_eq!(ranked.len(), 3);
+        assert_eq!(ranked[0].planned_from, best.planned_from);
+        assert_eq!(ranked[0].planned_till, best.planned_till);
+        assert_eq!(ranked[0].spot_prices, best.spot_prices);
+        assert!(ranked[0].total_price(None) <= ranked[1].total_price(None));
+        assert!(ranked[1].total_price(None) <= ranked[2].total_price(None));
+        assert_ne!(ranked[0].planned_from, ranked[1].planned_from);
+        assert_ne!(ranked[1].planned_from, ranked[2].planned_from);
+
+        Ok(())
+    }
+
+    #[test]
+    fn get_best_spot_prices_ranked_orders_candidates_by_strategy() -> Result<(), Box<dyn Error>> {
+        let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig::default());
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+
+        // act
+        let ranked = spot_price_planner.get_best_spot_prices_ranked(
+            &PlanningRequest {
+                spot_prices: hourly_spot_prices(from, &[0.30, 0.10, 0.20, 0.40, 0.05, 0.15]),
+                load_profile: LoadProfile {
+                    sections: vec![LoadProfileSection {
+                        duration_seconds: 3600,
+                        power_draw_watt: 1000.0,
+                        end_power_draw_watt: None,
+                        id: None,
+                    }],
+                },
+                planning_strategy: PlanningStrategy::HighestPrice,
+                ..PlanningRequest::default()
+            },
+            3,
+        )?;
+
+        assert_eq!(
+            ranked
+                .iter()
+                .map(|response| response.planned_from)
+                .collect::<Vec<_>>(),
+            vec![
+                Some(from + Duration::hours(3)),
+                Some(from),
+                Some(from + Duration::hours(2))
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn get_best_spot_prices_does_not_plan_block_across_non_plannable_gap(
+    ) -> Result<(), Box<dyn Error>> {
+        let load_profile = LoadProfile {
+            sections: vec![LoadProfileSection {
+                duration_seconds: 7200,
+                power_draw_watt: 2000.0,
+                end_power_draw_watt: None,
+                id: None,
+            }],
+        };
+        let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig {
+            plannable_local_time_slots: HashMap::from([(
+                Weekday::Sat,
+                vec![
+                    TimeSlot {
+                        from: NaiveTime::from_hms_opt(0, 0, 0).unwrap(),
+                        till: NaiveTime::from_hms_opt(1, 0, 0).unwrap(),
+                        wraps_midnight: false,
+                    },
+                    TimeSlot {
+                        from: NaiveTime::from_hms_opt(2, 0, 0).unwrap(),
+                        till: NaiveTime::from_hms_opt(4, 0, 0).unwrap(),
+                        wraps_midnight: false,
+                    },
+                ],
+            )]),
+            ..SpotPricePlannerConfig::default()
+        });
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+
+        // act
+        let response = spot_price_planner
+            .get_best_spot_prices(&PlanningRequest {
+                spot_prices: hourly_spot_prices(from, &[0.05, 0.50, 0.10, 0.30]),
+                load_profile,
+                ..PlanningRequest::default()
+            })?
+            .into_planned()
+            .ok_or("not plannable")?;
+
+        assert_eq!(response.planned_from, Some(from + Duration::hours(2)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn get_best_spot_prices_respects_min_block_duration_for_non_consecutive_strategy(
+    ) -> Result<(), Box<dyn Error>> {
+        let load_profile = LoadProfile {
+            sections: vec![LoadProfileSection {
+                duration_seconds: 4 * 3600,
+                power_draw_watt: 1000.0,
+                end_power_draw_watt: None,
+                id: None,
+            }],
+        };
+        let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig::default());
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+        let request = PlanningRequest {
+            spot_prices: hourly_spot_prices(
+                from,
+                &[0.05, 0.40, 0.06, 0.30, 0.10, 0.12, 0.50, 0.07, 0.08],
+            ),
+            load_profile,
+            planning_strategy: PlanningStrategy::LowestPriceNonConsecutive,
+            ..PlanningRequest::default()
+        };
+
+        // act
+        let unrestricted = spot_price_planner
+            .get_best_spot_prices(&request)?
+            .into_planned()
+            .ok_or("not plannable")?;
+        let restricted = spot_price_planner
+            .get_best_spot_prices(&PlanningRequest {
+                min_block_duration_seconds: Some(7200),
+                ..request
+            })?
+            .into_planned()
+            .ok_or("not plannable")?;
+
+        assert_eq!(
+            unrestricted.segments,
+            vec![
+                (from, from + Duration::hours(1)),
+                (from + Duration::hours(2), from + Duration::hours(3)),
+                (from + Duration::hours(7), from + Duration::hours(9)),
+            ]
+        );
+        assert_eq!(
+            restricted.segments,
+            vec![
+                (from + Duration::hours(4), from + Duration::hours(6)),
+                (from + Duration::hours(7), from + Duration::hours(9)),
+            ]
+        );
+        assert!((restricted.total_price(None) - 0.37).abs() < 1e-9);
+
+        Ok(())
+    }
+
+    #[test]
+    fn get_best_spot_prices_with_min_block_duration_handles_two_days_of_prices(
+    ) -> Result<(), Box<dyn Error>> {
+        let load_profile = LoadProfile {
+            sections: vec![LoadProfileSection {
+                duration_seconds: 12 * 3600,
+                power_draw_watt: 1000.0,
+                end_power_draw_watt: None,
+                id: None,
+            }],
+        };
+        let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig::default());
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+        let market_prices: Vec<f64> = (0..48).map(|i| ((i * 37) % 23) as f64 / 100.0).collect();
+
+        // act
+        let response = spot_price_planner
+            .get_best_spot_prices(&PlanningRequest {
+                spot_prices: hourly_spot_prices(from, &market_prices),
+                load_profile,
+                planning_strategy: PlanningStrategy::LowestPriceNonConsecutive,
+                min_block_duration_seconds: Some(3 * 3600),
+                ..PlanningRequest::default()
+            })?
+            .into_planned()
+            .ok_or("not plannable")?;
+
+        assert_eq!(response.spot_prices.len(), 12);
+        assert!(response
+            .segments
+            .iter()
+            .all(|(from, till)| *till - *from >= Duration::hours(3)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn cost_breakdown_allocates_load_sections_to_straddled_slots() -> Result<(), Box<dyn Error>> {
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+        let response = PlanningResponse {
+            spot_prices: hourly_spot_prices(from, &[0.10, 0.20, 0.30]),
+            load_profile: LoadProfile {
+                sections: vec![
                     LoadProfileSection {
-                        duration_seconds: 3600,
+                        duration_seconds: 5400,
                         power_draw_watt: 2000.0,
+                        end_power_draw_watt: None,
+                        id: None,
                     },
                     LoadProfileSection {
                         duration_seconds: 1800,
-                        power_draw_watt: 8000.0,
+                        power_draw_watt: 1000.0,
+                        end_power_draw_watt: None,
+                        id: None,
                     },
                 ],
             },
-            None,
+            planned_from: Some(from + Duration::minutes(30)),
+            planned_till: Some(from + Duration::hours(2) + Duration::minutes(30)),
+            segments: vec![],
+            last_slot_used_seconds: Some(1800),
+            fallback_used: false,
+            percentile_fallback_used: false,
+            forced: false,
+            spike_fallback_used: false,
+            self_consumed_kwh: None,
+            grid_kwh: None,
+            price_components: PriceComponents::default(),
+            explanation: vec![],
+        };
+
+        // act
+        let cost_breakdown = response.cost_breakdown(None);
+
+        assert_eq!(cost_breakdown.len(), 3);
+        assert_eq!(cost_breakdown[0].allocated_seconds, 1800);
+        assert!((cost_breakdown[0].energy_kwh - 1.0).abs() < 1e-9);
+        assert!((cost_breakdown[0].cost - 0.1).abs() < 1e-9);
+        assert_eq!(cost_breakdown[1].allocated_seconds, 3600);
+        assert!((cost_breakdown[1].energy_kwh - 2.0).abs() < 1e-9);
+        assert!((cost_breakdown[1].cost - 0.4).abs() < 1e-9);
+        assert_eq!(cost_breakdown[2].allocated_seconds, 1800);
+        assert!((cost_breakdown[2].energy_kwh - 0.5).abs() < 1e-9);
+        assert!((cost_breakdown[2].cost - 0.15).abs() < 1e-9);
+        assert!(
+            (cost_breakdown.iter().map(|c| c.cost).sum::<f64>() - response.total_price(None)).abs()
+                < 1e-9
+        );
+        assert!(serde_json::to_string(&cost_breakdown[0])?.contains("\"allocatedSeconds\":1800"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn get_charge_discharge_windows_resolves_overlapping_cheapest_and_most_expensive_blocks(
+    ) -> Result<(), Box<dyn Error>> {
+        let load_profile = LoadProfile {
+            sections: vec![LoadProfileSection {
+                duration_seconds: 7200,
+                power_draw_watt: 1000.0,
+                end_power_draw_watt: None,
+                id: None,
+            }],
+        };
+        let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig::default());
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+
+        // cheapest block is 02:00-04:00, most expensive 03:00-05:00
+        let request = ChargeDischargeRequest {
+            spot_prices: hourly_spot_prices(from, &[0.10, 0.20, 0.02, 0.05, 0.90, 0.04, 0.30]),
+            charge_load_profile: load_profile.clone(),
+            discharge_load_profile: load_profile,
+            min_price_spread_per_kwh: 0.10,
+            ..ChargeDischargeRequest::default()
+        };
+
+        // act
+        let response = spot_price_planner.get_charge_discharge_windows(&request)?;
+
+        match response {
+            ChargeDischargeResponse::Windows {
+                charge,
+                discharge,
+                price_spread_per_kwh,
+            } => {
+                assert_eq!(charge.planned_from, Some(from + Duration::hours(2)));
+                assert_eq!(discharge.planned_from, Some(from + Duration::hours(4)));
+                assert!((price_spread_per_kwh - 0.435).abs() < 1e-9);
+            }
+            _ => panic!("expected windows"),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn get_charge_discharge_windows_returns_below_spread_threshold_below_min_price_spread(
+    ) -> Result<(), Box<dyn Error>> {
+        let load_profile = LoadProfile {
+            sections: vec![LoadProfileSection {
+                duration_seconds: 7200,
+                power_draw_watt: 1000.0,
+                end_power_draw_watt: None,
+                id: None,
+            }],
+        };
+        let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig::default());
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+
+        // act
+        let response =
+            spot_price_planner.get_charge_discharge_windows(&ChargeDischargeRequest {
+                spot_prices: hourly_spot_prices(from, &[0.10, 0.20, 0.02, 0.05, 0.90, 0.04, 0.30]),
+                charge_load_profile: load_profile.clone(),
+                discharge_load_profile: load_profile,
+                min_price_spread_per_kwh: 1.0,
+                ..ChargeDischargeRequest::default()
+            })?;
+
+        match response {
+            ChargeDischargeResponse::BelowSpreadThreshold {
+                best_price_spread_per_kwh,
+            } => assert!((best_price_spread_per_kwh - 0.435).abs() < 1e-9),
+            _ => panic!("expected below spread threshold"),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn get_best_spot_prices_ranks_blocks_on_configured_price_components(
+    ) -> Result<(), Box<dyn Error>> {
+        let load_profile = LoadProfile {
+            sections: vec![LoadProfileSection {
+                duration_seconds: 3600,
+                power_draw_watt: 1000.0,
+                end_power_draw_watt: None,
+                id: None,
+            }],
+        };
+        let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig::default());
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+        let mut spot_prices = hourly_spot_prices(from, &[0.10, 0.20]);
+        spot_prices[0].market_price_tax = 0.30;
+        let request = PlanningRequest {
+            spot_prices,
+            load_profile,
+            ..PlanningRequest::default()
+        };
+
+        // act
+        let with_taxes = spot_price_planner
+            .get_best_spot_prices(&request)?
+            .into_planned()
+            .ok_or("not plannable")?;
+        let without_taxes = spot_price_planner
+            .get_best_spot_prices(&PlanningRequest {
+                price_components: Some(PriceComponents {
+                    market_price_tax: false,
+                    ..PriceComponents::default()
+                }),
+                ..request
+            })?
+            .into_planned()
+            .ok_or("not plannable")?;
+
+        assert_eq!(with_taxes.planned_from, Some(from + Duration::hours(1)));
+        assert_eq!(without_taxes.planned_from, Some(from));
+        assert!((without_taxes.total_price(None) - 0.10).abs() < 1e-9);
+        assert!((without_taxes.total_price(Some(|sp| sp.total_price())) - 0.40).abs() < 1e-9);
+
+        Ok(())
+    }
+
+    #[test]
+    fn price_components_deserialize_with_missing_fields_defaulting_to_true(
+    ) -> Result<(), Box<dyn Error>> {
+        // act
+        let price_components: PriceComponents = serde_yaml::from_str("energyTaxPrice: false\n")?;
+
+        assert_eq!(
+            price_components,
+            PriceComponents {
+                energy_tax_price: false,
+                ..PriceComponents::default()
+            }
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn get_best_spot_prices_does_not_plan_when_negative_window_is_shorter_than_load_for_only_negative_prices_strategy(
+    ) -> Result<(), Box<dyn Error>> {
+        let load_profile = LoadProfile {
+            sections: vec![LoadProfileSection {
+                duration_seconds: 3 * 3600,
+                power_draw_watt: 3000.0,
+                end_power_draw_watt: None,
+                id: None,
+            }],
+        };
+        let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig::default());
+        let from = Utc.with_ymd_and_hms(2022, 6, 12, 10, 0, 0).unwrap();
+
+        // act
+        let outcome = spot_price_planner.get_best_spot_prices(&PlanningRequest {
+            spot_prices: hourly_spot_prices(from, &[0.05, -0.02, -0.04, 0.01, 0.10]),
+            load_profile,
+            planning_strategy: PlanningStrategy::OnlyNegativePrices,
+            ..PlanningRequest::default()
+        })?;
+
+        assert!(outcome.planned().is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn get_best_spot_prices_plans_inside_negative_window_for_only_negative_prices_strategy(
+    ) -> Result<(), Box<dyn Error>> {
+        let load_profile = LoadProfile {
+            sections: vec![LoadProfileSection {
+                duration_seconds: 2 * 3600,
+                power_draw_watt: 3000.0,
+                end_power_draw_watt: None,
+                id: None,
+            }],
+        };
+        let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig::default());
+        let from = Utc.with_ymd_and_hms(2022, 6, 12, 10, 0, 0).unwrap();
+
+        // act
+        let response = spot_price_planner
+            .get_best_spot_prices(&PlanningRequest {
+                spot_prices: hourly_spot_prices(from, &[0.05, -0.02, -0.04, -0.01, 0.10]),
+                load_profile,
+                planning_strategy: PlanningStrategy::OnlyNegativePrices,
+                ..PlanningRequest::default()
+            })?
+            .into_planned()
+            .ok_or("not plannable")?;
+
+        assert_eq!(response.planned_from, Some(from + Duration::hours(1)));
+        assert!(response
+            .spot_prices
+            .iter()
+            .all(|sp| sp.is_negative_market_price()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn get_best_spot_prices_prefers_largest_revenue_for_negative_totals_with_lowest_price_strategy(
+    ) -> Result<(), Box<dyn Error>> {
+        let load_profile = LoadProfile {
+            sections: vec![LoadProfileSection {
+                duration_seconds: 2 * 3600,
+                power_draw_watt: 1000.0,
+                end_power_draw_watt: None,
+                id: None,
+            }],
+        };
+        let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig::default());
+        let from = Utc.with_ymd_and_hms(2022, 6, 12, 10, 0, 0).unwrap();
+
+        // act
+        let response = spot_price_planner
+            .get_best_spot_prices(&PlanningRequest {
+                spot_prices: hourly_spot_prices(from, &[-0.01, -0.02, 0.03, -0.10, -0.05]),
+                load_profile,
+                ..PlanningRequest::default()
+            })?
+            .into_planned()
+            .ok_or("not plannable")?;
+
+        assert_eq!(response.planned_from, Some(from + Duration::hours(3)));
+        assert!((response.total_price(None) + 0.15).abs() < 1e-9);
+
+        Ok(())
+    }
+
+    fn plannable_hours(
+        time_slots: Vec<(Weekday, TimeSlot)>,
+        spot_prices: &[SpotPrice],
+    ) -> Result<Vec<DateTime<Utc>>, Box<dyn Error>> {
+        let mut plannable_local_time_slots: HashMap<Weekday, Vec<TimeSlot>> = HashMap::new();
+        for (weekday, time_slot) in time_slots {
+            plannable_local_time_slots
+                .entry(weekday)
+                .or_default()
+                .push(time_slot);
+        }
+        let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig {
+            plannable_local_time_slots,
+            ..SpotPricePlannerConfig::default()
+        });
+
+        Ok(spot_price_planner
+            .get_plannable_spot_prices(spot_prices, &None, &None)?
+            .iter()
+            .map(|sp| sp.from)
+            .collect())
+    }
+
+    #[test]
+    fn get_plannable_spot_prices_uses_holiday_time_slots_on_holidays() -> Result<(), Box<dyn Error>>
+    {
+        let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig {
+            plannable_local_time_slots: HashMap::from([
+                (Weekday::Mon, vec![time_slot((0, 0), (7, 0))]),
+                (Weekday::Tue, vec![time_slot((0, 0), (7, 0))]),
+            ]),
+            local_time_zone: "Europe/Amsterdam".to_string(),
+            holidays: vec![NaiveDate::from_ymd_opt(2022, 4, 18).unwrap()],
+            holiday_time_slots: vec![time_slot((10, 0), (16, 0))],
+            ..SpotPricePlannerConfig::default()
+        });
+        // easter monday and the tuesday after, from midnight local time
+        let from = Utc.with_ymd_and_hms(2022, 4, 17, 22, 0, 0).unwrap();
+        let spot_prices = hourly_spot_prices(from, &[0.2; 48]);
+
+        // act
+        let plannable: Vec<DateTime<Utc>> = spot_price_planner
+            .get_plannable_spot_prices(&spot_prices, &None, &None)?
+            .iter()
+            .map(|sp| sp.from)
+            .collect();
+
+        let holiday_hours = (10..16).map(|h| from + Duration::hours(h));
+        let tuesday_hours = (24..31).map(|h| from + Duration::hours(h));
+        assert_eq!(
+            plannable,
+            holiday_hours.chain(tuesday_hours).collect::<Vec<_>>()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn deserialize_holidays_from_dates() -> Result<(), Box<dyn Error>> {
+        // act
+        let config: SpotPricePlannerConfig = serde_yaml::from_str(
+            "holidays:\n  - 2022-04-18\n  - 2022-12-25\nholidayTimeSlots:\n  - from: 10:00:00\n    till: 16:00:00\n",
+        )?;
+
+        assert_eq!(
+            config.holidays,
+            vec![
+                NaiveDate::from_ymd_opt(2022, 4, 18).unwrap(),
+                NaiveDate::from_ymd_opt(2022, 12, 25).unwrap()
+            ]
+        );
+        assert_eq!(config.holiday_time_slots.len(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn get_plannable_spot_prices_clips_spot_prices_to_bounds_and_time_slots(
+    ) -> Result<(), Box<dyn Error>> {
+        let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig {
+            plannable_local_time_slots: HashMap::from([(
+                Weekday::Sat,
+                vec![time_slot((0, 0), (7, 30))],
+            )]),
+            clip_to_bounds: true,
+            ..SpotPricePlannerConfig::default()
+        });
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+
+        // act
+        let plannable = spot_price_planner.get_plannable_spot_prices(
+            &hourly_spot_prices(from, &[0.2; 10]),
+            &Some(from + Duration::hours(5) + Duration::minutes(20)),
+            &None,
+        )?;
+
+        assert_eq!(
+            plannable
+                .iter()
+                .map(|sp| (sp.from, sp.duration_seconds()))
+                .collect::<Vec<_>>(),
+            vec![
+                (from + Duration::hours(5) + Duration::minutes(20), 2400),
+                (from + Duration::hours(6), 3600),
+                (from + Duration::hours(7), 1800),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn get_best_spot_prices_starts_mid_hour_when_clipping_to_bounds() -> Result<(), Box<dyn Error>>
+    {
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 13, 0, 0).unwrap();
+        let now = from + Duration::minutes(20);
+        let request = PlanningRequest {
+            spot_prices: hourly_spot_prices(from, &[0.10, 0.10, 0.30, 0.30]),
+            load_profile: LoadProfile {
+                sections: vec![LoadProfileSection {
+                    duration_seconds: 3600,
+                    power_draw_watt: 1000.0,
+                    end_power_draw_watt: None,
+                    id: None,
+                }],
+            },
+            after: Some(now),
+            ..PlanningRequest::default()
+        };
+
+        // act
+        let clipped = SpotPricePlanner::new(SpotPricePlannerConfig {
+            clip_to_bounds: true,
+            ..SpotPricePlannerConfig::default()
+        })
+        .get_best_spot_prices(&request)?
+        .into_planned()
+        .ok_or("not plannable")?;
+        let unclipped = SpotPricePlanner::new(SpotPricePlannerConfig::default())
+            .get_best_spot_prices(&request)?
+            .into_planned()
+            .ok_or("not plannable")?;
+
+        assert_eq!(clipped.planned_from, Some(now));
+        assert_eq!(clipped.planned_till, Some(now + Duration::hours(1)));
+        assert_eq!(unclipped.planned_from, Some(from + Duration::hours(1)));
+
+        Ok(())
+    }
+
+    fn time_slot(from: (u32, u32), till: (u32, u32)) -> TimeSlot {
+        TimeSlot {
+            from: NaiveTime::from_hms_opt(from.0, from.1, 0).unwrap(),
+            till: NaiveTime::from_hms_opt(till.0, till.1, 0).unwrap(),
+            wraps_midnight: false,
+        }
+    }
+
+    #[test]
+    fn get_plannable_spot_prices_treats_midnight_to_midnight_as_whole_day(
+    ) -> Result<(), Box<dyn Error>> {
+        // friday 2022-04-15 22:00 until saturday 02:00
+        let from = Utc.with_ymd_and_hms(2022, 4, 15, 22, 0, 0).unwrap();
+
+        // act
+        let plannable = plannable_hours(
+            vec![(Weekday::Fri, time_slot((0, 0), (0, 0)))],
+            &hourly_spot_prices(from, &[0.1, 0.1, 0.1, 0.1]),
+        )?;
+
+        assert_eq!(plannable, vec![from, from + Duration::hours(1)]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn get_plannable_spot_prices_treats_till_midnight_as_end_of_day() -> Result<(), Box<dyn Error>>
+    {
+        let from = Utc.with_ymd_and_hms(2022, 4, 15, 21, 0, 0).unwrap();
+
+        // act
+        let plannable = plannable_hours(
+            vec![
+                (Weekday::Fri, time_slot((22, 0), (0, 0))),
+                (Weekday::Sat, time_slot((0, 0), (0, 0))),
+            ],
+            &hourly_spot_prices(from, &[0.1, 0.1, 0.1, 0.1]),
+        )?;
+
+        assert_eq!(
+            plannable,
+            vec![
+                from + Duration::hours(1),
+                from + Duration::hours(2),
+                from + Duration::hours(3),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn get_plannable_spot_prices_wraps_time_slot_past_midnight() -> Result<(), Box<dyn Error>> {
+        let from = Utc.with_ymd_and_hms(2022, 4, 15, 22, 0, 0).unwrap();
+
+        // act
+        let plannable = plannable_hours(
+            vec![(Weekday::Fri, time_slot((23, 30), (1, 30)))],
+            &hourly_spot_prices(from, &[0.1, 0.1, 0.1, 0.1]),
+        )?;
+
+        // only saturday 00:00-01:00 fits entirely inside friday 23:30 until saturday 01:30
+        assert_eq!(plannable, vec![from + Duration::hours(2)]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn get_plannable_spot_prices_keeps_time_slot_ending_half_past_midnight_on_same_day(
+    ) -> Result<(), Box<dyn Error>> {
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+
+        // act
+        let plannable = plannable_hours(
+            vec![(Weekday::Sat, time_slot((0, 0), (0, 30)))],
+            &mixed_granularity_spot_prices()?,
+        )?;
+
+        assert_eq!(plannable, vec![from, from + Duration::minutes(15)]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn get_best_spot_prices_returns_no_plannable_prices_reason_when_window_is_empty(
+    ) -> Result<(), Box<dyn Error>> {
+        let load_profile = LoadProfile {
+            sections: vec![LoadProfileSection {
+                duration_seconds: 3600,
+                power_draw_watt: 1000.0,
+                end_power_draw_watt: None,
+                id: None,
+            }],
+        };
+        let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig::default());
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+
+        // act
+        let outcome = spot_price_planner.get_best_spot_prices(&PlanningRequest {
+            spot_prices: hourly_spot_prices(from, &[0.10, 0.20]),
+            load_profile,
+            after: Some(from + Duration::hours(2)),
+            ..PlanningRequest::default()
+        })?;
+
+        assert!(matches!(
+            outcome,
+            PlanningOutcome::NotPlannable {
+                reason: NotPlannableReason::NoPlannablePrices,
+                ..
+            }
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn get_best_spot_prices_returns_not_enough_consecutive_duration_reason_when_load_does_not_fit(
+    ) -> Result<(), Box<dyn Error>> {
+        let load_profile = LoadProfile {
+            sections: vec![LoadProfileSection {
+                duration_seconds: 3 * 3600,
+                power_draw_watt: 1000.0,
+                end_power_draw_watt: None,
+                id: None,
+            }],
+        };
+        let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig::default());
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+
+        // act
+        let outcome = spot_price_planner.get_best_spot_prices(&PlanningRequest {
+            spot_prices: hourly_spot_prices(from, &[0.10, 0.20]),
+            load_profile,
+            ..PlanningRequest::default()
+        })?;
+
+        assert!(matches!(
+            outcome,
+            PlanningOutcome::NotPlannable {
+                reason: NotPlannableReason::NotEnoughConsecutiveDuration,
+                ..
+            }
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn get_best_spot_prices_legacy_returns_empty_response_when_not_plannable(
+    ) -> Result<(), Box<dyn Error>> {
+        let load_profile = LoadProfile {
+            sections: vec![LoadProfileSection {
+                duration_seconds: 3 * 3600,
+                power_draw_watt: 1000.0,
+                end_power_draw_watt: None,
+                id: None,
+            }],
+        };
+        let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig::default());
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+
+        // act
+        let response = spot_price_planner.get_best_spot_prices_legacy(&PlanningRequest {
+            spot_prices: hourly_spot_prices(from, &[0.10, 0.20]),
+            load_profile,
+            ..PlanningRequest::default()
+        })?;
+
+        assert!(response.spot_prices.is_empty());
+        assert_eq!(response.planned_from, None);
+
+        Ok(())
+    }
+
+    fn one_hour_load_profile() -> LoadProfile {
+        LoadProfile {
+            sections: vec![LoadProfileSection {
+                duration_seconds: 3600,
+                power_draw_watt: 1000.0,
+                end_power_draw_watt: None,
+                id: None,
+            }],
+        }
+    }
+
+    #[test]
+    fn get_best_spot_prices_plans_when_spot_prices_cover_window() -> Result<(), Box<dyn Error>> {
+        let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig::default());
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+
+        // act
+        let outcome = spot_price_planner.get_best_spot_prices(&PlanningRequest {
+            spot_prices: hourly_spot_prices(from, &[0.30, 0.10, 0.20, 0.40]),
+            load_profile: one_hour_load_profile(),
+            after: Some(from),
+            before: Some(from + Duration::hours(4)),
+            require_full_coverage: true,
+            ..PlanningRequest::default()
+        })?;
+
+        assert_eq!(
+            outcome.planned().and_then(|response| response.planned_from),
+            Some(from + Duration::hours(1))
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn get_best_spot_prices_returns_coverage_incomplete_for_trailing_gap(
+    ) -> Result<(), Box<dyn Error>> {
+        let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig::default());
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+
+        // act
+        let outcome = spot_price_planner.get_best_spot_prices(&PlanningRequest {
+            spot_prices: hourly_spot_prices(from, &[0.30, 0.10, 0.20, 0.40]),
+            load_profile: one_hour_load_profile(),
+            after: Some(from),
+            before: Some(from + Duration::hours(7)),
+            require_full_coverage: true,
+            ..PlanningRequest::default()
+        })?;
+
+        match outcome {
+            PlanningOutcome::CoverageIncomplete {
+                uncovered_intervals,
+            } => assert_eq!(
+                uncovered_intervals,
+                vec![(from + Duration::hours(4), from + Duration::hours(7))]
+            ),
+            _ => panic!("expected CoverageIncomplete, got {:?}", outcome),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn get_best_spot_prices_returns_coverage_incomplete_for_leading_gap(
+    ) -> Result<(), Box<dyn Error>> {
+        let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig::default());
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+
+        // act
+        let outcome = spot_price_planner.get_best_spot_prices(&PlanningRequest {
+            spot_prices: hourly_spot_prices(from, &[0.30, 0.10, 0.20, 0.40]),
+            load_profile: one_hour_load_profile(),
+            after: Some(from - Duration::hours(2)),
+            before: Some(from + Duration::hours(4)),
+            require_full_coverage: true,
+            ..PlanningRequest::default()
+        })?;
+
+        match outcome {
+            PlanningOutcome::CoverageIncomplete {
+                uncovered_intervals,
+            } => assert_eq!(uncovered_intervals, vec![(from - Duration::hours(2), from)]),
+            _ => panic!("expected CoverageIncomplete, got {:?}", outcome),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn get_best_spot_prices_ignores_gap_outside_plannable_time_slots() -> Result<(), Box<dyn Error>>
+    {
+        // 2022-04-16 is a saturday; plannable from 00:00 till 04:00 UTC+2, so 22:00 till 02:00 UTC
+        let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig {
+            plannable_local_time_slots: HashMap::from([(
+                Weekday::Sat,
+                vec![time_slot((0, 0), (4, 0))],
+            )]),
+            local_time_zone: "Europe/Amsterdam".to_string(),
+            ..SpotPricePlannerConfig::default()
+        });
+        let from = Utc.with_ymd_and_hms(2022, 4, 15, 22, 0, 0).unwrap();
+
+        // act
+        let outcome = spot_price_planner.get_best_spot_prices(&PlanningRequest {
+            spot_prices: hourly_spot_prices(from, &[0.30, 0.10, 0.20, 0.40]),
+            load_profile: one_hour_load_profile(),
+            after: Some(from),
+            before: Some(from + Duration::hours(12)),
+            require_full_coverage: true,
+            ..PlanningRequest::default()
+        })?;
+
+        assert_eq!(
+            outcome.planned().and_then(|response| response.planned_from),
+            Some(from + Duration::hours(1))
+        );
+
+        Ok(())
+    }
+
+    fn wash_and_heat_load_profile() -> LoadProfile {
+        LoadProfile {
+            sections: vec![
+                LoadProfileSection {
+                    duration_seconds: 90 * 60,
+                    power_draw_watt: 100.0,
+                    end_power_draw_watt: None,
+                    id: Some("wash".to_string()),
+                },
+                LoadProfileSection {
+                    duration_seconds: 30 * 60,
+                    power_draw_watt: 2000.0,
+                    end_power_draw_watt: None,
+                    id: Some("heat".to_string()),
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn get_best_spot_prices_per_section_plans_heating_in_cheaper_later_hour(
+    ) -> Result<(), Box<dyn Error>> {
+        let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig::default());
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+
+        // act
+        let response = spot_price_planner
+            .get_best_spot_prices_per_section(&PlanningRequest {
+                spot_prices: hourly_spot_prices(from, &[0.20, 0.20, 0.40, 0.05, 0.30]),
+                load_profile: wash_and_heat_load_profile(),
+                ..PlanningRequest::default()
+            })?
+            .unwrap();
+
+        let wash = response.section("wash").unwrap();
+        let heat = response.section("heat").unwrap();
+        assert_eq!((wash.from, wash.till), (from, from + Duration::minutes(90)));
+        assert_eq!(
+            (heat.from, heat.till),
+            (from + Duration::hours(3), from + Duration::minutes(210))
+        );
+        // 0.15 kWh washing at 0.20 and 1 kWh heating at 0.05
+        assert!((response.total_price - 0.08).abs() < 1e-9);
+
+        Ok(())
+    }
+
+    #[test]
+    fn get_best_spot_prices_per_section_keeps_section_order() -> Result<(), Box<dyn Error>> {
+        let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig::default());
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+
+        // act
+        let response = spot_price_planner
+            .get_best_spot_prices_per_section(&PlanningRequest {
+                spot_prices: hourly_spot_prices(from, &[0.05, 0.40, 0.30]),
+                load_profile: wash_and_heat_load_profile(),
+                ..PlanningRequest::default()
+            })?
+            .unwrap();
+
+        let wash = response.section("wash").unwrap();
+        let heat = response.section("heat").unwrap();
+        assert_eq!(wash.from, from);
+        assert_eq!(heat.from, from + Duration::hours(2));
+
+        Ok(())
+    }
+
+    #[test]
+    fn get_best_spot_prices_per_section_returns_none_when_sections_do_not_fit(
+    ) -> Result<(), Box<dyn Error>> {
+        let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig::default());
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+
+        // act
+        let response = spot_price_planner.get_best_spot_prices_per_section(&PlanningRequest {
+            spot_prices: hourly_spot_prices(from, &[0.10]),
+            load_profile: wash_and_heat_load_profile(),
+            ..PlanningRequest::default()
+        })?;
+
+        assert_eq!(response, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn get_plannable_spot_prices_excludes_weekday_with_empty_time_slots(
+    ) -> Result<(), Box<dyn Error>> {
+        // 2022-04-16 is a saturday, 2022-04-17 a sunday
+        let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig {
+            plannable_local_time_slots: HashMap::from([
+                (Weekday::Sat, vec![]),
+                (Weekday::Sun, vec![time_slot((0, 0), (0, 0))]),
+            ]),
+            ..SpotPricePlannerConfig::default()
+        });
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 22, 0, 0).unwrap();
+
+        // act
+        let plannable_spot_prices = spot_price_planner.get_plannable_spot_prices(
+            &hourly_spot_prices(from, &[0.10, 0.20, 0.30, 0.40]),
+            &None,
+            &None,
+        )?;
+
+        assert_eq!(
+            plannable_spot_prices
+                .iter()
+                .map(|sp| sp.from)
+                .collect::<Vec<_>>(),
+            vec![from + Duration::hours(2), from + Duration::hours(3)]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn get_plannable_spot_prices_excludes_weekday_missing_from_populated_time_slots(
+    ) -> Result<(), Box<dyn Error>> {
+        let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig {
+            plannable_local_time_slots: HashMap::from([(
+                Weekday::Sun,
+                vec![time_slot((0, 0), (0, 0))],
+            )]),
+            ..SpotPricePlannerConfig::default()
+        });
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 22, 0, 0).unwrap();
+
+        // act
+        let plannable_spot_prices = spot_price_planner.get_plannable_spot_prices(
+            &hourly_spot_prices(from, &[0.10, 0.20, 0.30, 0.40]),
+            &None,
+            &None,
+        )?;
+
+        assert_eq!(plannable_spot_prices.len(), 2);
+        assert_eq!(plannable_spot_prices[0].from, from + Duration::hours(2));
+
+        Ok(())
+    }
+
+    fn pool_pump_request(max_runs_per_local_day: Option<usize>) -> PlanningRequest {
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+        PlanningRequest {
+            spot_prices: hourly_spot_prices(
+                from,
+                &[0.10, 0.50, 0.10, 0.20, 0.50, 0.50, 0.12, 0.50],
+            ),
+            load_profile: LoadProfile {
+                sections: vec![LoadProfileSection {
+                    duration_seconds: 3 * 3600,
+                    power_draw_watt: 1000.0,
+                    end_power_draw_watt: None,
+                    id: None,
+                }],
+            },
+            planning_strategy: PlanningStrategy::LowestPriceNonConsecutive,
+            max_runs_per_local_day,
+            ..PlanningRequest::default()
+        }
+    }
+
+    #[test]
+    fn get_best_spot_prices_limits_runs_per_local_day() -> Result<(), Box<dyn Error>> {
+        let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig::default());
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+
+        // act
+        let unconstrained = spot_price_planner
+            .get_best_spot_prices(&pool_pump_request(None))?
+            .into_planned()
+            .unwrap();
+        let constrained = spot_price_planner
+            .get_best_spot_prices(&pool_pump_request(Some(2)))?
+            .into_planned()
+            .unwrap();
+
+        assert_eq!(unconstrained.segments.len(), 3);
+        assert_eq!(
+            constrained.segments,
+            vec![
+                (from, from + Duration::hours(1)),
+                (from + Duration::hours(2), from + Duration::hours(4)),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn runs_per_local_day_splits_run_at_local_midnight() -> Result<(), Box<dyn Error>> {
+        let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig::default());
+        let from = Utc.with_ymd_and_hms(2022, 4, 15, 22, 0, 0).unwrap();
+        let mut request = pool_pump_request(Some(1));
+        request.spot_prices = hourly_spot_prices(from, &[0.50, 0.10, 0.10, 0.50, 0.10]);
+        request.load_profile.sections[0].duration_seconds = 2 * 3600;
+
+        // act
+        let response = spot_price_planner
+            .get_best_spot_prices(&request)?
+            .into_planned()
+            .unwrap();
+
+        let midnight = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+        assert_eq!(
+            response.runs_per_local_day(&Tz::UTC),
+            vec![
+                (
+                    NaiveDate::from_ymd_opt(2022, 4, 15).unwrap(),
+                    vec![(from + Duration::hours(1), midnight)]
+                ),
+                (
+                    NaiveDate::from_ymd_opt(2022, 4, 16).unwrap(),
+                    vec![(midnight, midnight + Duration::hours(1))]
+                ),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn get_best_spot_prices_at_forces_plan_as_deadline_approaches() -> Result<(), Box<dyn Error>> {
+        let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig::default());
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+        let request = PlanningRequest {
+            spot_prices: hourly_spot_prices(from, &[0.30, 0.20, 0.25, 0.40, 0.35, 0.30, 0.10]),
+            load_profile: one_hour_load_profile(),
+            max_acceptable_total_price_per_kwh: Some(0.05),
+            deadline_policy: Some(DeadlinePolicy {
+                deadline: from + Duration::hours(6),
+                relax_after: Duration::hours(2),
+                ignore_time_slots: false,
+            }),
+            ..PlanningRequest::default()
+        };
+
+        for hour in 0..3 {
+            // act
+            let outcome = spot_price_planner
+                .get_best_spot_prices_at(&request, from + Duration::hours(hour))?;
+
+            assert!(matches!(
+                outcome,
+                PlanningOutcome::NotPlannable {
+                    reason: NotPlannableReason::PriceThresholdNotMet,
+                    ..
+                }
+            ));
+        }
+
+        // act
+        let response = spot_price_planner
+            .get_best_spot_prices_at(&request, from + Duration::hours(3))?
+            .into_planned()
+            .unwrap();
+
+        assert!(response.forced);
+        assert_eq!(response.planned_from, Some(from + Duration::hours(5)));
+
+        Ok(())
+    }
+
+    // determines the plannable spot prices the way the planner did before expanding the time slots up front
+    fn reference_plannable_spot_prices(
+        config: &SpotPricePlannerConfig,
+        spot_prices: &[SpotPrice],
+        after: &Option<DateTime<Utc>>,
+        before: &Option<DateTime<Utc>>,
+    ) -> Vec<SpotPrice> {
+        let local_time_zone = config.get_local_time_zone().unwrap();
+        let mut plannable_spot_prices = vec![];
+        for spot_price in spot_prices {
+            let spot_price = if config.clip_to_bounds {
+                match spot_price.clip(
+                    after.unwrap_or(spot_price.from),
+                    before.unwrap_or(spot_price.till),
+                ) {
+                    Some(clipped) => clipped,
+                    None => continue,
+                }
+            } else if is_within_bounds(spot_price, after, before) {
+                spot_price.clone()
+            } else {
+                continue;
+            };
+
+            let local_from = spot_price.from.with_timezone(&local_time_zone);
+            let local_till = spot_price.till.with_timezone(&local_time_zone);
+            let date = local_from.date_naive();
+            if config.plannable_local_time_slots.is_empty() && !config.holidays.contains(&date) {
+                plannable_spot_prices.push(spot_price);
+                continue;
+            }
+
+            let mut overlaps = vec![];
+            for date in [date, date.pred_opt().unwrap()] {
+                for time_slot in config.time_slots_on(date) {
+                    let (time_slot_from, time_slot_till) =
+                        time_slot.local_interval_on(date, &local_time_zone).unwrap();
+                    if !config.clip_to_bounds {
+                        if local_from >= time_slot_from && local_till <= time_slot_till {
+                            overlaps = vec![(spot_price.from, spot_price.till)];
+                        }
+                    } else if time_slot_from.with_timezone(&Utc) < spot_price.till
+                        && time_slot_till.with_timezone(&Utc) > spot_price.from
+                    {
+                        overlaps.push((
+                            time_slot_from.with_timezone(&Utc).max(spot_price.from),
+                            time_slot_till.with_timezone(&Utc).min(spot_price.till),
+                        ));
+                    }
+                }
+            }
+            plannable_spot_prices.extend(
+                merge_intervals(overlaps)
+                    .into_iter()
+                    .filter_map(|(from, till)| spot_price.clip(from, till)),
+            );
+        }
+        plannable_spot_prices
+    }
+
+    #[test]
+    fn get_plannable_spot_prices_matches_reference_on_fixtures() -> Result<(), Box<dyn Error>> {
+        let time_slots = HashMap::from([
+            (Weekday::Fri, vec![time_slot((23, 0), (2, 0))]),
+            (
+                Weekday::Sat,
+                vec![time_slot((0, 0), (6, 30)), time_slot((21, 45), (0, 0))],
+            ),
+            (Weekday::Sun, vec![time_slot((10, 10), (14, 50))]),
+        ]);
+        let after = Utc.with_ymd_and_hms(2022, 4, 16, 1, 20, 0).unwrap();
+        let before = Utc.with_ymd_and_hms(2022, 4, 16, 22, 40, 0).unwrap();
+
+        for fixture in [
+            "spot_price_predictions.json",
+            "spot_price_predictions_mixed.json",
+        ] {
+            let spot_price_response: SpotPriceResponse =
+                serde_json::from_str(&std::fs::read_to_string(fixture)?)?;
+            let spot_prices = spot_price_response.data.market_prices_electricity;
+
+            for (plannable_local_time_slots, holidays) in [
+                (HashMap::new(), vec![]),
+                (time_slots.clone(), vec![]),
+                (
+                    HashMap::new(),
+                    vec![NaiveDate::from_ymd_opt(2022, 4, 16).unwrap()],
+                ),
+            ] {
+                for clip_to_bounds in [false, true] {
+                    for (after, before) in [(None, None), (Some(after), Some(before))] {
+                        let config = SpotPricePlannerConfig {
+                            plannable_local_time_slots: plannable_local_time_slots.clone(),
+                            local_time_zone: "Europe/Amsterdam".to_string(),
+                            holidays: holidays.clone(),
+                            holiday_time_slots: vec![time_slot((12, 0), (14, 0))],
+                            clip_to_bounds,
+                            ..SpotPricePlannerConfig::default()
+                        };
+                        let expected =
+                            reference_plannable_spot_prices(&config, &spot_prices, &after, &before);
+                        let spot_price_planner = SpotPricePlanner::new(config);
+
+                        // act
+                        let plannable_spot_prices = spot_price_planner.get_plannable_spot_prices(
+                            &spot_prices,
+                            &after,
+                            &before,
+                        )?;
+
+                        assert_eq!(plannable_spot_prices, expected);
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn get_plannable_spot_prices_uses_time_zone_changed_after_creation(
+    ) -> Result<(), Box<dyn Error>> {
+        let mut spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig {
+            plannable_local_time_slots: HashMap::from([(
+                Weekday::Sat,
+                vec![time_slot((0, 0), (1, 0))],
+            )]),
+            ..SpotPricePlannerConfig::default()
+        });
+        spot_price_planner.config.local_time_zone = "Europe/Amsterdam".to_string();
+        let from = Utc.with_ymd_and_hms(2022, 4, 15, 22, 0, 0).unwrap();
+
+        // act
+        let plannable_spot_prices = spot_price_planner.get_plannable_spot_prices(
+            &hourly_spot_prices(from, &[0.10, 0.20, 0.30]),
+            &None,
+            &None,
+        )?;
+
+        assert_eq!(plannable_spot_prices.len(), 1);
+        assert_eq!(plannable_spot_prices[0].from, from);
+
+        Ok(())
+    }
+
+    #[test]
+    fn evaluate_plan_compares_plan_with_reference_start_and_worst_case(
+    ) -> Result<(), Box<dyn Error>> {
+        let spot_price_response: SpotPriceResponse =
+            serde_json::from_str(&std::fs::read_to_string("spot_price_predictions.json")?)?;
+        let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig::default());
+        let request = PlanningRequest {
+            spot_prices: spot_price_response.data.market_prices_electricity,
+            load_profile: LoadProfile {
+                sections: vec![LoadProfileSection {
+                    duration_seconds: 2 * 3600,
+                    power_draw_watt: 1000.0,
+                    end_power_draw_watt: None,
+                    id: None,
+                }],
+            },
+            ..PlanningRequest::default()
+        };
+        let response = spot_price_planner
+            .get_best_spot_prices(&request)?
+            .into_planned()
+            .unwrap();
+
+        // act
+        let evaluation = spot_price_planner.evaluate_plan(
+            &request,
+            &response,
+            Utc.with_ymd_and_hms(2022, 4, 7, 23, 0, 0).unwrap(),
+        );
+
+        let assert_close = |actual: Option<f64>, expected: f64| {
+            assert!(
+                (actual.unwrap() - expected).abs() < 1e-9,
+                "{:?} != {}",
+                actual,
+                expected
+            )
+        };
+        assert_close(Some(evaluation.plan_cost), 0.586977);
+        assert_close(evaluation.reference_cost, 0.6027448);
+        assert_close(evaluation.worst_case_cost, 0.931659);
+        assert_close(evaluation.savings_vs_reference, 0.0157678);
+        assert_close(
+            evaluation.savings_vs_reference_percentage,
+            2.6159993416782505,
+        );
+        assert_close(evaluation.savings_vs_worst_case, 0.344682);
+        assert_close(
+            evaluation.savings_vs_worst_case_percentage,
+            36.99658351392515,
         );
 
-        assert_eq!(total_price, 2.0207701999998684); // round error, should be 2.0207702
+        Ok(())
     }
 
     #[test]
-    fn get_plannable_spot_prices_returns_only_spot_prices_fitting_in_plannable_time_slots(
+    fn evaluate_plan_has_no_reference_cost_when_reference_run_is_not_covered(
+    ) -> Result<(), Box<dyn Error>> {
+        let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig::default());
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+        let request = PlanningRequest {
+            spot_prices: hourly_spot_prices(from, &[0.30, 0.10, 0.20]),
+            load_profile: one_hour_load_profile(),
+            ..PlanningRequest::default()
+        };
+        let response = spot_price_planner
+            .get_best_spot_prices(&request)?
+            .into_planned()
+            .unwrap();
+
+        // act
+        let evaluation =
+            spot_price_planner.evaluate_plan(&request, &response, from + Duration::minutes(150));
+
+        assert_eq!(evaluation.reference_cost, None);
+        assert_eq!(evaluation.savings_vs_reference, None);
+        assert!(evaluation.worst_case_cost.is_some());
+
+        Ok(())
+    }
+
+    fn solar_request(expected_production_watt: HashMap<DateTime<Utc>, f64>) -> PlanningRequest {
+        let from = Utc.with_ymd_and_hms(2022, 6, 18, 0, 0, 0).unwrap();
+        let market_prices: Vec<f64> = (0..24)
+            .map(|hour| if hour < 6 { 0.05 } else { 0.30 })
+            .collect();
+
+        PlanningRequest {
+            spot_prices: hourly_spot_prices(from, &market_prices),
+            load_profile: LoadProfile {
+                sections: vec![LoadProfileSection {
+                    duration_seconds: 2 * 3600,
+                    power_draw_watt: 1000.0,
+                    end_power_draw_watt: None,
+                    id: None,
+                }],
+            },
+            planning_strategy: PlanningStrategy::MaximizeSelfConsumption,
+            expected_production_watt,
+            ..PlanningRequest::default()
+        }
+    }
+
+    #[test]
+    fn get_best_spot_prices_plans_self_consumption_under_solar_bump() -> Result<(), Box<dyn Error>>
+    {
+        let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig::default());
+        let from = Utc.with_ymd_and_hms(2022, 6, 18, 0, 0, 0).unwrap();
+        let request = solar_request(HashMap::from([
+            (from + Duration::hours(11), 1500.0),
+            (from + Duration::hours(12), 2000.0),
+            (from + Duration::hours(13), 500.0),
+        ]));
+
+        // act
+        let response = spot_price_planner
+            .get_best_spot_prices(&request)?
+            .into_planned()
+            .unwrap();
+
+        assert_eq!(response.planned_from, Some(from + Duration::hours(11)));
+        assert_eq!(response.self_consumed_kwh, Some(2.0));
+        assert_eq!(response.grid_kwh, Some(0.0));
+
+        Ok(())
+    }
+
+    #[test]
+    fn get_best_spot_prices_plans_self_consumption_on_lowest_price_without_production(
     ) -> Result<(), Box<dyn Error>> {
+        let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig::default());
+        let from = Utc.with_ymd_and_hms(2022, 6, 18, 0, 0, 0).unwrap();
+
+        // act
+        let response = spot_price_planner
+            .get_best_spot_prices(&solar_request(HashMap::new()))?
+            .into_planned()
+            .unwrap();
+
+        assert_eq!(response.planned_from, Some(from));
+        assert_eq!(response.self_consumed_kwh, None);
+
+        Ok(())
+    }
+
+    fn battery_request(market_prices: &[f64], efficiency: Option<f64>) -> ChargeDischargeRequest {
         let load_profile = LoadProfile {
             sections: vec![LoadProfileSection {
                 duration_seconds: 7200,
-                power_draw_watt: 2000.0,
+                power_draw_watt: 1000.0,
+                end_power_draw_watt: None,
+                id: None,
             }],
         };
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+
+        ChargeDischargeRequest {
+            spot_prices: hourly_spot_prices(from, market_prices),
+            charge_load_profile: load_profile.clone(),
+            discharge_load_profile: load_profile,
+            min_price_spread_per_kwh: 0.06,
+            efficiency,
+            ..ChargeDischargeRequest::default()
+        }
+    }
+
+    #[test]
+    fn get_charge_discharge_windows_returns_below_spread_threshold_on_flat_day(
+    ) -> Result<(), Box<dyn Error>> {
+        let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig::default());
+
+        // act
+        let response = spot_price_planner
+            .get_charge_discharge_windows(&battery_request(&[0.20, 0.20, 0.20, 0.20], None))?;
+
+        match response {
+            ChargeDischargeResponse::BelowSpreadThreshold {
+                best_price_spread_per_kwh,
+            } => assert!(best_price_spread_per_kwh.abs() < 1e-9),
+            _ => panic!("expected below spread threshold"),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn get_charge_discharge_windows_applies_efficiency_to_discharge_revenue(
+    ) -> Result<(), Box<dyn Error>> {
+        let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig::default());
+        let market_prices = [0.10, 0.10, 0.30, 0.30];
+
+        // act
+        let lossless = spot_price_planner
+            .get_charge_discharge_windows(&battery_request(&market_prices, None))?;
+        let lossy = spot_price_planner
+            .get_charge_discharge_windows(&battery_request(&market_prices, Some(0.5)))?;
+
+        assert!(matches!(lossless, ChargeDischargeResponse::Windows { .. }));
+        match lossy {
+            ChargeDischargeResponse::BelowSpreadThreshold {
+                best_price_spread_per_kwh,
+            } => assert!((best_price_spread_per_kwh - 0.05).abs() < 1e-9),
+            _ => panic!("expected below spread threshold"),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn get_charge_discharge_windows_refuses_spot_prices_in_mixed_currencies() {
+        let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig::default());
+        let mut request = battery_request(&[0.10, 5.0, 0.12], None);
+        request.spot_prices[0].currency = Some("EUR".to_string());
+        request.spot_prices[1].currency = Some("CZK".to_string());
+        request.spot_prices[2].currency = Some("EUR".to_string());
+
+        // act
+        let result = spot_price_planner.get_charge_discharge_windows(&request);
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn get_charge_discharge_windows_plans_unsorted_spot_prices() -> Result<(), Box<dyn Error>> {
+        let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig::default());
+        let mut request = battery_request(&[0.10, 0.10, 0.30, 0.30], None);
+        request.spot_prices.reverse();
+
+        // act
+        let response = spot_price_planner.get_charge_discharge_windows(&request)?;
+
+        match response {
+            ChargeDischargeResponse::Windows {
+                charge, discharge, ..
+            } => {
+                assert_eq!(charge.planned_from, Some(request.spot_prices[3].from));
+                assert_eq!(discharge.planned_from, Some(request.spot_prices[1].from));
+            }
+            _ => panic!("expected windows"),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn get_best_spot_prices_drops_spot_price_already_started_at_now() -> Result<(), Box<dyn Error>>
+    {
+        let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig::default());
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+        let request = PlanningRequest {
+            spot_prices: hourly_spot_prices(from, &[0.10, 0.20, 0.30, 0.40]),
+            load_profile: one_hour_load_profile(),
+            now: Some(from + Duration::minutes(30)),
+            ..PlanningRequest::default()
+        };
+
+        // act
+        let response = spot_price_planner
+            .get_best_spot_prices(&request)?
+            .into_planned()
+            .unwrap();
+
+        assert_eq!(response.planned_from, Some(from + Duration::hours(1)));
 
+        Ok(())
+    }
+
+    #[test]
+    fn get_best_spot_prices_clips_spot_price_already_started_at_now() -> Result<(), Box<dyn Error>>
+    {
         let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig {
-            load_profile: load_profile.clone(),
-            plannable_local_time_slots: HashMap::from([(
-                Weekday::Thu,
-                vec![TimeSlot {
-                    from: NaiveTime::from_hms_opt(14, 0, 0).unwrap(),
-                    till: NaiveTime::from_hms_opt(16, 0, 0).unwrap(),
-                }],
-            )]),
-            local_time_zone: "Europe/Amsterdam".to_string(),
+            clip_to_bounds: true,
+            ..SpotPricePlannerConfig::default()
         });
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+        let request = PlanningRequest {
+            spot_prices: hourly_spot_prices(from, &[0.10, 0.20, 0.30, 0.40]),
+            load_profile: one_hour_load_profile(),
+            now: Some(from + Duration::minutes(30)),
+            ..PlanningRequest::default()
+        };
 
-        let future_spot_prices: Vec<SpotPrice> = vec![
-            SpotPrice {
-                id: None,
-                source: None,
-                from: Utc.with_ymd_and_hms(2022, 4, 14, 11, 0, 0).unwrap(),
-                till: Utc.with_ymd_and_hms(2022, 4, 14, 12, 0, 0).unwrap(),
-                market_price: 0.202,
-                market_price_tax: 0.0424053,
-                sourcing_markup_price: 0.017,
-                energy_tax_price: 0.081,
-            },
-            SpotPrice {
-                id: None,
-                source: None,
-                from: Utc.with_ymd_and_hms(2022, 4, 14, 12, 0, 0).unwrap(),
-                till: Utc.with_ymd_and_hms(2022, 4, 14, 13, 0, 0).unwrap(),
-                market_price: 0.195,
-                market_price_tax: 0.0409899,
-                sourcing_markup_price: 0.017,
-                energy_tax_price: 0.081,
-            },
-            SpotPrice {
-                id: None,
-                source: None,
-                from: Utc.with_ymd_and_hms(2022, 4, 14, 13, 0, 0).unwrap(),
-                till: Utc.with_ymd_and_hms(2022, 4, 14, 14, 0, 0).unwrap(),
-                market_price: 0.194,
-                market_price_tax: 0.0406644,
-                sourcing_markup_price: 0.017,
-                energy_tax_price: 0.081,
-            },
-            SpotPrice {
-                id: None,
-                source: None,
-                from: Utc.with_ymd_and_hms(2022, 4, 14, 14, 0, 0).unwrap(),
-                till: Utc.with_ymd_and_hms(2022, 4, 14, 15, 0, 0).unwrap(),
-                market_price: 0.192,
-                market_price_tax: 0.0403179,
-                sourcing_markup_price: 0.017,
-                energy_tax_price: 0.081,
+        // act
+        let response = spot_price_planner
+            .get_best_spot_prices(&request)?
+            .into_planned()
+            .unwrap();
+
+        assert_eq!(response.planned_from, Some(from + Duration::minutes(30)));
+        assert_eq!(response.planned_till, Some(from + Duration::minutes(90)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn get_best_spot_prices_uses_planner_now_for_request_without_now() -> Result<(), Box<dyn Error>>
+    {
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+        let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig::default())
+            .with_now(from + Duration::minutes(90));
+        let request = PlanningRequest {
+            spot_prices: hourly_spot_prices(from, &[0.10, 0.20, 0.30, 0.40]),
+            load_profile: one_hour_load_profile(),
+            ..PlanningRequest::default()
+        };
+
+        // act
+        let response = spot_price_planner
+            .get_best_spot_prices(&request)?
+            .into_planned()
+            .unwrap();
+
+        assert_eq!(response.planned_from, Some(from + Duration::hours(2)));
+
+        Ok(())
+    }
+
+    fn four_hour_request(market_prices: &[f64]) -> PlanningRequest {
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+        PlanningRequest {
+            spot_prices: hourly_spot_prices(from, market_prices),
+            load_profile: LoadProfile {
+                sections: vec![LoadProfileSection {
+                    duration_seconds: 4 * 3600,
+                    power_draw_watt: 1000.0,
+                    end_power_draw_watt: None,
+                    id: None,
+                }],
             },
-        ];
+            max_price_median_multiplier: Some(2.0),
+            ..PlanningRequest::default()
+        }
+    }
+
+    #[test]
+    fn get_best_spot_prices_avoids_spot_price_above_median_multiple() -> Result<(), Box<dyn Error>>
+    {
+        let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig::default());
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+        // the median is 0.175, so the 0.40 spike at 03:00 is above twice the median
+        let mut request = four_hour_request(&[0.30, 0.05, 0.05, 0.40, 0.05, 0.05, 0.30, 0.30]);
 
         // act
-        let plannable_spot_prices =
-            spot_price_planner.get_plannable_spot_prices(&future_spot_prices, &None, &None)?;
+        let avoiding = spot_price_planner
+            .get_best_spot_prices(&request)?
+            .into_planned()
+            .unwrap();
+        request.max_price_median_multiplier = None;
+        let cheapest = spot_price_planner
+            .get_best_spot_prices(&request)?
+            .into_planned()
+            .unwrap();
 
-        assert_eq!(plannable_spot_prices.len(), 2);
-        assert_eq!(
-            plannable_spot_prices[0].from,
-            Utc.with_ymd_and_hms(2022, 4, 14, 12, 0, 0).unwrap()
-        );
+        assert_eq!(avoiding.planned_from, Some(from + Duration::hours(4)));
+        assert!(!avoiding.spike_fallback_used);
+        assert_eq!(cheapest.planned_from, Some(from + Duration::hours(1)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn get_best_spot_prices_adds_back_spike_when_load_does_not_fit_without(
+    ) -> Result<(), Box<dyn Error>> {
+        let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig::default());
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+
+        // act
+        let response = spot_price_planner
+            .get_best_spot_prices(&four_hour_request(&[0.05, 0.05, 0.90, 0.05, 0.05]))?
+            .into_planned()
+            .unwrap();
+
+        assert_eq!(response.planned_from, Some(from));
+        assert!(response.spike_fallback_used);
+
+        Ok(())
+    }
+
+    fn golden_planning_request() -> PlanningRequest {
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+        PlanningRequest {
+            spot_prices: hourly_spot_prices(from, &[0.10]),
+            load_profile: one_hour_load_profile(),
+            planning_strategy: PlanningStrategy::LowestPricePercentile { percentile: 25.0 },
+            after: Some(from),
+            max_acceptable_total_price_per_kwh: Some(0.30),
+            tie_breaker: Some(TieBreaker::LatestStart),
+            deadline_policy: Some(DeadlinePolicy {
+                deadline: from + Duration::hours(6),
+                relax_after: Duration::hours(2),
+                ignore_time_slots: false,
+            }),
+            ..PlanningRequest::default()
+        }
+    }
+
+    fn golden_planning_response() -> PlanningResponse {
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+        PlanningResponse::for_spot_prices(
+            hourly_spot_prices(from, &[0.10]),
+            0,
+            &one_hour_load_profile(),
+            PriceComponents::default(),
+        )
+    }
+
+    fn read_json(path: &str) -> Result<serde_json::Value, Box<dyn Error>> {
+        Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?)
+    }
+
+    #[test]
+    fn planning_request_serializes_to_golden_json() -> Result<(), Box<dyn Error>> {
+        // act
+        let json = serde_json::to_value(golden_planning_request())?;
+
+        assert_eq!(json, read_json("planning_request.json")?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn planning_request_deserializes_from_legacy_json() -> Result<(), Box<dyn Error>> {
+        let legacy_json = std::fs::read_to_string("planning_request_legacy.json")?;
+
+        // act
+        let request: PlanningRequest = serde_json::from_str(&legacy_json)?;
+
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
         assert_eq!(
-            plannable_spot_prices[0].till,
-            Utc.with_ymd_and_hms(2022, 4, 14, 13, 0, 0).unwrap()
+            serde_json::to_value(request)?,
+            serde_json::to_value(PlanningRequest {
+                spot_prices: hourly_spot_prices(from, &[0.10]),
+                load_profile: one_hour_load_profile(),
+                planning_strategy: PlanningStrategy::HighestPrice,
+                after: Some(from),
+                ..PlanningRequest::default()
+            })?
         );
+
+        Ok(())
+    }
+
+    #[test]
+    fn charge_discharge_request_serializes_to_golden_json() -> Result<(), Box<dyn Error>> {
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+        let request = ChargeDischargeRequest {
+            spot_prices: hourly_spot_prices(from, &[0.10]),
+            charge_load_profile: one_hour_load_profile(),
+            discharge_load_profile: one_hour_load_profile(),
+            min_price_spread_per_kwh: 0.10,
+            efficiency: Some(0.9),
+            after: Some(from),
+            before: None,
+        };
+
+        // act
+        let json = serde_json::to_value(request)?;
+
+        assert_eq!(json, read_json("charge_discharge_request.json")?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn charge_discharge_request_accepts_min_spread_per_kwh() -> Result<(), Box<dyn Error>> {
+        let json = std::fs::read_to_string("charge_discharge_request.json")?
+            .replace("minPriceSpreadPerKwh", "min_spread_per_kwh");
+
+        // act
+        let request: ChargeDischargeRequest = serde_json::from_str(&json)?;
+
+        assert_eq!(request.min_price_spread_per_kwh, 0.1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn charge_discharge_response_serializes_to_golden_json() -> Result<(), Box<dyn Error>> {
+        // act
+        let json = serde_json::to_value(ChargeDischargeResponse::BelowSpreadThreshold {
+            best_price_spread_per_kwh: 0.05,
+        })?;
+
+        assert_eq!(json, read_json("charge_discharge_response.json")?);
         assert_eq!(
-            plannable_spot_prices[1].from,
-            Utc.with_ymd_and_hms(2022, 4, 14, 13, 0, 0).unwrap()
+            serde_json::to_value(ChargeDischargeResponse::NoAction)?,
+            serde_json::json!("noAction")
         );
+
+        Ok(())
+    }
+
+    #[test]
+    fn planning_outcome_serializes_to_golden_json() -> Result<(), Box<dyn Error>> {
+        // act
+        let json = serde_json::to_value(PlanningOutcome::NotPlannable {
+            reason: NotPlannableReason::NotEnoughConsecutiveDuration,
+            explanation: vec![],
+        })?;
+
+        assert_eq!(json, read_json("planning_outcome.json")?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn explained_planning_outcome_serializes_to_golden_json() -> Result<(), Box<dyn Error>> {
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+
+        // act
+        let json = serde_json::to_value(PlanningOutcome::NotPlannable {
+            reason: NotPlannableReason::PriceThresholdNotMet,
+            explanation: vec![
+                CandidateRejection {
+                    from,
+                    reason: RejectionReason::OutsideTimeSlots,
+                    price_delta: None,
+                },
+                CandidateRejection {
+                    from: from + Duration::hours(1),
+                    reason: RejectionReason::AbovePriceThreshold,
+                    price_delta: Some(0.05),
+                },
+            ],
+        })?;
+
+        assert_eq!(json, read_json("planning_outcome_explained.json")?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn planning_response_serializes_to_golden_json() -> Result<(), Box<dyn Error>> {
+        // act
+        let json = serde_json::to_value(golden_planning_response())?;
+
+        assert_eq!(json, read_json("planning_response.json")?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn planning_response_deserializes_from_legacy_json() -> Result<(), Box<dyn Error>> {
+        let legacy_json = std::fs::read_to_string("planning_response_legacy.json")?;
+
+        // act
+        let response: PlanningResponse = serde_json::from_str(&legacy_json)?;
+
+        let golden = golden_planning_response();
         assert_eq!(
-            plannable_spot_prices[1].till,
-            Utc.with_ymd_and_hms(2022, 4, 14, 14, 0, 0).unwrap()
+            serde_json::to_value((&response.spot_prices, &response.load_profile))?,
+            serde_json::to_value((&golden.spot_prices, &golden.load_profile))?
         );
 
         Ok(())
     }
 
     #[test]
-    fn get_plannable_spot_prices_returns_only_spot_prices_fitting_in_plannable_time_slots_when_includes_next_day(
+    fn get_best_spot_prices_windowed_matches_exhaustive_search_on_week_of_hourly_prices(
+    ) -> Result<(), Box<dyn Error>> {
+        let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig::default());
+        let from = Utc.with_ymd_and_hms(2022, 4, 11, 0, 0, 0).unwrap();
+        let market_prices: Vec<f64> = (0..168).map(|i| ((i * 37) % 97) as f64 / 1000.0).collect();
+
+        for planning_strategy in [
+            PlanningStrategy::LowestPrice,
+            PlanningStrategy::HighestPrice,
+        ] {
+            for hours in [1, 3, 5] {
+                let request = PlanningRequest {
+                    spot_prices: hourly_spot_prices(from, &market_prices),
+                    load_profile: LoadProfile {
+                        sections: vec![LoadProfileSection {
+                            duration_seconds: hours * 3600,
+                            power_draw_watt: 1000.0,
+                            end_power_draw_watt: None,
+                            id: None,
+                        }],
+                    },
+                    planning_strategy,
+                    ..PlanningRequest::default()
+                };
+                let exhaustive = spot_price_planner
+                    .get_best_spot_prices(&request)?
+                    .into_planned()
+                    .unwrap();
+
+                // act
+                let windowed = spot_price_planner
+                    .get_best_spot_prices_windowed(
+                        &request,
+                        Duration::hours(24),
+                        Duration::hours(12),
+                    )?
+                    .into_planned()
+                    .unwrap();
+
+                assert_eq!(windowed.planned_from, exhaustive.planned_from);
+                assert_eq!(windowed.total_price(None), exhaustive.total_price(None));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn quarter_hour_spot_prices(from: DateTime<Utc>, market_prices: &[f64]) -> Vec<SpotPrice> {
+        hourly_spot_prices(from, market_prices)
+            .into_iter()
+            .enumerate()
+            .map(|(i, spot_price)| SpotPrice {
+                from: from + Duration::minutes(15 * i as i64),
+                till: from + Duration::minutes(15 * (i as i64 + 1)),
+                ..spot_price
+            })
+            .collect()
+    }
+
+    #[test]
+    fn get_best_spot_prices_widens_short_gap_between_runs_for_non_consecutive_strategy(
     ) -> Result<(), Box<dyn Error>> {
-        let load_profile = LoadProfile {
-            sections: vec![LoadProfileSection {
-                duration_seconds: 18000,
-                power_draw_watt: 2000.0,
-            }],
+        let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig::default());
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+        let request = PlanningRequest {
+            spot_prices: quarter_hour_spot_prices(
+                from,
+                &[0.10, 0.20, 0.90, 0.10, 0.10, 0.50, 0.60, 0.70],
+            ),
+            load_profile: one_hour_load_profile(),
+            planning_strategy: PlanningStrategy::LowestPriceNonConsecutive,
+            ..PlanningRequest::default()
         };
 
-        let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig {
-            load_profile: load_profile.clone(),
-            plannable_local_time_slots: HashMap::from([
-                (
-                    Weekday::Thu,
-                    vec![
-                        TimeSlot {
-                            from: NaiveTime::from_hms_opt(14, 0, 0).unwrap(),
-                            till: NaiveTime::from_hms_opt(16, 0, 0).unwrap(),
-                        },
-                        TimeSlot {
-                            from: NaiveTime::from_hms_opt(23, 0, 0).unwrap(),
-                            till: NaiveTime::from_hms_opt(0, 0, 0).unwrap(),
-                        },
-                    ],
-                ),
-                (
-                    Weekday::Fri,
-                    vec![TimeSlot {
-                        from: NaiveTime::from_hms_opt(0, 0, 0).unwrap(),
-                        till: NaiveTime::from_hms_opt(2, 0, 0).unwrap(),
-                    }],
-                ),
-            ]),
-            local_time_zone: "Europe/Amsterdam".to_string(),
-        });
-
-        let future_spot_prices: Vec<SpotPrice> = vec![
-            SpotPrice {
-                id: None,
-                source: None,
-                from: Utc.with_ymd_and_hms(2022, 4, 14, 20, 0, 0).unwrap(),
-                till: Utc.with_ymd_and_hms(2022, 4, 14, 21, 0, 0).unwrap(),
-                market_price: 0.265,
-                market_price_tax: 0.0557466,
-                sourcing_markup_price: 0.017,
-                energy_tax_price: 0.081,
-            },
-            SpotPrice {
-                id: None,
-                source: None,
-                from: Utc.with_ymd_and_hms(2022, 4, 14, 21, 0, 0).unwrap(),
-                till: Utc.with_ymd_and_hms(2022, 4, 14, 22, 0, 0).unwrap(),
-                market_price: 0.254,
-                market_price_tax: 0.0532728,
-                sourcing_markup_price: 0.017,
-                energy_tax_price: 0.081,
-            },
-            SpotPrice {
-                id: None,
-                source: None,
-                from: Utc.with_ymd_and_hms(2022, 4, 14, 22, 0, 0).unwrap(),
-                till: Utc.with_ymd_and_hms(2022, 4, 14, 23, 0, 0).unwrap(),
-                market_price: 0.231,
-                market_price_tax: 0.0484281,
-                sourcing_markup_price: 0.017,
-                energy_tax_price: 0.081,
-            },
-            SpotPrice {
-                id: None,
-                source: None,
-                from: Utc.with_ymd_and_hms(2022, 4, 14, 23, 0, 0).unwrap(),
-                till: Utc.with_ymd_and_hms(2022, 4, 15, 0, 0, 0).unwrap(),
-                market_price: 0.215,
-                market_price_tax: 0.045129,
-                sourcing_markup_price: 0.017,
-                energy_tax_price: 0.081,
-            },
-            SpotPrice {
-                id: None,
-                source: None,
-                from: Utc.with_ymd_and_hms(2022, 4, 15, 0, 0, 0).unwrap(),
-                till: Utc.with_ymd_and_hms(2022, 4, 15, 1, 0, 0).unwrap(),
-                market_price: 0.217,
-                market_price_tax: 0.04557,
-                sourcing_markup_price: 0.017,
-                energy_tax_price: 0.081,
-            },
-            SpotPrice {
-                id: None,
-                source: None,
-                from: Utc.with_ymd_and_hms(2022, 4, 15, 1, 0, 0).unwrap(),
-                till: Utc.with_ymd_and_hms(2022, 4, 15, 2, 0, 0).unwrap(),
-                market_price: 0.208,
-                market_price_tax: 0.0437535,
-                sourcing_markup_price: 0.017,
-                energy_tax_price: 0.081,
-            },
-        ];
-
         // act
-        let plannable_spot_prices =
-            spot_price_planner.get_plannable_spot_prices(&future_spot_prices, &None, &None)?;
+        let unrestricted = spot_price_planner
+            .get_best_spot_prices(&request)?
+            .into_planned()
+            .ok_or("not plannable")?;
+        let restricted = spot_price_planner
+            .get_best_spot_prices(&PlanningRequest {
+                min_gap_between_runs_seconds: Some(30 * 60),
+                ..request
+            })?
+            .into_planned()
+            .ok_or("not plannable")?;
 
-        assert_eq!(plannable_spot_prices.len(), 3);
-        assert_eq!(
-            plannable_spot_prices[0].from,
-            Utc.with_ymd_and_hms(2022, 4, 14, 21, 0, 0).unwrap()
-        );
+        // resting for only the expensive quarter is cheapest without the constraint
         assert_eq!(
-            plannable_spot_prices[0].till,
-            Utc.with_ymd_and_hms(2022, 4, 14, 22, 0, 0).unwrap()
+            unrestricted.segments,
+            vec![
+                (from, from + Duration::minutes(30)),
+                (from + Duration::minutes(45), from + Duration::minutes(75)),
+            ]
         );
         assert_eq!(
-            plannable_spot_prices[1].from,
-            Utc.with_ymd_and_hms(2022, 4, 14, 22, 0, 0).unwrap()
+            restricted.segments,
+            vec![
+                (from, from + Duration::minutes(15)),
+                (from + Duration::minutes(45), from + Duration::minutes(90)),
+            ]
         );
+        assert!((restricted.total_price(None) - 0.2).abs() < 1e-9);
+
+        Ok(())
+    }
+
+    #[test]
+    fn get_best_spot_prices_merges_runs_when_gap_between_runs_cannot_be_widened(
+    ) -> Result<(), Box<dyn Error>> {
+        let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig::default());
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+
+        // act
+        let response = spot_price_planner
+            .get_best_spot_prices(&PlanningRequest {
+                spot_prices: quarter_hour_spot_prices(from, &[0.10, 0.10, 0.90, 0.10, 0.10]),
+                load_profile: one_hour_load_profile(),
+                planning_strategy: PlanningStrategy::LowestPriceNonConsecutive,
+                min_gap_between_runs_seconds: Some(30 * 60),
+                ..PlanningRequest::default()
+            })?
+            .into_planned()
+            .ok_or("not plannable")?;
+
         assert_eq!(
-            plannable_spot_prices[1].till,
-            Utc.with_ymd_and_hms(2022, 4, 14, 23, 0, 0).unwrap()
+            response.segments,
+            vec![(from, from + Duration::minutes(60))]
         );
 
+        Ok(())
+    }
+
+    #[test]
+    fn get_best_spot_prices_per_section_rests_between_sections() -> Result<(), Box<dyn Error>> {
+        let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig::default());
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+        let request = PlanningRequest {
+            spot_prices: hourly_spot_prices(from, &[0.20, 0.90, 0.05, 0.90, 0.10]),
+            load_profile: LoadProfile {
+                sections: vec![
+                    LoadProfileSection {
+                        duration_seconds: 3600,
+                        power_draw_watt: 100.0,
+                        end_power_draw_watt: None,
+                        id: Some("wash".to_string()),
+                    },
+                    LoadProfileSection {
+                        duration_seconds: 30 * 60,
+                        power_draw_watt: 2000.0,
+                        end_power_draw_watt: None,
+                        id: Some("heat".to_string()),
+                    },
+                ],
+            },
+            ..PlanningRequest::default()
+        };
+
+        // act
+        let unrestricted = spot_price_planner
+            .get_best_spot_prices_per_section(&request)?
+            .unwrap();
+        let restricted = spot_price_planner
+            .get_best_spot_prices_per_section(&PlanningRequest {
+                min_gap_between_runs_seconds: Some(2 * 3600),
+                ..request
+            })?
+            .unwrap();
+
         assert_eq!(
-            plannable_spot_prices[2].from,
-            Utc.with_ymd_and_hms(2022, 4, 14, 23, 0, 0).unwrap()
+            unrestricted.section("heat").unwrap().from,
+            from + Duration::hours(2)
         );
+        assert_eq!(restricted.section("wash").unwrap().from, from);
         assert_eq!(
-            plannable_spot_prices[2].till,
-            Utc.with_ymd_and_hms(2022, 4, 15, 0, 0, 0).unwrap()
+            restricted.section("heat").unwrap().from,
+            from + Duration::hours(4)
         );
 
         Ok(())
     }
 
     #[test]
-    fn get_plannable_spot_prices_with_before() -> Result<(), Box<dyn Error>> {
-        let load_profile = LoadProfile {
-            sections: vec![LoadProfileSection {
-                duration_seconds: 18000,
-                power_draw_watt: 2000.0,
-            }],
-        };
-
+    fn get_daily_plans_plans_each_local_day_on_its_own() -> Result<(), Box<dyn Error>> {
         let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig {
-            load_profile: load_profile.clone(),
-            plannable_local_time_slots: HashMap::from([
+            local_time_zone: "Europe/Amsterdam".to_string(),
+            ..SpotPricePlannerConfig::default()
+        });
+        // local midnight of 16 April in Amsterdam
+        let from = Utc.with_ymd_and_hms(2022, 4, 15, 22, 0, 0).unwrap();
+        let mut market_prices = vec![0.30; 48];
+        market_prices[3] = 0.05;
+        market_prices[24 + 14] = 0.10;
+
+        // act
+        let daily_plans = spot_price_planner.get_daily_plans(
+            &PlanningRequest {
+                spot_prices: hourly_spot_prices(from, &market_prices),
+                load_profile: one_hour_load_profile(),
+                ..PlanningRequest::default()
+            },
+            2,
+        )?;
+
+        assert_eq!(
+            daily_plans
+                .iter()
+                .map(|(date, outcome)| (
+                    *date,
+                    outcome.planned().and_then(|response| response.planned_from)
+                ))
+                .collect::<Vec<_>>(),
+            vec![
                 (
-                    Weekday::Thu,
-                    vec![
-                        TimeSlot {
-                            from: NaiveTime::from_hms_opt(0, 0, 0).unwrap(),
-                            till: NaiveTime::from_hms_opt(7, 0, 0).unwrap(),
-                        },
-                        TimeSlot {
-                            from: NaiveTime::from_hms_opt(23, 0, 0).unwrap(),
-                            till: NaiveTime::from_hms_opt(0, 0, 0).unwrap(),
-                        },
-                    ],
+                    NaiveDate::from_ymd_opt(2022, 4, 16).unwrap(),
+                    Some(from + Duration::hours(3))
                 ),
                 (
-                    Weekday::Fri,
-                    vec![
-                        TimeSlot {
-                            from: NaiveTime::from_hms_opt(0, 0, 0).unwrap(),
-                            till: NaiveTime::from_hms_opt(7, 0, 0).unwrap(),
-                        },
-                        TimeSlot {
-                            from: NaiveTime::from_hms_opt(23, 0, 0).unwrap(),
-                            till: NaiveTime::from_hms_opt(0, 0, 0).unwrap(),
-                        },
-                    ],
+                    NaiveDate::from_ymd_opt(2022, 4, 17).unwrap(),
+                    Some(from + Duration::hours(24 + 14))
                 ),
-            ]),
-            local_time_zone: "Europe/Amsterdam".to_string(),
-        });
+            ]
+        );
 
-        let future_spot_prices: Vec<SpotPrice> = vec![
-            SpotPrice {
-                id: None,
-                source: None,
-                from: Utc.with_ymd_and_hms(2022, 4, 21, 19, 0, 0).unwrap(),
-                till: Utc.with_ymd_and_hms(2022, 4, 21, 20, 0, 0).unwrap(),
-                market_price: 0.224,
-                market_price_tax: 0.0469581,
-                sourcing_markup_price: 0.017,
-                energy_tax_price: 0.081,
-            },
-            SpotPrice {
-                id: None,
-                source: None,
-                from: Utc.with_ymd_and_hms(2022, 4, 21, 20, 0, 0).unwrap(),
-                till: Utc.with_ymd_and_hms(2022, 4, 21, 21, 0, 0).unwrap(),
-                market_price: 0.22,
-                market_price_tax: 0.0462924,
-                sourcing_markup_price: 0.017,
-                energy_tax_price: 0.081,
-            },
-            SpotPrice {
-                id: None,
-                source: None,
-                from: Utc.with_ymd_and_hms(2022, 4, 21, 21, 0, 0).unwrap(),
-                till: Utc.with_ymd_and_hms(2022, 4, 21, 22, 0, 0).unwrap(),
-                market_price: 0.2,
-                market_price_tax: 0.0419391,
-                sourcing_markup_price: 0.017,
-                energy_tax_price: 0.081,
-            },
-            SpotPrice {
-                id: None,
-                source: None,
-                from: Utc.with_ymd_and_hms(2022, 4, 21, 22, 0, 0).unwrap(),
-                till: Utc.with_ymd_and_hms(2022, 4, 21, 23, 0, 0).unwrap(),
-                market_price: 0.193,
-                market_price_tax: 0.040614,
-                sourcing_markup_price: 0.017,
-                energy_tax_price: 0.081,
-            },
-            SpotPrice {
-                id: None,
-                source: None,
-                from: Utc.with_ymd_and_hms(2022, 4, 21, 23, 0, 0).unwrap(),
-                till: Utc.with_ymd_and_hms(2022, 4, 22, 0, 0, 0).unwrap(),
-                market_price: 0.206,
-                market_price_tax: 0.04326,
-                sourcing_markup_price: 0.017,
-                energy_tax_price: 0.081,
-            },
-            SpotPrice {
-                id: None,
-                source: None,
-                from: Utc.with_ymd_and_hms(2022, 4, 22, 0, 0, 0).unwrap(),
-                till: Utc.with_ymd_and_hms(2022, 4, 22, 1, 0, 0).unwrap(),
-                market_price: 0.187,
-                market_price_tax: 0.0393078,
-                sourcing_markup_price: 0.017,
-                energy_tax_price: 0.081,
-            },
-            SpotPrice {
-                id: None,
-                source: None,
-                from: Utc.with_ymd_and_hms(2022, 4, 22, 1, 0, 0).unwrap(),
-                till: Utc.with_ymd_and_hms(2022, 4, 22, 2, 0, 0).unwrap(),
-                market_price: 0.187,
-                market_price_tax: 0.0392721,
-                sourcing_markup_price: 0.017,
-                energy_tax_price: 0.081,
-            },
-            SpotPrice {
-                id: None,
-                source: None,
-                from: Utc.with_ymd_and_hms(2022, 4, 22, 2, 0, 0).unwrap(),
-                till: Utc.with_ymd_and_hms(2022, 4, 22, 3, 0, 0).unwrap(),
-                market_price: 0.179,
-                market_price_tax: 0.0376761,
-                sourcing_markup_price: 0.017,
-                energy_tax_price: 0.081,
-            },
-            SpotPrice {
-                id: None,
-                source: None,
-                from: Utc.with_ymd_and_hms(2022, 4, 22, 3, 0, 0).unwrap(),
-                till: Utc.with_ymd_and_hms(2022, 4, 22, 4, 0, 0).unwrap(),
-                market_price: 0.176,
-                market_price_tax: 0.0369789,
-                sourcing_markup_price: 0.017,
-                energy_tax_price: 0.081,
-            },
-            SpotPrice {
-                id: None,
-                source: None,
-                from: Utc.with_ymd_and_hms(2022, 4, 22, 4, 0, 0).unwrap(),
-                till: Utc.with_ymd_and_hms(2022, 4, 22, 5, 0, 0).unwrap(),
-                market_price: 0.19,
-                market_price_tax: 0.03981180000000001,
-                sourcing_markup_price: 0.017,
-                energy_tax_price: 0.081,
-            },
-            SpotPrice {
-                id: None,
-                source: None,
-                from: Utc.with_ymd_and_hms(2022, 4, 22, 5, 0, 0).unwrap(),
-                till: Utc.with_ymd_and_hms(2022, 4, 22, 6, 0, 0).unwrap(),
-                market_price: 0.218,
-                market_price_tax: 0.0457947,
-                sourcing_markup_price: 0.017,
-                energy_tax_price: 0.081,
-            },
-            SpotPrice {
-                id: None,
-                source: None,
-                from: Utc.with_ymd_and_hms(2022, 4, 22, 6, 0, 0).unwrap(),
-                till: Utc.with_ymd_and_hms(2022, 4, 22, 7, 0, 0).unwrap(),
-                market_price: 0.24,
-                market_price_tax: 0.0503895,
-                sourcing_markup_price: 0.017,
-                energy_tax_price: 0.081,
-            },
-            SpotPrice {
-                id: None,
-                source: None,
-                from: Utc.with_ymd_and_hms(2022, 4, 22, 7, 0, 0).unwrap(),
-                till: Utc.with_ymd_and_hms(2022, 4, 22, 8, 0, 0).unwrap(),
-                market_price: 0.244,
-                market_price_tax: 0.051260999999999994,
-                sourcing_markup_price: 0.017,
-                energy_tax_price: 0.081,
-            },
-            SpotPrice {
-                id: None,
-                source: None,
-                from: Utc.with_ymd_and_hms(2022, 4, 22, 8, 0, 0).unwrap(),
-                till: Utc.with_ymd_and_hms(2022, 4, 22, 9, 0, 0).unwrap(),
-                market_price: 0.221,
-                market_price_tax: 0.0464205,
-                sourcing_markup_price: 0.017,
-                energy_tax_price: 0.081,
-            },
-            SpotPrice {
-                id: None,
-                source: None,
-                from: Utc.with_ymd_and_hms(2022, 4, 22, 9, 0, 0).unwrap(),
-                till: Utc.with_ymd_and_hms(2022, 4, 22, 10, 0, 0).unwrap(),
-                market_price: 0.197,
-                market_price_tax: 0.0412776,
-                sourcing_markup_price: 0.017,
-                energy_tax_price: 0.081,
-            },
-            SpotPrice {
-                id: None,
-                source: None,
-                from: Utc.with_ymd_and_hms(2022, 4, 22, 10, 0, 0).unwrap(),
-                till: Utc.with_ymd_and_hms(2022, 4, 22, 11, 0, 0).unwrap(),
-                market_price: 0.157,
-                market_price_tax: 0.0330561,
-                sourcing_markup_price: 0.017,
-                energy_tax_price: 0.081,
-            },
-            SpotPrice {
-                id: None,
-                source: None,
-                from: Utc.with_ymd_and_hms(2022, 4, 22, 11, 0, 0).unwrap(),
-                till: Utc.with_ymd_and_hms(2022, 4, 22, 12, 0, 0).unwrap(),
-                market_price: 0.15,
-                market_price_tax: 0.03141599999999999,
-                sourcing_markup_price: 0.017,
-                energy_tax_price: 0.081,
-            },
-            SpotPrice {
-                id: None,
-                source: None,
-                from: Utc.with_ymd_and_hms(2022, 4, 22, 12, 0, 0).unwrap(),
-                till: Utc.with_ymd_and_hms(2022, 4, 22, 13, 0, 0).unwrap(),
-                market_price: 0.102,
-                market_price_tax: 0.02142,
-                sourcing_markup_price: 0.017,
-                energy_tax_price: 0.081,
-            },
-            SpotPrice {
-                id: None,
-                source: None,
-                from: Utc.with_ymd_and_hms(2022, 4, 22, 13, 0, 0).unwrap(),
-                till: Utc.with_ymd_and_hms(2022, 4, 22, 14, 0, 0).unwrap(),
-                market_price: 0.1,
-                market_price_tax: 0.021,
-                sourcing_markup_price: 0.017,
-                energy_tax_price: 0.081,
-            },
-            SpotPrice {
-                id: None,
-                source: None,
-                from: Utc.with_ymd_and_hms(2022, 4, 22, 14, 0, 0).unwrap(),
-                till: Utc.with_ymd_and_hms(2022, 4, 22, 15, 0, 0).unwrap(),
-                market_price: 0.087,
-                market_price_tax: 0.0182217,
-                sourcing_markup_price: 0.017,
-                energy_tax_price: 0.081,
-            },
-            SpotPrice {
-                id: None,
-                source: None,
-                from: Utc.with_ymd_and_hms(2022, 4, 22, 15, 0, 0).unwrap(),
-                till: Utc.with_ymd_and_hms(2022, 4, 22, 16, 0, 0).unwrap(),
-                market_price: 0.119,
-                market_price_tax: 0.0249837,
-                sourcing_markup_price: 0.017,
-                energy_tax_price: 0.081,
-            },
-            SpotPrice {
-                id: None,
-                source: None,
-                from: Utc.with_ymd_and_hms(2022, 4, 22, 16, 0, 0).unwrap(),
-                till: Utc.with_ymd_and_hms(2022, 4, 22, 17, 0, 0).unwrap(),
-                market_price: 0.167,
-                market_price_tax: 0.03507,
-                sourcing_markup_price: 0.017,
-                energy_tax_price: 0.081,
-            },
-            SpotPrice {
-                id: None,
-                source: None,
-                from: Utc.with_ymd_and_hms(2022, 4, 22, 17, 0, 0).unwrap(),
-                till: Utc.with_ymd_and_hms(2022, 4, 22, 18, 0, 0).unwrap(),
-                market_price: 0.185,
-                market_price_tax: 0.038829,
-                sourcing_markup_price: 0.017,
-                energy_tax_price: 0.081,
-            },
-            SpotPrice {
-                id: None,
-                source: None,
-                from: Utc.with_ymd_and_hms(2022, 4, 22, 18, 0, 0).unwrap(),
-                till: Utc.with_ymd_and_hms(2022, 4, 22, 19, 0, 0).unwrap(),
-                market_price: 0.21,
-                market_price_tax: 0.0440181,
-                sourcing_markup_price: 0.017,
-                energy_tax_price: 0.081,
-            },
-            SpotPrice {
-                id: None,
-                source: None,
-                from: Utc.with_ymd_and_hms(2022, 4, 22, 19, 0, 0).unwrap(),
-                till: Utc.with_ymd_and_hms(2022, 4, 22, 20, 0, 0).unwrap(),
-                market_price: 0.21,
-                market_price_tax: 0.0440937,
-                sourcing_markup_price: 0.017,
-                energy_tax_price: 0.081,
-            },
-            SpotPrice {
-                id: None,
-                source: None,
-                from: Utc.with_ymd_and_hms(2022, 4, 22, 20, 0, 0).unwrap(),
-                till: Utc.with_ymd_and_hms(2022, 4, 22, 21, 0, 0).unwrap(),
-                market_price: 0.21,
-                market_price_tax: 0.0440286,
-                sourcing_markup_price: 0.017,
-                energy_tax_price: 0.081,
-            },
-            SpotPrice {
-                id: None,
-                source: None,
-                from: Utc.with_ymd_and_hms(2022, 4, 22, 21, 0, 0).unwrap(),
-                till: Utc.with_ymd_and_hms(2022, 4, 22, 22, 0, 0).unwrap(),
-                market_price: 0.192,
-                market_price_tax: 0.04032,
-                sourcing_markup_price: 0.017,
-                energy_tax_price: 0.081,
-            },
-            SpotPrice {
-                id: None,
-                source: None,
-                from: Utc.with_ymd_and_hms(2022, 4, 22, 22, 0, 0).unwrap(),
-                till: Utc.with_ymd_and_hms(2022, 4, 22, 23, 0, 0).unwrap(),
-                market_price: 0.178,
-                market_price_tax: 0.0372855,
-                sourcing_markup_price: 0.017,
-                energy_tax_price: 0.081,
-            },
-        ];
+        Ok(())
+    }
+
+    #[test]
+    fn get_daily_plans_returns_not_plannable_for_day_without_spot_prices(
+    ) -> Result<(), Box<dyn Error>> {
+        let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig::default());
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
 
         // act
-        let plannable_spot_prices = spot_price_planner.get_plannable_spot_prices(
-            &future_spot_prices,
-            &Some(Utc.with_ymd_and_hms(2022, 4, 21, 21, 32, 28).unwrap()),
-            &Some(Utc.with_ymd_and_hms(2022, 4, 22, 7, 32, 28).unwrap()),
+        let daily_plans = spot_price_planner.get_daily_plans(
+            &PlanningRequest {
+                spot_prices: hourly_spot_prices(from, &[0.30, 0.10, 0.20]),
+                load_profile: one_hour_load_profile(),
+                ..PlanningRequest::default()
+            },
+            2,
         )?;
 
-        assert_eq!(plannable_spot_prices.len(), 7);
+        assert_eq!(daily_plans.len(), 2);
         assert_eq!(
-            plannable_spot_prices[0].from,
-            Utc.with_ymd_and_hms(2022, 4, 21, 22, 0, 0).unwrap()
+            daily_plans[0]
+                .1
+                .planned()
+                .and_then(|response| response.planned_from),
+            Some(from + Duration::hours(1))
         );
         assert_eq!(
-            plannable_spot_prices[6].till,
-            Utc.with_ymd_and_hms(2022, 4, 22, 5, 0, 0).unwrap()
+            daily_plans[1].0,
+            NaiveDate::from_ymd_opt(2022, 4, 17).unwrap()
+        );
+        assert!(matches!(
+            daily_plans[1].1,
+            PlanningOutcome::NotPlannable {
+                reason: NotPlannableReason::NoPlannablePrices,
+                ..
+            }
+        ));
+
+        Ok(())
+    }
+
+    fn boost_and_keep_warm_response() -> PlanningResponse {
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+        let spot_prices = hourly_spot_prices(from, &[0.20, 0.40])
+            .into_iter()
+            .map(|spot_price| SpotPrice {
+                energy_tax_price: 0.10,
+                ..spot_price
+            })
+            .collect();
+        PlanningResponse::for_spot_prices(
+            spot_prices,
+            0,
+            &LoadProfile {
+                sections: vec![
+                    LoadProfileSection {
+                        duration_seconds: 30 * 60,
+                        power_draw_watt: 2000.0,
+                        end_power_draw_watt: None,
+                        id: None,
+                    },
+                    LoadProfileSection {
+                        duration_seconds: 3600,
+                        power_draw_watt: 500.0,
+                        end_power_draw_watt: None,
+                        id: None,
+                    },
+                ],
+            },
+            PriceComponents::default(),
+        )
+    }
+
+    #[test]
+    fn total_energy_kwh_sums_energy_of_all_sections() {
+        let response = boost_and_keep_warm_response();
+
+        // act
+        let total_energy_kwh = response.total_energy_kwh();
+
+        // 1 kWh boosting and 0.5 kWh keeping warm
+        assert!((total_energy_kwh - 1.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn average_price_per_kwh_divides_total_price_by_energy() {
+        let response = boost_and_keep_warm_response();
+        let market_price_only = PriceComponents {
+            market_price: true,
+            market_price_tax: false,
+            sourcing_markup_price: false,
+            energy_tax_price: false,
+            excluded_extra_components: vec![],
+        };
+
+        // act
+        let average_price_per_kwh = response.average_price_per_kwh(PriceComponents::default());
+        let average_market_price_per_kwh = response.average_price_per_kwh(market_price_only);
+
+        // 1.25 kWh at 0.20 and 0.25 kWh at 0.40, plus 0.10 energy tax on every kWh
+        assert!((average_market_price_per_kwh.unwrap() - 0.35 / 1.5).abs() < 1e-9);
+        assert!((average_price_per_kwh.unwrap() - (0.35 / 1.5 + 0.10)).abs() < 1e-9);
+        assert!(
+            (average_price_per_kwh.unwrap() * response.total_energy_kwh()
+                - response.total_price(None))
+            .abs()
+                < 1e-9
+        );
+    }
+
+    #[test]
+    fn average_price_per_kwh_returns_none_for_empty_plan() {
+        let response = PlanningResponse::for_spot_prices(
+            vec![],
+            0,
+            &one_hour_load_profile(),
+            PriceComponents::default(),
         );
 
+        // act
+        let average_price_per_kwh = response.average_price_per_kwh(PriceComponents::default());
+
+        assert_eq!(response.total_energy_kwh(), 0.0);
+        assert_eq!(average_price_per_kwh, None);
+    }
+
+    #[test]
+    fn planning_response_serializes_derived_energy_and_average_price() -> Result<(), Box<dyn Error>>
+    {
+        let response = boost_and_keep_warm_response();
+
+        // act
+        let json = serde_json::to_value(&response)?;
+
+        assert_eq!(json["totalEnergyKwh"], serde_json::json!(1.5));
+        assert!((json["averagePricePerKwh"].as_f64().unwrap() - (0.35 / 1.5 + 0.10)).abs() < 1e-9);
+
         Ok(())
     }
 
     #[test]
-    fn get_best_spot_prices_returns_cheapest_combined_block_spot_of_prices_amounting_to_enough_duration_ordered_by_time_for_lowest_price_strategy(
+    fn get_best_spot_prices_plans_jittered_spot_prices_like_clean_ones(
     ) -> Result<(), Box<dyn Error>> {
-        let load_profile = LoadProfile {
-            sections: vec![LoadProfileSection {
-                duration_seconds: 18000,
-                power_draw_watt: 2000.0,
-            }],
+        let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig::default());
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+        let clean = PlanningRequest {
+            spot_prices: quarter_hour_spot_prices(
+                from,
+                &[0.30, 0.25, 0.10, 0.05, 0.08, 0.20, 0.40, 0.35],
+            ),
+            load_profile: one_hour_load_profile(),
+            ..PlanningRequest::default()
+        };
+        let jitter_millis = [300, -300, 120, -250, 0, 280, -10, 150];
+        let jittered = PlanningRequest {
+            spot_prices: clean
+                .spot_prices
+                .iter()
+                .enumerate()
+                .map(|(i, spot_price)| SpotPrice {
+                    from: spot_price.from + Duration::milliseconds(jitter_millis[i]),
+                    till: spot_price.till - Duration::milliseconds(jitter_millis[7 - i]),
+                    ..spot_price.clone()
+                })
+                .collect(),
+            ..clean.clone()
         };
 
-        let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig {
-            load_profile: load_profile.clone(),
-            plannable_local_time_slots: HashMap::from([(
-                Weekday::Sat,
-                vec![TimeSlot {
-                    from: NaiveTime::from_hms_opt(0, 0, 0).unwrap(),
-                    till: NaiveTime::from_hms_opt(0, 0, 0).unwrap(),
-                }],
-            )]),
-            local_time_zone: "Europe/Amsterdam".to_string(),
-        });
+        // act
+        let clean_response = spot_price_planner
+            .get_best_spot_prices(&clean)?
+            .into_planned()
+            .ok_or("not plannable")?;
+        let jittered_response = spot_price_planner
+            .get_best_spot_prices(&jittered)?
+            .into_planned()
+            .ok_or("not plannable")?;
+
+        assert_eq!(jittered_response.planned_from, clean_response.planned_from);
+        assert_eq!(jittered_response.planned_till, clean_response.planned_till);
+        assert!(
+            (jittered_response.total_price(None) - clean_response.total_price(None)).abs() < 1e-9
+        );
+
+        Ok(())
+    }
 
+    #[test]
+    fn get_best_spot_prices_starts_at_now_mid_slot_when_prices_keep_rising(
+    ) -> Result<(), Box<dyn Error>> {
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 17, 0, 0).unwrap();
+        let now = from + Duration::minutes(20);
+        let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig {
+            clip_to_bounds: true,
+            ..SpotPricePlannerConfig::default()
+        })
+        .with_now(now);
         let request = PlanningRequest {
-            spot_prices: vec![
-                SpotPrice {
-                    id: None,
-                    source: None,
-                    from: Utc.with_ymd_and_hms(2022, 4, 16, 5, 0, 0).unwrap(),
-                    till: Utc.with_ymd_and_hms(2022, 4, 16, 6, 0, 0).unwrap(),
-                    market_price: 0.189,
-                    market_price_tax: 0.03968579999999999,
-                    sourcing_markup_price: 0.017,
-                    energy_tax_price: 0.081,
-                },
-                SpotPrice {
-                    id: None,
-                    source: None,
-                    from: Utc.with_ymd_and_hms(2022, 4, 16, 6, 0, 0).unwrap(),
-                    till: Utc.with_ymd_and_hms(2022, 4, 16, 7, 0, 0).unwrap(),
-                    market_price: 0.191,
-                    market_price_tax: 0.0401352,
-                    sourcing_markup_price: 0.017,
-                    energy_tax_price: 0.081,
-                },
-                SpotPrice {
-                    id: None,
-                    source: None,
-                    from: Utc.with_ymd_and_hms(2022, 4, 16, 7, 0, 0).unwrap(),
-                    till: Utc.with_ymd_and_hms(2022, 4, 16, 8, 0, 0).unwrap(),
-                    market_price: 0.19,
-                    market_price_tax: 0.039816,
-                    sourcing_markup_price: 0.017,
-                    energy_tax_price: 0.081,
-                },
-                SpotPrice {
-                    id: None,
-                    source: None,
-                    from: Utc.with_ymd_and_hms(2022, 4, 16, 8, 0, 0).unwrap(),
-                    till: Utc.with_ymd_and_hms(2022, 4, 16, 9, 0, 0).unwrap(),
-                    market_price: 0.173,
-                    market_price_tax: 0.0362502,
-                    sourcing_markup_price: 0.017,
-                    energy_tax_price: 0.081,
-                },
-                SpotPrice {
-                    id: None,
-                    source: None,
-                    from: Utc.with_ymd_and_hms(2022, 4, 16, 9, 0, 0).unwrap(),
-                    till: Utc.with_ymd_and_hms(2022, 4, 16, 10, 0, 0).unwrap(),
-                    market_price: 0.147,
-                    market_price_tax: 0.030781800000000005,
-                    sourcing_markup_price: 0.017,
-                    energy_tax_price: 0.081,
-                },
-                SpotPrice {
-                    id: None,
-                    source: None,
-                    from: Utc.with_ymd_and_hms(2022, 4, 16, 10, 0, 0).unwrap(),
-                    till: Utc.with_ymd_and_hms(2022, 4, 16, 11, 0, 0).unwrap(),
-                    market_price: 0.122,
-                    market_price_tax: 0.0256179,
-                    sourcing_markup_price: 0.017,
-                    energy_tax_price: 0.081,
-                },
-                SpotPrice {
-                    id: None,
-                    source: None,
-                    from: Utc.with_ymd_and_hms(2022, 4, 16, 11, 0, 0).unwrap(),
-                    till: Utc.with_ymd_and_hms(2022, 4, 16, 12, 0, 0).unwrap(),
-                    market_price: 0.069,
-                    market_price_tax: 0.0145446,
-                    sourcing_markup_price: 0.017,
-                    energy_tax_price: 0.081,
-                },
-                SpotPrice {
-                    id: None,
-                    source: None,
-                    from: Utc.with_ymd_and_hms(2022, 4, 16, 12, 0, 0).unwrap(),
-                    till: Utc.with_ymd_and_hms(2022, 4, 16, 13, 0, 0).unwrap(),
-                    market_price: 0.025,
-                    market_price_tax: 0.0052605,
-                    sourcing_markup_price: 0.017,
-                    energy_tax_price: 0.081,
-                },
-                SpotPrice {
-                    id: None,
-                    source: None,
-                    from: Utc.with_ymd_and_hms(2022, 4, 16, 13, 0, 0).unwrap(),
-                    till: Utc.with_ymd_and_hms(2022, 4, 16, 14, 0, 0).unwrap(),
-                    market_price: 0.027,
-                    market_price_tax: 0.0056364,
-                    sourcing_markup_price: 0.017,
-                    energy_tax_price: 0.081,
-                },
-                SpotPrice {
-                    id: None,
-                    source: None,
-                    from: Utc.with_ymd_and_hms(2022, 4, 16, 14, 0, 0).unwrap(),
-                    till: Utc.with_ymd_and_hms(2022, 4, 16, 15, 0, 0).unwrap(),
-                    market_price: 0.04,
-                    market_price_tax: 0.0084672,
-                    sourcing_markup_price: 0.017,
-                    energy_tax_price: 0.081,
-                },
-                SpotPrice {
-                    id: None,
-                    source: None,
-                    from: Utc.with_ymd_and_hms(2022, 4, 16, 15, 0, 0).unwrap(),
-                    till: Utc.with_ymd_and_hms(2022, 4, 16, 16, 0, 0).unwrap(),
-                    market_price: 0.066,
-                    market_price_tax: 0.013826400000000004,
-                    sourcing_markup_price: 0.017,
-                    energy_tax_price: 0.081,
-                },
-                SpotPrice {
-                    id: None,
-                    source: None,
-                    from: Utc.with_ymd_and_hms(2022, 4, 16, 16, 0, 0).unwrap(),
-                    till: Utc.with_ymd_and_hms(2022, 4, 16, 17, 0, 0).unwrap(),
-                    market_price: 0.108,
-                    market_price_tax: 0.0226191,
-                    sourcing_markup_price: 0.017,
-                    energy_tax_price: 0.081,
-                },
-                SpotPrice {
-                    id: None,
-                    source: None,
-                    from: Utc.with_ymd_and_hms(2022, 4, 16, 17, 0, 0).unwrap(),
-                    till: Utc.with_ymd_and_hms(2022, 4, 16, 18, 0, 0).unwrap(),
-                    market_price: 0.171,
-                    market_price_tax: 0.0359499,
-                    sourcing_markup_price: 0.017,
-                    energy_tax_price: 0.081,
-                },
-                SpotPrice {
-                    id: None,
-                    source: None,
-                    from: Utc.with_ymd_and_hms(2022, 4, 16, 18, 0, 0).unwrap(),
-                    till: Utc.with_ymd_and_hms(2022, 4, 16, 19, 0, 0).unwrap(),
-                    market_price: 0.195,
-                    market_price_tax: 0.0409668,
-                    sourcing_markup_price: 0.017,
-                    energy_tax_price: 0.081,
-                },
-                SpotPrice {
-                    id: None,
-                    source: None,
-                    from: Utc.with_ymd_and_hms(2022, 4, 16, 19, 0, 0).unwrap(),
-                    till: Utc.with_ymd_and_hms(2022, 4, 16, 20, 0, 0).unwrap(),
-                    market_price: 0.206,
-                    market_price_tax: 0.0432201,
-                    sourcing_markup_price: 0.017,
-                    energy_tax_price: 0.081,
-                },
-                SpotPrice {
-                    id: None,
-                    source: None,
-                    from: Utc.with_ymd_and_hms(2022, 4, 16, 20, 0, 0).unwrap(),
-                    till: Utc.with_ymd_and_hms(2022, 4, 16, 21, 0, 0).unwrap(),
-                    market_price: 0.194,
-                    market_price_tax: 0.0408387,
-                    sourcing_markup_price: 0.017,
-                    energy_tax_price: 0.081,
-                },
-                SpotPrice {
-                    id: None,
-                    source: None,
-                    from: Utc.with_ymd_and_hms(2022, 4, 16, 21, 0, 0).unwrap(),
-                    till: Utc.with_ymd_and_hms(2022, 4, 16, 22, 0, 0).unwrap(),
-                    market_price: 0.176,
-                    market_price_tax: 0.0369264,
-                    sourcing_markup_price: 0.017,
-                    energy_tax_price: 0.081,
-                },
-                SpotPrice {
-                    id: None,
-                    source: None,
-                    from: Utc.with_ymd_and_hms(2022, 4, 16, 22, 0, 0).unwrap(),
-                    till: Utc.with_ymd_and_hms(2022, 4, 16, 23, 0, 0).unwrap(),
-                    market_price: 0.167,
-                    market_price_tax: 0.0350448,
-                    sourcing_markup_price: 0.017,
-                    energy_tax_price: 0.081,
-                },
-            ],
-            load_profile: load_profile,
-            planning_strategy: PlanningStrategy::LowestPrice,
-            after: None,
-            before: None,
+            spot_prices: hourly_spot_prices(from, &[0.10, 0.20, 0.30, 0.40]),
+            load_profile: one_hour_load_profile(),
+            after: Some(from),
+            ..PlanningRequest::default()
         };
 
         // act
-        let response = spot_price_planner.get_best_spot_prices(&request)?;
+        let lowest = spot_price_planner
+            .get_best_spot_prices(&request)?
+            .into_planned()
+            .ok_or("not plannable")?;
+        let highest = spot_price_planner
+            .get_best_spot_prices(&PlanningRequest {
+                planning_strategy: PlanningStrategy::HighestPrice,
+                ..request
+            })?
+            .into_planned()
+            .ok_or("not plannable")?;
 
-        assert_eq!(response.total_price(None), 1.5294701999999742);
+        assert_eq!(lowest.planned_from, Some(now));
+        // 40 minutes at 0.10 and 20 minutes at 0.20
+        assert!((lowest.total_price(None) - (0.4 / 6.0 + 0.2 / 3.0)).abs() < 1e-9);
+        assert_eq!(highest.planned_from, Some(from + Duration::hours(3)));
 
-        assert_eq!(response.spot_prices.len(), 5);
-        assert_eq!(
-            response.spot_prices[0].from,
-            Utc.with_ymd_and_hms(2022, 4, 16, 11, 0, 0).unwrap()
-        );
-        assert_eq!(response.spot_prices[0].market_price, 0.069);
+        Ok(())
+    }
 
-        assert_eq!(
-            response.spot_prices[1].from,
-            Utc.with_ymd_and_hms(2022, 4, 16, 12, 0, 0).unwrap()
-        );
-        assert_eq!(response.spot_prices[1].market_price, 0.025);
+    fn explained_request() -> PlanningRequest {
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+        PlanningRequest {
+            spot_prices: hourly_spot_prices(from, &[0.10, 0.50, 0.20, 0.25, 0.30, 0.40, 0.05]),
+            load_profile: LoadProfile {
+                sections: vec![LoadProfileSection {
+                    duration_seconds: 2 * 3600,
+                    power_draw_watt: 1000.0,
+                    end_power_draw_watt: None,
+                    id: None,
+                }],
+            },
+            after: Some(from + Duration::hours(1)),
+            max_acceptable_total_price_per_kwh: Some(0.30),
+            finish_by: Some(from + Duration::hours(6)),
+            available_power_watt: HashMap::from([(from + Duration::hours(5), 500.0)]),
+            explain: true,
+            ..PlanningRequest::default()
+        }
+    }
+
+    #[test]
+    fn get_best_spot_prices_explains_why_other_candidates_were_rejected(
+    ) -> Result<(), Box<dyn Error>> {
+        let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig::default());
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+
+        // act
+        let outcome = spot_price_planner.get_best_spot_prices(&explained_request())?;
 
         assert_eq!(
-            response.spot_prices[2].from,
-            Utc.with_ymd_and_hms(2022, 4, 16, 13, 0, 0).unwrap()
+            outcome.planned().and_then(|response| response.planned_from),
+            Some(from + Duration::hours(2))
         );
-        assert_eq!(response.spot_prices[2].market_price, 0.027);
-
         assert_eq!(
-            response.spot_prices[3].from,
-            Utc.with_ymd_and_hms(2022, 4, 16, 14, 0, 0).unwrap()
+            outcome
+                .explanation()
+                .iter()
+                .map(|rejection| (rejection.from, rejection.reason))
+                .collect::<Vec<_>>(),
+            vec![
+                (from, RejectionReason::OutsideTimeSlots),
+                (
+                    from + Duration::hours(1),
+                    RejectionReason::AbovePriceThreshold
+                ),
+                (from + Duration::hours(3), RejectionReason::LostOnPrice),
+                (
+                    from + Duration::hours(4),
+                    RejectionReason::ExceedsAvailablePower
+                ),
+                (from + Duration::hours(5), RejectionReason::OutsideWindow),
+                (from + Duration::hours(6), RejectionReason::OutsideWindow),
+            ]
         );
-        assert_eq!(response.spot_prices[3].market_price, 0.04);
+        // 0.70 for the block against a threshold of 0.60, and 0.55 against the planned 0.45
+        assert!((outcome.explanation()[1].price_delta.unwrap() - 0.10).abs() < 1e-9);
+        assert!((outcome.explanation()[2].price_delta.unwrap() - 0.10).abs() < 1e-9);
+
+        Ok(())
+    }
+
+    #[test]
+    fn get_best_spot_prices_explains_rejections_when_not_plannable() -> Result<(), Box<dyn Error>> {
+        let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig::default());
+
+        // act
+        let outcome = spot_price_planner.get_best_spot_prices(&PlanningRequest {
+            max_acceptable_total_price_per_kwh: Some(0.20),
+            ..explained_request()
+        })?;
 
+        assert!(outcome.planned().is_none());
         assert_eq!(
-            response.spot_prices[4].from,
-            Utc.with_ymd_and_hms(2022, 4, 16, 15, 0, 0).unwrap()
+            outcome
+                .explanation()
+                .iter()
+                .filter(|rejection| rejection.reason == RejectionReason::AbovePriceThreshold)
+                .count(),
+            3
         );
-        assert_eq!(response.spot_prices[4].market_price, 0.066);
 
         Ok(())
     }
 
     #[test]
-    fn get_best_spot_prices_returns_most_expensive_combined_block_spot_of_prices_amounting_to_enough_duration_ordered_by_time_for_highest_price_strategy(
-    ) -> Result<(), Box<dyn Error>> {
-        let load_profile = LoadProfile {
-            sections: vec![
-                LoadProfileSection {
-                    duration_seconds: 7200,
-                    power_draw_watt: 2000.0,
-                },
-                LoadProfileSection {
-                    duration_seconds: 1800,
-                    power_draw_watt: 8000.0,
-                },
-            ],
+    fn get_best_spot_prices_leaves_explanation_empty_unless_asked() -> Result<(), Box<dyn Error>> {
+        let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig::default());
+
+        // act
+        let outcome = spot_price_planner.get_best_spot_prices(&PlanningRequest {
+            explain: false,
+            ..explained_request()
+        })?;
+
+        assert!(outcome.planned().is_some());
+        assert!(outcome.explanation().is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn total_price_for_load_is_identical_for_permutations_of_the_same_spot_prices() {
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+        let three_hour_load_profile = LoadProfile {
+            sections: vec![LoadProfileSection {
+                duration_seconds: 3 * 3600,
+                power_draw_watt: 1000.0,
+                end_power_draw_watt: None,
+                id: None,
+            }],
         };
 
+        // act
+        let ascending = total_price_for_load(
+            &hourly_spot_prices(from, &[0.1, 0.2, 0.3]),
+            &three_hour_load_profile,
+            None,
+        );
+        let descending = total_price_for_load(
+            &hourly_spot_prices(from, &[0.3, 0.2, 0.1]),
+            &three_hour_load_profile,
+            None,
+        );
+
+        // summed as floats these differ in the last bit
+        assert_ne!(0.1 + 0.2 + 0.3, 0.3 + 0.2 + 0.1);
+        assert_eq!(ascending, descending);
+        assert_eq!(ascending, 0.6);
+    }
+
+    #[test]
+    fn get_best_spot_prices_detects_tie_between_permuted_blocks_exactly(
+    ) -> Result<(), Box<dyn Error>> {
         let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig {
-            load_profile: load_profile.clone(),
-            plannable_local_time_slots: HashMap::from([(
-                Weekday::Sat,
-                vec![TimeSlot {
-                    from: NaiveTime::from_hms_opt(0, 0, 0).unwrap(),
-                    till: NaiveTime::from_hms_opt(0, 0, 0).unwrap(),
-                }],
-            )]),
-            local_time_zone: "Europe/Amsterdam".to_string(),
+            tie_breaker: TieBreaker::LatestStart,
+            tie_breaker_epsilon: Some(0.0),
+            ..SpotPricePlannerConfig::default()
         });
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
 
-        let request = PlanningRequest {
-            spot_prices: vec![
-                SpotPrice {
-                    id: None,
-                    source: None,
-                    from: Utc.with_ymd_and_hms(2022, 4, 16, 5, 0, 0).unwrap(),
-                    till: Utc.with_ymd_and_hms(2022, 4, 16, 6, 0, 0).unwrap(),
-                    market_price: 0.189,
-                    market_price_tax: 0.03968579999999999,
-                    sourcing_markup_price: 0.017,
-                    energy_tax_price: 0.081,
-                },
-                SpotPrice {
-                    id: None,
-                    source: None,
-                    from: Utc.with_ymd_and_hms(2022, 4, 16, 6, 0, 0).unwrap(),
-                    till: Utc.with_ymd_and_hms(2022, 4, 16, 7, 0, 0).unwrap(),
-                    market_price: 0.191,
-                    market_price_tax: 0.0401352,
-                    sourcing_markup_price: 0.017,
-                    energy_tax_price: 0.081,
-                },
-                SpotPrice {
-                    id: None,
-                    source: None,
-                    from: Utc.with_ymd_and_hms(2022, 4, 16, 7, 0, 0).unwrap(),
-                    till: Utc.with_ymd_and_hms(2022, 4, 16, 8, 0, 0).unwrap(),
-                    market_price: 0.19,
-                    market_price_tax: 0.039816,
-                    sourcing_markup_price: 0.017,
-                    energy_tax_price: 0.081,
-                },
-                SpotPrice {
-                    id: None,
-                    source: None,
-                    from: Utc.with_ymd_and_hms(2022, 4, 16, 8, 0, 0).unwrap(),
-                    till: Utc.with_ymd_and_hms(2022, 4, 16, 9, 0, 0).unwrap(),
-                    market_price: 0.173,
-                    market_price_tax: 0.0362502,
-                    sourcing_markup_price: 0.017,
-                    energy_tax_price: 0.081,
-                },
-                SpotPrice {
-                    id: None,
-                    source: None,
-                    from: Utc.with_ymd_and_hms(2022, 4, 16, 9, 0, 0).unwrap(),
-                    till: Utc.with_ymd_and_hms(2022, 4, 16, 10, 0, 0).unwrap(),
-                    market_price: 0.147,
-                    market_price_tax: 0.030781800000000005,
-                    sourcing_markup_price: 0.017,
-                    energy_tax_price: 0.081,
-                },
-                SpotPrice {
-                    id: None,
-                    source: None,
-                    from: Utc.with_ymd_and_hms(2022, 4, 16, 10, 0, 0).unwrap(),
-                    till: Utc.with_ymd_and_hms(2022, 4, 16, 11, 0, 0).unwrap(),
-                    market_price: 0.122,
-                    market_price_tax: 0.0256179,
-                    sourcing_markup_price: 0.017,
-                    energy_tax_price: 0.081,
-                },
-                SpotPrice {
-                    id: None,
-                    source: None,
-                    from: Utc.with_ymd_and_hms(2022, 4, 16, 11, 0, 0).unwrap(),
-                    till: Utc.with_ymd_and_hms(2022, 4, 16, 12, 0, 0).unwrap(),
-                    market_price: 0.069,
-                    market_price_tax: 0.0145446,
-                    sourcing_markup_price: 0.017,
-                    energy_tax_price: 0.081,
-                },
-                SpotPrice {
-                    id: None,
-                    source: None,
-                    from: Utc.with_ymd_and_hms(2022, 4, 16, 12, 0, 0).unwrap(),
-                    till: Utc.with_ymd_and_hms(2022, 4, 16, 13, 0, 0).unwrap(),
-                    market_price: 0.025,
-                    market_price_tax: 0.0052605,
-                    sourcing_markup_price: 0.017,
-                    energy_tax_price: 0.081,
-                },
-                SpotPrice {
-                    id: None,
-                    source: None,
-                    from: Utc.with_ymd_and_hms(2022, 4, 16, 13, 0, 0).unwrap(),
-                    till: Utc.with_ymd_and_hms(2022, 4, 16, 14, 0, 0).unwrap(),
-                    market_price: 0.027,
-                    market_price_tax: 0.0056364,
-                    sourcing_markup_price: 0.017,
-                    energy_tax_price: 0.081,
-                },
-                SpotPrice {
-                    id: None,
-                    source: None,
-                    from: Utc.with_ymd_and_hms(2022, 4, 16, 14, 0, 0).unwrap(),
-                    till: Utc.with_ymd_and_hms(2022, 4, 16, 15, 0, 0).unwrap(),
-                    market_price: 0.04,
-                    market_price_tax: 0.0084672,
-                    sourcing_markup_price: 0.017,
-                    energy_tax_price: 0.081,
-                },
-                SpotPrice {
-                    id: None,
-                    source: None,
-                    from: Utc.with_ymd_and_hms(2022, 4, 16, 15, 0, 0).unwrap(),
-                    till: Utc.with_ymd_and_hms(2022, 4, 16, 16, 0, 0).unwrap(),
-                    market_price: 0.066,
-                    market_price_tax: 0.013826400000000004,
-                    sourcing_markup_price: 0.017,
-                    energy_tax_price: 0.081,
-                },
-                SpotPrice {
-                    id: None,
-                    source: None,
-                    from: Utc.with_ymd_and_hms(2022, 4, 16, 16, 0, 0).unwrap(),
-                    till: Utc.with_ymd_and_hms(2022, 4, 16, 17, 0, 0).unwrap(),
-                    market_price: 0.108,
-                    market_price_tax: 0.0226191,
-                    sourcing_markup_price: 0.017,
-                    energy_tax_price: 0.081,
-                },
-                SpotPrice {
-                    id: None,
-                    source: None,
-                    from: Utc.with_ymd_and_hms(2022, 4, 16, 17, 0, 0).unwrap(),
-                    till: Utc.with_ymd_and_hms(2022, 4, 16, 18, 0, 0).unwrap(),
-                    market_price: 0.171,
-                    market_price_tax: 0.0359499,
-                    sourcing_markup_price: 0.017,
-                    energy_tax_price: 0.081,
-                },
-                SpotPrice {
-                    id: None,
-                    source: None,
-                    from: Utc.with_ymd_and_hms(2022, 4, 16, 18, 0, 0).unwrap(),
-                    till: Utc.with_ymd_and_hms(2022, 4, 16, 19, 0, 0).unwrap(),
-                    market_price: 0.195,
-                    market_price_tax: 0.0409668,
-                    sourcing_markup_price: 0.017,
-                    energy_tax_price: 0.081,
-                },
-                SpotPrice {
-                    id: None,
-                    source: None,
-                    from: Utc.with_ymd_and_hms(2022, 4, 16, 19, 0, 0).unwrap(),
-                    till: Utc.with_ymd_and_hms(2022, 4, 16, 20, 0, 0).unwrap(),
-                    market_price: 0.206,
-                    market_price_tax: 0.0432201,
-                    sourcing_markup_price: 0.017,
-                    energy_tax_price: 0.081,
-                },
-                SpotPrice {
-                    id: None,
-                    source: None,
-                    from: Utc.with_ymd_and_hms(2022, 4, 16, 20, 0, 0).unwrap(),
-                    till: Utc.with_ymd_and_hms(2022, 4, 16, 21, 0, 0).unwrap(),
-                    market_price: 0.194,
-                    market_price_tax: 0.0408387,
-                    sourcing_markup_price: 0.017,
-                    energy_tax_price: 0.081,
-                },
-                SpotPrice {
-                    id: None,
-                    source: None,
-                    from: Utc.with_ymd_and_hms(2022, 4, 16, 21, 0, 0).unwrap(),
-                    till: Utc.with_ymd_and_hms(2022, 4, 16, 22, 0, 0).unwrap(),
-                    market_price: 0.176,
-                    market_price_tax: 0.0369264,
-                    sourcing_markup_price: 0.017,
-                    energy_tax_price: 0.081,
-                },
-                SpotPrice {
-                    id: None,
-                    source: None,
-                    from: Utc.with_ymd_and_hms(2022, 4, 16, 22, 0, 0).unwrap(),
-                    till: Utc.with_ymd_and_hms(2022, 4, 16, 23, 0, 0).unwrap(),
-                    market_price: 0.167,
-                    market_price_tax: 0.0350448,
-                    sourcing_markup_price: 0.017,
-                    energy_tax_price: 0.081,
+        // act
+        let response = spot_price_planner
+            .get_best_spot_prices(&PlanningRequest {
+                spot_prices: hourly_spot_prices(from, &[0.1, 0.2, 0.3, 0.9, 0.3, 0.2, 0.1]),
+                load_profile: LoadProfile {
+                    sections: vec![LoadProfileSection {
+                        duration_seconds: 3 * 3600,
+                        power_draw_watt: 1000.0,
+                        end_power_draw_watt: None,
+                        id: None,
+                    }],
                 },
-            ],
-            load_profile: load_profile,
-            planning_strategy: PlanningStrategy::HighestPrice,
-            after: None,
-            before: None,
-        };
+                ..PlanningRequest::default()
+            })?
+            .into_planned()
+            .ok_or("not plannable")?;
+
+        assert_eq!(response.planned_from, Some(from + Duration::hours(4)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn get_best_spot_prices_refuses_spot_prices_in_mixed_currencies() {
+        let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig::default());
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+        let mut spot_prices = hourly_spot_prices(from, &[0.20, 5.0]);
+        spot_prices[0].currency = Some("EUR".to_string());
+        spot_prices[1].currency = Some("CZK".to_string());
 
         // act
-        let response = spot_price_planner.get_best_spot_prices(&request)?;
+        let result = spot_price_planner.get_best_spot_prices(&PlanningRequest {
+            spot_prices,
+            load_profile: one_hour_load_profile(),
+            ..PlanningRequest::default()
+        });
 
-        assert_eq!(response.total_price(None), 2.693728600000162);
+        assert!(result.is_err());
+    }
 
-        assert_eq!(response.spot_prices.len(), 3);
-        assert_eq!(
-            response.spot_prices[0].from,
-            Utc.with_ymd_and_hms(2022, 4, 16, 18, 0, 0).unwrap()
-        );
-        assert_eq!(response.spot_prices[0].market_price, 0.195);
+    #[test]
+    fn get_best_spot_prices_plans_spot_prices_per_mwh() -> Result<(), Box<dyn Error>> {
+        let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig::default());
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+        let spot_prices = hourly_spot_prices(from, &[200.0, 100.0, 300.0])
+            .into_iter()
+            .map(|spot_price| SpotPrice {
+                currency: Some("EUR".to_string()),
+                unit: Some(PriceUnit::EurPerMwh),
+                ..spot_price
+            })
+            .collect();
 
-        assert_eq!(
-            response.spot_prices[1].from,
-            Utc.with_ymd_and_hms(2022, 4, 16, 19, 0, 0).unwrap()
-        );
-        assert_eq!(response.spot_prices[1].market_price, 0.206);
+        // act
+        let response = spot_price_planner
+            .get_best_spot_prices(&PlanningRequest {
+                spot_prices,
+                load_profile: one_hour_load_profile(),
+                ..PlanningRequest::default()
+            })?
+            .into_planned()
+            .ok_or("not plannable")?;
 
-        assert_eq!(
-            response.spot_prices[2].from,
-            Utc.with_ymd_and_hms(2022, 4, 16, 20, 0, 0).unwrap()
+        assert_eq!(response.planned_from, Some(from + Duration::hours(1)));
+        assert_eq!(response.total_price(None), 0.1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn get_best_spot_prices_plans_merged_spot_prices_like_unmerged() -> Result<(), Box<dyn Error>> {
+        let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig::default());
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+        let spot_prices = quarter_hour_spot_prices(
+            from,
+            &[
+                0.30, 0.30, 0.20, 0.20, 0.10, 0.10, 0.10, 0.10, 0.10, 0.10, 0.25, 0.25, 0.05, 0.40,
+            ],
         );
-        assert_eq!(response.spot_prices[2].market_price, 0.194);
+        let load_profile = LoadProfile {
+            sections: vec![LoadProfileSection {
+                duration_seconds: 5400,
+                power_draw_watt: 2000.0,
+                end_power_draw_watt: None,
+                id: None,
+            }],
+        };
+        let merged_spot_prices = SpotPrice::merge_adjacent(spot_prices.clone(), 1e-9);
+        assert_eq!(merged_spot_prices.len(), 6);
+
+        for planning_strategy in [
+            PlanningStrategy::LowestPrice,
+            PlanningStrategy::HighestPrice,
+        ] {
+            let plan = |spot_prices: Vec<SpotPrice>| {
+                spot_price_planner
+                    .get_best_spot_prices(&PlanningRequest {
+                        spot_prices,
+                        load_profile: load_profile.clone(),
+                        planning_strategy,
+                        ..PlanningRequest::default()
+                    })?
+                    .into_planned()
+                    .ok_or_else(|| Box::<dyn Error>::from("not plannable"))
+            };
+
+            // act
+            let response = plan(spot_prices.clone())?;
+            let merged_response = plan(merged_spot_prices.clone())?;
+
+            assert_eq!(merged_response.planned_from, response.planned_from);
+            assert_eq!(merged_response.planned_till, response.planned_till);
+            assert_eq!(merged_response.segments, response.segments);
+            assert!((merged_response.total_price(None) - response.total_price(None)).abs() < 1e-9);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn get_best_spot_prices_starts_inside_merged_spot_price_like_unmerged(
+    ) -> Result<(), Box<dyn Error>> {
+        let spot_price_planner = SpotPricePlanner::new(SpotPricePlannerConfig::default());
+        let from = Utc.with_ymd_and_hms(2022, 4, 16, 0, 0, 0).unwrap();
+        let spot_prices = quarter_hour_spot_prices(from, &[0.30, 0.30, 0.30, 0.30, 0.01]);
+        let merged_spot_prices = SpotPrice::merge_adjacent(spot_prices.clone(), 1e-9);
+        assert_eq!(merged_spot_prices.len(), 2);
+        let plan = |spot_prices: Vec<SpotPrice>| {
+            spot_price_planner
+                .get_best_spot_prices(&PlanningRequest {
+                    spot_prices,
+                    load_profile: LoadProfile {
+                        sections: vec![LoadProfileSection {
+                            duration_seconds: 1800,
+                            power_draw_watt: 2000.0,
+                            end_power_draw_watt: None,
+                            id: None,
+                        }],
+                    },
+                    ..PlanningRequest::default()
+                })?
+                .into_planned()
+                .ok_or_else(|| Box::<dyn Error>::from("not plannable"))
+        };
+
+        // act
+        let response = plan(spot_prices)?;
+        let merged_response = plan(merged_spot_prices)?;
+
+        assert_eq!(response.planned_from, Some(from + Duration::minutes(45)));
+        assert!((response.total_price(None) - 0.155).abs() < 1e-9);
+        assert_eq!(merged_response.planned_from, response.planned_from);
+        assert!((merged_response.total_price(None) - response.total_price(None)).abs() < 1e-9);
 
         Ok(())
     }
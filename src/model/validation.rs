@@ -0,0 +1,52 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Default for how far measured_at_time may lie in the future, to allow for clock skew between hosts.
+pub const DEFAULT_MAX_FUTURE_SKEW_SECONDS: i64 = 300;
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub enum Severity {
+    /// Suspicious, but still fit to publish.
+    Warning,
+    /// Not fit to publish.
+    Error,
+}
+
+/// Problem with a field of a measurement or sample, as found by validate.
+#[derive(Clone, PartialEq, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ValidationIssue {
+    pub field: String,
+    pub message: String,
+    pub severity: Severity,
+}
+
+impl ValidationIssue {
+    pub(crate) fn error(field: &str, message: &str) -> Self {
+        ValidationIssue {
+            field: field.to_string(),
+            message: message.to_string(),
+            severity: Severity::Error,
+        }
+    }
+
+    pub(crate) fn warning(field: &str, message: &str) -> Self {
+        ValidationIssue {
+            field: field.to_string(),
+            message: message.to_string(),
+            severity: Severity::Warning,
+        }
+    }
+}
+
+impl fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?} in {}: {}", self.severity, self.field, self.message)
+    }
+}
+
+/// Whether any of issues is an error.
+pub fn has_errors(issues: &[ValidationIssue]) -> bool {
+    issues.iter().any(|issue| issue.severity == Severity::Error)
+}
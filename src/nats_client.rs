@@ -3,6 +3,9 @@ use std::env;
 use std::error::Error;
 use tracing::{debug, info};
 
+/// Header carrying the schema version of a published measurement.
+pub const SCHEMA_VERSION_HEADER: &str = "jarvis-schema-version";
+
 pub struct NatsClientConfig {
     pub host: String,
     pub subject: String,
@@ -108,7 +111,12 @@ impl NatsClient {
         self.connection
             .as_ref()
             .unwrap()
-            .publish(&self.config.subject, msg)
+            .publish_with_reply_or_headers(
+                &self.config.subject,
+                None,
+                Some(&headers(measurement)),
+                msg,
+            )
             .unwrap_or_else(|_| {
                 panic!(
                     "Failed to publish measurement to nats subject {}",
@@ -119,3 +127,35 @@ impl NatsClient {
         Ok(())
     }
 }
+
+fn headers(measurement: &Measurement) -> nats::HeaderMap {
+    let mut headers = nats::HeaderMap::new();
+    headers.insert(
+        SCHEMA_VERSION_HEADER,
+        measurement.schema_version.to_string(),
+    );
+    headers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{Sample, SampleType};
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn headers_carry_schema_version_of_measurement() {
+        let measurement = Measurement::builder("jarvis-modbus-exporter", "My Home")
+            .sample(Sample::builder(SampleType::Temperature, 21.5).build())
+            .build()
+            .unwrap();
+
+        // act
+        let headers = headers(&measurement);
+
+        assert_eq!(
+            headers.get(SCHEMA_VERSION_HEADER),
+            Some(&Measurement::CURRENT_SCHEMA_VERSION.to_string())
+        );
+    }
+}
@@ -2,6 +2,7 @@ use crate::config_client::{ConfigClient, SetDefaults};
 use crate::model::*;
 use crate::planner_client::PlannerClient;
 use crate::spot_prices_state_client::SpotPricesStateClient;
+use chrono::Utc;
 use serde::de::DeserializeOwned;
 use std::error::Error;
 
@@ -41,9 +42,10 @@ impl<T> PlannerService<T> {
         let spot_prices_state = self.config.spot_prices_state_client.read_state()?;
 
         if let Some(state) = spot_prices_state {
-            let config: T = self.config.config_client.read_config_from_file()?;
+            let config: T = self.config.config_client.read_config().await?;
             let spot_price_planner =
-                SpotPricePlanner::new(self.config.config_client.read_planner_config_from_file()?);
+                SpotPricePlanner::try_new(self.config.config_client.read_planner_config().await?)?
+                    .with_now(Utc::now());
 
             self.config
                 .planner_client
@@ -77,18 +77,17 @@ impl StateClient {
             Err(_) => return Ok(Option::None),
         };
 
-        let last_measurements: Option<Vec<Measurement>> =
-            match serde_yaml::from_str(&state_file_contents) {
-                Ok(lm) => Some(lm),
-                Err(_) => return Ok(Option::None),
-            };
+        let last_measurements = match parse_state(&state_file_contents) {
+            Some(lm) => lm,
+            None => return Ok(Option::None),
+        };
 
         info!(
             "Read previous measurements from state file at {}",
             &self.config.measurement_file_path
         );
 
-        Ok(last_measurements)
+        Ok(Some(last_measurements))
     }
 
     async fn get_state_configmap(&self) -> Result<ConfigMap, Box<dyn std::error::Error>> {
@@ -165,6 +164,17 @@ impl StateClient {
     }
 }
 
+/// Measurements in a state file, with PascalCase or camelCase field names, migrated to the current schema version;
+/// None if it can't be parsed.
+fn parse_state(state_file_contents: &str) -> Option<Vec<Measurement>> {
+    let mut measurements: Vec<Measurement> = serde_yaml::from_str(state_file_contents).ok()?;
+    for measurement in &mut measurements {
+        measurement.migrate_to_current();
+    }
+
+    Some(measurements)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -253,4 +263,35 @@ mod tests {
             Err(e) => panic!("get_state_configmap errored: {}", e),
         }
     }
+
+    #[test]
+    fn parse_state_reads_pascal_case_and_camel_case_files_alike() {
+        let pascal_case = fs::read_to_string("test-measurement.yaml").unwrap();
+        let camel_case = fs::read_to_string("test-measurement-camel-case.yaml").unwrap();
+
+        // act
+        let measurements = parse_state(&camel_case);
+
+        assert!(measurements.is_some());
+        assert_eq!(measurements, parse_state(&pascal_case));
+    }
+
+    #[test]
+    fn parse_state_migrates_energy_names_of_version_1() {
+        let state_file_contents = fs::read_to_string("test-measurement.yaml")
+            .unwrap()
+            .replace(
+                "SAMPLE_TYPE_ELECTRICITY_CONSUMPTION",
+                "SAMPLE_TYPE_GAS_CONSUMPTION",
+            );
+
+        // act
+        let measurements = parse_state(&state_file_contents).unwrap();
+
+        assert_eq!(measurements[0].samples[0].sample_type, SampleType::Energy);
+        assert_eq!(
+            measurements[0].schema_version,
+            Measurement::CURRENT_SCHEMA_VERSION
+        );
+    }
 }